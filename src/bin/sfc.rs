@@ -4,8 +4,8 @@ use std::path::{Path, PathBuf};
 use std::thread;
 use std::time::Duration;
 
-use anyhow::{anyhow, Result};
-use clap::{Parser, Subcommand};
+use anyhow::{anyhow, Context, Result};
+use clap::{ArgGroup, Parser, Subcommand};
 use owo_colors::OwoColorize;
 use crossterm::{
     queue,
@@ -16,9 +16,16 @@ use crossterm::{
 };
 use colored::control as colored_control;
 use my_lib::sfc as core;
-use my_lib::container::ContainerConfig;
+use my_lib::config::{ImportReport, SfcConfig, Template, TemplateOrigin, TemplateStore};
+use my_lib::container::{spawn_error_code, ContainerConfig, PackageSource, PackageSpec};
+use my_lib::flake::{checkout_source, FlakeImport, FlakeRef};
 use my_lib::history::History;
+use my_lib::sharing::{registry, DevcontainerExporter, DockerExporter, FlakeRemote, Inventory, PullOutcome, PushOutcome, KeyStore, RegistryServer, RegistryStore, SbomFormat, ShareManager};
 use my_lib::package::PackageManager;
+use my_lib::core::task::TaskRunner;
+use my_lib::core::{bind_project, default_project_dir, restore_lockfiles, ChangeKind, SnapshotDiff, SnapshotManager, TagStore, WorkspaceManager};
+use my_lib::error::SfcError;
+use my_lib::system::shell::{find_marker, hook_env, ShellKind};
 use indicatif::{ProgressBar, ProgressStyle};
 use figlet_rs::FIGfont;
 
@@ -34,22 +41,80 @@ enum Commands {
     /// Create one or more containers
     Create { 
         names: Vec<String>,
-        #[arg(long)] from: Option<String>, // Recreate from snapshot hash
+        /// Recreate from a snapshot hash or a registry (registry://host/name:tag)
+        #[arg(long)] from: Option<String>,
+        /// Seed packages, env, toolchains and tasks from a template
+        #[arg(short = 't', long)] template: Option<String>,
+        /// Only record seeded packages/toolchains; don't install them
+        #[arg(long)] no_install: bool,
+        /// Also seed from version files in the current directory (.tool-versions, .nvmrc, ...)
+        #[arg(long)] detect: bool,
+        /// Recreate from a bundle written by 'sfc share --bundle'
+        #[arg(long, conflicts_with_all = ["from", "template", "detect"])] from_bundle: Option<PathBuf>,
     },
 
+    /// Create or update a container so it matches a container TOML file
+    Apply {
+        file: PathBuf,
+        /// Container name (defaults to the name in the file)
+        #[arg(long)] name: Option<String>,
+        /// Only record packages/toolchains; don't install them
+        #[arg(long)] no_install: bool,
+        /// Remove packages that aren't in the file
+        #[arg(long)] prune: bool,
+    },
+
+    /// Import toolchains and packages from a version file (.tool-versions, mise.toml, ...)
+    Import {
+        file: PathBuf,
+        /// Container to import into (defaults to the current one)
+        #[arg(short, long)] container: Option<String>,
+        /// Only record packages/toolchains; don't install them
+        #[arg(long)] no_install: bool,
+    },
+
+    /// Manage container templates
+    Template {
+        #[command(subcommand)]
+        cmd: TemplateCmd,
+    },
+
+    /// Fork a container: config, history and tree are copied, snapshots are shared
+    Clone { src: String, dst: String },
+
+    /// Rename a container and everything that refers to it
+    Rename { old: String, new: String },
+
     /// Open a temp environment (uses current container if name not provided)
+    #[command(args_conflicts_with_subcommands = true)]
     Temp {
+        #[command(subcommand)]
+        cmd: Option<TempCmd>,
         name: Option<String>,
+        /// Name for the temp (default: a timestamp); promote, discard and enter take it
+        #[arg(long = "name")] temp_name: Option<String>,
         #[arg(long)] node: Option<String>,
         #[arg(long)] npm: Option<String>,
         #[arg(long)] rust: Option<String>,
     },
 
     /// Promote a temp snapshot to stable (uses current container if name not provided)
-    Promote { name: Option<String>, temp_alias: Option<String> },
+    Promote {
+        name: Option<String>,
+        /// Temp name or alias; required when the container has several temps
+        temp_alias: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long)] yes: bool,
+    },
 
     /// Discard a temp snapshot (uses current container if name not provided)
-    Discard { name: Option<String>, temp_alias: Option<String> },
+    Discard {
+        name: Option<String>,
+        /// Temp name or alias; required when the container has several temps
+        temp_alias: Option<String>,
+        /// Don't ask for confirmation
+        #[arg(short = 'y', long)] yes: bool,
+    },
 
     /// List containers and temps
     List,
@@ -72,8 +137,23 @@ enum Commands {
     /// Clean dangling links and orphaned store snapshots
     Clean { #[arg(long)] age: Option<String> },
 
-    /// Rollback NAME to a previous stable link target
-    Rollback { name: String, target: String },
+    /// Bind a container to the project directory whose lockfiles temp, promote and rollback track (instead of its src/)
+    Bind {
+        /// Container (defaults to the current one)
+        name: Option<String>,
+        /// Project directory (defaults to the one whose .sfc-container names it, else the current directory)
+        dir: Option<PathBuf>,
+    },
+
+    /// Rollback NAME to a previous stable link target and restore its lockfiles
+    Rollback {
+        name: String,
+        /// Store directory name, snapshot hash, tag or relative reference (stable~1)
+        target: String,
+        /// Overwrite lockfiles edited since the current snapshot was taken
+        #[arg(long)]
+        force: bool,
+    },
 
     /// Manage shared toolchains stored under workspace .sfc/toolchains (Language-specific)
     Toolchain {
@@ -108,6 +188,12 @@ enum Commands {
         cmd: FlakeCmd,
     },
 
+    /// Export a container for use outside sfc
+    Export {
+        #[command(subcommand)]
+        cmd: ExportCmd,
+    },
+
     /// Show animated SFC banner
     Banner,
 
@@ -123,10 +209,67 @@ enum Commands {
     /// List all snapshots for a container
     Snapshots { name: String },
 
+    /// Export a snapshot to a compressed tarball or import one
+    Snapshot {
+        #[command(subcommand)]
+        cmd: SnapshotCmd,
+    },
+
+    /// Compare two snapshots: packages, toolchains, env and lockfiles
+    Diff {
+        container: String,
+        /// Older snapshot (hash prefix, tag or relative reference)
+        from: String,
+        /// Newer snapshot
+        to: String,
+        /// Print the differences as JSON
+        #[arg(long)] json: bool,
+    },
+
+    /// Name a snapshot; tags work anywhere a hash does, as do `stable~2`-style references
+    Tag {
+        container: String,
+        /// Snapshot hash (prefix), tag or relative reference
+        hash: String,
+        name: String,
+    },
+
     /// Share a container snapshot for others to recreate
+    #[command(group = ArgGroup::new("output").args(["bundle", "publish"]).multiple(true))]
     Share { 
         name: String, 
         hash: Option<String>,
+        /// Also write a self-contained bundle (.sfcb) to this path
+        #[arg(long)] bundle: Option<PathBuf>,
+        /// Publish the bundle to a registry (registry://host[:port][/name:tag])
+        #[arg(long)] publish: Option<String>,
+        /// Include installed package artifacts in the bundle
+        #[arg(long, requires = "output")] artifacts: bool,
+        /// Sign with this key (defaults to sharing.signing_key, then the "default" key)
+        #[arg(long, requires = "output")] key: Option<String>,
+    },
+
+    /// Run or query a share bundle registry
+    Registry {
+        #[command(subcommand)]
+        cmd: RegistryCmd,
+    },
+
+    /// Manage signing keys and trusted public keys for shares
+    Keys {
+        #[command(subcommand)]
+        cmd: KeysCmd,
+    },
+
+    /// Write a software bill of materials for a container or snapshot
+    Sbom {
+        container: String,
+        /// Snapshot hash (prefix) to describe instead of the live container
+        #[arg(long)] hash: Option<String>,
+        /// cyclonedx or spdx
+        #[arg(long, default_value = "cyclonedx")] format: String,
+        /// Output file (default: stdout)
+        #[arg(short = 'o', long)] output: Option<PathBuf>,
     },
 
     /// Delete a specific snapshot
@@ -135,6 +278,28 @@ enum Commands {
         hash: String,
         #[arg(short = 'f', long = "force")] force: bool,
     },
+
+    /// Run a command inside a container environment without a shell
+    Exec {
+        container: String,
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Run a task from the container's [tasks] table (lists tasks when omitted)
+    Run {
+        task: Option<String>,
+        #[arg(short = 'c', long = "container")] container: Option<String>,
+        #[arg(short = 'f', long = "force")] force: bool,
+        #[arg(short = 'j', long = "jobs")] jobs: Option<usize>,
+    },
+
+    /// Print shell integration for auto-activating containers (eval "$(sfc hook bash)")
+    Hook { shell: String },
+
+    /// Emit activation code for the current directory (used by `sfc hook`)
+    #[command(hide = true)]
+    HookEnv { shell: String },
 }
 
 #[derive(Subcommand, Debug)]
@@ -159,6 +324,105 @@ enum ToolchainCmd {
 
 
 
+#[derive(Subcommand, Debug)]
+enum ExportCmd {
+    /// Generate a Dockerfile (and optionally an OCI image of local/)
+    Docker {
+        container: String,
+        /// Directory to write into (defaults to the current directory)
+        #[arg(short, long)] output: Option<PathBuf>,
+        /// Base image instead of the one matching this host's distribution
+        #[arg(long)] base: Option<String>,
+        /// Also write <container>-oci.tar, an OCI image layout of local/
+        #[arg(long)] oci: bool,
+        /// Overwrite existing files
+        #[arg(short, long)] force: bool,
+    },
+    /// Generate .devcontainer/ (devcontainer.json, Dockerfile, container TOML)
+    Devcontainer {
+        container: String,
+        /// Project directory to create .devcontainer/ in (defaults to the current directory)
+        #[arg(short, long)] output: Option<PathBuf>,
+        /// Base image instead of the one matching this host's distribution
+        #[arg(long)] base: Option<String>,
+        /// Overwrite existing files
+        #[arg(short, long)] force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum TemplateCmd {
+    /// List built-in and user templates
+    Ls,
+    /// Show what a template seeds
+    Show { name: String },
+    /// Save a container's packages, env, toolchains and tasks as a template
+    SaveFrom {
+        container: String,
+        /// Template name (defaults to the container name)
+        #[arg(long)] name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum KeysCmd {
+    /// Generate a signing key
+    Generate {
+        #[arg(default_value = "default")] name: String,
+    },
+    /// List your keys and trusted keys
+    List,
+    /// Trust a public key (hex, "ed25519 <hex> [name]" line, or .pub file)
+    Trust {
+        key: String,
+        /// Name to record the key under
+        #[arg(long)] name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum RegistryCmd {
+    /// Serve bundles stored in a directory over plain HTTP
+    Serve {
+        /// Directory holding the registry's bundles and index
+        #[arg(long)] dir: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:7373")] bind: String,
+        /// Token publishers must send (default: $SFC_REGISTRY_TOKEN); without one the registry is read-only
+        #[arg(long)] token: Option<String>,
+    },
+    /// List the name:tag entries in a registry
+    Ls { registry: String },
+}
+
+#[derive(Subcommand, Debug)]
+enum TempCmd {
+    /// List a container's temps (every container's when none is current)
+    Ls { container: Option<String> },
+    /// Open a shell whose PATH and env point at a temp's toolchains
+    Enter {
+        /// Temp name or alias
+        temp: String,
+        #[arg(short = 'c', long)] container: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+enum SnapshotCmd {
+    /// Write a snapshot (tree, modes, symlinks and manifest) to a .tar.zst
+    Export {
+        container: String,
+        /// Snapshot hash (prefix), tag or relative reference
+        hash: String,
+        #[arg(short = 'o', long)] output: PathBuf,
+    },
+    /// Import a snapshot archive into the store, checking its content hash
+    Import {
+        file: PathBuf,
+        /// Name to link it under in links/ (default: <container>-<hash prefix>)
+        #[arg(long)] alias: Option<String>,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 enum HistoryCmd {
     /// Show history log (like git reflog)
@@ -173,10 +437,44 @@ enum HistoryCmd {
 enum FlakeCmd {
     /// Generate flake.nix for current container
     Generate,
-    /// Push container config to GitHub
-    Push { repo: String },
-    /// Pull container config from GitHub
-    Pull { repo: String },
+    /// Pin flake inputs in flake.lock for current container
+    Lock {
+        /// Re-resolve every input, bumping existing pins
+        #[arg(long)] update: bool,
+        /// Set the container's nixpkgs input (e.g. github:NixOS/nixpkgs/nixos-24.05)
+        #[arg(long)] nixpkgs: Option<String>,
+    },
+    /// Push a container's flake and config to a git repository
+    Push {
+        /// Git remote URL or path (e.g. git@github.com:me/envs.git)
+        repo: String,
+        /// Container to push (default: current)
+        #[arg(short = 'c', long)] container: Option<String>,
+        #[arg(long, default_value = "main")] branch: String,
+        /// Overwrite the remote copy even if it changed since the last sync
+        #[arg(short = 'f', long)] force: bool,
+    },
+    /// Pull a container's flake and config from a git repository
+    Pull {
+        /// Git remote URL or path
+        repo: String,
+        /// Container to pull (default: current; omit with none to list the remote)
+        #[arg(short = 'c', long)] container: Option<String>,
+        #[arg(long, default_value = "main")] branch: String,
+        /// Replace local changes made since the last sync
+        #[arg(short = 'f', long)] force: bool,
+    },
+    /// Create a container from the dev shell of an existing flake
+    Import {
+        /// flake.nix, a directory containing one, or a git repository (github:owner/repo, *.git)
+        source: String,
+        /// Name of the new container (default: derived from the source)
+        #[arg(short = 'n', long)] name: Option<String>,
+        /// Dev shell to import (default: devShells.<system>.default)
+        #[arg(long)] shell: Option<String>,
+        /// Only record packages in the config, don't install them
+        #[arg(long)] no_install: bool,
+    },
 }
 
 // No local metadata types; use library implementation
@@ -186,19 +484,33 @@ fn main() -> Result<()> {
     if std::env::var_os("NO_COLOR").is_none() {
         let _ = colored_control::set_override(true);
     }
-    print_banner();
     let cli = Cli::parse();
+    // Hook output is eval'd by the shell, so it must stay free of decoration
+    // (as is an SBOM written to stdout)
+    if !matches!(cli.command, Commands::Hook { .. } | Commands::HookEnv { .. } | Commands::Exec { .. } | Commands::Sbom { output: None, .. } | Commands::Diff { json: true, .. }) {
+        print_banner();
+    }
     match cli.command {
-        Commands::Create { names, from } => cmd_create(&names, from.as_deref()),
-        Commands::Temp { name, node, npm, rust } => cmd_temp(name.as_deref(), node.as_deref(), npm.as_deref(), rust.as_deref()),
-        Commands::Promote { name, temp_alias } => cmd_promote(name.as_deref(), temp_alias.as_deref()),
-        Commands::Discard { name, temp_alias } => cmd_discard(name.as_deref(), temp_alias.as_deref()),
+        Commands::Create { names, from_bundle: Some(bundle), no_install, .. } => cmd_create_from_bundle(&names, &bundle, no_install),
+        Commands::Create { names, from: Some(from), no_install, template: None, detect: false, .. } if registry::is_registry_url(&from) => cmd_create_from_registry(&names, &from, no_install),
+        Commands::Create { names, from, template, no_install, detect, from_bundle: None } => cmd_create(&names, from.as_deref(), template.as_deref(), no_install, detect),
+        Commands::Import { file, container, no_install } => cmd_import(&file, container.as_deref(), no_install),
+        Commands::Apply { file, name, no_install, prune } => cmd_apply(&file, name.as_deref(), no_install, prune),
+        Commands::Template { cmd } => cmd_template(&cmd),
+        Commands::Clone { src, dst } => cmd_clone(&src, &dst),
+        Commands::Rename { old, new } => cmd_rename(&old, &new),
+        Commands::Temp { cmd: Some(TempCmd::Ls { container }), .. } => cmd_temp_ls(container.as_deref()),
+        Commands::Temp { cmd: Some(TempCmd::Enter { temp, container }), .. } => cmd_temp_enter(container.as_deref(), &temp),
+        Commands::Temp { cmd: None, name, temp_name, node, npm, rust } => cmd_temp(name.as_deref(), temp_name.as_deref(), node.as_deref(), npm.as_deref(), rust.as_deref()),
+        Commands::Promote { name, temp_alias, yes } => cmd_promote(name.as_deref(), temp_alias.as_deref(), yes),
+        Commands::Discard { name, temp_alias, yes } => cmd_discard(name.as_deref(), temp_alias.as_deref(), yes),
         Commands::List => cmd_list(),
         Commands::Switch { name, enter } => cmd_switch(name.as_deref(), &enter),
         Commands::Delete { names, force } => cmd_delete(&names, force),
         Commands::Status { name } => cmd_status(name.as_deref()),
         Commands::Clean { age } => cmd_clean(age.as_deref()),
-        Commands::Rollback { name, target } => cmd_rollback(&name, &target),
+        Commands::Bind { name, dir } => cmd_bind(name.as_deref(), dir.as_deref()),
+        Commands::Rollback { name, target, force } => cmd_rollback(&name, &target, force),
         Commands::Toolchain { lang } => cmd_toolchain(lang),
         Commands::Add { package, version } => cmd_add(&package, version.as_deref()),
         Commands::Remove { package } => cmd_remove(&package),
@@ -206,12 +518,23 @@ fn main() -> Result<()> {
         Commands::Packages => cmd_packages(),
         Commands::History { cmd } => cmd_history(&cmd),
         Commands::Flake { cmd } => cmd_flake(&cmd),
+        Commands::Export { cmd } => cmd_export(&cmd),
         Commands::Banner => cmd_banner(),
         Commands::SwitchBin { name, force } => cmd_switch_bin(&name, force),
         Commands::RestoreBin => cmd_restore_bin(),
         Commands::Snapshots { name } => cmd_snapshots(&name),
-        Commands::Share { name, hash } => cmd_share(&name, hash.as_deref()),
+        Commands::Snapshot { cmd } => cmd_snapshot(&cmd),
+        Commands::Tag { container, hash, name } => cmd_tag(&container, &hash, &name),
+        Commands::Diff { container, from, to, json } => cmd_diff(&container, &from, &to, json),
+        Commands::Share { name, hash, bundle, publish, artifacts, key } => cmd_share(&name, hash.as_deref(), bundle.as_deref(), publish.as_deref(), artifacts, key.as_deref()),
+        Commands::Registry { cmd } => cmd_registry(&cmd),
+        Commands::Keys { cmd } => cmd_keys(&cmd),
+        Commands::Sbom { container, hash, format, output } => cmd_sbom(&container, hash.as_deref(), &format, output.as_deref()),
         Commands::DeleteSnapshot { name, hash, force } => cmd_delete_snapshot(&name, &hash, force),
+        Commands::Exec { container, command } => cmd_exec(&container, &command),
+        Commands::Run { task, container, force, jobs } => cmd_run(task.as_deref(), container.as_deref(), force, jobs),
+        Commands::Hook { shell } => cmd_hook(&shell),
+        Commands::HookEnv { shell } => cmd_hook_env(&shell),
     }
 }
 
@@ -307,61 +630,38 @@ fn validate_name(name: &str) -> Result<()> { core::validate_name(name) }
 
 
 
-fn cmd_create(names: &[String], from_hash: Option<&str>) -> Result<()> {
+fn cmd_create(names: &[String], from_hash: Option<&str>, template: Option<&str>, no_install: bool, detect: bool) -> Result<()> {
     if names.is_empty() {
         return Err(anyhow!("no container names provided"));
     }
+    if from_hash.is_some() && (template.is_some() || detect) {
+        return Err(anyhow!("--from cannot be combined with --template or --detect"));
+    }
     let root = workspace_root()?;
+
+    // Configured defaults, with the template (if any) layered on top
+    let settings = SfcConfig::load_global().unwrap_or_default();
+    let mut seed = match template {
+        Some(t) => TemplateStore::new(&root).load(t)?.0,
+        None => Template::default(),
+    }
+    .over_defaults(&settings.defaults);
+
+    // Pinned versions from the project's own files win over template/defaults
+    if detect {
+        let report = ImportReport::detect(&std::env::current_dir()?)?;
+        if report.sources.is_empty() {
+            println!("{}", "No version files found to detect".yellow());
+        }
+        print_import_report(&report);
+        seed.extend(&report.template);
+    }
     
     let mut created_names = Vec::new();
     let mut any_error = false;
     
     for name in names {
-        let create_one = || -> Result<()> {
-            validate_name(name)?;
-            let container_dir = root.join("containers").join(name);
-            if container_dir.exists() {
-                return Err(anyhow!("container '{}' already exists", name));
-            }
-            fs::create_dir_all(container_dir.join("src"))?;
-            fs::create_dir_all(container_dir.join("temp"))?;
-
-            let snapshot_dir = if let Some(hash) = from_hash {
-                // Recreate from existing snapshot hash
-                let hash_short = &hash[..12.min(hash.len())];
-                println!("🔄 {} container '{}' from snapshot {}",
-                        "Recreating".yellow().bold(),
-                        name.cyan(),
-                        hash_short.bright_yellow());
-                
-                recreate_from_snapshot(&root, name, hash)?
-            } else {
-                // Create new snapshot
-                let snapshot_dir = core::create_snapshot_dir(&root, "snapshot-000")?;
-                core::seed_lockfiles(&snapshot_dir)?;
-                
-                // Auto-generate hash for new snapshot
-                let hash = core::compute_snapshot_hash(&snapshot_dir)?;
-                let hash_short = &hash[..12];
-                println!("{} {} at snapshot {}",
-                        "Created container".green(),
-                        name.bold(),
-                        hash_short.bright_yellow());
-                
-                snapshot_dir
-            };
-
-            let alias = format!("{}-stable", name);
-            let rel = Path::new("../store").join(snapshot_dir.file_name().unwrap());
-            core::link_alias_to_store(&root, &alias, &rel)?;
-
-            let container_stable = container_dir.join("stable");
-            core::create_or_update_symlink(Path::new("../../links").join(&alias), &container_stable)?;
-
-            Ok(())
-        };
-
-        if let Err(e) = create_one() {
+        if let Err(e) = create_one(&root, name, from_hash, &seed, no_install) {
             any_error = true;
             eprintln!("{} {}: {}", "Error creating".red(), name, e);
         } else {
@@ -373,10 +673,13 @@ fn cmd_create(names: &[String], from_hash: Option<&str>) -> Result<()> {
     if created_names.len() == 1 {
         core::set_current_container(&created_names[0])?;
         println!("{} {}", "Switched to container".cyan(), created_names[0].bold());
+        if !settings.defaults.auto_enter {
+            return if any_error { Err(anyhow!("one or more containers failed to create")) } else { Ok(()) };
+        }
         
         // Auto-enter the container
         let workspace = workspace_root()?;
-        let container = ContainerConfig::load(&workspace, &created_names[0])?;
+        let container = ContainerConfig::load_effective(&workspace, &created_names[0])?;
         container.enter_shell(&workspace)?;
     }
 
@@ -386,7 +689,206 @@ fn cmd_create(names: &[String], from_hash: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-fn recreate_from_snapshot(root: &Path, container_name: &str, hash: &str) -> Result<PathBuf> {
+/// Create one container: src/temp dirs, a stable snapshot, and (unless recreating) its seeded config
+fn create_one(root: &Path, name: &str, from_hash: Option<&str>, seed: &Template, no_install: bool) -> Result<()> {
+    validate_name(name)?;
+    let container_dir = root.join("containers").join(name);
+    if container_dir.exists() {
+        return Err(anyhow!("container '{}' already exists", name));
+    }
+    fs::create_dir_all(container_dir.join("src"))?;
+    fs::create_dir_all(container_dir.join("temp"))?;
+
+    let snapshot_dir = if let Some(reference) = from_hash {
+        // Recreate from existing snapshot hash (or a tag naming one)
+        let hash = &SnapshotManager::new(root).resolve_any(reference)?;
+        let hash_short = &hash[..12.min(hash.len())];
+        println!("🔄 {} container '{}' from snapshot {}",
+                "Recreating".yellow().bold(),
+                name.cyan(),
+                hash_short.bright_yellow());
+        
+        recreate_from_snapshot(root, name, hash, no_install)?
+    } else {
+        // Create new snapshot
+        let snapshot_dir = core::create_snapshot_dir(root, "snapshot-000")?;
+        core::capture_project_lockfiles(root, name, &snapshot_dir)?;
+        snapshot_dir
+    };
+
+    let alias = format!("{}-stable", name);
+    let rel = Path::new("../store").join(snapshot_dir.file_name().unwrap());
+    core::link_alias_to_store(root, &alias, &rel)?;
+
+    let container_stable = container_dir.join("stable");
+    core::create_or_update_symlink(Path::new("../../links").join(&alias), &container_stable)?;
+
+    if from_hash.is_none() {
+        seed_container(root, name, seed, &snapshot_dir, no_install)?;
+        // The manifest records what was seeded, so hash once it's written
+        SnapshotManager::new(root).record_metadata(name, &snapshot_dir, None)?;
+        let hash = core::compute_snapshot_hash(&snapshot_dir)?;
+        println!("{} {} at snapshot {}",
+                "Created container".green(),
+                name.bold(),
+                (&hash[..12]).bright_yellow());
+    }
+
+    Ok(())
+}
+
+/// Write the seeded config for a new container and install its packages/toolchains
+fn seed_container(root: &Path, name: &str, seed: &Template, snapshot_dir: &Path, no_install: bool) -> Result<()> {
+    let mut config = ContainerConfig::new(name.to_string());
+    seed.apply(&mut config);
+    config.save(root)?;
+    install_packages(root, &mut config, &seed.packages, no_install)?;
+
+    let tool = |key: &str| seed.toolchains.get(key).map(String::as_str);
+    if !no_install && (tool("node").is_some() || tool("npm").is_some() || tool("rust").is_some()) {
+        let pb = ProgressBar::new_spinner();
+        pb.enable_steady_tick(std::time::Duration::from_millis(80));
+        pb.set_style(ProgressStyle::with_template("{spinner:.green} {msg}").unwrap());
+        pb.set_message("Installing toolchains...");
+        let result = core::setup_toolchains(snapshot_dir, tool("node"), tool("npm"), tool("rust"));
+        pb.finish_and_clear();
+        if let Err(e) = result {
+            eprintln!("{} {}", "Toolchain setup failed:".red().bold(), e);
+        }
+    }
+    Ok(())
+}
+
+/// Install package specs into a container, or only record them with `no_install`
+fn install_packages(root: &Path, config: &mut ContainerConfig, specs: &[String], no_install: bool) -> Result<()> {
+    if no_install {
+        for spec in specs {
+            config.add_package(PackageSpec::parse(spec))?;
+        }
+        config.save(root)?;
+        return Ok(());
+    }
+
+    let pkg_mgr = PackageManager::new(root.to_path_buf());
+    for spec in specs {
+        if let Err(e) = pkg_mgr.add_package(config, spec) {
+            eprintln!("{} {}: {}", "Skipping package".yellow(), spec, e);
+        }
+    }
+    Ok(())
+}
+
+fn print_import_report(report: &ImportReport) {
+    for source in &report.sources {
+        println!("🔎 {} {}", "Read".cyan(), source.display());
+    }
+    let seeded = &report.template;
+    if !seeded.toolchains.is_empty() {
+        let mut tools: Vec<_> = seeded.toolchains.iter().map(|(k, v)| format!("{}@{}", k, v)).collect();
+        tools.sort();
+        println!("   {} {}", "Toolchains:".bold(), tools.join(", ").green());
+    }
+    if !seeded.packages.is_empty() {
+        println!("   {} {}", "Packages:".bold(), seeded.packages.join(", ").green());
+    }
+    if !seeded.environment.is_empty() {
+        let mut keys: Vec<_> = seeded.environment.keys().cloned().collect();
+        keys.sort();
+        println!("   {} {}", "Environment:".bold(), keys.join(", ").green());
+    }
+    if !report.unmapped.is_empty() {
+        println!("⚠️  {}", "Not mapped:".yellow().bold());
+        for item in &report.unmapped {
+            println!("   {} {} {}", item.source.dimmed(), item.entry, format!("({})", item.reason).dimmed());
+        }
+    }
+}
+
+fn cmd_import(file: &Path, container: Option<&str>, no_install: bool) -> Result<()> {
+    let root = workspace_root()?;
+    let name = match container {
+        Some(n) => n.to_string(),
+        None => core::current_container()?
+            .ok_or_else(|| anyhow!("no current container selected; use 'sfc switch' to select one"))?,
+    };
+    if !ContainerConfig::exists(&root, &name) {
+        return Err(anyhow!("container '{}' not found", name));
+    }
+
+    let report = ImportReport::from_file(file)?;
+    print_import_report(&report);
+    if report.is_empty() {
+        println!("{}", "Nothing to import".yellow());
+        return Ok(());
+    }
+
+    let mut config = ContainerConfig::load(&root, &name)?;
+    report.template.apply(&mut config);
+    config.save(&root)?;
+    install_packages(&root, &mut config, &report.template.packages, no_install)?;
+    println!("{} {} into {}", "Imported".green(), file.display(), name.bold());
+
+    // Toolchain changes go through a temp snapshot, like `sfc temp --node ...`
+    let tool = |key: &str| report.template.toolchains.get(key).map(String::as_str);
+    if !no_install && (tool("node").is_some() || tool("npm").is_some() || tool("rust").is_some()) {
+        cmd_temp(Some(&name), None, tool("node"), tool("npm"), tool("rust"))?;
+        println!("{}", "Run 'sfc promote' to keep the new toolchains".dimmed());
+    }
+    Ok(())
+}
+
+fn cmd_template(cmd: &TemplateCmd) -> Result<()> {
+    let root = workspace_root()?;
+    let store = TemplateStore::new(&root);
+
+    match cmd {
+        TemplateCmd::Ls => {
+            println!("📋 {}", "Templates".bold());
+            for (name, origin) in store.list()? {
+                let (template, _) = store.load(&name)?;
+                let tag = match origin {
+                    TemplateOrigin::BuiltIn => "built-in",
+                    TemplateOrigin::User => "user",
+                };
+                println!("   {} {} {}", name.cyan().bold(), format!("({})", tag).dimmed(), template.description);
+            }
+        }
+        TemplateCmd::Show { name } => {
+            let (template, _) = store.load(name)?;
+            print!("{}", toml::to_string_pretty(&template)?);
+        }
+        TemplateCmd::SaveFrom { container, name } => {
+            if !ContainerConfig::exists(&root, container) {
+                return Err(anyhow!("container '{}' not found", container));
+            }
+            let config = ContainerConfig::load(&root, container)?;
+            let name = name.as_deref().unwrap_or(container);
+            validate_name(name)?;
+            let path = store.save(name, &Template::from_container(&config))?;
+            println!("{} template {} → {}", "Saved".green(), name.cyan().bold(), path.display().to_string().dimmed());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_clone(src: &str, dst: &str) -> Result<()> {
+    let root = workspace_root()?;
+    let aliases = my_lib::core::clone_container(&root, src, dst)?;
+    println!("{} {} → {}", "Cloned".green(), src.cyan(), dst.cyan().bold());
+    for alias in aliases {
+        println!("   🔗 {}", alias.dimmed());
+    }
+    Ok(())
+}
+
+fn cmd_rename(old: &str, new: &str) -> Result<()> {
+    let root = workspace_root()?;
+    my_lib::core::rename_container(&root, old, new)?;
+    println!("{} {} → {}", "Renamed".green(), old.cyan(), new.cyan().bold());
+    Ok(())
+}
+
+fn recreate_from_snapshot(root: &Path, container_name: &str, hash: &str, no_install: bool) -> Result<PathBuf> {
     // Find the source snapshot
     let source_snapshot = core::find_snapshot_by_hash(root, hash)
         .map_err(|_| anyhow!("Snapshot with hash '{}' not found", &hash[..12.min(hash.len())]))?;
@@ -397,38 +899,27 @@ fn recreate_from_snapshot(root: &Path, container_name: &str, hash: &str) -> Resu
     // Copy all files from source snapshot
     copy_dir_all(&source_snapshot, &new_snapshot_dir)?;
     
-    // Load and recreate the container configuration if it exists
-    let share_info = core::generate_share_info(root, "temp", hash)?;
-    
-    // Create container config
-    let container_config_path = root.join(".sfc").join("containers").join(format!("{}.toml", container_name));
-    let mut container = ContainerConfig::new(container_name.to_string());
-    
-    // Add packages from the shared snapshot
-    for package in &share_info.packages {
-        use my_lib::container::{PackageSpec, PackageSource};
-        let spec = PackageSpec {
-            name: package.name.clone(),
-            version: package.version.clone(),
-            channel: Some("stable".to_string()),
-            source: match package.source.as_str() {
-                "github" => PackageSource::GitHub { 
-                    repo: "unknown/unknown".to_string(), 
-                    rev: "main".to_string() 
-                },
-                "url" => PackageSource::Url("unknown".to_string()),
-                _ => PackageSource::Nixpkgs,
-            },
-        };
-        container.add_package(spec)?;
-    }
-    
+    // Packages, toolchains and env come from the container the snapshot belongs to
+    let workspace = WorkspaceManager::new(root)?;
+    let mut container = match ShareManager::new(&workspace).share_info_for_hash(hash) {
+        Ok(share_info) => share_info.to_container(container_name),
+        Err(_) => {
+            eprintln!("{} no container links to this snapshot; recreating its files only", "Warning:".yellow());
+            ContainerConfig::new(container_name.to_string())
+        }
+    };
     container.save(root)?;
+
+    if !no_install {
+        for (spec, e) in PackageManager::new(root.to_path_buf()).install_all(&mut container) {
+            eprintln!("{} {}: {}", "Skipping package".yellow(), spec, e);
+        }
+    }
     
     println!("📦 {} {} packages and {} toolchains", 
             "Recreated".green(),
-            share_info.packages.len().to_string().cyan(),
-            share_info.toolchains.len().to_string().cyan());
+            container.packages.len().to_string().cyan(),
+            container.toolchains.len().to_string().cyan());
     
     Ok(new_snapshot_dir)
 }
@@ -449,7 +940,7 @@ fn copy_dir_all(src: &Path, dst: &Path) -> Result<()> {
 
 // delegations removed in favor of library module
 
-fn cmd_temp(name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Option<&str>) -> Result<()> {
+fn cmd_temp(name: Option<&str>, temp_name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Option<&str>) -> Result<()> {
     let name = match name {
         Some(n) => n.to_string(),
         None => match core::current_container()? {
@@ -459,13 +950,36 @@ fn cmd_temp(name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Opt
     };
     validate_name(&name)?;
     let root = workspace_root()?;
-    let alias = format!("{}-temp-{}", name, monotonic_suffix());
+    let taken = |temp: &str| fs::symlink_metadata(root.join("links").join(core::temp_alias(&name, temp))).is_ok();
+    let temp_name = match temp_name {
+        Some(temp_name) => {
+            validate_name(temp_name)?;
+            if taken(temp_name) {
+                return Err(anyhow!("{} already has a temp named '{}'", name, temp_name));
+            }
+            temp_name.to_string()
+        }
+        None => {
+            // Two unnamed temps in the same second would share a timestamp
+            let stamp = monotonic_suffix();
+            let mut temp_name = stamp.clone();
+            let mut n = 2;
+            while taken(&temp_name) {
+                temp_name = format!("{}-{}", stamp, n);
+                n += 1;
+            }
+            temp_name
+        }
+    };
+    let alias = core::temp_alias(&name, &temp_name);
     let temp_snapshot = core::create_snapshot_dir(&root, "snapshot-temp")?;
     // Copy lockfiles from stable snapshot into temp snapshot
     let stable_snapshot = core::resolve_stable_snapshot(&root, &name)?;
     core::copy_lockfiles(&stable_snapshot, &temp_snapshot)?;
+    print_captured(&core::capture_project_lockfiles(&root, &name, &temp_snapshot)?);
 
     // Optional toolchain setup inside this snapshot
+    let mut pinned = Vec::new();
     if node.is_some() || npm.is_some() || rust.is_some() {
         let pb = ProgressBar::new_spinner();
         pb.enable_steady_tick(std::time::Duration::from_millis(80));
@@ -474,7 +988,11 @@ fn cmd_temp(name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Opt
         let result = core::setup_toolchains(&temp_snapshot, node, npm, rust);
         pb.finish_and_clear();
         match result {
-            Ok(()) => println!("{}", "Toolchains installed in temp snapshot".green()),
+            Ok(()) => {
+                println!("{}", "Toolchains installed in temp snapshot".green());
+                pinned.extend(node.map(|v| ("node", v)));
+                pinned.extend(rust.map(|v| ("rust", v)));
+            }
             Err(e) => {
                 eprintln!("{} {}", "Toolchain setup failed:".red().bold(), e);
                 eprintln!("{}", "Proceeding without toolchain installs".yellow());
@@ -482,80 +1000,187 @@ fn cmd_temp(name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Opt
         }
     }
 
+    let parent = core::compute_snapshot_hash(&stable_snapshot)?;
+    let mut metadata = SnapshotManager::new(&root).record_metadata(&name, &temp_snapshot, Some(parent))?;
+    // Record the versions this temp pins so `sfc temp enter` can select them
+    if !pinned.is_empty() {
+        for (toolchain, version) in &pinned {
+            fs::write(temp_snapshot.join(format!("{}_version", toolchain)), format!("{}\n", version))?;
+            metadata.toolchains.insert(toolchain.to_string(), version.to_string());
+        }
+        metadata.save(&temp_snapshot)?;
+    }
+
     // link alias in links/
     let rel = Path::new("../store").join(temp_snapshot.file_name().unwrap());
     core::link_alias_to_store(&root, &alias, &rel)?;
     println!("{} {} -> {}", "Temp created".green(), name.bold(), alias.cyan());
+    println!("   Enter it with {}", format!("sfc temp enter {} -c {}", temp_name, name).cyan());
     Ok(())
 }
 
+fn print_captured(files: &[String]) {
+    if !files.is_empty() {
+        println!("{} {}", "Captured lockfiles:".dimmed(), files.join(", "));
+    }
+}
+
 fn monotonic_suffix() -> String {
     // timestamp-based suffix for readability
     let now = chrono::Utc::now();
     now.format("%Y%m%d%H%M%S").to_string()
 }
 
-fn cmd_promote(name: Option<&str>, temp_alias: Option<&str>) -> Result<()> {
-    let name = match name {
-        Some(n) => n.to_string(),
+fn container_or_current(name: Option<&str>) -> Result<String> {
+    match name {
+        Some(n) => Ok(n.to_string()),
+        None => core::current_container()?
+            .ok_or_else(|| anyhow!("no current container selected; use 'sfc switch' to select one")),
+    }
+}
+
+fn cmd_temp_ls(container: Option<&str>) -> Result<()> {
+    let root = workspace_root()?;
+    let containers = match container {
+        Some(c) => vec![c.to_string()],
         None => match core::current_container()? {
-            Some(current) => current,
-            None => return Err(anyhow!("no current container selected; use 'sfc switch' to select one")),
-        }
+            Some(current) => vec![current],
+            None => core::list_containers()?,
+        },
     };
+    let mut any = false;
+    for container in &containers {
+        let temps = core::list_temps(&root, container)?;
+        if temps.is_empty() {
+            continue;
+        }
+        any = true;
+        println!("🧪 {} {}", "Temps for".bold().green(), container.cyan().bold());
+        for temp in temps {
+            let hash = core::compute_snapshot_hash(&temp.path)?;
+            let created = temp.created.map(|t| t.format("%Y-%m-%d %H:%M:%S").to_string()).unwrap_or_default();
+            let toolchains = my_lib::core::diff::SnapshotContents::load(&temp.path)?
+                .toolchains
+                .iter()
+                .map(|(k, v)| format!("{}@{}", k, v))
+                .collect::<Vec<_>>()
+                .join(", ");
+            println!("   {} {} {} {}",
+                    temp.name.bold(),
+                    (&hash[..12]).bright_yellow(),
+                    created.dimmed(),
+                    toolchains.bright_blue());
+        }
+    }
+    if !any {
+        println!("{}", "No temps; create one with: sfc temp --name <name>".dimmed());
+    }
+    Ok(())
+}
+
+fn cmd_temp_enter(container: Option<&str>, temp: &str) -> Result<()> {
+    let container = container_or_current(container)?;
     let root = workspace_root()?;
-    let chosen = match temp_alias {
-        Some(alias) => alias.to_string(),
-        None => core::find_latest_temp_alias(&root, &name)?.ok_or_else(|| anyhow!("no temp snapshots for {}", name))?,
-    };
-    let link_path = root.join("links").join(&chosen);
-    if !link_path.exists() {
-        return Err(anyhow!("temp alias not found: {}", chosen));
+    let temp = core::resolve_temp(&root, &container, Some(temp))?;
+    let shell = core::temp_shell_env(&root, &container, &temp)?;
+    for missing in &shell.missing {
+        println!("{} {} is pinned but not installed; install it with sfc toolchain", "⚠️".yellow(), missing.yellow());
     }
+    if !shell.toolchains.is_empty() {
+        let pins: Vec<String> = shell.toolchains.iter().map(|(k, v)| format!("{}@{}", k, v)).collect();
+        println!("{} {}", "Toolchains:".dimmed(), pins.join(", ").bright_blue());
+    }
+    let config = ContainerConfig::load_effective(&root, &container)?;
+    config.enter_shell_with(&root, &format!("{}:{}", container, temp.name), &shell.env, &shell.bins)
+}
+
+/// Show what replacing `old` with `new` changes and ask before going ahead
+fn confirm_change(question: &str, old: Option<&Path>, new: &Path, yes: bool) -> Result<bool> {
+    if let Some(old) = old {
+        let (old_hash, new_hash) = (core::compute_snapshot_hash(old)?, core::compute_snapshot_hash(new)?);
+        let diff = SnapshotDiff::between(&old_hash, old, &new_hash, new)?;
+        if diff.is_empty() {
+            println!("   {}", "No differences from stable".dimmed());
+        } else {
+            print_diff(&diff, false);
+        }
+    }
+    if yes {
+        return Ok(true);
+    }
+    print!("{} [y/N]: ", question);
+    let _ = std::io::stdout().flush();
+    let mut input = String::new();
+    std::io::stdin().read_line(&mut input)?;
+    let input = input.trim().to_lowercase();
+    Ok(input == "y" || input == "yes")
+}
+
+fn cmd_promote(name: Option<&str>, temp_alias: Option<&str>, yes: bool) -> Result<()> {
+    let name = container_or_current(name)?;
+    let root = workspace_root()?;
+    let temp = core::resolve_temp(&root, &name, temp_alias)?;
+    let chosen = temp.alias.clone();
+    let link_path = root.join("links").join(&chosen);
 
     let new_target_rel = fs::read_link(&link_path)?; // ../store/<dir>
     // Compute change summary and generation hash info
     let old_stable = root.join("links").join(format!("{}-stable", name));
     let old_target_rel = fs::read_link(&old_stable).ok();
     let old_abs = old_target_rel.as_ref().and_then(|rel| old_stable.parent().map(|p| p.join(rel))).and_then(|p| p.canonicalize().ok());
-    let new_abs = old_stable.parent().unwrap().join(&new_target_rel).canonicalize()?;
+    let new_abs = temp.path.clone();
     let old_hash = old_abs.as_ref().and_then(|p| core::compute_snapshot_hash(p).ok());
+    // The temp picks up whatever the project's lockfiles became while it was
+    // in use, but only once the promote is confirmed; show that on a copy
+    let (preview, captured) = core::preview_project_lockfiles(&root, &name, &new_abs)?;
+    println!("⬆️  {} temp {} of {}", "Promoting".bold(), temp.name.cyan(), name.bold());
+    let confirmed = confirm_change(&format!("Make {} the stable snapshot of {}?", temp.name, name), old_abs.as_deref(), &preview, yes);
+    let _ = fs::remove_dir_all(&preview);
+    if !confirmed? {
+        println!("{} Left {} as it was", "✋".yellow(), name);
+        return Ok(());
+    }
+    core::capture_project_lockfiles(&root, &name, &new_abs)?;
+    print_captured(&captured);
     let new_hash = core::compute_snapshot_hash(&new_abs)?;
     let msg = core::build_change_message(old_abs.as_deref(), &new_abs, old_hash.as_deref(), &new_hash)?;
 
     // Update stable alias atomically (stow or symlink)
     let new_rel = new_target_rel;
     core::link_alias_to_store(&root, &format!("{}-stable", name), &new_rel)?;
+    // The temp lives on as stable; its own alias would only clutter `sfc temp ls`
+    core::unlink_alias_from_links(&root, &chosen)?;
     println!("{}", msg);
     println!("{} {} -> {}", "Promoted".green(), name.bold(), chosen.cyan());
     Ok(())
 }
 
-fn cmd_discard(name: Option<&str>, temp_alias: Option<&str>) -> Result<()> {
-    let name = match name {
-        Some(n) => n.to_string(),
-        None => match core::current_container()? {
-            Some(current) => current,
-            None => return Err(anyhow!("no current container selected; use 'sfc switch' to select one")),
-        }
-    };
+fn cmd_discard(name: Option<&str>, temp_alias: Option<&str>, yes: bool) -> Result<()> {
+    let name = container_or_current(name)?;
     let root = workspace_root()?;
-    let alias = match temp_alias {
-        Some(a) => a.to_string(),
-        None => core::find_latest_temp_alias(&root, &name)?.ok_or_else(|| anyhow!("no temp snapshots for {}", name))?,
+    let temp = match core::resolve_temp(&root, &name, temp_alias) {
+        Ok(temp) => temp,
+        Err(_) if temp_alias.is_none() && core::list_temps(&root, &name)?.is_empty() => {
+            println!("{}", "Nothing to discard".yellow());
+            return Ok(());
+        }
+        Err(e) => return Err(e),
     };
+    let alias = temp.alias.clone();
     let link = root.join("links").join(&alias);
-    if link.exists() {
-        let target_rel = fs::read_link(&link).ok();
-        core::unlink_alias_from_links(&root, &alias)?;
-        if let Some(target_rel) = target_rel {
-            // if no other links point to this snapshot, we can remove it
-            core::try_remove_store_if_orphan(&root, &target_rel)?;
-        }
-        println!("{} {}", "Discarded temp".yellow(), alias.cyan());
-    } else {
-        println!("{}", "Nothing to discard".yellow());
+    println!("🗑️  {} temp {} of {}", "Discarding".bold(), temp.name.cyan(), name.bold());
+    let stable = core::resolve_stable_snapshot(&root, &name).ok();
+    if !confirm_change(&format!("Throw away these changes in {}?", temp.name), stable.as_deref(), &temp.path, yes)? {
+        println!("{} Kept {}", "✋".yellow(), temp.name);
+        return Ok(());
     }
+    let target_rel = fs::read_link(&link).ok();
+    core::unlink_alias_from_links(&root, &alias)?;
+    if let Some(target_rel) = target_rel {
+        // if no other links point to this snapshot, we can remove it
+        core::try_remove_store_if_orphan(&root, &target_rel)?;
+    }
+    println!("{} {}", "Discarded temp".yellow(), alias.cyan());
     Ok(())
 }
 
@@ -741,6 +1366,14 @@ fn cmd_delete(names: &[String], force: bool) -> Result<()> {
             }
         }
         
+        // Remove project binding
+        let binding = root.join(".sfc").join("projects").join(name);
+        if binding.exists() {
+            if let Err(e) = std::fs::remove_file(&binding) {
+                deletion_errors.push(format!("project binding: {}", e));
+            }
+        }
+        
         // Remove stable link
         let stable_link = root.join("links").join(format!("{}-stable", name));
         if stable_link.exists() {
@@ -803,7 +1436,7 @@ fn cmd_switch(name: Option<&str>, enter: &bool) -> Result<()> {
             if *enter {
                 // Load container and enter shell
                 let workspace = core::workspace_root()?;
-                let container = ContainerConfig::load(&workspace, n)?;
+                let container = ContainerConfig::load_effective(&workspace, n)?;
                 container.enter_shell(&workspace)?;
             } else {
                 println!("\nTo enter the container shell, run:");
@@ -839,7 +1472,7 @@ fn cmd_switch(name: Option<&str>, enter: &bool) -> Result<()> {
                     
                     if *enter {
                         let workspace = core::workspace_root()?;
-                        let container = ContainerConfig::load(&workspace, selected)?;
+                        let container = ContainerConfig::load_effective(&workspace, selected)?;
                         container.enter_shell(&workspace)?;
                     } else {
                         println!("\nTo enter the container shell, run:");
@@ -882,37 +1515,78 @@ fn cmd_status(name: Option<&str>) -> Result<()> {
             name.cyan().bold(), 
             target.display().to_string().dimmed());
     
-    // Load container config to show packages
-    if let Ok(container) = ContainerConfig::load(&root, &name) {
+    // Show the effective config: own settings merged with everything inherited
+    let raw = ContainerConfig::load(&root, &name)?;
+    let container = raw.effective(&root)?;
+    let ancestors: Vec<ContainerConfig> = container.extends.iter()
+        .map(|base| ContainerConfig::load(&root, base))
+        .collect::<Result<_>>()?;
+    // Which container a package/variable/toolchain comes from, if not this one
+    let origin = |is_own: &dyn Fn(&ContainerConfig) -> bool| -> String {
+        if is_own(&raw) {
+            return String::new();
+        }
+        ancestors.iter()
+            .find(|a| is_own(a))
+            .map(|a| format!(" (from {})", a.name))
+            .unwrap_or_default()
+    };
+
+    if !container.extends.is_empty() {
         println!("");
-        println!("📦 {} ({})", 
-                "Installed packages".bold(),
-                container.packages.len().to_string().cyan().bold());
-        
-        if container.packages.is_empty() {
-            println!("   {} - try {} to add packages", 
-                    "No packages installed".dimmed(),
-                    "sfc add <package>".cyan());
-        } else {
-            for (i, pkg) in container.packages.iter().take(5).enumerate() {
-                let version = pkg.version.as_ref()
-                    .map(|v| format!("@{}", v))
-                    .unwrap_or_else(|| "@latest".to_string());
-                println!("   {} {} {} {}", 
-                        format!("{}.", i + 1).dimmed(),
-                        "❄️",
-                        pkg.name.cyan(),
-                        version.bright_blue());
-            }
-            if container.packages.len() > 5 {
-                println!("   {} and {} more packages", 
-                        "...".dimmed(),
-                        (container.packages.len() - 5).to_string().yellow());
-                println!("   Use {} to see all", "sfc packages".cyan());
-            }
+        println!("🧬 {} {}", "Extends".bold(), container.extends.join(" → ").cyan());
+    }
+
+    println!("");
+    println!("📦 {} ({})", 
+            "Installed packages".bold(),
+            container.packages.len().to_string().cyan().bold());
+
+    if container.packages.is_empty() {
+        println!("   {} - try {} to add packages", 
+                "No packages installed".dimmed(),
+                "sfc add <package>".cyan());
+    } else {
+        for (i, pkg) in container.packages.iter().take(5).enumerate() {
+            let version = pkg.version.as_ref()
+                .map(|v| format!("@{}", v))
+                .unwrap_or_else(|| "@latest".to_string());
+            let from = origin(&|c: &ContainerConfig| c.packages.iter().any(|p| p.name == pkg.name));
+            println!("   {} {} {} {}{}", 
+                    format!("{}.", i + 1).dimmed(),
+                    "❄️",
+                    pkg.name.cyan(),
+                    version.bright_blue(),
+                    from.dimmed());
+        }
+        if container.packages.len() > 5 {
+            println!("   {} and {} more packages", 
+                    "...".dimmed(),
+                    (container.packages.len() - 5).to_string().yellow());
+            println!("   Use {} to see all", "sfc packages".cyan());
         }
     }
-    
+
+    if !container.toolchains.is_empty() {
+        println!("");
+        println!("🔧 {}", "Toolchains".bold());
+        for (tool, version) in &container.toolchains {
+            let from = origin(&|c: &ContainerConfig| c.toolchains.contains_key(tool));
+            println!("   {} {}{}", tool.cyan(), version.bright_blue(), from.dimmed());
+        }
+    }
+
+    let mut env_keys: Vec<&String> = container.environment.keys().filter(|k| *k != "PATH").collect();
+    if !env_keys.is_empty() {
+        env_keys.sort();
+        println!("");
+        println!("🌱 {}", "Environment".bold());
+        for key in env_keys {
+            let from = origin(&|c: &ContainerConfig| c.environment.contains_key(key));
+            println!("   {}={}{}", key.cyan(), container.environment[key], from.dimmed());
+        }
+    }
+
     // List temporary environments
     let prefix = format!("{}-temp-", name);
     let mut temp_count = 0;
@@ -997,27 +1671,69 @@ fn cmd_clean(_age: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-fn cmd_rollback(name: &str, target: &str) -> Result<()> {
+fn cmd_bind(name: Option<&str>, dir: Option<&Path>) -> Result<()> {
+    let name = container_or_current(name)?;
     let root = workspace_root()?;
-    // target can be a commit-ish or a direct snapshot name. Here we accept direct snapshot dir name under store/
-    let candidate = root.join("store").join(target);
-    if !candidate.exists() {
-        return Err(anyhow!("target snapshot not found in store/: {}", target));
+    if !core::list_containers()?.contains(&name) {
+        return Err(anyhow!("Container '{}' not found", name));
     }
+    let cwd = std::env::current_dir()?;
+    let dir = match dir {
+        Some(dir) => dir.to_path_buf(),
+        None => default_project_dir(&name, &cwd),
+    };
+    if !dir.is_dir() {
+        return Err(anyhow!("{} is not a directory", dir.display()));
+    }
+    let bound = bind_project(&root, &name, &dir)?;
+    println!("{} {} -> {}", "Bound".green(), name.bold(), bound.display().to_string().cyan());
+    Ok(())
+}
+
+fn cmd_rollback(name: &str, target: &str, force: bool) -> Result<()> {
+    let root = workspace_root()?;
+    // target is a snapshot dir name under store/, or a hash, tag or relative reference
+    let candidate = root.join("store").join(target);
+    let new_abs = if candidate.is_dir() {
+        candidate.canonicalize()?
+    } else {
+        let manager = SnapshotManager::new(&root);
+        let hash = manager.resolve(name, target).map_err(|e| match e {
+            SfcError::NotFound { .. } => anyhow!("target snapshot not found: {}", target),
+            e => e.into(),
+        })?;
+        manager.find_snapshot_by_hash(&hash)?
+    };
     // Compute change summary and generation hash info
     let stable_alias = root.join("links").join(format!("{}-stable", name));
     let old_target_rel = fs::read_link(&stable_alias).ok();
     let old_abs = old_target_rel.as_ref().and_then(|rel| stable_alias.parent().map(|p| p.join(rel))).and_then(|p| p.canonicalize().ok());
-    let new_abs = root.join("store").join(target).canonicalize()?;
     let old_hash = old_abs.as_ref().and_then(|p| core::compute_snapshot_hash(p).ok());
     let new_hash = core::compute_snapshot_hash(&new_abs)?;
     let msg = core::build_change_message(old_abs.as_deref(), &new_abs, old_hash.as_deref(), &new_hash)?;
 
     let alias = format!("{}-stable", name);
-    let rel = Path::new("../store").join(target);
+    let dir_name = new_abs.file_name().unwrap();
+    let rel = Path::new("../store").join(dir_name);
     core::link_alias_to_store(&root, &alias, &rel)?;
     println!("{}", msg);
-    println!("{} {} -> {}", "Rolled back".green(), name.bold(), target.cyan());
+
+    // Put the project's lockfiles back the way the target snapshot had them
+    if let Some(project) = core::container_project_dir(&root, name)? {
+        let restored = restore_lockfiles(&new_abs, &project, old_abs.as_deref(), force)?;
+        for file in &restored.restored {
+            println!("  {} {}", "restored".green(), file);
+        }
+        for file in &restored.removed {
+            println!("  {} {}", "removed".red(), file);
+        }
+        for file in &restored.kept {
+            println!("  {} {} (changed since the last snapshot; use --force to overwrite)", "kept".yellow(), file);
+        }
+    } else {
+        println!("{}", "No project directory; lockfiles left alone (bind one with: sfc bind)".dimmed());
+    }
+    println!("{} {} -> {}", "Rolled back".green(), name.bold(), dir_name.to_string_lossy().cyan());
     Ok(())
 }
 
@@ -1135,10 +1851,17 @@ fn cmd_history(cmd: &HistoryCmd) -> Result<()> {
 
 fn cmd_flake(cmd: &FlakeCmd) -> Result<()> {
     let workspace = workspace_root()?;
+    if let FlakeCmd::Push { repo, container, branch, force } | FlakeCmd::Pull { repo, container, branch, force } = cmd {
+        let pull = matches!(cmd, FlakeCmd::Pull { .. });
+        return cmd_flake_remote(&workspace, repo, container.clone(), branch, *force, pull);
+    }
+    if let FlakeCmd::Import { source, name, shell, no_install } = cmd {
+        return cmd_flake_import(&workspace, source, name.as_deref(), shell.as_deref(), *no_install);
+    }
     let current_container = core::current_container()?
         .ok_or_else(|| anyhow!("no current container selected; use 'sfc switch' to select one"))?;
     
-    let container = ContainerConfig::load(&workspace, &current_container)?;
+    let container = ContainerConfig::load_effective(&workspace, &current_container)?;
     
     match cmd {
         FlakeCmd::Generate => {
@@ -1146,15 +1869,268 @@ fn cmd_flake(cmd: &FlakeCmd) -> Result<()> {
             flake.save(&workspace, &current_container)?;
             println!("{} flake.nix for container {}", "Generated".green(), current_container.cyan());
             println!("Location: ~/.sfc/containers/{}/flake.nix", current_container);
+            let unlocked = flake.unlocked_inputs(&workspace, &current_container)?;
+            if !unlocked.is_empty() {
+                println!("{} {} {}", "Unpinned inputs:".yellow(), unlocked.join(", "),
+                    "(run 'sfc flake lock' to pin them)".dimmed());
+            }
         }
-        FlakeCmd::Push { repo } => {
-            println!("{} pushing to {}", "TODO:".yellow(), repo.cyan());
-            // TODO: Implement GitHub push
+        FlakeCmd::Lock { update, nixpkgs } => {
+            let mut container = container;
+            if let Some(reference) = nixpkgs {
+                FlakeRef::parse(reference)?;
+                let mut raw = ContainerConfig::load(&workspace, &current_container)?;
+                raw.nixpkgs = Some(reference.clone());
+                raw.save(&workspace)?;
+                container.nixpkgs = Some(reference.clone());
+                println!("📌 {} nixpkgs to {}", "Pinned".green(), reference.cyan());
+            }
+
+            let pb = ProgressBar::new_spinner();
+            pb.enable_steady_tick(Duration::from_millis(80));
+            pb.set_message("Resolving flake inputs...");
+            let changes = container.to_flake().lock(&workspace, &current_container, *update);
+            pb.finish_and_clear();
+            let changes = changes?;
+
+            if changes.is_empty() {
+                println!("{} flake.lock for {} is up to date", "✓".green(), current_container.cyan());
+            }
+            for change in &changes {
+                match (&change.old, &change.new) {
+                    (None, Some(new)) => println!("  {} {} {}", "+".green(), change.input, new.dimmed()),
+                    (Some(old), Some(new)) => println!("  {} {} {} → {}", "~".yellow(), change.input, old.dimmed(), new),
+                    (Some(old), None) => println!("  {} {} {}", "-".red(), change.input, old.dimmed()),
+                    (None, None) => {}
+                }
+            }
+            println!("Location: ~/.sfc/containers/{}/flake.lock", current_container);
         }
-        FlakeCmd::Pull { repo } => {
-            println!("{} pulling from {}", "TODO:".yellow(), repo.cyan());
-            // TODO: Implement GitHub pull
+        FlakeCmd::Push { .. } | FlakeCmd::Pull { .. } | FlakeCmd::Import { .. } => unreachable!("handled above"),
+    }
+    Ok(())
+}
+
+/// Container name for an imported flake: the directory or repository name
+fn import_name(source: &str) -> String {
+    let trimmed = source.trim_end_matches('/').trim_end_matches("/flake.nix");
+    let last = trimmed.rsplit(['/', ':']).next().unwrap_or(trimmed);
+    let last = last.split('?').next().unwrap_or(last).trim_end_matches(".git");
+    let name: String = last.chars().map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' }).collect();
+    if name.trim_matches('-').is_empty() { "imported".to_string() } else { name }
+}
+
+fn cmd_flake_import(workspace: &Path, source: &str, name: Option<&str>, shell: Option<&str>, no_install: bool) -> Result<()> {
+    let local = Path::new(source);
+    let checkout = if local.exists() {
+        local.to_path_buf()
+    } else {
+        let pb = ProgressBar::new_spinner();
+        pb.enable_steady_tick(Duration::from_millis(80));
+        pb.set_message(format!("Cloning {}...", source));
+        let result = checkout_source(workspace, source);
+        pb.finish_and_clear();
+        result?
+    };
+    let import = FlakeImport::from_path(&checkout, shell)?;
+    let name = name.map(String::from).unwrap_or_else(|| {
+        let base = if local.exists() { fs::canonicalize(local).unwrap_or_else(|_| local.to_path_buf()).display().to_string() } else { source.to_string() };
+        import_name(&base)
+    });
+
+    print_import_report(&import.report);
+    let others = if import.other_shells.is_empty() { String::new() } else { format!(" (also: {})", import.other_shells.join(", ")) };
+    println!("   {} {}{}", "Shell:".bold(), import.shell.green(), others.dimmed());
+
+    let settings = SfcConfig::load_global().unwrap_or_default();
+    let seed = import.report.template.over_defaults(&settings.defaults);
+    create_one(workspace, &name, None, &seed, no_install)?;
+
+    // Keep the flake's nixpkgs and any pins of inputs that carry over
+    if let Some(nixpkgs) = &import.nixpkgs {
+        let mut raw = ContainerConfig::load(workspace, &name)?;
+        raw.nixpkgs = Some(nixpkgs.clone());
+        raw.save(workspace)?;
+    }
+    let source_dir = if checkout.is_dir() { checkout.clone() } else { checkout.parent().map(Path::to_path_buf).unwrap_or_default() };
+    let lock = source_dir.join("flake.lock");
+    if lock.is_file() {
+        fs::copy(&lock, workspace.join("containers").join(&name).join("flake.lock"))
+            .with_context(|| format!("copying {}", lock.display()))?;
+    }
+    let flake = ContainerConfig::load_effective(workspace, &name)?.to_flake();
+    flake.save(workspace, &name)?;
+
+    println!("{} {} into {}", "Imported".green(), source, name.bold());
+    let unlocked = flake.unlocked_inputs(workspace, &name)?;
+    if !unlocked.is_empty() {
+        println!("{} {} {}", "Unpinned inputs:".yellow(), unlocked.join(", "),
+            "(run 'sfc flake lock' to pin them)".dimmed());
+    }
+    println!("{}", format!("Run 'sfc switch {}' to use it", name).dimmed());
+    Ok(())
+}
+
+fn cmd_flake_remote(workspace: &Path, repo: &str, container: Option<String>, branch: &str, force: bool, pull: bool) -> Result<()> {
+    let remote = FlakeRemote::new(workspace, repo, Some(branch));
+    let name = match container.or(core::current_container()?) {
+        Some(name) => name,
+        None if pull => {
+            let names = remote.list()?;
+            println!("📚 {} {}", "Containers in".bold(), repo.cyan());
+            for name in &names {
+                println!("  • {}", name.cyan());
+            }
+            if names.is_empty() {
+                println!("  {}", "(none)".dimmed());
+            }
+            println!("💡 Pull one with {}", format!("sfc flake pull {} -c <name>", repo).cyan());
+            return Ok(());
+        }
+        None => return Err(anyhow!("no container given and none selected; use -c <name>")),
+    };
+
+    if pull {
+        match remote.pull(&name, force)? {
+            PullOutcome::UpToDate => println!("{} {} matches {}", "✓".green(), name.cyan(), repo),
+            PullOutcome::LocalAhead => println!("{} local {} is ahead of {} (push to update it)", "↑".yellow(), name.cyan(), repo),
+            PullOutcome::Updated { commit, created } => {
+                let verb = if created { "Created" } else { "Updated" };
+                println!("⬇️  {} {} from {} {}", verb.green(), name.cyan(), repo, format!("({})", &commit[..12]).dimmed());
+                let config = workspace.join(".sfc").join("containers").join(format!("{}.toml", name));
+                println!("💡 Run {} to install its packages", format!("sfc apply {}", config.display()).cyan());
+            }
+        }
+    } else {
+        match remote.push(&name, force)? {
+            PushOutcome::UpToDate => println!("{} {} already matches {}", "✓".green(), name.cyan(), repo),
+            PushOutcome::Pushed { commit } => {
+                println!("⬆️  {} {} to {} {}", "Pushed".green(), name.cyan(), repo, format!("({}:containers/{})", &commit[..12], name).dimmed());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_export(cmd: &ExportCmd) -> Result<()> {
+    let root = workspace_root()?;
+    match cmd {
+        ExportCmd::Docker { container, output, base, oci, force } => {
+            if !ContainerConfig::exists(&root, container) {
+                return Err(anyhow!("container '{}' not found", container));
+            }
+            let config = ContainerConfig::load_effective(&root, container)?;
+            let snapshot = core::get_current_snapshot_hash(&root, container).ok();
+            let out_dir = output.clone().unwrap_or_else(|| PathBuf::from("."));
+            fs::create_dir_all(&out_dir)?;
+
+            let dockerfile_path = out_dir.join("Dockerfile");
+            let oci_path = out_dir.join(format!("{}-oci.tar", container));
+            for path in std::iter::once(&dockerfile_path).chain(oci.then_some(&oci_path)) {
+                if path.exists() && !force {
+                    return Err(anyhow!("{} already exists (use --force to overwrite)", path.display()));
+                }
+            }
+
+            let exporter = DockerExporter::new(&root);
+            let export = exporter.dockerfile(&config, base.as_deref(), snapshot.as_deref());
+            fs::write(&dockerfile_path, &export.dockerfile)?;
+            println!("🐳 {} {} {}", "Wrote".green(), dockerfile_path.display(), format!("(FROM {})", export.base_image).dimmed());
+
+            if *oci {
+                let digest = exporter.write_oci_layout(&config, snapshot.as_deref(), &oci_path)?;
+                println!("📦 {} {} {}", "Wrote".green(), oci_path.display(), digest.dimmed());
+                println!("   {}", format!("Load with: skopeo copy oci-archive:{} docker-daemon:{}:latest", oci_path.display(), container).dimmed());
+            }
+
+            for note in &export.notes {
+                println!("   {} {}", "note:".yellow(), note);
+            }
+        }
+        ExportCmd::Devcontainer { container, output, base, force } => {
+            if !ContainerConfig::exists(&root, container) {
+                return Err(anyhow!("container '{}' not found", container));
+            }
+            let config = ContainerConfig::load_effective(&root, container)?;
+            let snapshot = core::get_current_snapshot_hash(&root, container).ok();
+            let export = DevcontainerExporter::new(&root).export(&config, base.as_deref(), snapshot.as_deref())?;
+
+            let dir = output.clone().unwrap_or_else(|| PathBuf::from(".")).join(".devcontainer");
+            let files = [
+                (dir.join("devcontainer.json"), &export.devcontainer_json),
+                (dir.join("Dockerfile"), &export.dockerfile),
+                (dir.join(&export.config_file), &export.config_toml),
+            ];
+            if let Some((path, _)) = files.iter().find(|(path, _)| path.exists() && !force) {
+                return Err(anyhow!("{} already exists (use --force to overwrite)", path.display()));
+            }
+            fs::create_dir_all(&dir)?;
+            for (path, content) in &files {
+                fs::write(path, content)?;
+                println!("🧰 {} {}", "Wrote".green(), path.display());
+            }
+            for note in &export.notes {
+                println!("   {} {}", "note:".yellow(), note);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_apply(file: &Path, name: Option<&str>, no_install: bool, prune: bool) -> Result<()> {
+    let root = workspace_root()?;
+    let content = fs::read_to_string(file)
+        .with_context(|| format!("reading {}", file.display()))?;
+    let desired: ContainerConfig = toml::from_str(&content)
+        .with_context(|| format!("parsing {}", file.display()))?;
+    let name = name.unwrap_or(&desired.name).to_string();
+    validate_name(&name)?;
+    if let Some(base) = desired.extends.iter().find(|base| !ContainerConfig::exists(&root, base)) {
+        return Err(anyhow!("'{}' extends '{}' which does not exist", name, base));
+    }
+
+    if !ContainerConfig::exists(&root, &name) {
+        create_one(&root, &name, None, &Template::default(), true)?;
+    }
+    let mut config = ContainerConfig::load(&root, &name)?;
+    let toolchains_changed = config.toolchains != desired.toolchains;
+
+    // Everything except packages is taken from the file as-is
+    config.apply_settings(&desired);
+    config.save(&root)?;
+
+    let have: Vec<String> = config.packages.iter().map(PackageSpec::to_spec_string).collect();
+    let want: Vec<String> = desired.packages.iter().map(PackageSpec::to_spec_string).collect();
+    let missing: Vec<String> = want.iter().filter(|spec| !have.contains(spec)).cloned().collect();
+    let extra: Vec<String> = config
+        .packages
+        .iter()
+        .filter(|pkg| !desired.packages.iter().any(|d| d.name == pkg.name))
+        .map(|pkg| pkg.name.clone())
+        .collect();
+    install_packages(&root, &mut config, &missing, no_install)?;
+
+    if prune {
+        let pkg_mgr = PackageManager::new(root.clone());
+        for pkg in &extra {
+            if no_install {
+                config.remove_package(pkg)?;
+                config.save(&root)?;
+            } else {
+                pkg_mgr.remove_package(&mut config, pkg)?;
+            }
         }
+    } else if !extra.is_empty() {
+        println!("{} {} {}", "Not in file:".yellow(), extra.join(", "), "(use --prune to remove)".dimmed());
+    }
+
+    println!("{} {} from {} {}", "Applied".green(), name.bold(), file.display(),
+             format!("({} package(s) added)", missing.len()).dimmed());
+
+    let tool = |key: &str| config.toolchains.get(key).map(String::as_str);
+    if toolchains_changed && !no_install && (tool("node").is_some() || tool("npm").is_some() || tool("rust").is_some()) {
+        cmd_temp(Some(&name), None, tool("node"), tool("npm"), tool("rust"))?;
+        println!("{}", "Run 'sfc promote' to keep the new toolchains".dimmed());
     }
     Ok(())
 }
@@ -1337,6 +2313,8 @@ fn cmd_snapshots(name: &str) -> Result<()> {
         return Ok(());
     }
 
+    let tags = TagStore::new(&workspace).for_container(name)?;
+
     println!("");
     for (i, snapshot) in snapshots.iter().enumerate() {
         let status_icon = if snapshot.is_active { "🎯" } else { "📸" };
@@ -1344,24 +2322,144 @@ fn cmd_snapshots(name: &str) -> Result<()> {
         let hash_display = hash_str.bright_yellow();
         let time_str = snapshot.timestamp.format("%Y-%m-%d %H:%M:%S").to_string();
         let time_display = time_str.dimmed();
+        let names: Vec<&str> = tags.iter().filter(|(_, hash)| **hash == snapshot.hash).map(|(tag, _)| tag.as_str()).collect();
+        let tag_display = if names.is_empty() { String::new() } else { format!(" ({})", names.join(", ")) };
         
-        println!("   {} {} {} {} {}", 
+        println!("   {} {} {} {} {}{}", 
                 format!("{:2}.", i + 1).dimmed(),
                 status_icon,
                 hash_display,
                 time_display,
-                snapshot.description.cyan());
+                snapshot.description.cyan(),
+                tag_display.magenta());
+
+        let mut details = Vec::new();
+        if let Some(user) = &snapshot.created_by {
+            details.push(format!("by {}", user));
+        }
+        if let Some(parent) = &snapshot.parent {
+            details.push(format!("from {}", &parent[..12.min(parent.len())]));
+        }
+        if !snapshot.toolchains.is_empty() {
+            let toolchains: Vec<String> = snapshot.toolchains.iter().map(|(tool, version)| format!("{} {}", tool, version)).collect();
+            details.push(toolchains.join(", "));
+        }
+        if !snapshot.packages.is_empty() {
+            let packages: Vec<&str> = snapshot.packages.iter().map(|p| p.name.as_str()).collect();
+            details.push(format!("{} package(s): {}", packages.len(), packages.join(", ")));
+        }
+        if !details.is_empty() {
+            println!("        {}", details.join(" · ").dimmed());
+        }
     }
     
     println!("");
-    println!("💡 Use {} to share or {} to recreate", 
+    println!("💡 Use {} to share, {} to name or {} to recreate", 
             "sfc share".cyan(), 
-            "sfc create --from <hash>".cyan());
+            "sfc tag".cyan(),
+            "sfc create --from <hash|container:tag>".cyan());
     
     Ok(())
 }
 
-fn cmd_share(name: &str, hash: Option<&str>) -> Result<()> {
+fn cmd_tag(container: &str, reference: &str, name: &str) -> Result<()> {
+    let root = workspace_root()?;
+    if !ContainerConfig::exists(&root, container) {
+        return Err(anyhow!("container '{}' not found", container));
+    }
+    let hash = SnapshotManager::new(&root).resolve(container, reference)?;
+    let previous = TagStore::new(&root).tag(container, name, &hash)?;
+    println!("🏷️  {} {} {} as {}", "Tagged".green().bold(), container.cyan(), (&hash[..12]).bright_yellow(), name.magenta().bold());
+    if let Some(previous) = previous.filter(|p| *p != hash) {
+        println!("   {}", format!("moved from {}", &previous[..12.min(previous.len())]).dimmed());
+    }
+    Ok(())
+}
+
+fn cmd_diff(container: &str, from: &str, to: &str, json: bool) -> Result<()> {
+    let root = workspace_root()?;
+    if !ContainerConfig::exists(&root, container) {
+        return Err(anyhow!("container '{}' not found", container));
+    }
+    let snapshots = SnapshotManager::new(&root);
+    let (from, to) = (snapshots.resolve(container, from)?, snapshots.resolve(container, to)?);
+    let diff = SnapshotDiff::between(&from, &snapshots.find_snapshot_by_hash(&from)?, &to, &snapshots.find_snapshot_by_hash(&to)?)?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&diff)?);
+        return Ok(());
+    }
+
+    println!("🔍 {} {} {} → {}", "Diff".bold().green(), container.cyan().bold(), (&from[..12]).bright_yellow(), (&to[..12]).bright_yellow());
+    if diff.is_empty() {
+        println!("   {}", "No differences".dimmed());
+        return Ok(());
+    }
+    print_diff(&diff, true);
+    Ok(())
+}
+
+/// Print a snapshot diff; lockfiles get their full unified diff only when `lockfile_diffs`
+fn print_diff(diff: &SnapshotDiff, lockfile_diffs: bool) {
+    for (title, changes) in [("Packages", &diff.packages), ("Toolchains", &diff.toolchains), ("Environment", &diff.environment)] {
+        if changes.is_empty() {
+            continue;
+        }
+        println!();
+        println!("{}", title.bold());
+        for change in changes {
+            let (old, new) = (change.old.as_deref().unwrap_or_default(), change.new.as_deref().unwrap_or_default());
+            match change.kind {
+                ChangeKind::Added => println!("   {} {} {}", "+".green(), change.name.green(), new.dimmed()),
+                ChangeKind::Removed => println!("   {} {} {}", "-".red(), change.name.red(), old.dimmed()),
+                ChangeKind::Changed => println!("   {} {} {} → {}", "~".yellow(), change.name.yellow(), old.dimmed(), new),
+            }
+        }
+    }
+    for lockfile in &diff.lockfiles {
+        println!();
+        println!("{} {}", lockfile.file.bold(), format!("(+{} -{})", lockfile.added, lockfile.removed).dimmed());
+        if !lockfile_diffs {
+            continue;
+        }
+        for line in lockfile.diff.lines() {
+            if line.starts_with("+++") || line.starts_with("---") {
+                println!("{}", line.bold());
+            } else if line.starts_with('+') {
+                println!("{}", line.green());
+            } else if line.starts_with('-') {
+                println!("{}", line.red());
+            } else if line.starts_with("@@") {
+                println!("{}", line.cyan());
+            } else {
+                println!("{}", line);
+            }
+        }
+    }
+}
+
+fn cmd_sbom(container: &str, hash: Option<&str>, format: &str, output: Option<&Path>) -> Result<()> {
+    let root = workspace_root()?;
+    if !ContainerConfig::exists(&root, container) {
+        return Err(anyhow!("container '{}' not found", container));
+    }
+    let format: SbomFormat = format.parse()?;
+    let hash = hash.map(|h| SnapshotManager::new(&root).resolve(container, h)).transpose()?;
+    let config = ContainerConfig::load_effective(&root, container)?;
+    let inventory = Inventory::collect(&root, &config, hash.as_deref())?;
+    let json = serde_json::to_string_pretty(&inventory.render(format, chrono::Utc::now()))?;
+
+    match output {
+        Some(path) => {
+            fs::write(path, json + "\n").with_context(|| format!("writing {}", path.display()))?;
+            println!("📋 {} {} {}", "Wrote".green(), path.display(),
+                format!("({} components, {})", inventory.components.len(), format).dimmed());
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
+
+fn cmd_share(name: &str, hash: Option<&str>, bundle: Option<&Path>, publish: Option<&str>, artifacts: bool, key: Option<&str>) -> Result<()> {
     let workspace = workspace_root()?;
     let containers = core::list_containers()?;
     
@@ -1369,21 +2467,24 @@ fn cmd_share(name: &str, hash: Option<&str>) -> Result<()> {
         return Err(anyhow!("❌ Container '{}' not found", name));
     }
 
-    let snapshot_hash = match hash {
-        Some(h) => h.to_string(),
-        None => {
-            // Use current stable snapshot
-            core::get_current_snapshot_hash(&workspace, name)?
-        }
+    let manager = WorkspaceManager::new(&workspace)?;
+    let hash = hash.map(|h| SnapshotManager::new(&workspace).resolve(name, h)).transpose()?;
+    let hash = hash.as_deref();
+    let signer = match bundle.is_some() || publish.is_some() {
+        true => KeyStore::new(&workspace).signer(key, &manager.config.sharing)?,
+        false => None,
     };
+    let signed_by = signer.as_ref().map(|k| k.public());
+    let share_manager = ShareManager::new(&manager).with_signer(signer);
+    // Defaults to the current stable snapshot
+    let share_info = share_manager.generate_share_info(name, hash)?;
+    let snapshot_hash = &share_info.hash;
 
     let hash_short = &snapshot_hash[..12];
     println!("🔗 {} snapshot {} for container '{}'",
             "Sharing".yellow().bold(),
             hash_short.bright_yellow(),
             name.cyan().bold());
-
-    let share_info = core::generate_share_info(&workspace, name, &snapshot_hash)?;
     
     println!("");
     println!("📋 {} this command to recreate the environment:", "Share".green().bold());
@@ -1392,7 +2493,12 @@ fn cmd_share(name: &str, hash: Option<&str>) -> Result<()> {
     println!("");
     println!("📦 {} packages in this snapshot:", "Included".blue());
     for package in &share_info.packages {
-        println!("   • {} {}", package.name.cyan(), package.version.as_deref().unwrap_or("latest").dimmed());
+        let detail = match &package.source {
+            PackageSource::Nixpkgs => package.version.clone().unwrap_or_else(|| "latest".to_string()),
+            PackageSource::GitHub { repo, rev } => format!("github:{}@{}", repo, rev),
+            PackageSource::Url(url) => url.clone(),
+        };
+        println!("   • {} {}", package.name.cyan(), detail.dimmed());
     }
     
     if !share_info.toolchains.is_empty() {
@@ -1402,10 +2508,169 @@ fn cmd_share(name: &str, hash: Option<&str>) -> Result<()> {
             println!("   • {} {}", toolchain.cyan(), version.dimmed());
         }
     }
+
+    if !share_info.environment.is_empty() {
+        let mut keys: Vec<_> = share_info.environment.keys().cloned().collect();
+        keys.sort();
+        println!("");
+        println!("🌍 {} environment: {}", "Included".blue(), keys.join(", "));
+    }
+
+    if let Some(path) = bundle {
+        let manifest = share_manager.export_bundle(name, Some(snapshot_hash), path, artifacts)?;
+        println!("");
+        println!("📦 {} {} ({} files)", "Wrote bundle".green().bold(), path.display(), manifest.files.len());
+        match &signed_by {
+            Some(key) => println!("   🔏 signed by {} {}", key.name.cyan(), key.fingerprint().dimmed()),
+            None => println!("   {}", "unsigned (run 'sfc keys generate' to sign bundles)".dimmed()),
+        }
+        println!("   {}", format!("sfc create {} --from-bundle {}", name, path.display()).on_bright_black().white());
+    }
+
+    if let Some(url) = publish {
+        let (client, registry_name, tag) = registry::publish_target(url, name)?;
+        // Without --bundle the bundle only needs to live long enough to upload
+        let path = match bundle {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let dir = workspace.join(".sfc").join("cache").join("registry");
+                fs::create_dir_all(&dir)?;
+                let path = dir.join(format!("{}-{}.sfcb", name, hash_short));
+                share_manager.export_bundle(name, Some(snapshot_hash), &path, artifacts)?;
+                path
+            }
+        };
+        let data = fs::read(&path).with_context(|| format!("reading {}", path.display()))?;
+        if bundle.is_none() {
+            let _ = fs::remove_file(&path);
+        }
+        let entry = client.publish(&registry_name, &tag, &data)?;
+        let url = client.url_for(&registry::BundleRef::Tag { name: entry.name, tag: entry.tag });
+        println!();
+        println!("🚀 {} {}", "Published".green().bold(), url);
+        if bundle.is_none() {
+            match &signed_by {
+                Some(key) => println!("   🔏 signed by {} {}", key.name.cyan(), key.fingerprint().dimmed()),
+                None => println!("   {}", "unsigned (run 'sfc keys generate' to sign bundles)".dimmed()),
+            }
+        }
+        println!("   {}", format!("sfc create {} --from {}", name, url).on_bright_black().white());
+    }
     
     Ok(())
 }
 
+fn cmd_snapshot(cmd: &SnapshotCmd) -> Result<()> {
+    let manager = SnapshotManager::new(workspace_root()?);
+    match cmd {
+        SnapshotCmd::Export { container, hash, output } => {
+            let header = manager.export_snapshot(container, hash, output)?;
+            let size = fs::metadata(output).map(|m| m.len()).unwrap_or(0);
+            println!("{} {} {} to {} ({} bytes)",
+                    "Exported".green(),
+                    container.bold(),
+                    (&header.hash[..12]).bright_yellow(),
+                    output.display(),
+                    size);
+        }
+        SnapshotCmd::Import { file, alias } => {
+            let imported = manager.import_snapshot(file, alias.as_deref())?;
+            let hash = &imported.header.hash[..12];
+            if imported.reused {
+                println!("{} {} is already in the store", "Snapshot".dimmed(), hash.bright_yellow());
+            }
+            println!("{} {} from {} as {}",
+                    "Imported".green(),
+                    hash.bright_yellow(),
+                    imported.header.container.bold(),
+                    format!("links/{}", imported.alias).cyan());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_registry(cmd: &RegistryCmd) -> Result<()> {
+    match cmd {
+        RegistryCmd::Serve { dir, bind, token } => {
+            let token = token.clone().or_else(|| std::env::var(registry::TOKEN_ENV).ok());
+            let server = RegistryServer::new(RegistryStore::new(dir)?).with_token(token.clone());
+            let listener = std::net::TcpListener::bind(bind).with_context(|| format!("binding {}", bind))?;
+            println!("{} {} on {}", "Serving registry".green().bold(), dir.display(), bind.cyan());
+            if token.as_deref().unwrap_or_default().is_empty() {
+                println!("   {}", "No --token given: publishing is disabled".yellow());
+            }
+            server.serve(listener, |line| println!("   {}", line.dimmed()))?;
+        }
+        RegistryCmd::Ls { registry: url } => {
+            let (client, _) = registry::RegistryClient::parse(url)?;
+            let entries = client.list()?;
+            if entries.is_empty() {
+                println!("{}", "No bundles published".dimmed());
+            }
+            for entry in entries {
+                println!("{}:{} {}", entry.name.cyan(), entry.tag, (&entry.hash[..12]).dimmed());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cmd_keys(cmd: &KeysCmd) -> Result<()> {
+    let store = KeyStore::new(&workspace_root()?);
+    match cmd {
+        KeysCmd::Generate { name } => {
+            let key = store.generate(name)?;
+            println!("{} key {} {}", "Generated".green(), key.name.cyan().bold(), key.fingerprint().dimmed());
+            println!("   Others can trust it with:");
+            println!("   {}", format!("sfc keys trust '{}'", key.to_line()).on_bright_black().white());
+        }
+        KeysCmd::List => {
+            println!("🔑 {}", "Your keys".bold());
+            for key in store.list()? {
+                println!("   {} {} {}", key.name.cyan().bold(), key.fingerprint().dimmed(), key.key);
+            }
+            println!("🤝 {}", "Trusted keys".bold());
+            for key in store.trusted()? {
+                println!("   {} {} {}", key.name.cyan().bold(), key.fingerprint().dimmed(), key.key);
+            }
+        }
+        KeysCmd::Trust { key, name } => {
+            let key = store.trust(key, name.as_deref())?;
+            println!("{} {} {}", "Trusted".green(), key.name.cyan().bold(), key.fingerprint().dimmed());
+        }
+    }
+    Ok(())
+}
+
+fn cmd_create_from_bundle(names: &[String], bundle: &Path, no_install: bool) -> Result<()> {
+    let [name] = names else {
+        return Err(anyhow!("--from-bundle creates exactly one container"));
+    };
+    validate_name(name)?;
+    let root = workspace_root()?;
+    let manager = ShareManager::new(&WorkspaceManager::new(&root)?);
+    let (hash, verdict) = manager.import_bundle(bundle, name)?;
+    println!("{} {} from {} (snapshot {}, {})", "Created container".green(), name.bold(), bundle.display(), &hash[..12], verdict.describe());
+    // Bundles made with --artifacts already carry the installed packages
+    let packages_dir = root.join("containers").join(name).join("packages");
+    let has_artifacts = fs::read_dir(&packages_dir).map(|mut d| d.next().is_some()).unwrap_or(false);
+    if !no_install && !has_artifacts {
+        let mut config = ContainerConfig::load(&root, name)?;
+        for (spec, e) in PackageManager::new(root.clone()).install_all(&mut config) {
+            eprintln!("{} {}: {}", "Skipping package".yellow(), spec, e);
+        }
+    }
+    core::set_current_container(name)?;
+    println!("{} {}", "Switched to container".cyan(), name.bold());
+    Ok(())
+}
+
+fn cmd_create_from_registry(names: &[String], url: &str, no_install: bool) -> Result<()> {
+    let bundle = registry::download(&workspace_root()?, url)?;
+    println!("{} {}", "Fetched".green(), url);
+    cmd_create_from_bundle(names, &bundle, no_install)
+}
+
 fn cmd_delete_snapshot(name: &str, hash: &str, force: bool) -> Result<()> {
     let workspace = workspace_root()?;
     let containers = core::list_containers()?;
@@ -1414,9 +2679,11 @@ fn cmd_delete_snapshot(name: &str, hash: &str, force: bool) -> Result<()> {
         return Err(anyhow!("❌ Container '{}' not found", name));
     }
 
+    let hash = &SnapshotManager::new(&workspace).resolve(name, hash)?;
+
     // Check if trying to delete active snapshot
     let current_hash = core::get_current_snapshot_hash(&workspace, name)?;
-    if hash.starts_with(&current_hash[..hash.len()]) && !force {
+    if *hash == current_hash && !force {
         return Err(anyhow!("❌ Cannot delete active snapshot '{}'. Use --force to override or switch to another snapshot first", &hash[..12.min(hash.len())]));
     }
 
@@ -1441,8 +2708,87 @@ fn cmd_delete_snapshot(name: &str, hash: &str, force: bool) -> Result<()> {
     println!("🗑️  {} snapshot {}", "Deleting".yellow().bold(), hash_short.red());
     
     core::delete_snapshot(&workspace, name, hash)?;
+    let untagged = TagStore::new(&workspace).remove_hash(name, hash)?;
     
     println!("{} Snapshot deleted successfully", "✅".green());
+    if !untagged.is_empty() {
+        println!("   {}", format!("removed tag(s): {}", untagged.join(", ")).dimmed());
+    }
     
     Ok(())
 }
+
+fn cmd_exec(name: &str, command: &[String]) -> Result<()> {
+    let workspace = workspace_root()?;
+    if !core::list_containers()?.contains(&name.to_string()) {
+        return Err(anyhow!("container '{}' not found", name));
+    }
+    let container = ContainerConfig::load_effective(&workspace, name)?;
+
+    // exec only returns if the program could not be started
+    let err = container.exec(&workspace, command);
+    eprintln!("sfc exec: {}: {}", command[0], err);
+    std::process::exit(spawn_error_code(&err));
+}
+
+fn cmd_run(task: Option<&str>, container: Option<&str>, force: bool, jobs: Option<usize>) -> Result<()> {
+    let workspace = workspace_root()?;
+    let cwd = std::env::current_dir()?;
+    let marker = find_marker(&cwd);
+
+    let name = match (container, &marker) {
+        (Some(name), _) => name.to_string(),
+        (None, Some((_, bound))) => bound.clone(),
+        (None, None) => core::current_container()?
+            .ok_or_else(|| anyhow!("no container specified and no current container set"))?,
+    };
+    let project_root = match &marker {
+        Some((dir, bound)) if *bound == name => dir.clone(),
+        _ => cwd,
+    };
+    let config = ContainerConfig::load_effective(&workspace, &name)?;
+
+    let Some(task) = task else {
+        if config.tasks.is_empty() {
+            println!("{} No tasks defined for container '{}'", "ℹ️".blue(), name);
+        }
+        for (task_name, spec) in &config.tasks {
+            println!("  {} {}", task_name.cyan(), spec.command.dimmed());
+        }
+        return Ok(());
+    };
+
+    let mut runner = TaskRunner::new(config, workspace, project_root).with_force(force);
+    if let Some(jobs) = jobs {
+        runner = runner.with_jobs(jobs);
+    }
+    match runner.run(task) {
+        Ok(_) => Ok(()),
+        // The runner already reported which task failed; pass its exit code through
+        Err(SfcError::Command { exit_code: Some(code), .. }) => std::process::exit(code),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn cmd_hook(shell: &str) -> Result<()> {
+    let kind: ShellKind = shell.parse()?;
+    let exe = std::env::current_exe()?;
+    print!("{}", kind.init_script(&exe.to_string_lossy()));
+    Ok(())
+}
+
+fn cmd_hook_env(shell: &str) -> Result<()> {
+    let kind: ShellKind = shell.parse()?;
+    // Runs at every prompt: look the workspace up, never create it
+    let workspace = core::default_workspace()?;
+    if !workspace.join(".sfc").is_dir() {
+        return Ok(());
+    }
+    let cwd = std::env::current_dir()?;
+    let current_env: std::collections::HashMap<String, String> = std::env::vars().collect();
+    let script = hook_env(kind, &workspace, &cwd, &current_env)?;
+    if !script.is_empty() {
+        println!("{}", script);
+    }
+    Ok(())
+}
@@ -23,8 +23,19 @@ fn main() {
     
     // Run the application
     if let Err(error) = run_app(cli) {
-        print_error(&error);
-        process::exit(1);
+        match &error {
+            // Commands run on the user's behalf keep their own exit code
+            SfcError::Command { exit_code: Some(code), stderr, .. } => {
+                if !stderr.is_empty() {
+                    print_error(&error);
+                }
+                process::exit(*code);
+            }
+            _ => {
+                print_error(&error);
+                process::exit(1);
+            }
+        }
     }
 }
 
@@ -43,8 +54,29 @@ fn run_app(cli: Cli) -> Result<()> {
     // Dispatch commands
     match cli.command {
         // Container management
-        Commands::Create { names, from } => {
-            my_lib::cli::handlers::handle_create(&workspace, &names, from.as_deref())
+        Commands::Create { names, from_bundle: Some(bundle), no_install, .. } => {
+            my_lib::cli::handlers::handle_create_from_bundle(&workspace, &names, &bundle, no_install)
+        }
+        Commands::Create { names, from: Some(from), no_install, template: None, detect: false, .. } if my_lib::sharing::registry::is_registry_url(&from) => {
+            my_lib::cli::handlers::handle_create_from_registry(&workspace, &names, &from, no_install)
+        }
+        Commands::Create { names, from, template, no_install, detect, from_bundle: None } => {
+            my_lib::cli::handlers::handle_create(&workspace, &names, from.as_deref(), template.as_deref(), no_install, detect)
+        }
+        Commands::Import { file, container } => {
+            my_lib::cli::handlers::handle_import(&workspace, &file, container.as_deref())
+        }
+        Commands::Apply { file, name, no_install, prune } => {
+            my_lib::cli::handlers::handle_apply(&workspace, &file, name.as_deref(), no_install, prune)
+        }
+        Commands::Template { cmd } => {
+            my_lib::cli::handlers::handle_template(&workspace, cmd)
+        }
+        Commands::Clone { src, dst } => {
+            my_lib::cli::handlers::handle_clone(&workspace, &src, &dst)
+        }
+        Commands::Rename { old, new } => {
+            my_lib::cli::handlers::handle_rename(&workspace, &old, &new)
         }
         Commands::List => {
             my_lib::cli::handlers::handle_list(&workspace)
@@ -91,8 +123,26 @@ fn run_app(cli: Cli) -> Result<()> {
         Commands::Snapshots { name } => {
             my_lib::cli::handlers::handle_snapshots(&workspace, &name)
         }
-        Commands::Share { name, hash } => {
-            my_lib::cli::handlers::handle_share(&workspace, &name, hash.as_deref())
+        Commands::Snapshot { cmd } => {
+            my_lib::cli::handlers::handle_snapshot(&workspace, cmd)
+        }
+        Commands::Diff { container, from, to, json } => {
+            my_lib::cli::handlers::handle_diff(&workspace, &container, &from, &to, json)
+        }
+        Commands::Tag { container, hash, name } => {
+            my_lib::cli::handlers::handle_tag(&workspace, &container, &hash, &name)
+        }
+        Commands::Share { name, hash, bundle, publish, artifacts, key } => {
+            my_lib::cli::handlers::handle_share(&workspace, &name, hash.as_deref(), bundle.as_deref(), publish.as_deref(), artifacts, key.as_deref())
+        }
+        Commands::Registry { cmd } => {
+            my_lib::cli::handlers::handle_registry(&workspace, cmd)
+        }
+        Commands::Keys { cmd } => {
+            my_lib::cli::handlers::handle_keys(&workspace, cmd)
+        }
+        Commands::Sbom { container, hash, format, output } => {
+            my_lib::cli::handlers::handle_sbom(&workspace, &container, hash.as_deref(), &format, output)
         }
         Commands::DeleteSnapshot { name, hash, force } => {
             my_lib::cli::handlers::handle_delete_snapshot(&workspace, &name, &hash, force)
@@ -120,6 +170,9 @@ fn run_app(cli: Cli) -> Result<()> {
         Commands::Flake { cmd } => {
             my_lib::cli::handlers::handle_flake(&workspace, cmd)
         }
+        Commands::Export { cmd } => {
+            my_lib::cli::handlers::handle_export(&workspace, cmd)
+        }
         
         // Maintenance
         Commands::Clean { age } => {
@@ -138,6 +191,20 @@ fn run_app(cli: Cli) -> Result<()> {
         Commands::Shell { container, command, keep } => {
             my_lib::cli::handlers::handle_shell(&workspace, container.as_deref(), command.as_deref(), keep)
         }
+        Commands::Exec { container, command } => {
+            my_lib::cli::handlers::handle_exec(&workspace, &container, &command)
+        }
+        Commands::Run { task, container, force, jobs } => {
+            my_lib::cli::handlers::handle_run(&workspace, task.as_deref(), container.as_deref(), force, jobs)
+        }
+        
+        // Shell integration
+        Commands::Hook { shell } => {
+            my_lib::cli::handlers::handle_hook(&shell)
+        }
+        Commands::HookEnv { shell } => {
+            my_lib::cli::handlers::handle_hook_env(&workspace, &shell)
+        }
     }
 }
 
@@ -158,8 +225,8 @@ fn initialize_workspace(cli: &Cli, config: &SfcConfig) -> Result<WorkspaceManage
     
     let workspace = WorkspaceManager::new(workspace_path)?;
     
-    // Auto-initialize if configured to do so
-    if config.workspace.auto_init {
+    // Auto-initialize if configured to do so; the prompt hook only ever reads
+    if config.workspace.auto_init && !matches!(cli.command, Commands::HookEnv { .. }) {
         workspace.ensure_initialized()?;
     }
     
@@ -170,6 +237,9 @@ fn should_print_banner(command: &Commands) -> bool {
     match command {
         Commands::Banner => false, // Banner command handles its own output
         Commands::Config { .. } => false, // Config should be minimal
+        Commands::Hook { .. } | Commands::HookEnv { .. } => false, // Output is eval'd by the shell
+        Commands::Exec { .. } => false, // stdout belongs to the executed command
+        Commands::Diff { json: true, .. } => false, // JSON output must stay parseable
         _ => true,
     }
 }
@@ -1,4 +1,5 @@
-use clap::{Parser, Subcommand};
+use std::path::PathBuf;
+use clap::{ArgGroup, Parser, Subcommand};
 
 #[derive(Parser, Debug)]
 #[command(name = "sfc", version, about = "Suffix-container CLI (symlink-based environment management)")]
@@ -24,8 +25,52 @@ pub enum Commands {
     /// Create one or more containers
     Create {
         names: Vec<String>,
-        #[arg(long, help = "Recreate from snapshot hash")]
+        #[arg(long, help = "Recreate from snapshot hash or a registry (registry://host/name:tag)")]
         from: Option<String>,
+        #[arg(short = 't', long, help = "Seed packages, env, toolchains and tasks from a template")]
+        template: Option<String>,
+        #[arg(long, help = "Only record seeded packages/toolchains; don't install them")]
+        no_install: bool,
+        #[arg(long, help = "Also seed from version files in the current directory")]
+        detect: bool,
+        #[arg(long, conflicts_with_all = ["from", "template", "detect"], help = "Recreate from a bundle written by 'sfc share --bundle'")]
+        from_bundle: Option<PathBuf>,
+    },
+
+    /// Create or update a container so it matches a container TOML file
+    Apply {
+        file: PathBuf,
+        #[arg(long, help = "Container name (defaults to the name in the file)")]
+        name: Option<String>,
+        #[arg(long, help = "Only record packages/toolchains; don't install them")]
+        no_install: bool,
+        #[arg(long, help = "Remove packages that aren't in the file")]
+        prune: bool,
+    },
+
+    /// Import toolchains and packages from a version file (.tool-versions, mise.toml, ...)
+    Import {
+        file: PathBuf,
+        #[arg(short, long, help = "Container to import into (defaults to the current one)")]
+        container: Option<String>,
+    },
+
+    /// Manage container templates
+    Template {
+        #[command(subcommand)]
+        cmd: TemplateCmd,
+    },
+
+    /// Fork a container: config, history and tree are copied, snapshots are shared
+    Clone {
+        src: String,
+        dst: String,
+    },
+
+    /// Rename a container and everything that refers to it
+    Rename {
+        old: String,
+        new: String,
     },
 
     /// Open a temp environment (uses current container if name not provided)
@@ -120,6 +165,12 @@ pub enum Commands {
         cmd: FlakeCmd,
     },
 
+    /// Export a container for use outside sfc
+    Export {
+        #[command(subcommand)]
+        cmd: ExportCmd,
+    },
+
     /// Switch system binaries to use container binaries (requires sudo)
     SwitchBin {
         name: String,
@@ -135,10 +186,67 @@ pub enum Commands {
         name: String 
     },
 
+    /// Export a snapshot to a compressed tarball or import one
+    Snapshot {
+        #[command(subcommand)]
+        cmd: SnapshotCmd,
+    },
+
+    /// Compare two snapshots: packages, toolchains, env and lockfiles
+    Diff {
+        container: String,
+        #[arg(help = "Older snapshot (hash prefix, tag or relative reference)")]
+        from: String,
+        #[arg(help = "Newer snapshot")]
+        to: String,
+        #[arg(long, help = "Print the differences as JSON")]
+        json: bool,
+    },
+
+    /// Name a snapshot; tags work anywhere a hash does, as do `stable~2`-style references
+    Tag {
+        container: String,
+        #[arg(help = "Snapshot hash (prefix), tag or relative reference")]
+        hash: String,
+        name: String,
+    },
+
     /// Share a container snapshot for others to recreate
+    #[command(group = ArgGroup::new("output").args(["bundle", "publish"]).multiple(true))]
     Share {
         name: String,
         hash: Option<String>,
+        #[arg(long, help = "Also write a self-contained bundle (.sfcb) to this path")]
+        bundle: Option<PathBuf>,
+        #[arg(long, help = "Publish the bundle to a registry (registry://host[:port][/name:tag])")]
+        publish: Option<String>,
+        #[arg(long, requires = "output", help = "Include installed package artifacts in the bundle")]
+        artifacts: bool,
+        #[arg(long, requires = "output", help = "Sign with this key (default: sharing.signing_key, then the \"default\" key)")]
+        key: Option<String>,
+    },
+
+    /// Run or query a share bundle registry
+    Registry {
+        #[command(subcommand)]
+        cmd: RegistryCmd,
+    },
+
+    /// Manage signing keys and trusted public keys for shares
+    Keys {
+        #[command(subcommand)]
+        cmd: KeysCmd,
+    },
+
+    /// Write a software bill of materials for a container or snapshot
+    Sbom {
+        container: String,
+        #[arg(long, help = "Snapshot hash (prefix) to describe instead of the live container")]
+        hash: Option<String>,
+        #[arg(long, default_value = "cyclonedx", help = "cyclonedx or spdx")]
+        format: String,
+        #[arg(short = 'o', long, help = "Output file (default: stdout)")]
+        output: Option<PathBuf>,
     },
 
     /// Delete a specific snapshot
@@ -164,11 +272,47 @@ pub enum Commands {
         keep: bool,
     },
 
+    /// Run a command inside a container environment without a shell
+    Exec {
+        /// Container whose environment to use
+        container: String,
+        /// Command and arguments (after `--`)
+        #[arg(trailing_var_arg = true, allow_hyphen_values = true, required = true)]
+        command: Vec<String>,
+    },
+
+    /// Run a task from the container's [tasks] table (lists tasks when omitted)
+    Run {
+        /// Task to run, along with its dependencies
+        task: Option<String>,
+        /// Container to use (defaults to the directory binding or current container)
+        #[arg(short, long)]
+        container: Option<String>,
+        /// Ignore cached results
+        #[arg(short, long)]
+        force: bool,
+        /// Maximum number of tasks to run in parallel
+        #[arg(short, long)]
+        jobs: Option<usize>,
+    },
+
     /// Show configuration information
     Config {
         #[command(subcommand)]
         cmd: Option<ConfigCmd>,
     },
+
+    /// Print shell integration for auto-activating containers (eval "$(sfc hook bash)")
+    Hook {
+        /// Shell to generate the hook for (bash, zsh, fish)
+        shell: String,
+    },
+
+    /// Emit activation code for the current directory (used by `sfc hook`)
+    #[command(hide = true)]
+    HookEnv {
+        shell: String,
+    },
 }
 
 #[derive(Subcommand, Debug)]
@@ -207,14 +351,141 @@ pub enum HistoryCmd {
     Rollback { hash: String },
 }
 
+#[derive(Subcommand, Debug)]
+pub enum TemplateCmd {
+    /// List built-in and user templates
+    Ls,
+    /// Show what a template seeds
+    Show { name: String },
+    /// Save a container's packages, env, toolchains and tasks as a template
+    SaveFrom {
+        container: String,
+        #[arg(long, help = "Template name (defaults to the container name)")]
+        name: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum ExportCmd {
+    /// Generate a Dockerfile (and optionally an OCI image of local/)
+    Docker {
+        container: String,
+        #[arg(short, long, help = "Directory to write into (defaults to the current directory)")]
+        output: Option<PathBuf>,
+        #[arg(long, help = "Base image instead of the one matching this host's distribution")]
+        base: Option<String>,
+        #[arg(long, help = "Also write <container>-oci.tar, an OCI image layout of local/")]
+        oci: bool,
+        #[arg(short, long, help = "Overwrite existing files")]
+        force: bool,
+    },
+    /// Generate .devcontainer/ (devcontainer.json, Dockerfile, container TOML)
+    Devcontainer {
+        container: String,
+        #[arg(short, long, help = "Project directory to create .devcontainer/ in (defaults to the current directory)")]
+        output: Option<PathBuf>,
+        #[arg(long, help = "Base image instead of the one matching this host's distribution")]
+        base: Option<String>,
+        #[arg(short, long, help = "Overwrite existing files")]
+        force: bool,
+    },
+}
+
 #[derive(Subcommand, Debug)]
 pub enum FlakeCmd {
     /// Generate flake.nix for current container
     Generate,
-    /// Push container config to GitHub
-    Push { repo: String },
-    /// Pull container config from GitHub
-    Pull { repo: String },
+    /// Pin flake inputs in flake.lock for current container
+    Lock {
+        #[arg(long, help = "Re-resolve every input, bumping existing pins")]
+        update: bool,
+        #[arg(long, help = "Set the container's nixpkgs input (e.g. github:NixOS/nixpkgs/nixos-24.05)")]
+        nixpkgs: Option<String>,
+    },
+    /// Push a container's flake and config to a git repository
+    Push {
+        #[arg(help = "Git remote URL or path")]
+        repo: String,
+        #[arg(short = 'c', long, help = "Container to push (default: current)")]
+        container: Option<String>,
+        #[arg(long, default_value = "main")]
+        branch: String,
+        #[arg(short = 'f', long, help = "Overwrite the remote copy even if it changed since the last sync")]
+        force: bool,
+    },
+    /// Pull a container's flake and config from a git repository
+    Pull {
+        #[arg(help = "Git remote URL or path")]
+        repo: String,
+        #[arg(short = 'c', long, help = "Container to pull (default: current)")]
+        container: Option<String>,
+        #[arg(long, default_value = "main")]
+        branch: String,
+        #[arg(short = 'f', long, help = "Replace local changes made since the last sync")]
+        force: bool,
+    },
+    /// Import the dev shell of an existing flake into a container
+    Import {
+        #[arg(help = "flake.nix, a directory containing one, or a git repository")]
+        source: String,
+        #[arg(short = 'n', long, help = "Container to import into (default: current)")]
+        name: Option<String>,
+        #[arg(long, help = "Dev shell to import (default: devShells.<system>.default)")]
+        shell: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum SnapshotCmd {
+    /// Write a snapshot (tree, modes, symlinks and manifest) to a .tar.zst
+    Export {
+        container: String,
+        #[arg(help = "Snapshot hash (prefix), tag or relative reference")]
+        hash: String,
+        #[arg(short = 'o', long)]
+        output: PathBuf,
+    },
+    /// Import a snapshot archive into the store, checking its content hash
+    Import {
+        file: PathBuf,
+        #[arg(long, help = "Name to link it under in links/ (default: <container>-<hash prefix>)")]
+        alias: Option<String>,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum RegistryCmd {
+    /// Serve bundles stored in a directory over plain HTTP
+    Serve {
+        #[arg(long, help = "Directory holding the registry's bundles and index")]
+        dir: PathBuf,
+        #[arg(long, default_value = "127.0.0.1:7373")]
+        bind: String,
+        #[arg(long, help = "Token publishers must send (default: $SFC_REGISTRY_TOKEN); without one the registry is read-only")]
+        token: Option<String>,
+    },
+    /// List the name:tag entries in a registry
+    Ls {
+        registry: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum KeysCmd {
+    /// Generate a signing key
+    Generate {
+        #[arg(default_value = "default")]
+        name: String,
+    },
+    /// List your keys and trusted keys
+    List,
+    /// Trust a public key
+    Trust {
+        #[arg(help = "Hex public key, an \"ed25519 <hex> [name]\" line, or a .pub file")]
+        key: String,
+        #[arg(long, help = "Name to record the key under")]
+        name: Option<String>,
+    },
 }
 
 #[derive(Subcommand, Debug)]
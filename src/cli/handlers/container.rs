@@ -1,16 +1,200 @@
 use std::env;
+use std::fs;
+use std::path::Path;
 use std::process::{Command, Stdio};
 
-use crate::core::WorkspaceManager;
-use crate::error::{Result, SfcError};
-use crate::cli::ui::{print_success, print_error};
+use crate::config::{ImportReport, SfcConfig, Template, TemplateStore};
+use crate::core::{SnapshotManager, SymlinkManager, WorkspaceManager};
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::cli::ui::{print_success, print_info, print_warning};
+use crate::container::{exit_code, spawn_error_code, ContainerConfig, PackageSpec};
+use crate::package::PackageManager;
+use crate::sfc::validate_name;
+use crate::sharing::{registry, ShareManager};
+use crate::system::shell::ShellKind;
 use owo_colors::OwoColorize;
-use crossterm::style::Stylize;
 
 /// Handle container creation
-pub fn handle_create(workspace: &WorkspaceManager, names: &[String], from: Option<&str>) -> Result<()> {
-    // TODO: Implement container creation logic
-    print_success(&format!("Container creation not yet implemented. Would create: {:?}", names));
+pub fn handle_create(
+    workspace: &WorkspaceManager,
+    names: &[String],
+    from: Option<&str>,
+    template: Option<&str>,
+    no_install: bool,
+    detect: bool,
+) -> Result<()> {
+    if names.is_empty() {
+        return Err(SfcError::Validation {
+            field: "names".to_string(),
+            value: String::new(),
+            reason: "no container names provided".to_string(),
+        });
+    }
+    if let (Some(from), true) = (from, template.is_some() || detect) {
+        return Err(SfcError::Validation {
+            field: "from".to_string(),
+            value: from.to_string(),
+            reason: "--from cannot be combined with --template or --detect".to_string(),
+        });
+    }
+
+    // Configured defaults, with the template (if any) layered on top
+    let settings = SfcConfig::load_global().unwrap_or_default();
+    let mut seed = match template {
+        Some(t) => TemplateStore::new(&workspace.root).load(t)?.0,
+        None => Template::default(),
+    }
+    .over_defaults(&settings.defaults);
+
+    // Pinned versions from the project's own files win over template/defaults
+    if detect {
+        let cwd = env::current_dir().with_io_context(|| "reading current directory".to_string())?;
+        let report = ImportReport::detect(&cwd)?;
+        if report.sources.is_empty() {
+            print_info("No version files found to detect");
+        }
+        for source in &report.sources {
+            print_info(&format!("Read {}", source.display()));
+        }
+        for item in &report.unmapped {
+            println!("{} {} {}", item.source.dimmed(), item.entry.yellow(), format!("({})", item.reason).dimmed());
+        }
+        seed.extend(&report.template);
+    }
+
+    let mut created = Vec::new();
+    for name in names {
+        match create_one(workspace, name, from, &seed, no_install) {
+            Ok(hash) => {
+                print_success(&format!("Created container {} at snapshot {}", name, &hash[..12]));
+                created.push(name);
+            }
+            Err(e) => print_warning(&format!("Error creating {}: {}", name, e)),
+        }
+    }
+    if let [name] = created.as_slice() {
+        workspace.set_current_container(name)?;
+        print_info(&format!("Switched to container {}", name));
+    }
+    if created.len() < names.len() {
+        return Err(SfcError::Generic {
+            message: "one or more containers failed to create".to_string(),
+            source: None,
+        });
+    }
+    Ok(())
+}
+
+/// Create one container: src/temp dirs, a stable snapshot and its config,
+/// seeded from `seed` or recreated from the snapshot `from` names. Returns
+/// the snapshot hash.
+pub(crate) fn create_one(workspace: &WorkspaceManager, name: &str, from: Option<&str>, seed: &Template, no_install: bool) -> Result<String> {
+    validate_name(name)?;
+    let root = &workspace.root;
+    let container_dir = root.join("containers").join(name);
+    if container_dir.exists() || ContainerConfig::exists(root, name) {
+        return Err(SfcError::AlreadyExists {
+            resource: "container".to_string(),
+            identifier: name.to_string(),
+        });
+    }
+
+    let snapshots = SnapshotManager::new(root);
+    let (snapshot_dir, mut config) = match from {
+        Some(reference) => {
+            let hash = snapshots.resolve_any(reference)?;
+            // Packages, toolchains and env come from the container the snapshot belongs to
+            let config = match ShareManager::new(workspace).share_info_for_hash(&hash) {
+                Ok(share_info) => share_info.to_container(name),
+                Err(_) => {
+                    print_warning("No container links to this snapshot; recreating its files only");
+                    ContainerConfig::new(name.to_string())
+                }
+            };
+            (snapshots.copy_snapshot(&hash, "snapshot-recreated")?, config)
+        }
+        None => {
+            let mut config = ContainerConfig::new(name.to_string());
+            seed.apply(&mut config);
+            for spec in &seed.packages {
+                config.add_package(PackageSpec::parse(spec))?;
+            }
+            (snapshots.create_snapshot("snapshot-000")?, config)
+        }
+    };
+
+    for sub in ["src", "temp"] {
+        let dir = container_dir.join(sub);
+        fs::create_dir_all(&dir).with_io_context(|| format!("creating {}", dir.display()))?;
+    }
+    let alias = format!("{}-stable", name);
+    let links = SymlinkManager::new(root);
+    links.link_alias_to_store(&alias, &Path::new("../store").join(snapshot_dir.file_name().unwrap_or_default()))?;
+    links.create_or_update(&Path::new("../../links").join(&alias), &container_dir.join("stable"))?;
+    config.save(root)?;
+
+    if !no_install {
+        for (spec, e) in PackageManager::new(root.clone()).install_all(&mut config) {
+            print_warning(&format!("Skipping package {}: {}", spec, e));
+        }
+    }
+    if from.is_none() {
+        let tool = |key: &str| seed.toolchains.get(key).map(String::as_str);
+        if !no_install && (tool("node").is_some() || tool("npm").is_some() || tool("rust").is_some()) {
+            if let Err(e) = crate::sfc::setup_toolchains(&snapshot_dir, tool("node"), tool("npm"), tool("rust")) {
+                print_warning(&format!("Toolchain setup failed: {}", e));
+            }
+        }
+        crate::sfc::capture_project_lockfiles(root, name, &snapshot_dir)?;
+        // The manifest records what was seeded, so hash once it's written
+        snapshots.record_metadata(name, &snapshot_dir, None)?;
+    }
+    crate::core::compute_snapshot_hash(&snapshot_dir)
+}
+
+/// Handle container creation from a share bundle
+pub fn handle_create_from_bundle(workspace: &WorkspaceManager, names: &[String], bundle: &Path, no_install: bool) -> Result<()> {
+    let [name] = names else {
+        return Err(SfcError::Validation {
+            field: "names".to_string(),
+            value: names.join(" "),
+            reason: "--from-bundle creates exactly one container".to_string(),
+        });
+    };
+    validate_name(name)?;
+    let (hash, verdict) = ShareManager::new(workspace).import_bundle(bundle, name)?;
+    print_success(&format!("Created container {} from {} (snapshot {}, {})", name, bundle.display(), &hash[..12], verdict.describe()));
+    // Bundles made with --artifacts already carry the installed packages
+    let packages_dir = workspace.root.join("containers").join(name).join("packages");
+    let has_artifacts = std::fs::read_dir(&packages_dir).map(|mut d| d.next().is_some()).unwrap_or(false);
+    if !no_install && !has_artifacts {
+        let mut config = ContainerConfig::load(&workspace.root, name)?;
+        for (spec, e) in PackageManager::new(workspace.root.clone()).install_all(&mut config) {
+            print_warning(&format!("Skipping package {}: {}", spec, e));
+        }
+    }
+    workspace.set_current_container(name)?;
+    Ok(())
+}
+
+/// Handle container creation from a bundle in a registry
+pub fn handle_create_from_registry(workspace: &WorkspaceManager, names: &[String], url: &str, no_install: bool) -> Result<()> {
+    let bundle = registry::download(&workspace.root, url)?;
+    print_info(&format!("Fetched {}", url));
+    handle_create_from_bundle(workspace, names, &bundle, no_install)
+}
+
+/// Handle container cloning
+pub fn handle_clone(workspace: &WorkspaceManager, src: &str, dst: &str) -> Result<()> {
+    let aliases = crate::core::clone_container(&workspace.root, src, dst)?;
+    print_success(&format!("Cloned '{}' to '{}' ({} snapshot link(s) shared)", src, dst, aliases.len()));
+    Ok(())
+}
+
+/// Handle container renaming
+pub fn handle_rename(workspace: &WorkspaceManager, old: &str, new: &str) -> Result<()> {
+    crate::core::rename_container(&workspace.root, old, new)?;
+    print_success(&format!("Renamed '{}' to '{}'", old, new));
     Ok(())
 }
 
@@ -65,8 +249,6 @@ pub fn handle_banner() -> Result<()> {
 
 /// Handle temporary shell environment (like nix shell)
 pub fn handle_shell(workspace: &WorkspaceManager, container: Option<&str>, command: Option<&str>, keep: bool) -> Result<()> {
-    use crate::container::ContainerConfig;
-
     // Determine which container to use
     let container_name = match container {
         Some(name) => name.to_string(),
@@ -80,22 +262,25 @@ pub fn handle_shell(workspace: &WorkspaceManager, container: Option<&str>, comma
     };
 
     // Load the container configuration
-    let container_config = ContainerConfig::load(&workspace.root, &container_name)?;
+    let container_config = ContainerConfig::load_effective(&workspace.root, &container_name)?;
 
     // Get current working directory
-    let current_dir = env::current_dir()?;
+    let current_dir = env::current_dir()
+        .with_io_context(|| "reading current directory".to_string())?;
 
     println!("{} temporary shell for container '{}' in {}", "Starting".green(), container_name.cyan(), current_dir.display());
 
     // Build environment like enter_shell but for current directory
-    let mut env = container_config.environment.clone();
-    env.insert("SFC_CONTAINER".to_string(), container_name.clone());
-    env.insert("SFC_WORKSPACE".to_string(), workspace.root.to_string_lossy().to_string());
+    let base_path = env::var("PATH").unwrap_or_default();
+    let mut env = container_config.activation_env(&workspace.root, &base_path);
     env.insert("SFC_TEMP_SHELL".to_string(), "1".to_string());
 
-    // Set PS1 to show it's a temp shell
-    let ps1 = format!("\\[\\033[33m\\]sfc-temp[{}]\\[\\033[0m\\] \\w $ ", container_name);
-    env.insert("PS1".to_string(), ps1);
+    // Prompt shows it's a temp shell, in whatever syntax the shell understands
+    let shell_kind = ShellKind::from_path(&container_config.shell);
+    let prompt_label = format!("temp:{}", container_name);
+    if let Some(kind) = shell_kind {
+        env.extend(kind.prompt_env(&prompt_label));
+    }
 
     // Show active packages
     let package_names: Vec<String> = container_config.packages.iter().map(|p| p.name.clone()).collect();
@@ -119,30 +304,40 @@ pub fn handle_shell(workspace: &WorkspaceManager, container: Option<&str>, comma
 
         // Use shell to execute the command
         cmd.arg("-c").arg(cmd_str);
+        container_config.isolate(&workspace.root, &mut cmd)
+            .with_io_context(|| format!("isolating container {}", container_name))?;
 
-        let status = cmd.status()?;
-        Ok(status.success())
+        let status = cmd.status()
+            .with_io_context(|| format!("spawning shell {}", container_config.shell))?;
+        Ok(if status.success() { Ok(()) } else { Err(status) })
     } else {
         // Start interactive shell
         let mut cmd = Command::new(&container_config.shell);
         cmd.current_dir(&current_dir);
+        if let Some(kind) = shell_kind {
+            cmd.args(kind.prompt_args(&prompt_label));
+        }
 
         // Set up environment
         for (k, v) in env {
             cmd.env(k, v);
         }
 
+        container_config.isolate(&workspace.root, &mut cmd)
+            .with_io_context(|| format!("isolating container {}", container_name))?;
+
         // Make it interactive
         cmd.stdin(Stdio::inherit());
         cmd.stdout(Stdio::inherit());
         cmd.stderr(Stdio::inherit());
 
-        let status = cmd.status()?;
-        Ok(status.success())
+        let status = cmd.status()
+            .with_io_context(|| format!("spawning shell {}", container_config.shell))?;
+        Ok(if status.success() { Ok(()) } else { Err(status) })
     };
 
     match shell_result {
-        Ok(true) => {
+        Ok(Ok(())) => {
             if !keep {
                 println!("{}", "Temporary shell session ended".dimmed());
             } else {
@@ -150,12 +345,33 @@ pub fn handle_shell(workspace: &WorkspaceManager, container: Option<&str>, comma
             }
             Ok(())
         }
-        Ok(false) => {
-            Err(crate::error::SfcError::System {
-                operation: "shell execution".to_string(),
-                reason: "command exited with non-zero status".to_string(),
+        Ok(Err(status)) => {
+            Err(SfcError::Command {
+                command: command.unwrap_or(&container_config.shell).to_string(),
+                exit_code: Some(exit_code(&status)),
+                stderr: String::new(),
             })
         }
         Err(e) => Err(e),
     }
 }
+
+/// Handle running a command directly inside a container environment
+pub fn handle_exec(workspace: &WorkspaceManager, container: &str, command: &[String]) -> Result<()> {
+    if !workspace.root.join("containers").join(container).is_dir() {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: container.to_string(),
+        });
+    }
+
+    let container_config = ContainerConfig::load_effective(&workspace.root, container)?;
+
+    // Only returns when the program could not be started
+    let err = container_config.exec(&workspace.root, command);
+    Err(SfcError::Command {
+        command: command.first().cloned().unwrap_or_default(),
+        exit_code: Some(spawn_error_code(&err)),
+        stderr: err.to_string(),
+    })
+}
@@ -0,0 +1,123 @@
+use std::fs;
+use std::path::PathBuf;
+
+use crate::core::{SnapshotManager, WorkspaceManager};
+use crate::container::ContainerConfig;
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::cli::ui::{print_info, print_success};
+use crate::cli::commands::ExportCmd;
+use crate::sharing::{DevcontainerExporter, DockerExporter, Inventory, SbomFormat};
+
+/// Handle export operations
+pub fn handle_export(workspace: &WorkspaceManager, cmd: ExportCmd) -> Result<()> {
+    match cmd {
+        ExportCmd::Docker { container, output, base, oci, force } => {
+            if !ContainerConfig::exists(&workspace.root, &container) {
+                return Err(SfcError::NotFound {
+                    resource: "container".to_string(),
+                    identifier: container,
+                });
+            }
+            let config = ContainerConfig::load_effective(&workspace.root, &container)?;
+            let snapshot = crate::sfc::get_current_snapshot_hash(&workspace.root, &container).ok();
+            let out_dir = output.unwrap_or_else(|| PathBuf::from("."));
+            fs::create_dir_all(&out_dir)
+                .with_io_context(|| format!("creating {}", out_dir.display()))?;
+
+            let dockerfile_path = out_dir.join("Dockerfile");
+            let oci_path = out_dir.join(format!("{}-oci.tar", container));
+            for path in std::iter::once(&dockerfile_path).chain(oci.then_some(&oci_path)) {
+                if path.exists() && !force {
+                    return Err(SfcError::AlreadyExists {
+                        resource: "file".to_string(),
+                        identifier: path.display().to_string(),
+                    });
+                }
+            }
+
+            let exporter = DockerExporter::new(&workspace.root);
+            let export = exporter.dockerfile(&config, base.as_deref(), snapshot.as_deref());
+            fs::write(&dockerfile_path, &export.dockerfile)
+                .with_io_context(|| format!("writing {}", dockerfile_path.display()))?;
+            print_success(&format!("Wrote {} (FROM {})", dockerfile_path.display(), export.base_image));
+
+            if oci {
+                let digest = exporter.write_oci_layout(&config, snapshot.as_deref(), &oci_path)?;
+                print_success(&format!("Wrote {} ({})", oci_path.display(), digest));
+            }
+            for note in &export.notes {
+                print_info(note);
+            }
+        }
+        ExportCmd::Devcontainer { container, output, base, force } => {
+            if !ContainerConfig::exists(&workspace.root, &container) {
+                return Err(SfcError::NotFound {
+                    resource: "container".to_string(),
+                    identifier: container,
+                });
+            }
+            let config = ContainerConfig::load_effective(&workspace.root, &container)?;
+            let snapshot = crate::sfc::get_current_snapshot_hash(&workspace.root, &container).ok();
+            let export = DevcontainerExporter::new(&workspace.root).export(&config, base.as_deref(), snapshot.as_deref())?;
+
+            let dir = output.unwrap_or_else(|| PathBuf::from(".")).join(".devcontainer");
+            let files = [
+                (dir.join("devcontainer.json"), &export.devcontainer_json),
+                (dir.join("Dockerfile"), &export.dockerfile),
+                (dir.join(&export.config_file), &export.config_toml),
+            ];
+            if let Some((path, _)) = files.iter().find(|(path, _)| path.exists() && !force) {
+                return Err(SfcError::AlreadyExists {
+                    resource: "file".to_string(),
+                    identifier: path.display().to_string(),
+                });
+            }
+            fs::create_dir_all(&dir)
+                .with_io_context(|| format!("creating {}", dir.display()))?;
+            for (path, content) in &files {
+                fs::write(path, content)
+                    .with_io_context(|| format!("writing {}", path.display()))?;
+            }
+            print_success(&format!("Wrote {}", dir.display()));
+            for note in &export.notes {
+                print_info(note);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Handle `sfc sbom`
+pub fn handle_sbom(
+    workspace: &WorkspaceManager,
+    container: &str,
+    hash: Option<&str>,
+    format: &str,
+    output: Option<PathBuf>,
+) -> Result<()> {
+    if !ContainerConfig::exists(&workspace.root, container) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: container.to_string(),
+        });
+    }
+    let format: SbomFormat = format.parse()?;
+    let hash = hash.map(|h| SnapshotManager::new(&workspace.root).resolve(container, h)).transpose()?;
+    let config = ContainerConfig::load_effective(&workspace.root, container)?;
+    let inventory = Inventory::collect(&workspace.root, &config, hash.as_deref())?;
+    let json = serde_json::to_string_pretty(&inventory.render(format, chrono::Utc::now()))
+        .map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize SBOM: {}", e),
+            source: None,
+        })?;
+
+    match output {
+        Some(path) => {
+            fs::write(&path, json + "\n")
+                .with_io_context(|| format!("writing {}", path.display()))?;
+            print_success(&format!("Wrote {} ({} components, {})", path.display(), inventory.components.len(), format));
+        }
+        None => println!("{}", json),
+    }
+    Ok(())
+}
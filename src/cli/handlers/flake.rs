@@ -1,20 +1,108 @@
 use crate::core::WorkspaceManager;
-use crate::error::Result;
-use crate::cli::ui::{print_success, print_error};
+use crate::container::{ContainerConfig, PackageSpec};
+use crate::error::{Result, SfcError};
+use crate::cli::ui::{print_info, print_success};
 use crate::cli::commands::FlakeCmd;
+use crate::flake::{checkout_source, FlakeImport, FlakeRef};
+use crate::sharing::{FlakeRemote, PullOutcome, PushOutcome};
+
+fn current_container(workspace: &WorkspaceManager) -> Result<String> {
+    workspace.current_container()?.ok_or_else(|| SfcError::Validation {
+        field: "container".to_string(),
+        value: String::new(),
+        reason: "no current container selected; use 'sfc switch' to select one".to_string(),
+    })
+}
 
 /// Handle flake operations
 pub fn handle_flake(workspace: &WorkspaceManager, cmd: FlakeCmd) -> Result<()> {
-    // TODO: Implement flake operations logic
     match cmd {
         FlakeCmd::Generate => {
-            print_success("Flake generation not yet implemented");
+            let name = current_container(workspace)?;
+            let flake = ContainerConfig::load_effective(&workspace.root, &name)?.to_flake();
+            flake.save(&workspace.root, &name)?;
+            print_success(&format!("Generated flake.nix for container {}", name));
+            let unlocked = flake.unlocked_inputs(&workspace.root, &name)?;
+            if !unlocked.is_empty() {
+                print_info(&format!("Unpinned inputs: {} (run 'sfc flake lock' to pin them)", unlocked.join(", ")));
+            }
+        }
+        FlakeCmd::Lock { update, nixpkgs } => {
+            let name = current_container(workspace)?;
+            let mut container = ContainerConfig::load_effective(&workspace.root, &name)?;
+            if let Some(reference) = nixpkgs {
+                FlakeRef::parse(&reference)?;
+                let mut raw = ContainerConfig::load(&workspace.root, &name)?;
+                raw.nixpkgs = Some(reference.clone());
+                raw.save(&workspace.root)?;
+                container.nixpkgs = Some(reference.clone());
+                print_success(&format!("Pinned nixpkgs to {}", reference));
+            }
+
+            let changes = container.to_flake().lock(&workspace.root, &name, update)?;
+            if changes.is_empty() {
+                print_success(&format!("flake.lock for {} is up to date", name));
+            }
+            for change in &changes {
+                let old = change.old.as_deref().unwrap_or("-");
+                let new = change.new.as_deref().unwrap_or("-");
+                print_info(&format!("{}: {} -> {}", change.input, old, new));
+            }
+        }
+        FlakeCmd::Push { repo, container, branch, force } => {
+            let name = match container {
+                Some(name) => name,
+                None => current_container(workspace)?,
+            };
+            match FlakeRemote::new(&workspace.root, &repo, Some(&branch)).push(&name, force)? {
+                PushOutcome::UpToDate => print_success(&format!("{} already matches {}", name, repo)),
+                PushOutcome::Pushed { commit } => print_success(&format!("Pushed {} to {} ({})", name, repo, commit)),
+            }
         }
-        FlakeCmd::Push { repo } => {
-            print_success(&format!("Flake push not yet implemented. Repo: {}", repo));
+        FlakeCmd::Pull { repo, container, branch, force } => {
+            let name = match container {
+                Some(name) => name,
+                None => current_container(workspace)?,
+            };
+            match FlakeRemote::new(&workspace.root, &repo, Some(&branch)).pull(&name, force)? {
+                PullOutcome::UpToDate => print_success(&format!("{} matches {}", name, repo)),
+                PullOutcome::LocalAhead => print_info(&format!("Local {} is ahead of {}", name, repo)),
+                PullOutcome::Updated { commit, .. } => print_success(&format!("Pulled {} from {} ({})", name, repo, commit)),
+            }
         }
-        FlakeCmd::Pull { repo } => {
-            print_success(&format!("Flake pull not yet implemented. Repo: {}", repo));
+        FlakeCmd::Import { source, name, shell } => {
+            let name = match name {
+                Some(name) => name,
+                None => current_container(workspace)?,
+            };
+            if !ContainerConfig::exists(&workspace.root, &name) {
+                return Err(SfcError::NotFound { resource: "container".to_string(), identifier: name });
+            }
+            let checkout = checkout_source(&workspace.root, &source)?;
+            let import = FlakeImport::from_path(&checkout, shell.as_deref())?;
+            for item in &import.report.unmapped {
+                print_info(&format!("{}: {} ({})", item.source, item.entry, item.reason));
+            }
+
+            let mut config = ContainerConfig::load(&workspace.root, &name)?;
+            import.report.template.apply(&mut config);
+            for spec in &import.report.template.packages {
+                config.add_package(PackageSpec::parse(spec))?;
+            }
+            if import.nixpkgs.is_some() {
+                config.nixpkgs = import.nixpkgs.clone();
+            }
+            config.save(&workspace.root)?;
+            ContainerConfig::load_effective(&workspace.root, &name)?.to_flake().save(&workspace.root, &name)?;
+            print_success(&format!(
+                "Imported shell '{}' from {} into '{}': {} toolchain(s), {} package(s), {} not mapped",
+                import.shell,
+                source,
+                name,
+                import.report.template.toolchains.len(),
+                import.report.template.packages.len(),
+                import.report.unmapped.len()
+            ));
         }
     }
     Ok(())
@@ -0,0 +1,32 @@
+use std::collections::HashMap;
+use std::env;
+
+use crate::core::WorkspaceManager;
+use crate::error::{Result, ErrorContext};
+use crate::system::shell::{self, ShellKind};
+
+/// Print the shell init script for `eval "$(sfc hook <shell>)"`
+pub fn handle_hook(shell: &str) -> Result<()> {
+    let kind: ShellKind = shell.parse()?;
+    let exe = env::current_exe()
+        .with_io_context(|| "locating the sfc executable".to_string())?;
+    print!("{}", kind.init_script(&exe.to_string_lossy()));
+    Ok(())
+}
+
+/// Print the activation/deactivation code for the current directory (called
+/// by the hook at every prompt, so nothing is written, even without a workspace)
+pub fn handle_hook_env(workspace: &WorkspaceManager, shell: &str) -> Result<()> {
+    let kind: ShellKind = shell.parse()?;
+    if !workspace.root.join(".sfc").is_dir() {
+        return Ok(());
+    }
+    let cwd = env::current_dir()
+        .with_io_context(|| "reading current directory".to_string())?;
+    let current_env: HashMap<String, String> = env::vars().collect();
+    let script = shell::hook_env(kind, &workspace.root, &cwd, &current_env)?;
+    if !script.is_empty() {
+        println!("{}", script);
+    }
+    Ok(())
+}
@@ -0,0 +1,125 @@
+use std::path::Path;
+
+use owo_colors::OwoColorize;
+
+use crate::core::WorkspaceManager;
+use crate::config::{ImportReport, Template};
+use crate::container::{ContainerConfig, PackageSpec};
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::cli::handlers::container::create_one;
+use crate::cli::ui::{print_info, print_success, print_warning};
+use crate::package::PackageManager;
+use crate::sfc::validate_name;
+
+/// Handle `sfc import`: record toolchains, packages and env from a version file
+pub fn handle_import(workspace: &WorkspaceManager, file: &Path, container: Option<&str>) -> Result<()> {
+    let name = match container {
+        Some(name) => name.to_string(),
+        None => workspace.current_container()?.ok_or_else(|| SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: "current".to_string(),
+        })?,
+    };
+    if !ContainerConfig::exists(&workspace.root, &name) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: name,
+        });
+    }
+
+    let report = ImportReport::from_file(file)?;
+    for item in &report.unmapped {
+        println!("{} {} {}", item.source.dimmed(), item.entry.yellow(), format!("({})", item.reason).dimmed());
+    }
+    if report.is_empty() {
+        print_info("Nothing to import");
+        return Ok(());
+    }
+
+    let mut config = ContainerConfig::load(&workspace.root, &name)?;
+    report.template.apply(&mut config);
+    for spec in &report.template.packages {
+        config.add_package(PackageSpec::parse(spec))?;
+    }
+    config.save(&workspace.root)?;
+
+    print_success(&format!(
+        "Imported {} into '{}': {} toolchain(s), {} package(s), {} not mapped",
+        file.display(),
+        name,
+        report.template.toolchains.len(),
+        report.template.packages.len(),
+        report.unmapped.len()
+    ));
+    Ok(())
+}
+
+/// Handle `sfc apply`: create or update a container so it matches a TOML file
+pub fn handle_apply(workspace: &WorkspaceManager, file: &Path, name: Option<&str>, no_install: bool, prune: bool) -> Result<()> {
+    let content = std::fs::read_to_string(file)
+        .with_io_context(|| format!("reading {}", file.display()))?;
+    let desired: ContainerConfig = toml::from_str(&content).map_err(|e| SfcError::Config {
+        message: format!("Invalid container config: {}", e),
+        path: Some(file.to_path_buf()),
+    })?;
+    let name = name.unwrap_or(&desired.name).to_string();
+    validate_name(&name)?;
+    if let Some(base) = desired.extends.iter().find(|base| !ContainerConfig::exists(&workspace.root, base)) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: format!("{} (extended by {})", base, name),
+        });
+    }
+
+    if !ContainerConfig::exists(&workspace.root, &name) {
+        create_one(workspace, &name, None, &Template::default(), true)?;
+        print_info(&format!("Created container '{}'", name));
+    }
+    let mut config = ContainerConfig::load(&workspace.root, &name)?;
+    let toolchains_changed = config.toolchains != desired.toolchains;
+
+    // Everything except packages is taken from the file as-is
+    config.apply_settings(&desired);
+    config.save(&workspace.root)?;
+
+    let have: Vec<String> = config.packages.iter().map(PackageSpec::to_spec_string).collect();
+    let missing: Vec<String> = desired
+        .packages
+        .iter()
+        .map(PackageSpec::to_spec_string)
+        .filter(|spec| !have.contains(spec))
+        .collect();
+    let extra: Vec<String> = config
+        .packages
+        .iter()
+        .filter(|pkg| !desired.packages.iter().any(|d| d.name == pkg.name))
+        .map(|pkg| pkg.name.clone())
+        .collect();
+
+    let pkg_mgr = PackageManager::new(workspace.root.clone());
+    for spec in &missing {
+        if no_install {
+            config.add_package(PackageSpec::parse(spec))?;
+        } else if let Err(e) = pkg_mgr.add_package(&mut config, spec) {
+            print_warning(&format!("Skipping package {}: {}", spec, e));
+        }
+    }
+    if prune {
+        for pkg in &extra {
+            if no_install {
+                config.remove_package(pkg)?;
+            } else {
+                pkg_mgr.remove_package(&mut config, pkg)?;
+            }
+        }
+    } else if !extra.is_empty() {
+        println!("{} {} {}", "Not in file:".yellow(), extra.join(", "), "(use --prune to remove)".dimmed());
+    }
+    config.save(&workspace.root)?;
+
+    print_success(&format!("Applied {} to '{}' ({} package(s) added)", file.display(), name, missing.len()));
+    if toolchains_changed && !no_install && !config.toolchains.is_empty() {
+        print_info("Toolchains changed; run 'sfc temp' and 'sfc promote' to install them");
+    }
+    Ok(())
+}
@@ -0,0 +1,32 @@
+use crate::core::WorkspaceManager;
+use crate::error::Result;
+use crate::cli::ui::{print_info, print_success};
+use crate::cli::commands::KeysCmd;
+use crate::sharing::KeyStore;
+
+/// Handle signing key operations
+pub fn handle_keys(workspace: &WorkspaceManager, cmd: KeysCmd) -> Result<()> {
+    let store = KeyStore::new(&workspace.root);
+    match cmd {
+        KeysCmd::Generate { name } => {
+            let key = store.generate(&name)?;
+            print_success(&format!("Generated key {} ({})", key.name, key.fingerprint()));
+            print_info(&format!("Others can trust it with: sfc keys trust '{}'", key.to_line()));
+        }
+        KeysCmd::List => {
+            println!("Your keys:");
+            for key in store.list()? {
+                println!("  {} {} {}", key.name, key.fingerprint(), key.key);
+            }
+            println!("Trusted keys:");
+            for key in store.trusted()? {
+                println!("  {} {} {}", key.name, key.fingerprint(), key.key);
+            }
+        }
+        KeysCmd::Trust { key, name } => {
+            let key = store.trust(&key, name.as_deref())?;
+            print_success(&format!("Trusted {} ({})", key.name, key.fingerprint()));
+        }
+    }
+    Ok(())
+}
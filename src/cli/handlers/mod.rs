@@ -6,6 +6,13 @@ pub mod toolchain;
 pub mod history;
 pub mod flake;
 pub mod config;
+pub mod hook;
+pub mod task;
+pub mod template;
+pub mod import;
+pub mod export;
+pub mod keys;
+pub mod registry;
 
 pub use container::*;
 pub use package::*;
@@ -15,3 +22,10 @@ pub use toolchain::*;
 pub use history::*;
 pub use flake::*;
 pub use config::*;
+pub use hook::*;
+pub use task::*;
+pub use template::*;
+pub use import::*;
+pub use export::*;
+pub use keys::*;
+pub use registry::*;
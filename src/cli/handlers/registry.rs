@@ -0,0 +1,34 @@
+use std::net::TcpListener;
+
+use crate::core::WorkspaceManager;
+use crate::error::{Result, ErrorContext};
+use crate::cli::ui::{print_info, print_success, print_warning};
+use crate::cli::commands::RegistryCmd;
+use crate::sharing::{registry, RegistryClient, RegistryServer, RegistryStore};
+
+/// Handle registry operations
+pub fn handle_registry(_workspace: &WorkspaceManager, cmd: RegistryCmd) -> Result<()> {
+    match cmd {
+        RegistryCmd::Serve { dir, bind, token } => {
+            let token = token.or_else(|| std::env::var(registry::TOKEN_ENV).ok());
+            let server = RegistryServer::new(RegistryStore::new(&dir)?).with_token(token.clone());
+            let listener = TcpListener::bind(&bind).with_io_context(|| format!("binding {}", bind))?;
+            print_success(&format!("Serving registry {} on {}", dir.display(), bind));
+            if token.unwrap_or_default().is_empty() {
+                print_warning("No --token given: publishing is disabled");
+            }
+            server.serve(listener, |line| println!("  {}", line))?;
+        }
+        RegistryCmd::Ls { registry } => {
+            let (client, _) = RegistryClient::parse(&registry)?;
+            let entries = client.list()?;
+            if entries.is_empty() {
+                print_info("No bundles published");
+            }
+            for entry in entries {
+                println!("{}:{} {}", entry.name, entry.tag, &entry.hash[..12]);
+            }
+        }
+    }
+    Ok(())
+}
@@ -1,6 +1,12 @@
-use crate::core::WorkspaceManager;
-use crate::error::Result;
-use crate::cli::ui::{print_success, print_error};
+use std::fs;
+use std::path::Path;
+
+use crate::container::ContainerConfig;
+use crate::core::{ChangeKind, SnapshotDiff, SnapshotManager, TagStore, WorkspaceManager};
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::cli::ui::{print_success, print_info};
+use crate::cli::commands::SnapshotCmd;
+use crate::sharing::{registry, BundleRef, KeyStore, ShareManager};
 
 /// Handle temporary environment creation
 pub fn handle_temp(workspace: &WorkspaceManager, name: Option<&str>, node: Option<&str>, npm: Option<&str>, rust: Option<&str>) -> Result<()> {
@@ -25,15 +31,179 @@ pub fn handle_discard(workspace: &WorkspaceManager, name: Option<&str>, temp_ali
 
 /// Handle container snapshots listing
 pub fn handle_snapshots(workspace: &WorkspaceManager, name: &str) -> Result<()> {
-    // TODO: Implement snapshots listing logic
-    print_success(&format!("Snapshots listing not yet implemented. Container: {}", name));
+    let snapshots = SnapshotManager::new(&workspace.root).list_container_snapshots(name)?;
+    if snapshots.is_empty() {
+        print_info(&format!("No snapshots found for {}", name));
+        return Ok(());
+    }
+    let tags = TagStore::new(&workspace.root).for_container(name)?;
+    for (i, snapshot) in snapshots.iter().enumerate() {
+        let names: Vec<&str> = tags.iter().filter(|(_, hash)| **hash == snapshot.hash).map(|(tag, _)| tag.as_str()).collect();
+        let marker = if snapshot.is_active { "*" } else { " " };
+        let tagged = if names.is_empty() { String::new() } else { format!(" ({})", names.join(", ")) };
+        println!(
+            "{:2}. {} {} {} {}{}",
+            i + 1,
+            marker,
+            &snapshot.hash[..12],
+            snapshot.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            snapshot.description,
+            tagged
+        );
+        if let Some(user) = &snapshot.created_by {
+            println!("      by {}{}", user, snapshot.parent.as_ref().map(|p| format!(", from {}", &p[..12.min(p.len())])).unwrap_or_default());
+        }
+        for (tool, version) in &snapshot.toolchains {
+            println!("      {} {}", tool, version);
+        }
+        if !snapshot.packages.is_empty() {
+            let packages: Vec<String> = snapshot.packages.iter().map(|p| p.to_spec_string()).collect();
+            println!("      packages: {}", packages.join(", "));
+        }
+    }
+    Ok(())
+}
+
+/// Handle snapshot comparison
+pub fn handle_diff(workspace: &WorkspaceManager, container: &str, from: &str, to: &str, json: bool) -> Result<()> {
+    if !ContainerConfig::exists(&workspace.root, container) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: container.to_string(),
+        });
+    }
+    let snapshots = SnapshotManager::new(&workspace.root);
+    let (from, to) = (snapshots.resolve(container, from)?, snapshots.resolve(container, to)?);
+    let diff = SnapshotDiff::between(&from, &snapshots.find_snapshot_by_hash(&from)?, &to, &snapshots.find_snapshot_by_hash(&to)?)?;
+    if json {
+        let output = serde_json::to_string_pretty(&diff).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize diff: {}", e),
+            source: None,
+        })?;
+        println!("{}", output);
+        return Ok(());
+    }
+
+    print_info(&format!("{} {} -> {}", container, &from[..12], &to[..12]));
+    if diff.is_empty() {
+        print_success("No differences");
+        return Ok(());
+    }
+    for (title, changes) in [("Packages", &diff.packages), ("Toolchains", &diff.toolchains), ("Environment", &diff.environment)] {
+        if changes.is_empty() {
+            continue;
+        }
+        println!("{}:", title);
+        for change in changes {
+            let (old, new) = (change.old.as_deref().unwrap_or_default(), change.new.as_deref().unwrap_or_default());
+            match change.kind {
+                ChangeKind::Added => println!("  + {} {}", change.name, new),
+                ChangeKind::Removed => println!("  - {} {}", change.name, old),
+                ChangeKind::Changed => println!("  ~ {} {} -> {}", change.name, old, new),
+            }
+        }
+    }
+    for lockfile in &diff.lockfiles {
+        println!("{} (+{} -{}):", lockfile.file, lockfile.added, lockfile.removed);
+        print!("{}", lockfile.diff);
+    }
+    Ok(())
+}
+
+/// Handle snapshot tagging
+pub fn handle_tag(workspace: &WorkspaceManager, container: &str, reference: &str, name: &str) -> Result<()> {
+    if !ContainerConfig::exists(&workspace.root, container) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: container.to_string(),
+        });
+    }
+    let hash = SnapshotManager::new(&workspace.root).resolve(container, reference)?;
+    let previous = TagStore::new(&workspace.root).tag(container, name, &hash)?;
+    print_success(&format!("Tagged {} {} as {}", container, &hash[..12], name));
+    if let Some(previous) = previous.filter(|p| *p != hash) {
+        print_info(&format!("Moved from {}", &previous[..12.min(previous.len())]));
+    }
+    Ok(())
+}
+
+/// Handle snapshot export and import
+pub fn handle_snapshot(workspace: &WorkspaceManager, cmd: SnapshotCmd) -> Result<()> {
+    let manager = SnapshotManager::new(&workspace.root);
+    match cmd {
+        SnapshotCmd::Export { container, hash, output } => {
+            let header = manager.export_snapshot(&container, &hash, &output)?;
+            print_success(&format!("Exported {} {} to {}", container, &header.hash[..12], output.display()));
+        }
+        SnapshotCmd::Import { file, alias } => {
+            let imported = manager.import_snapshot(&file, alias.as_deref())?;
+            if imported.reused {
+                print_info(&format!("Snapshot {} is already in the store", &imported.header.hash[..12]));
+            }
+            print_success(&format!(
+                "Imported {} from {} as links/{}",
+                &imported.header.hash[..12],
+                imported.header.container,
+                imported.alias
+            ));
+        }
+    }
     Ok(())
 }
 
 /// Handle snapshot sharing
-pub fn handle_share(workspace: &WorkspaceManager, name: &str, hash: Option<&str>) -> Result<()> {
-    // TODO: Implement snapshot sharing logic
-    print_success(&format!("Snapshot sharing not yet implemented. Container: {}, Hash: {:?}", name, hash));
+pub fn handle_share(
+    workspace: &WorkspaceManager,
+    name: &str,
+    hash: Option<&str>,
+    bundle: Option<&Path>,
+    publish: Option<&str>,
+    artifacts: bool,
+    key: Option<&str>,
+) -> Result<()> {
+    let hash = hash.map(|h| SnapshotManager::new(&workspace.root).resolve(name, h)).transpose()?;
+    let hash = hash.as_deref();
+    let signer = match bundle.is_some() || publish.is_some() {
+        true => KeyStore::new(&workspace.root).signer(key, &workspace.config.sharing)?,
+        false => None,
+    };
+    let signed_by = signer.as_ref().map(|k| k.public());
+    let manager = ShareManager::new(workspace).with_signer(signer);
+    let share_info = manager.generate_share_info(name, hash)?;
+    print!("{}", manager.format_share_info(&share_info));
+    if let Some(path) = bundle {
+        let manifest = manager.export_bundle(name, Some(&share_info.hash), path, artifacts)?;
+        print_success(&format!("Wrote bundle {} ({} files)", path.display(), manifest.files.len()));
+        print_info(&format!("Recreate with: sfc create {} --from-bundle {}", name, path.display()));
+    }
+    if let Some(url) = publish {
+        let (client, registry_name, tag) = registry::publish_target(url, name)?;
+        // Without --bundle the bundle only needs to live long enough to upload
+        let path = match bundle {
+            Some(path) => path.to_path_buf(),
+            None => {
+                let dir = workspace.root.join(".sfc").join("cache").join("registry");
+                fs::create_dir_all(&dir).with_io_context(|| format!("creating {}", dir.display()))?;
+                let path = dir.join(format!("{}-{}.sfcb", name, &share_info.hash[..12]));
+                manager.export_bundle(name, Some(&share_info.hash), &path, artifacts)?;
+                path
+            }
+        };
+        let data = fs::read(&path).with_io_context(|| format!("reading {}", path.display()))?;
+        if bundle.is_none() {
+            let _ = fs::remove_file(&path);
+        }
+        let entry = client.publish(&registry_name, &tag, &data)?;
+        let url = client.url_for(&BundleRef::Tag { name: entry.name, tag: entry.tag });
+        print_success(&format!("Published {}", url));
+        print_info(&format!("Recreate with: sfc create {} --from {}", name, url));
+    }
+    if bundle.is_some() || publish.is_some() {
+        match signed_by {
+            Some(key) => print_info(&format!("Signed by {} ({})", key.name, key.fingerprint())),
+            None => print_info("Unsigned (run 'sfc keys generate' to sign bundles)"),
+        }
+    }
     Ok(())
 }
 
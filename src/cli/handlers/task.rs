@@ -0,0 +1,65 @@
+use std::env;
+
+use crate::core::WorkspaceManager;
+use crate::core::task::{TaskOutcome, TaskRunner};
+use crate::container::ContainerConfig;
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::cli::ui::print_info;
+use crate::system::shell::find_marker;
+use owo_colors::OwoColorize;
+
+/// Handle `sfc run`: run a task (and its dependencies), or list tasks when none is given
+pub fn handle_run(
+    workspace: &WorkspaceManager,
+    task: Option<&str>,
+    container: Option<&str>,
+    force: bool,
+    jobs: Option<usize>,
+) -> Result<()> {
+    let cwd = env::current_dir()
+        .with_io_context(|| "reading current directory".to_string())?;
+    let marker = find_marker(&cwd);
+
+    // Explicit container wins, then the directory binding, then the current container
+    let container_name = match (container, &marker) {
+        (Some(name), _) => name.to_string(),
+        (None, Some((_, bound))) => bound.clone(),
+        (None, None) => workspace.current_container()?.ok_or_else(|| SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: "current".to_string(),
+        })?,
+    };
+    let project_root = match &marker {
+        Some((dir, bound)) if *bound == container_name => dir.clone(),
+        _ => cwd,
+    };
+
+    let config = ContainerConfig::load_effective(&workspace.root, &container_name)?;
+
+    let Some(task) = task else {
+        if config.tasks.is_empty() {
+            print_info(&format!("No tasks defined for container '{}'", container_name));
+        } else {
+            println!("{}", format!("Tasks in '{}':", container_name).bold());
+            for (name, spec) in &config.tasks {
+                let deps = if spec.depends_on.is_empty() {
+                    String::new()
+                } else {
+                    format!(" (after {})", spec.depends_on.join(", "))
+                };
+                println!("  {} {}{}", name.cyan(), spec.command.dimmed(), deps.dimmed());
+            }
+        }
+        return Ok(());
+    };
+
+    let mut runner = TaskRunner::new(config, workspace.root.clone(), project_root).with_force(force);
+    if let Some(jobs) = jobs {
+        runner = runner.with_jobs(jobs);
+    }
+
+    let results = runner.run(task)?;
+    let cached = results.iter().filter(|(_, o)| *o == TaskOutcome::Cached).count();
+    print_info(&format!("{} task(s) finished, {} from cache", results.len(), cached));
+    Ok(())
+}
@@ -0,0 +1,47 @@
+use owo_colors::OwoColorize;
+
+use crate::core::WorkspaceManager;
+use crate::config::{Template, TemplateOrigin, TemplateStore};
+use crate::container::ContainerConfig;
+use crate::error::{Result, SfcError};
+use crate::cli::ui::print_success;
+use crate::cli::commands::TemplateCmd;
+
+/// Handle template operations
+pub fn handle_template(workspace: &WorkspaceManager, cmd: TemplateCmd) -> Result<()> {
+    let store = TemplateStore::new(&workspace.root);
+
+    match cmd {
+        TemplateCmd::Ls => {
+            for (name, origin) in store.list()? {
+                let (template, _) = store.load(&name)?;
+                let tag = match origin {
+                    TemplateOrigin::BuiltIn => "built-in",
+                    TemplateOrigin::User => "user",
+                };
+                println!("{} {} {}", name.cyan().bold(), format!("({})", tag).dimmed(), template.description);
+            }
+        }
+        TemplateCmd::Show { name } => {
+            let (template, _) = store.load(&name)?;
+            let content = toml::to_string_pretty(&template).map_err(|e| SfcError::Config {
+                message: format!("Failed to serialize template: {}", e),
+                path: None,
+            })?;
+            print!("{}", content);
+        }
+        TemplateCmd::SaveFrom { container, name } => {
+            if !ContainerConfig::exists(&workspace.root, &container) {
+                return Err(SfcError::NotFound {
+                    resource: "container".to_string(),
+                    identifier: container,
+                });
+            }
+            let config = ContainerConfig::load(&workspace.root, &container)?;
+            let name = name.unwrap_or_else(|| container.clone());
+            let path = store.save(&name, &Template::from_container(&config))?;
+            print_success(&format!("Saved template '{}' to {}", name, path.display()));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,369 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::Template;
+use crate::container::PackageSpec;
+use crate::error::{Result, SfcError, ErrorContext};
+
+/// Version files `sfc create --detect` looks for, in order of precedence
+pub const DETECTED_FILES: &[&str] = &[
+    ".tool-versions",
+    "mise.toml",
+    ".mise.toml",
+    "rust-toolchain.toml",
+    "rust-toolchain",
+    ".nvmrc",
+    ".node-version",
+    ".python-version",
+    "package.json",
+];
+
+/// Something a version file asked for that sfc has no equivalent for
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Unmapped {
+    pub source: String,
+    pub entry: String,
+    pub reason: String,
+}
+
+/// Result of reading one or more version files
+#[derive(Debug, Clone, Default)]
+pub struct ImportReport {
+    /// Packages, toolchains and env in the same shape `sfc create --template` seeds
+    pub template: Template,
+    pub sources: Vec<PathBuf>,
+    pub unmapped: Vec<Unmapped>,
+}
+
+/// Where a tool from a version file ends up
+enum Target {
+    /// Installed by `setup_toolchains`
+    Toolchain(&'static str),
+    /// Installed through the package backends
+    Package(&'static str),
+}
+
+fn target_for(tool: &str) -> Option<Target> {
+    let target = match tool {
+        "node" | "nodejs" => Target::Toolchain("node"),
+        "npm" => Target::Toolchain("npm"),
+        "rust" => Target::Toolchain("rust"),
+        "python" => Target::Package("python3"),
+        "go" | "golang" => Target::Package("go"),
+        "java" => Target::Package("jdk"),
+        "ruby" => Target::Package("ruby"),
+        "deno" => Target::Package("deno"),
+        "bun" => Target::Package("bun"),
+        "yarn" => Target::Package("yarn"),
+        "pnpm" => Target::Package("pnpm"),
+        "terraform" => Target::Package("terraform"),
+        "kubectl" => Target::Package("kubectl"),
+        "helm" => Target::Package("helm"),
+        "zig" => Target::Package("zig"),
+        "erlang" => Target::Package("erlang"),
+        "elixir" => Target::Package("elixir"),
+        "php" => Target::Package("php"),
+        "lua" => Target::Package("lua"),
+        "cmake" => Target::Package("cmake"),
+        "jq" => Target::Package("jq"),
+        _ => return None,
+    };
+    Some(target)
+}
+
+/// A single concrete version, or None for anything sfc can't pin
+/// (`system`, `ref:`/`path:` specs, multi-clause ranges)
+fn clean_version(raw: &str) -> Option<String> {
+    let version = raw.trim().trim_start_matches('v');
+    if version.is_empty() || version == "system" {
+        return None;
+    }
+    let valid = version
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    valid.then(|| version.to_string())
+}
+
+/// Reduce a package.json `engines` range to the version it starts at
+fn range_floor(range: &str) -> Option<String> {
+    let range = range.trim();
+    if range.contains("||") || range.contains('<') || range.split_whitespace().count() > 1 {
+        return None;
+    }
+    let floor = range.trim_start_matches(['^', '~', '>', '=']);
+    let floor = floor.trim_end_matches(".x").trim_end_matches(".*");
+    clean_version(floor)
+}
+
+impl ImportReport {
+    /// Read every known version file in `dir`
+    pub fn detect(dir: &Path) -> Result<Self> {
+        let mut report = Self::default();
+        for name in DETECTED_FILES {
+            let path = dir.join(name);
+            if path.is_file() {
+                report.read(&path)?;
+            }
+        }
+        Ok(report)
+    }
+
+    /// Read a single version file, picking the parser from its name
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let mut report = Self::default();
+        report.read(path)?;
+        Ok(report)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.template.packages.is_empty()
+            && self.template.toolchains.is_empty()
+            && self.template.environment.is_empty()
+    }
+
+    fn read(&mut self, path: &Path) -> Result<()> {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let content = fs::read_to_string(path)
+            .with_io_context(|| format!("reading {}", path.display()))?;
+
+        match name.as_str() {
+            ".tool-versions" => self.read_tool_versions(&name, &content),
+            "mise.toml" | ".mise.toml" => self.read_mise(path, &name, &content)?,
+            "rust-toolchain.toml" => self.read_rust_toolchain(path, &name, &content)?,
+            "rust-toolchain" => {
+                let channel = content.lines().next().unwrap_or_default();
+                self.add_tool(&name, "rust", channel);
+            }
+            ".nvmrc" | ".node-version" => self.read_nvmrc(&name, &content),
+            ".python-version" => {
+                let version = content.lines().map(str::trim).find(|l| !l.is_empty() && !l.starts_with('#'));
+                self.add_tool(&name, "python", version.unwrap_or_default());
+            }
+            "package.json" => self.read_package_json(path, &name, &content)?,
+            _ => {
+                return Err(SfcError::Validation {
+                    field: "file".to_string(),
+                    value: path.display().to_string(),
+                    reason: format!("unsupported version file; expected one of: {}", DETECTED_FILES.join(", ")),
+                })
+            }
+        }
+        self.sources.push(path.to_path_buf());
+        Ok(())
+    }
+
+    fn skip(&mut self, source: &str, entry: impl Into<String>, reason: impl Into<String>) {
+        self.unmapped.push(Unmapped {
+            source: source.to_string(),
+            entry: entry.into(),
+            reason: reason.into(),
+        });
+    }
+
+    /// Map `tool@version`. The first file to name a tool wins; later
+    /// disagreements are reported rather than silently overriding it.
+    fn add_tool(&mut self, source: &str, tool: &str, version: &str) {
+        let entry = format!("{} {}", tool, version.trim());
+        let Some(target) = target_for(tool) else {
+            self.skip(source, entry, "no sfc package or toolchain for this tool");
+            return;
+        };
+        let Some(version) = clean_version(version) else {
+            self.skip(source, entry, "version can't be pinned (system, ref:, path: or a range)");
+            return;
+        };
+
+        match target {
+            Target::Toolchain(key) => match self.template.toolchains.get(key) {
+                Some(existing) if *existing != version => {
+                    let reason = format!("{} is already set to {}", key, existing);
+                    self.skip(source, entry, reason);
+                }
+                Some(_) => {}
+                None => {
+                    self.template.toolchains.insert(key.to_string(), version);
+                }
+            },
+            Target::Package(name) => {
+                let spec = match version.as_str() {
+                    "latest" => name.to_string(),
+                    _ => format!("{}@{}", name, version),
+                };
+                let existing = self.template.packages.iter().find(|p| PackageSpec::parse(p).name == name);
+                match existing {
+                    Some(existing) if *existing != spec => {
+                        let reason = format!("{} is already set to {}", name, existing);
+                        self.skip(source, entry, reason);
+                    }
+                    Some(_) => {}
+                    None => self.template.packages.push(spec),
+                }
+            }
+        }
+    }
+
+    /// asdf format: `tool version [fallback...]`
+    fn read_tool_versions(&mut self, source: &str, content: &str) {
+        for line in content.lines() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            let mut fields = line.split_whitespace();
+            let (Some(tool), Some(version)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            self.add_tool(source, tool, version);
+        }
+    }
+
+    fn read_mise(&mut self, path: &Path, source: &str, content: &str) -> Result<()> {
+        let doc = parse_toml(path, content)?;
+
+        if let Some(tools) = doc.get("tools").and_then(|t| t.as_table()) {
+            for (tool, value) in tools {
+                if tool.contains(':') {
+                    self.skip(source, tool.clone(), "mise backend plugins aren't supported");
+                    continue;
+                }
+                // "20", ["20", "18"] or { version = "20", ... }
+                let version = match value {
+                    toml::Value::String(s) => Some(s.clone()),
+                    toml::Value::Array(items) => items.first().and_then(|v| v.as_str()).map(str::to_string),
+                    toml::Value::Table(t) => t.get("version").and_then(|v| v.as_str()).map(str::to_string),
+                    _ => None,
+                };
+                match version {
+                    Some(version) => self.add_tool(source, tool, &version),
+                    None => self.skip(source, tool.clone(), "no version string"),
+                }
+            }
+        }
+
+        if let Some(env) = doc.get("env").and_then(|t| t.as_table()) {
+            for (key, value) in env {
+                match value.as_str() {
+                    Some(v) if !key.starts_with('_') => {
+                        self.template.environment.entry(key.clone()).or_insert_with(|| v.to_string());
+                    }
+                    _ => self.skip(source, format!("env.{}", key), "only plain string variables are imported"),
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn read_rust_toolchain(&mut self, path: &Path, source: &str, content: &str) -> Result<()> {
+        let doc = parse_toml(path, content)?;
+        let Some(toolchain) = doc.get("toolchain").and_then(|t| t.as_table()) else {
+            self.skip(source, "[toolchain]", "missing table");
+            return Ok(());
+        };
+
+        match toolchain.get("channel").and_then(|c| c.as_str()) {
+            Some(channel) => self.add_tool(source, "rust", channel),
+            None => self.skip(source, "toolchain.channel", "missing; path toolchains aren't supported"),
+        }
+        for key in ["components", "targets", "profile"] {
+            if let Some(value) = toolchain.get(key) {
+                self.skip(source, format!("toolchain.{} = {}", key, value), "sfc installs the default rustup profile only");
+            }
+        }
+        Ok(())
+    }
+
+    /// `v20.11.0`, `20`, `lts/*` or `node`
+    fn read_nvmrc(&mut self, source: &str, content: &str) {
+        let version = content.lines().map(str::trim).find(|l| !l.is_empty()).unwrap_or_default();
+        match version {
+            "lts/*" => self.add_tool(source, "node", "lts"),
+            "node" | "latest" => self.add_tool(source, "node", "latest"),
+            v if v.starts_with("lts/") => self.skip(source, v, "LTS codenames aren't supported; use a major version"),
+            v => self.add_tool(source, "node", v),
+        }
+    }
+
+    fn read_package_json(&mut self, path: &Path, source: &str, content: &str) -> Result<()> {
+        let doc: serde_json::Value = serde_json::from_str(content).map_err(|e| SfcError::Config {
+            message: format!("Invalid package.json: {}", e),
+            path: Some(path.to_path_buf()),
+        })?;
+
+        let Some(engines) = doc.get("engines").and_then(|e| e.as_object()) else {
+            return Ok(());
+        };
+        for (tool, range) in engines {
+            let Some(range) = range.as_str() else { continue };
+            match range_floor(range) {
+                Some(version) => self.add_tool(source, tool, &version),
+                None => self.skip(source, format!("engines.{} {}", tool, range), "range has no single lower bound"),
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_toml(path: &Path, content: &str) -> Result<toml::Value> {
+    toml::from_str(content).map_err(|e| SfcError::Config {
+        message: format!("Invalid TOML: {}", e),
+        path: Some(path.to_path_buf()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_maps_version_files_and_reports_leftovers() {
+        let dir = tempfile::tempdir().unwrap();
+        fs::write(dir.path().join(".tool-versions"), "nodejs 20.11.0 18.19.0\npython 3.12.1\nawscli 2.15.0\n").unwrap();
+        fs::write(
+            dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.75.0\"\ncomponents = [\"clippy\"]\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join(".nvmrc"), "v18\n").unwrap();
+        fs::write(
+            dir.path().join("package.json"),
+            r#"{"engines": {"npm": "^10.2", "node": ">=18 <21"}}"#,
+        )
+        .unwrap();
+
+        let report = ImportReport::detect(dir.path()).unwrap();
+        let toolchains = &report.template.toolchains;
+        assert_eq!(toolchains.get("node").map(String::as_str), Some("20.11.0"));
+        assert_eq!(toolchains.get("rust").map(String::as_str), Some("1.75.0"));
+        assert_eq!(toolchains.get("npm").map(String::as_str), Some("10.2"));
+        assert_eq!(report.template.packages, vec!["python3@3.12.1".to_string()]);
+        assert_eq!(report.sources.len(), 4);
+
+        let skipped: Vec<&str> = report.unmapped.iter().map(|u| u.entry.as_str()).collect();
+        assert_eq!(
+            skipped,
+            vec!["awscli 2.15.0", "toolchain.components = [\"clippy\"]", "node v18", "engines.node >=18 <21"]
+        );
+    }
+
+    #[test]
+    fn test_mise_tools_and_env() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("mise.toml");
+        fs::write(
+            &path,
+            "[tools]\ngo = \"1.22\"\nnode = [\"lts\", \"18\"]\n\"npm:prettier\" = \"3\"\nrust = { version = \"stable\" }\n\n[env]\nAPP_ENV = \"dev\"\n_.path = [\"bin\"]\n",
+        )
+        .unwrap();
+
+        let report = ImportReport::from_file(&path).unwrap();
+        assert_eq!(report.template.packages, vec!["go@1.22".to_string()]);
+        assert_eq!(report.template.toolchains.get("node").map(String::as_str), Some("lts"));
+        assert_eq!(report.template.toolchains.get("rust").map(String::as_str), Some("stable"));
+        assert_eq!(report.template.environment.get("APP_ENV").map(String::as_str), Some("dev"));
+        assert_eq!(report.unmapped.len(), 2);
+
+        let other = dir.path().join("Gemfile");
+        fs::write(&other, "").unwrap();
+        assert!(matches!(ImportReport::from_file(&other), Err(SfcError::Validation { .. })));
+    }
+}
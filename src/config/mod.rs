@@ -1,3 +1,7 @@
 pub mod settings;
+pub mod template;
+pub mod import;
 
-pub use settings::{SfcConfig, WorkspaceConfig, ContainerDefaults};
+pub use settings::{SfcConfig, WorkspaceConfig, ContainerDefaults, SharePolicy, SharingConfig};
+pub use template::{Template, TemplateStore, TemplateOrigin};
+pub use import::{ImportReport, Unmapped};
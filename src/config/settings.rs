@@ -4,14 +4,16 @@ use std::fs;
 use serde::{Deserialize, Serialize};
 use crate::error::{Result, SfcError, ErrorContext};
 
-/// Main SFC configuration
+/// Main SFC configuration; missing sections take their defaults
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SfcConfig {
     pub workspace: WorkspaceConfig,
     pub defaults: ContainerDefaults,
     pub package_sources: PackageSourceConfig,
     pub ui: UiConfig,
     pub advanced: AdvancedConfig,
+    pub sharing: SharingConfig,
 }
 
 /// Workspace-specific configuration
@@ -82,6 +84,40 @@ pub struct AdvancedConfig {
     pub snapshot_storage: Option<PathBuf>,
 }
 
+/// Signing and verification of shares and bundles
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SharingConfig {
+    /// Which shares 'sfc create --from-bundle' accepts
+    #[serde(default)]
+    pub policy: SharePolicy,
+    /// Key to sign shares with (defaults to the key named "default", if any)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signing_key: Option<String>,
+}
+
+/// Which shares may be imported
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SharePolicy {
+    /// Accept anything, verifying signatures that are present
+    #[default]
+    Any,
+    /// Refuse unsigned shares
+    Signed,
+    /// Refuse shares not signed by one of our keys or a trusted key
+    Trusted,
+}
+
+impl SharePolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SharePolicy::Any => "any",
+            SharePolicy::Signed => "signed",
+            SharePolicy::Trusted => "trusted",
+        }
+    }
+}
+
 impl Default for SfcConfig {
     fn default() -> Self {
         Self {
@@ -90,6 +126,7 @@ impl Default for SfcConfig {
             package_sources: PackageSourceConfig::default(),
             ui: UiConfig::default(),
             advanced: AdvancedConfig::default(),
+            sharing: SharingConfig::default(),
         }
     }
 }
@@ -0,0 +1,269 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use serde::{Deserialize, Serialize};
+
+use crate::config::ContainerDefaults;
+use crate::container::{ContainerConfig, PackageSpec};
+use crate::core::task::TaskSpec;
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::sfc::validate_name;
+
+/// Starting point for a new container: what `sfc create --template` seeds
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Template {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub description: String,
+    /// Package specs as accepted by `sfc add`
+    #[serde(default)]
+    pub packages: Vec<String>,
+    #[serde(default)]
+    pub environment: HashMap<String, String>,
+    #[serde(default)]
+    pub toolchains: HashMap<String, String>,
+    #[serde(default)]
+    pub tasks: BTreeMap<String, TaskSpec>,
+}
+
+/// Where a template was found
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateOrigin {
+    BuiltIn,
+    User,
+}
+
+const BUILTIN_NAMES: &[&str] = &["node", "python-data", "rust"];
+
+fn task(command: &str, depends_on: &[&str], inputs: &[&str]) -> TaskSpec {
+    TaskSpec {
+        command: command.to_string(),
+        depends_on: depends_on.iter().map(|s| s.to_string()).collect(),
+        inputs: inputs.iter().map(|s| s.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn pairs(items: &[(&str, &str)]) -> HashMap<String, String> {
+    items.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+}
+
+impl Template {
+    /// Templates shipped with sfc
+    pub fn builtin(name: &str) -> Option<Self> {
+        let template = match name {
+            "rust" => Self {
+                description: "Rust crate with cargo build/test/lint tasks".to_string(),
+                packages: vec!["pkg-config".to_string()],
+                environment: pairs(&[("CARGO_TERM_COLOR", "always"), ("RUST_BACKTRACE", "1")]),
+                toolchains: pairs(&[("rust", "stable")]),
+                tasks: BTreeMap::from([
+                    ("build".to_string(), task("cargo build", &[], &["Cargo.toml", "Cargo.lock", "src"])),
+                    ("test".to_string(), task("cargo test", &["build"], &["Cargo.toml", "Cargo.lock", "src", "tests"])),
+                    ("lint".to_string(), task("cargo clippy -- -D warnings", &[], &["Cargo.toml", "src"])),
+                ]),
+            },
+            "node" => Self {
+                description: "Node.js project with npm install/build/test tasks".to_string(),
+                packages: Vec::new(),
+                environment: pairs(&[("NODE_ENV", "development")]),
+                toolchains: pairs(&[("node", "20")]),
+                tasks: BTreeMap::from([
+                    ("install".to_string(), task("npm ci", &[], &["package.json", "package-lock.json"])),
+                    ("build".to_string(), task("npm run build", &["install"], &["package.json", "src"])),
+                    ("test".to_string(), task("npm test", &["install"], &[])),
+                ]),
+            },
+            "python-data" => Self {
+                description: "Python data work: pip requirements and Jupyter".to_string(),
+                packages: vec!["python3".to_string(), "jq".to_string()],
+                environment: pairs(&[
+                    ("PYTHONDONTWRITEBYTECODE", "1"),
+                    ("PIP_DISABLE_PIP_VERSION_CHECK", "1"),
+                ]),
+                toolchains: HashMap::new(),
+                tasks: BTreeMap::from([
+                    ("deps".to_string(), task("python3 -m pip install -r requirements.txt", &[], &["requirements.txt"])),
+                    ("notebook".to_string(), task("python3 -m jupyter lab", &["deps"], &[])),
+                ]),
+            },
+            _ => return None,
+        };
+        Some(template)
+    }
+
+    /// Capture the reusable parts of a container's config
+    pub fn from_container(container: &ContainerConfig) -> Self {
+        let mut environment = container.environment.clone();
+        // PATH points into the source container's directory
+        environment.remove("PATH");
+
+        Self {
+            description: format!("Saved from container '{}'", container.name),
+            packages: container.packages.iter().map(PackageSpec::to_spec_string).collect(),
+            environment,
+            toolchains: container.toolchains.iter().map(|(k, v)| (k.clone(), v.clone())).collect(),
+            tasks: container.tasks.clone(),
+        }
+    }
+
+    /// Configured defaults with this template layered on top
+    pub fn over_defaults(&self, defaults: &ContainerDefaults) -> Self {
+        let mut merged = Self {
+            description: self.description.clone(),
+            packages: defaults.packages.clone(),
+            environment: defaults.environment.clone(),
+            toolchains: defaults.toolchains.clone(),
+            tasks: BTreeMap::new(),
+        };
+        merged.extend(self);
+        merged
+    }
+
+    /// Layer `top` over this template; a package in both keeps `top`'s spec
+    pub fn extend(&mut self, top: &Template) {
+        for spec in &top.packages {
+            let name = PackageSpec::parse(spec).name;
+            self.packages.retain(|p| PackageSpec::parse(p).name != name);
+            self.packages.push(spec.clone());
+        }
+        self.environment.extend(top.environment.clone());
+        self.toolchains.extend(top.toolchains.clone());
+        self.tasks.extend(top.tasks.clone());
+    }
+
+    /// Seed environment, toolchains and tasks into `container`. Packages are
+    /// left to the caller, which normally installs them.
+    pub fn apply(&self, container: &mut ContainerConfig) {
+        container.environment.extend(self.environment.clone());
+        container.toolchains.extend(self.toolchains.clone());
+        container.tasks.extend(self.tasks.clone());
+    }
+}
+
+/// User templates in `<workspace>/templates/*.toml`, falling back to built-ins
+pub struct TemplateStore {
+    dir: PathBuf,
+}
+
+impl TemplateStore {
+    pub fn new<P: AsRef<Path>>(workspace_root: P) -> Self {
+        Self {
+            dir: workspace_root.as_ref().join("templates"),
+        }
+    }
+
+    /// Template names are file stems, so they follow the container name rules
+    fn path(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.dir.join(format!("{}.toml", name)))
+    }
+
+    /// All template names, user templates shadowing built-ins of the same name
+    pub fn list(&self) -> Result<Vec<(String, TemplateOrigin)>> {
+        let mut names: BTreeMap<String, TemplateOrigin> = BUILTIN_NAMES
+            .iter()
+            .map(|n| (n.to_string(), TemplateOrigin::BuiltIn))
+            .collect();
+
+        if self.dir.exists() {
+            let entries = fs::read_dir(&self.dir)
+                .with_io_context(|| format!("reading templates in {}", self.dir.display()))?;
+            for entry in entries.flatten() {
+                let path = entry.path();
+                if path.extension().is_some_and(|ext| ext == "toml") {
+                    if let Some(stem) = path.file_stem() {
+                        names.insert(stem.to_string_lossy().to_string(), TemplateOrigin::User);
+                    }
+                }
+            }
+        }
+        Ok(names.into_iter().collect())
+    }
+
+    pub fn load(&self, name: &str) -> Result<(Template, TemplateOrigin)> {
+        let path = self.path(name)?;
+        if path.exists() {
+            let content = fs::read_to_string(&path)
+                .with_io_context(|| format!("reading template {}", path.display()))?;
+            let template = toml::from_str(&content).map_err(|e| SfcError::Config {
+                message: format!("Invalid template '{}': {}", name, e),
+                path: Some(path.clone()),
+            })?;
+            return Ok((template, TemplateOrigin::User));
+        }
+
+        Template::builtin(name)
+            .map(|t| (t, TemplateOrigin::BuiltIn))
+            .ok_or_else(|| SfcError::NotFound {
+                resource: "template".to_string(),
+                identifier: name.to_string(),
+            })
+    }
+
+    pub fn save(&self, name: &str, template: &Template) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        fs::create_dir_all(&self.dir)
+            .with_io_context(|| format!("creating {}", self.dir.display()))?;
+        let content = toml::to_string_pretty(template).map_err(|e| SfcError::Config {
+            message: format!("Failed to serialize template: {}", e),
+            path: Some(path.clone()),
+        })?;
+        fs::write(&path, content)
+            .with_io_context(|| format!("writing template {}", path.display()))?;
+        Ok(path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_user_templates_shadow_builtins_and_round_trip() {
+        let ws = tempfile::tempdir().unwrap();
+        let store = TemplateStore::new(ws.path());
+
+        let mut container = ContainerConfig::new("api".to_string());
+        container.add_package(PackageSpec::parse("ripgrep@14")).unwrap();
+        container.add_package(PackageSpec::parse("github:owner/tool@v1")).unwrap();
+        container.environment.insert("PATH".to_string(), "/somewhere".to_string());
+        container.environment.insert("RUST_LOG".to_string(), "debug".to_string());
+        let saved = Template::from_container(&container);
+        store.save("rust", &saved).unwrap();
+
+        let (loaded, origin) = store.load("rust").unwrap();
+        assert_eq!(origin, TemplateOrigin::User);
+        assert_eq!(loaded, saved);
+        assert_eq!(loaded.packages, vec!["ripgrep@14".to_string(), "github:owner/tool@v1".to_string()]);
+        assert!(!loaded.environment.contains_key("PATH"));
+
+        let listed = store.list().unwrap();
+        assert!(listed.contains(&("rust".to_string(), TemplateOrigin::User)));
+        assert!(listed.contains(&("node".to_string(), TemplateOrigin::BuiltIn)));
+        assert!(matches!(store.load("missing"), Err(SfcError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_template_names_stay_in_the_templates_dir() {
+        let ws = tempfile::tempdir().unwrap();
+        let store = TemplateStore::new(ws.path().join("workspace"));
+        for name in ["../../x", "../escape", "a/b", ""] {
+            assert!(store.save(name, &Template::default()).is_err(), "{}", name);
+            assert!(store.load(name).is_err(), "{}", name);
+        }
+        assert!(!ws.path().join("x.toml").exists());
+        assert!(!ws.path().join("escape.toml").exists());
+        assert!(!ws.path().join("workspace").join("templates").exists());
+    }
+
+    #[test]
+    fn test_template_overrides_defaults() {
+        let defaults = ContainerDefaults::default();
+        let template = Template {
+            packages: vec!["git@2.45".to_string(), "cmake".to_string()],
+            ..Default::default()
+        };
+        let merged = template.over_defaults(&defaults);
+        assert_eq!(merged.packages, vec!["curl", "jq", "git@2.45", "cmake"]);
+    }
+}
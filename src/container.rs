@@ -1,6 +1,8 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::os::unix::process::{CommandExt, ExitStatusExt};
+use std::process::{Command, ExitStatus};
 use anyhow::{anyhow, Context, Result};
 use owo_colors::OwoColorize;
 use serde::{Deserialize, Serialize};
@@ -9,14 +11,52 @@ use chrono::{DateTime, Utc};
 
 use crate::history::HistoryEntry;
 use crate::flake::FlakeConfig;
+use crate::core::task::TaskSpec;
+use crate::system::sandbox::{self, Isolation, SandboxOptions};
+use crate::system::shell::ShellKind;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ContainerConfig {
     pub name: String,
     pub created_at: DateTime<Utc>,
+    /// Containers this one layers on top of; later entries override earlier ones
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub extends: Vec<String>,
     pub packages: Vec<PackageSpec>,
     pub environment: std::collections::HashMap<String, String>,
+    /// Toolchain versions by name (node, npm, rust)
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub toolchains: BTreeMap<String, String>,
+    /// Inherited entries this container drops
+    #[serde(default, skip_serializing_if = "Removals::is_empty")]
+    pub remove: Removals,
     pub shell: String,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub tasks: BTreeMap<String, TaskSpec>,
+    #[serde(default, skip_serializing_if = "Isolation::is_none")]
+    pub isolation: Isolation,
+    #[serde(default, skip_serializing_if = "SandboxOptions::is_default")]
+    pub sandbox: SandboxOptions,
+    /// Flake reference for the nixpkgs input (default: nixos-unstable)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nixpkgs: Option<String>,
+}
+
+/// Names removed from what a container inherits through `extends`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Removals {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub environment: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub toolchains: Vec<String>,
+}
+
+impl Removals {
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.environment.is_empty() && self.toolchains.is_empty()
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -43,15 +83,23 @@ impl ContainerConfig {
         Self {
             name,
             created_at: Utc::now(),
+            extends: Vec::new(),
             packages: Vec::new(),
             environment: std::collections::HashMap::new(),
+            toolchains: BTreeMap::new(),
+            remove: Removals::default(),
             shell: current_shell,
+            tasks: BTreeMap::new(),
+            isolation: Isolation::None,
+            sandbox: SandboxOptions::default(),
+            nixpkgs: None,
         }
     }
 
     pub fn compute_hash(&self) -> Result<String> {
         let mut hasher = Sha256::new();
-        let serialized = serde_json::to_string(self)?;
+        // Through a Value so map keys are sorted and the hash is stable
+        let serialized = serde_json::to_value(self)?.to_string();
         hasher.update(serialized.as_bytes());
         let digest = hasher.finalize();
         Ok(format!("{:x}", digest)[..16].to_string()) // Short hash
@@ -71,6 +119,25 @@ impl ContainerConfig {
         Ok(self.packages.len() < len_before)
     }
 
+    /// Take every setting but the name, creation time and packages from
+    /// `desired` (as `sfc apply` does). PATH keeps pointing at this host's
+    /// installs unless `desired` sets it.
+    pub fn apply_settings(&mut self, desired: &ContainerConfig) {
+        let host_path = self.environment.get("PATH").cloned();
+        self.extends = desired.extends.clone();
+        self.environment = desired.environment.clone();
+        if let Some(path) = host_path {
+            self.environment.entry("PATH".to_string()).or_insert(path);
+        }
+        self.toolchains = desired.toolchains.clone();
+        self.remove = desired.remove.clone();
+        self.shell = desired.shell.clone();
+        self.tasks = desired.tasks.clone();
+        self.isolation = desired.isolation;
+        self.sandbox = desired.sandbox.clone();
+        self.nixpkgs = desired.nixpkgs.clone();
+    }
+
     pub fn save(&self, workspace: &Path) -> Result<()> {
         let config_dir = workspace.join(".sfc").join("containers");
         fs::create_dir_all(&config_dir)?;
@@ -91,30 +158,171 @@ impl ContainerConfig {
         Ok(config)
     }
 
+    /// Whether `name` has a config or a container directory in `workspace`
+    pub fn exists(workspace: &Path, name: &str) -> bool {
+        workspace.join(".sfc").join("containers").join(format!("{}.toml", name)).exists()
+            || workspace.join("containers").join(name).is_dir()
+    }
+
+    /// Load a container with everything it inherits applied
+    pub fn load_effective(workspace: &Path, name: &str) -> Result<Self> {
+        Self::load(workspace, name)?.effective(workspace)
+    }
+
+    /// Merge in everything this container inherits. In the result, `extends`
+    /// lists every ancestor, nearest first.
+    pub fn effective(&self, workspace: &Path) -> Result<Self> {
+        self.resolve(workspace, &mut Vec::new())
+    }
+
+    fn resolve(&self, workspace: &Path, stack: &mut Vec<String>) -> Result<Self> {
+        if self.extends.is_empty() {
+            return Ok(self.clone());
+        }
+        if stack.contains(&self.name) {
+            stack.push(self.name.clone());
+            return Err(anyhow!("container inheritance cycle: {}", stack.join(" -> ")));
+        }
+        stack.push(self.name.clone());
+
+        let mut base: Option<Self> = None;
+        let mut branches: Vec<Vec<String>> = Vec::new();
+        for parent_name in &self.extends {
+            if !Self::exists(workspace, parent_name) {
+                return Err(anyhow!("container '{}' extends '{}', which does not exist", self.name, parent_name));
+            }
+            let parent = Self::load(workspace, parent_name)?.resolve(workspace, stack)?;
+            branches.push(std::iter::once(parent_name.clone()).chain(parent.extends.iter().cloned()).collect());
+            base = Some(match base {
+                Some(base) => base.layer(&parent),
+                None => parent,
+            });
+        }
+        stack.pop();
+
+        // Later parents take precedence, so their lineage comes first
+        let mut ancestors: Vec<String> = Vec::new();
+        for name in branches.into_iter().rev().flatten() {
+            if !ancestors.contains(&name) {
+                ancestors.push(name);
+            }
+        }
+
+        let mut merged = base.expect("extends is not empty").layer(self);
+        merged.extends = ancestors;
+        Ok(merged)
+    }
+
+    /// `top` applied over `self`: `top` wins on conflicts and its removals apply
+    /// to what `self` provides
+    fn layer(&self, top: &Self) -> Self {
+        let mut packages: Vec<PackageSpec> = self.packages.iter()
+            .filter(|p| !top.remove.packages.contains(&p.name))
+            .cloned()
+            .collect();
+        for spec in &top.packages {
+            packages.retain(|p| p.name != spec.name);
+            packages.push(spec.clone());
+        }
+        packages.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let mut environment: HashMap<String, String> = self.environment.iter()
+            .filter(|(k, _)| !top.remove.environment.contains(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        for (key, value) in &top.environment {
+            let value = match (key.as_str(), environment.get(key)) {
+                ("PATH", Some(inherited)) => compose_path(value, inherited),
+                _ => value.clone(),
+            };
+            environment.insert(key.clone(), value);
+        }
+
+        let mut toolchains: BTreeMap<String, String> = self.toolchains.iter()
+            .filter(|(k, _)| !top.remove.toolchains.contains(k))
+            .map(|(k, v)| (k.clone(), v.clone()))
+            .collect();
+        toolchains.extend(top.toolchains.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        let mut tasks = self.tasks.clone();
+        tasks.extend(top.tasks.iter().map(|(k, v)| (k.clone(), v.clone())));
+
+        Self {
+            name: top.name.clone(),
+            created_at: top.created_at,
+            extends: top.extends.clone(),
+            packages,
+            environment,
+            toolchains,
+            remove: Removals::default(),
+            shell: top.shell.clone(),
+            tasks,
+            isolation: top.isolation,
+            sandbox: top.sandbox.clone(),
+            nixpkgs: top.nixpkgs.clone().or_else(|| self.nixpkgs.clone()),
+        }
+    }
+
+    /// Compose the environment seen by processes running inside this container.
+    /// The `local/bin` of this container and of each container in `extends` are
+    /// put in front of the configured PATH, or of `base_path` when none is set.
+    pub fn activation_env(&self, workspace: &Path, base_path: &str) -> HashMap<String, String> {
+        let mut env = self.environment.clone();
+        env.insert("SFC_CONTAINER".to_string(), self.name.clone());
+        env.insert("SFC_WORKSPACE".to_string(), workspace.to_string_lossy().to_string());
+
+        let bins: Vec<String> = std::iter::once(&self.name)
+            .chain(self.extends.iter())
+            .map(|name| workspace.join("containers").join(name).join("local").join("bin"))
+            .filter(|dir| dir.is_dir())
+            .map(|dir| dir.to_string_lossy().to_string())
+            .collect();
+        let rest = env.get("PATH").cloned().unwrap_or_else(|| base_path.to_string());
+        env.insert("PATH".to_string(), compose_path(&bins.join(":"), &rest));
+        env
+    }
+
     pub fn enter_shell(&self, workspace: &Path) -> Result<()> {
+        self.enter_shell_with(workspace, &self.name, &HashMap::new(), &[])
+    }
+
+    /// Enter the container's shell with `extra` variables on top of its own
+    /// environment and `bins` ahead of its PATH; `label` shows in the prompt
+    pub fn enter_shell_with(&self, workspace: &Path, label: &str, extra: &HashMap<String, String>, bins: &[PathBuf]) -> Result<()> {
         let container_dir = workspace.join("containers").join(&self.name);
         fs::create_dir_all(&container_dir)?;
 
         // Build environment
-        let mut env = self.environment.clone();
-        env.insert("SFC_CONTAINER".to_string(), self.name.clone());
-        env.insert("SFC_WORKSPACE".to_string(), workspace.to_string_lossy().to_string());
-        
-        // Set PS1 to show container
-        let ps1 = format!("\\[\\033[32m\\]sfc[{}]\\[\\033[0m\\] \\w $ ", self.name);
-        env.insert("PS1".to_string(), ps1);
+        let base_path = std::env::var("PATH").unwrap_or_default();
+        let mut env = self.activation_env(workspace, &base_path);
+        env.extend(extra.iter().map(|(k, v)| (k.clone(), v.clone())));
+        if !bins.is_empty() {
+            let first: Vec<String> = bins.iter().map(|b| b.to_string_lossy().to_string()).collect();
+            let rest = env.get("PATH").cloned().unwrap_or_default();
+            env.insert("PATH".to_string(), compose_path(&first.join(":"), &rest));
+        }
 
-        println!("{} {}", "Entering container".green(), self.name.cyan());
+        // Prompt integration depends on the shell we are about to spawn
+        let shell_kind = ShellKind::from_path(&self.shell);
+        if let Some(kind) = shell_kind {
+            env.extend(kind.prompt_env(label));
+        }
+
+        println!("{} {}", "Entering container".green(), label.cyan());
         let package_names: Vec<String> = self.packages.iter().map(|p| p.name.clone()).collect();
         println!("{} packages: {}", "Active".dimmed(), package_names.join(", "));
 
         // Spawn shell in container directory
         let mut cmd = Command::new(&self.shell);
         cmd.current_dir(&container_dir);
+        if let Some(kind) = shell_kind {
+            cmd.args(kind.prompt_args(label));
+        }
         for (k, v) in env {
             cmd.env(k, v);
         }
-        
+        self.isolate(workspace, &mut cmd)?;
+
         let status = cmd.status()?;
         if !status.success() {
             return Err(anyhow!("Shell exited with non-zero status"));
@@ -122,12 +330,84 @@ impl ContainerConfig {
         Ok(())
     }
 
+    /// Apply this container's `isolation` setting to a command before it is spawned
+    pub fn isolate(&self, workspace: &Path, cmd: &mut Command) -> std::io::Result<()> {
+        if self.isolation == Isolation::Sandbox {
+            sandbox::apply(cmd, workspace, &self.name, &self.extends, &self.sandbox)?;
+        }
+        Ok(())
+    }
+
+    /// Build a command that runs `program` directly (no shell re-parsing) in this
+    /// container's environment. PATH lookup uses the container's PATH.
+    pub fn command(&self, workspace: &Path, program: &str) -> std::io::Result<Command> {
+        let base_path = std::env::var("PATH").unwrap_or_default();
+        let mut cmd = Command::new(program);
+        cmd.envs(self.activation_env(workspace, &base_path));
+        self.isolate(workspace, &mut cmd)?;
+        Ok(cmd)
+    }
+
+    /// Replace the current process with `argv` running inside this container.
+    /// Only returns if the program could not be started.
+    pub fn exec(&self, workspace: &Path, argv: &[String]) -> std::io::Error {
+        let Some((program, args)) = argv.split_first() else {
+            return std::io::Error::new(std::io::ErrorKind::InvalidInput, "no command given");
+        };
+        match self.command(workspace, program) {
+            Ok(mut cmd) => cmd.args(args).exec(),
+            Err(e) => e,
+        }
+    }
+
     pub fn to_flake(&self) -> FlakeConfig {
         FlakeConfig::from_container(self)
     }
 }
 
 impl PackageSpec {
+    /// Parse the spec syntax accepted by `sfc add`: `name[@version]`,
+    /// `github:owner/repo[@rev]` or an http(s) URL
+    pub fn parse(spec: &str) -> Self {
+        if let Some(rest) = spec.strip_prefix("github:") {
+            let (repo, rev) = rest.split_once('@').unwrap_or((rest, "main"));
+            let name = repo.rsplit('/').next().unwrap_or(repo).to_string();
+            return Self {
+                name,
+                version: None,
+                channel: None,
+                source: PackageSource::GitHub { repo: repo.to_string(), rev: rev.to_string() },
+            };
+        }
+
+        if spec.starts_with("http") {
+            let name = spec.rsplit('/').next().unwrap_or(spec).to_string();
+            return Self {
+                name,
+                version: None,
+                channel: None,
+                source: PackageSource::Url(spec.to_string()),
+            };
+        }
+
+        match spec.split_once('@') {
+            Some((name, version)) => Self::from_name(name).with_version(version),
+            None => Self::from_name(spec),
+        }
+    }
+
+    /// Inverse of [`PackageSpec::parse`]
+    pub fn to_spec_string(&self) -> String {
+        match &self.source {
+            PackageSource::GitHub { repo, rev } => format!("github:{}@{}", repo, rev),
+            PackageSource::Url(url) => url.clone(),
+            PackageSource::Nixpkgs => match &self.version {
+                Some(version) => format!("{}@{}", self.name, version),
+                None => self.name.clone(),
+            },
+        }
+    }
+
     pub fn from_name(name: &str) -> Self {
         Self {
             name: name.to_string(),
@@ -147,3 +427,129 @@ impl PackageSpec {
         self
     }
 }
+
+/// `first` followed by the entries of `rest` it doesn't already contain
+fn compose_path(first: &str, rest: &str) -> String {
+    let mut parts: Vec<&str> = first.split(':').filter(|p| !p.is_empty()).collect();
+    for part in rest.split(':').filter(|p| !p.is_empty()) {
+        if !parts.contains(&part) {
+            parts.push(part);
+        }
+    }
+    parts.join(":")
+}
+
+/// Shell-style exit code for a finished process (128 + signal when killed by a signal)
+pub fn exit_code(status: &ExitStatus) -> i32 {
+    status.code()
+        .or_else(|| status.signal().map(|sig| 128 + sig))
+        .unwrap_or(1)
+}
+
+/// Shell-style exit code for a program that could not be started
+pub fn spawn_error_code(err: &std::io::Error) -> i32 {
+    match err.kind() {
+        std::io::ErrorKind::NotFound => 127,
+        _ => 126,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_exit_codes_follow_the_shell() {
+        let ws = tempfile::tempdir().unwrap();
+        let config = ContainerConfig::new("web".to_string());
+        let run = |script: &str| {
+            let status = config.command(ws.path(), "sh").unwrap().args(["-c", script]).status().unwrap();
+            exit_code(&status)
+        };
+        assert_eq!(run("exit 0"), 0);
+        assert_eq!(run("exit 3"), 3);
+        assert_eq!(run("kill -TERM $$"), 128 + 15);
+        assert_eq!(run("kill -KILL $$"), 128 + 9);
+
+        // exec only returns when the program could not be started
+        let missing = config.exec(ws.path(), &["sfc-no-such-program".to_string()]);
+        assert_eq!(spawn_error_code(&missing), 127);
+        let script = ws.path().join("not-executable");
+        fs::write(&script, "#!/bin/sh\n").unwrap();
+        let denied = config.exec(ws.path(), &[script.display().to_string()]);
+        assert_eq!(spawn_error_code(&denied), 126);
+        assert_eq!(spawn_error_code(&config.exec(ws.path(), &[])), 126);
+    }
+
+    #[test]
+    fn test_apply_settings_takes_nixpkgs_and_keeps_host_path() {
+        let mut config = ContainerConfig::new("web".to_string());
+        config.add_package(PackageSpec::from_name("git")).unwrap();
+        config.environment.insert("PATH".to_string(), "/host/bin".to_string());
+        let mut desired = ContainerConfig::new("web".to_string());
+        desired.nixpkgs = Some("github:NixOS/nixpkgs/nixos-24.05".to_string());
+        desired.environment.insert("EDITOR".to_string(), "vim".to_string());
+        desired.toolchains.insert("node".to_string(), "20".to_string());
+
+        config.apply_settings(&desired);
+        assert_eq!(config.nixpkgs.as_deref(), Some("github:NixOS/nixpkgs/nixos-24.05"));
+        assert_eq!(config.environment["PATH"], "/host/bin");
+        assert_eq!(config.environment["EDITOR"], "vim");
+        assert_eq!(config.toolchains["node"], "20");
+        assert_eq!(config.packages.len(), 1);
+
+        desired.nixpkgs = None;
+        config.apply_settings(&desired);
+        assert_eq!(config.nixpkgs, None);
+    }
+
+    #[test]
+    fn test_effective_merges_parents_with_overrides_and_removals() {
+        let ws = tempfile::tempdir().unwrap();
+
+        let mut base = ContainerConfig::new("base".to_string());
+        base.add_package(PackageSpec::from_name("git")).unwrap();
+        base.add_package(PackageSpec::from_name("htop")).unwrap();
+        base.add_package(PackageSpec::from_name("node").with_version("18")).unwrap();
+        base.environment.insert("EDITOR".to_string(), "vim".to_string());
+        base.environment.insert("DEBUG".to_string(), "1".to_string());
+        base.toolchains.insert("node".to_string(), "18".to_string());
+        base.save(ws.path()).unwrap();
+
+        let mut app = ContainerConfig::new("app".to_string());
+        app.extends = vec!["base".to_string()];
+        app.add_package(PackageSpec::from_name("node").with_version("20")).unwrap();
+        app.environment.insert("EDITOR".to_string(), "nano".to_string());
+        app.remove.packages = vec!["htop".to_string()];
+        app.remove.environment = vec!["DEBUG".to_string()];
+        app.save(ws.path()).unwrap();
+
+        let effective = ContainerConfig::load_effective(ws.path(), "app").unwrap();
+        let packages: Vec<(&str, Option<&str>)> = effective.packages.iter()
+            .map(|p| (p.name.as_str(), p.version.as_deref()))
+            .collect();
+        assert_eq!(packages, vec![("git", None), ("node", Some("20"))]);
+        assert_eq!(effective.environment.get("EDITOR").map(String::as_str), Some("nano"));
+        assert!(!effective.environment.contains_key("DEBUG"));
+        assert_eq!(effective.toolchains.get("node").map(String::as_str), Some("18"));
+        assert_eq!(effective.extends, vec!["base".to_string()]);
+    }
+
+    #[test]
+    fn test_effective_rejects_cycles_and_missing_parents() {
+        let ws = tempfile::tempdir().unwrap();
+
+        let mut a = ContainerConfig::new("a".to_string());
+        a.extends = vec!["b".to_string()];
+        a.save(ws.path()).unwrap();
+        let mut b = ContainerConfig::new("b".to_string());
+        b.extends = vec!["a".to_string()];
+        b.save(ws.path()).unwrap();
+        let mut c = ContainerConfig::new("c".to_string());
+        c.extends = vec!["missing".to_string()];
+        c.save(ws.path()).unwrap();
+
+        assert!(ContainerConfig::load_effective(ws.path(), "a").is_err());
+        assert!(ContainerConfig::load_effective(ws.path(), "c").is_err());
+    }
+}
@@ -0,0 +1,576 @@
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::{symlink, OpenOptionsExt, PermissionsExt};
+use std::path::{Component, Path, PathBuf};
+
+use crate::error::{Result, SfcError, ErrorContext};
+
+const BLOCK: usize = 512;
+
+/// Minimal ustar writer (PAX headers for long names). Entries get a fixed
+/// owner and mtime so the same tree always produces the same bytes.
+pub struct TarWriter<W: Write> {
+    inner: W,
+    mtime: u64,
+}
+
+impl<W: Write> TarWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self { inner, mtime: 0 }
+    }
+
+    /// Timestamp recorded on every entry (seconds since the epoch)
+    pub fn with_mtime(mut self, mtime: u64) -> Self {
+        self.mtime = mtime;
+        self
+    }
+
+    pub fn append_dir(&mut self, path: &str, mode: u32) -> io::Result<()> {
+        let path = format!("{}/", path.trim_end_matches('/'));
+        self.header(&path, mode, 0, b'5', "")
+    }
+
+    pub fn append_file(&mut self, path: &str, mode: u32, data: &[u8]) -> io::Result<()> {
+        self.header(path, mode, data.len() as u64, b'0', "")?;
+        self.inner.write_all(data)?;
+        self.pad(data.len())
+    }
+
+    pub fn append_symlink(&mut self, path: &str, target: &str) -> io::Result<()> {
+        self.header(path, 0o777, 0, b'2', target)
+    }
+
+    /// Add `src` and everything below it as `prefix/...`, in sorted order.
+    /// Symlinks are stored as links, never followed.
+    pub fn append_tree(&mut self, src: &Path, prefix: &str) -> Result<()> {
+        let meta = fs::symlink_metadata(src)
+            .with_io_context(|| format!("reading {}", src.display()))?;
+        let mode = meta.permissions().mode() & 0o7777;
+
+        if meta.file_type().is_symlink() {
+            let target = fs::read_link(src)
+                .with_io_context(|| format!("reading link {}", src.display()))?;
+            self.append_symlink(prefix, &target.to_string_lossy())
+                .with_io_context(|| format!("archiving {}", src.display()))?;
+        } else if meta.is_dir() {
+            if !prefix.is_empty() {
+                self.append_dir(prefix, mode)
+                    .with_io_context(|| format!("archiving {}", src.display()))?;
+            }
+            let mut entries: Vec<_> = fs::read_dir(src)
+                .with_io_context(|| format!("reading {}", src.display()))?
+                .flatten()
+                .collect();
+            entries.sort_by_key(|e| e.file_name());
+            for entry in entries {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let child = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+                self.append_tree(&entry.path(), &child)?;
+            }
+        } else {
+            let data = fs::read(src).with_io_context(|| format!("reading {}", src.display()))?;
+            self.append_file(prefix, mode, &data)
+                .with_io_context(|| format!("archiving {}", src.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Add an entry read by [`read_tar`] or built by [`tree_entries`]
+    pub fn append_entry(&mut self, entry: &TarEntry) -> io::Result<()> {
+        match &entry.kind {
+            EntryKind::File(data) => self.append_file(&entry.path, entry.mode, data),
+            EntryKind::Dir => self.append_dir(&entry.path, entry.mode),
+            EntryKind::Symlink(target) => self.append_symlink(&entry.path, target),
+        }
+    }
+
+    /// Write the end-of-archive marker and hand back the writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.inner.write_all(&[0u8; BLOCK * 2])?;
+        self.inner.flush()?;
+        Ok(self.inner)
+    }
+
+    fn header(&mut self, path: &str, mode: u32, size: u64, kind: u8, link: &str) -> io::Result<()> {
+        let (name, prefix) = split_path(path);
+        let needs_pax = name.is_none() || link.len() > 100;
+        let (name, prefix) = name.map(|n| (n, prefix)).unwrap_or(("", ""));
+
+        if needs_pax {
+            let mut records = pax_record("path", path);
+            if link.len() > 100 {
+                records.push_str(&pax_record("linkpath", link));
+            }
+            let short = format!("PaxHeaders/{}", truncate(path.rsplit('/').find(|s| !s.is_empty()).unwrap_or("entry"), 80));
+            let block = header_block(&short, "", 0o644, records.len() as u64, self.mtime, b'x', "");
+            self.inner.write_all(&block)?;
+            self.inner.write_all(records.as_bytes())?;
+            self.pad(records.len())?;
+            let fallback = truncate(path, 100);
+            let block = header_block(fallback, "", mode, size, self.mtime, kind, truncate(link, 100));
+            return self.inner.write_all(&block);
+        }
+
+        let block = header_block(name, prefix, mode, size, self.mtime, kind, link);
+        self.inner.write_all(&block)
+    }
+
+    fn pad(&mut self, len: usize) -> io::Result<()> {
+        let rem = len % BLOCK;
+        if rem != 0 {
+            self.inner.write_all(&[0u8; BLOCK][..BLOCK - rem])?;
+        }
+        Ok(())
+    }
+}
+
+/// Split into ustar (name, prefix), or None when the path doesn't fit
+fn split_path(path: &str) -> (Option<&str>, &str) {
+    if path.len() <= 100 {
+        return (Some(path), "");
+    }
+    // The prefix must end at a '/', which is dropped from both halves
+    for (i, _) in path.match_indices('/') {
+        let (prefix, rest) = (&path[..i], &path[i + 1..]);
+        if prefix.len() <= 155 && rest.len() <= 100 && !rest.is_empty() {
+            return (Some(rest), prefix);
+        }
+    }
+    (None, "")
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    let mut end = s.len().min(max);
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
+/// `<len> <key>=<value>\n`, where len counts the whole record
+fn pax_record(key: &str, value: &str) -> String {
+    let body = format!(" {}={}\n", key, value);
+    let mut len = body.len() + 1;
+    while len.to_string().len() + body.len() != len {
+        len = len.to_string().len() + body.len();
+    }
+    format!("{}{}", len, body)
+}
+
+fn header_block(name: &str, prefix: &str, mode: u32, size: u64, mtime: u64, kind: u8, link: &str) -> [u8; BLOCK] {
+    let mut h = [0u8; BLOCK];
+    put(&mut h[0..100], name.as_bytes());
+    octal(&mut h[100..108], mode as u64);
+    octal(&mut h[108..116], 0);
+    octal(&mut h[116..124], 0);
+    octal(&mut h[124..136], size);
+    octal(&mut h[136..148], mtime);
+    h[156] = kind;
+    put(&mut h[157..257], link.as_bytes());
+    put(&mut h[257..263], b"ustar\0");
+    put(&mut h[263..265], b"00");
+    put(&mut h[265..269], b"root");
+    put(&mut h[297..301], b"root");
+    put(&mut h[345..500], prefix.as_bytes());
+
+    // Checksum is computed with its own field set to spaces
+    h[148..156].copy_from_slice(b"        ");
+    let sum: u32 = h.iter().map(|&b| b as u32).sum();
+    let field = format!("{:06o}\0 ", sum);
+    h[148..156].copy_from_slice(field.as_bytes());
+    h
+}
+
+fn put(field: &mut [u8], value: &[u8]) {
+    let n = value.len().min(field.len());
+    field[..n].copy_from_slice(&value[..n]);
+}
+
+fn octal(field: &mut [u8], value: u64) {
+    let digits = field.len() - 1;
+    let text = format!("{:0width$o}", value, width = digits);
+    put(field, text.as_bytes());
+}
+
+/// What a tar entry holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EntryKind {
+    File(Vec<u8>),
+    Dir,
+    Symlink(String),
+}
+
+/// One entry read back by [`read_tar`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TarEntry {
+    pub path: String,
+    pub mode: u32,
+    pub kind: EntryKind,
+}
+
+fn invalid(reason: impl Into<String>) -> SfcError {
+    SfcError::Validation {
+        field: "archive".to_string(),
+        value: String::new(),
+        reason: reason.into(),
+    }
+}
+
+/// Relative path of an archive entry, refusing anything that could land
+/// outside the directory it is unpacked into
+pub fn safe_path(path: &str) -> Result<PathBuf> {
+    let relative = Path::new(path);
+    let safe = !path.is_empty() && relative.components().all(|c| matches!(c, Component::Normal(_)));
+    if !safe {
+        return Err(invalid(format!("unsafe entry path '{}'", path)));
+    }
+    Ok(relative.to_path_buf())
+}
+
+/// Whether a symlink target stays below the link: relative and without `..`.
+/// A `..` can't be checked lexically, since it may climb out through an
+/// earlier link (`a/b -> ..` then `c -> a/b/..`).
+fn contained_target(target: &str) -> bool {
+    Path::new(target).components().all(|c| matches!(c, Component::Normal(_) | Component::CurDir))
+}
+
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).map(|m| m.file_type().is_symlink()).unwrap_or(false)
+}
+
+/// Unpack the entries under `dir` into `dest`, keeping modes and symlinks.
+/// Returns the number of files written.
+///
+/// Nothing is ever written through a symlink: every path is checked before
+/// anything is unpacked (no duplicates, no absolute or `..` link targets),
+/// files are opened with `O_NOFOLLOW`, and setuid/setgid/sticky bits are dropped.
+pub fn unpack_entries(entries: &[TarEntry], dir: &str, dest: &Path) -> Result<usize> {
+    let prefix = format!("{}/", dir);
+    let mut selected = Vec::new();
+    let mut seen = std::collections::HashSet::new();
+    for entry in entries {
+        let Some(relative) = entry.path.strip_prefix(&prefix) else { continue };
+        let relative = safe_path(relative)?;
+        if !seen.insert(relative.clone()) {
+            return Err(invalid(format!("{} appears more than once", entry.path)));
+        }
+        if let EntryKind::Symlink(link) = &entry.kind {
+            if !contained_target(link) {
+                return Err(invalid(format!("{} links outside its directory ({})", entry.path, link)));
+            }
+        }
+        selected.push((entry, relative));
+    }
+
+    fs::create_dir_all(dest).with_io_context(|| format!("creating {}", dest.display()))?;
+    let mut written = 0;
+    for (entry, relative) in selected {
+        // Never write through a symlink unpacked earlier
+        let mut parent = dest.to_path_buf();
+        for component in relative.parent().into_iter().flat_map(Path::components) {
+            parent.push(component);
+            if is_symlink(&parent) {
+                return Err(invalid(format!("{} is inside a symlink", entry.path)));
+            }
+        }
+
+        let target = dest.join(&relative);
+        let mode = entry.mode & 0o777;
+        match &entry.kind {
+            EntryKind::Dir => {
+                if is_symlink(&target) {
+                    return Err(invalid(format!("{} is a symlink", entry.path)));
+                }
+                fs::create_dir_all(&target).with_io_context(|| format!("creating {}", target.display()))?;
+                fs::set_permissions(&target, fs::Permissions::from_mode(mode | 0o700))
+                    .with_io_context(|| format!("setting mode on {}", target.display()))?;
+            }
+            EntryKind::File(data) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).with_io_context(|| format!("creating {}", parent.display()))?;
+                }
+                let mut file = fs::OpenOptions::new()
+                    .write(true)
+                    .create(true)
+                    .truncate(true)
+                    .mode(mode)
+                    .custom_flags(nix::libc::O_NOFOLLOW)
+                    .open(&target)
+                    .with_io_context(|| format!("writing {}", target.display()))?;
+                file.write_all(data).with_io_context(|| format!("writing {}", target.display()))?;
+                // The umask may have trimmed the mode given at creation
+                file.set_permissions(fs::Permissions::from_mode(mode))
+                    .with_io_context(|| format!("setting mode on {}", target.display()))?;
+                written += 1;
+            }
+            EntryKind::Symlink(link) => {
+                if let Some(parent) = target.parent() {
+                    fs::create_dir_all(parent).with_io_context(|| format!("creating {}", parent.display()))?;
+                }
+                if let Ok(meta) = fs::symlink_metadata(&target) {
+                    if meta.is_dir() {
+                        return Err(invalid(format!("{} would replace a directory", entry.path)));
+                    }
+                    fs::remove_file(&target).with_io_context(|| format!("replacing {}", target.display()))?;
+                }
+                symlink(link, &target).with_io_context(|| format!("linking {}", target.display()))?;
+            }
+        }
+    }
+    Ok(written)
+}
+
+/// `src` and everything below it as entries named `prefix/...`, in the
+/// order [`TarWriter::append_tree`] writes them
+pub fn tree_entries(src: &Path, prefix: &str) -> Result<Vec<TarEntry>> {
+    let mut entries = Vec::new();
+    collect_tree(src, prefix, &mut entries)?;
+    Ok(entries)
+}
+
+fn collect_tree(src: &Path, prefix: &str, out: &mut Vec<TarEntry>) -> Result<()> {
+    let meta = fs::symlink_metadata(src)
+        .with_io_context(|| format!("reading {}", src.display()))?;
+    let mode = meta.permissions().mode() & 0o7777;
+    if meta.file_type().is_symlink() {
+        let target = fs::read_link(src)
+            .with_io_context(|| format!("reading link {}", src.display()))?;
+        out.push(TarEntry { path: prefix.to_string(), mode: 0o777, kind: EntryKind::Symlink(target.to_string_lossy().into_owned()) });
+    } else if meta.is_dir() {
+        if !prefix.is_empty() {
+            out.push(TarEntry { path: prefix.to_string(), mode, kind: EntryKind::Dir });
+        }
+        let mut children: Vec<_> = fs::read_dir(src)
+            .with_io_context(|| format!("reading {}", src.display()))?
+            .flatten()
+            .collect();
+        children.sort_by_key(|e| e.file_name());
+        for child in children {
+            let name = child.file_name().to_string_lossy().to_string();
+            let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+            collect_tree(&child.path(), &path, out)?;
+        }
+    } else {
+        let data = fs::read(src).with_io_context(|| format!("reading {}", src.display()))?;
+        out.push(TarEntry { path: prefix.to_string(), mode, kind: EntryKind::File(data) });
+    }
+    Ok(())
+}
+
+/// NUL-terminated string field
+fn field_str(field: &[u8]) -> String {
+    let end = field.iter().position(|&b| b == 0).unwrap_or(field.len());
+    String::from_utf8_lossy(&field[..end]).into_owned()
+}
+
+fn parse_octal(field: &[u8]) -> Result<u64> {
+    let text = field_str(field);
+    let text = text.trim_matches(|c: char| c == ' ' || c == '\0');
+    if text.is_empty() {
+        return Ok(0);
+    }
+    u64::from_str_radix(text, 8).map_err(|_| invalid(format!("bad octal field '{}'", text)))
+}
+
+/// Records of a PAX extended header
+fn parse_pax(data: &[u8]) -> Vec<(String, String)> {
+    let mut records = Vec::new();
+    let mut rest = data;
+    while let Some(space) = rest.iter().position(|&b| b == b' ') {
+        let Some(len) = std::str::from_utf8(&rest[..space]).ok().and_then(|n| n.parse::<usize>().ok()) else {
+            break;
+        };
+        if len <= space || len > rest.len() {
+            break;
+        }
+        let record = String::from_utf8_lossy(&rest[space + 1..len]);
+        if let Some((key, value)) = record.trim_end_matches('\n').split_once('=') {
+            records.push((key.to_string(), value.to_string()));
+        }
+        rest = &rest[len..];
+    }
+    records
+}
+
+/// Read every entry of an uncompressed ustar/PAX archive, such as those
+/// [`TarWriter`] produces. Hard links, devices and FIFOs are rejected.
+pub fn read_tar(data: &[u8]) -> Result<Vec<TarEntry>> {
+    let mut entries = Vec::new();
+    let mut offset = 0;
+    let mut long_path: Option<String> = None;
+    let mut long_link: Option<String> = None;
+
+    while offset + BLOCK <= data.len() {
+        let header = &data[offset..offset + BLOCK];
+        if header.iter().all(|&b| b == 0) {
+            break;
+        }
+        let stored: u32 = parse_octal(&header[148..156])? as u32;
+        let sum: u32 = header.iter().enumerate()
+            .map(|(i, &b)| if (148..156).contains(&i) { b' ' as u32 } else { b as u32 })
+            .sum();
+        if stored != sum {
+            return Err(invalid(format!("header checksum mismatch at offset {}", offset)));
+        }
+
+        let size = parse_octal(&header[124..136])? as usize;
+        let start = offset + BLOCK;
+        let end = start.checked_add(size).filter(|&end| end <= data.len())
+            .ok_or_else(|| invalid("truncated archive"))?;
+        let body = &data[start..end];
+        offset = start + size.div_ceil(BLOCK) * BLOCK;
+
+        let kind = header[156];
+        match kind {
+            b'x' => {
+                for (key, value) in parse_pax(body) {
+                    match key.as_str() {
+                        "path" => long_path = Some(value),
+                        "linkpath" => long_link = Some(value),
+                        _ => {}
+                    }
+                }
+                continue;
+            }
+            b'g' => continue,
+            b'L' => {
+                long_path = Some(field_str(body));
+                continue;
+            }
+            b'K' => {
+                long_link = Some(field_str(body));
+                continue;
+            }
+            _ => {}
+        }
+
+        let path = long_path.take().unwrap_or_else(|| {
+            let (name, prefix) = (field_str(&header[0..100]), field_str(&header[345..500]));
+            if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) }
+        });
+        let link = long_link.take().unwrap_or_else(|| field_str(&header[157..257]));
+        let mode = parse_octal(&header[100..108])? as u32 & 0o7777;
+        let kind = match kind {
+            b'0' | 0 | b'7' => EntryKind::File(body.to_vec()),
+            b'5' => EntryKind::Dir,
+            b'2' => EntryKind::Symlink(link),
+            other => return Err(invalid(format!("unsupported entry type '{}' for {}", other as char, path))),
+        };
+        let path = path.trim_end_matches('/').to_string();
+        entries.push(TarEntry { path, mode, kind });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tar_matches_system_tar() {
+        let dir = tempfile::tempdir().unwrap();
+        let src = dir.path().join("src");
+        let deep = src.join("a".repeat(60)).join("b".repeat(60));
+        fs::create_dir_all(&deep).unwrap();
+        fs::write(deep.join("c".repeat(90)), "long").unwrap();
+        fs::write(src.join("d".repeat(120)), "pax").unwrap();
+        fs::write(src.join("run.sh"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(src.join("run.sh"), fs::Permissions::from_mode(0o755)).unwrap();
+        std::os::unix::fs::symlink("run.sh", src.join("link")).unwrap();
+
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append_tree(&src, "root").unwrap();
+        let bytes = tar.finish().unwrap();
+        assert_eq!(bytes.len() % BLOCK, 0);
+
+        // Deterministic output
+        let mut again = TarWriter::new(Vec::new());
+        again.append_tree(&src, "root").unwrap();
+        assert_eq!(again.finish().unwrap(), bytes);
+
+        if std::process::Command::new("tar").arg("--version").output().is_err() {
+            return;
+        }
+        let archive = dir.path().join("out.tar");
+        fs::write(&archive, &bytes).unwrap();
+        let out = dir.path().join("out");
+        fs::create_dir(&out).unwrap();
+        let status = std::process::Command::new("tar")
+            .arg("-xf").arg(&archive).arg("-C").arg(&out)
+            .status()
+            .unwrap();
+        assert!(status.success());
+
+        let root = out.join("root");
+        let extracted = root.join("a".repeat(60)).join("b".repeat(60)).join("c".repeat(90));
+        assert_eq!(fs::read_to_string(extracted).unwrap(), "long");
+        assert_eq!(fs::read_to_string(root.join("d".repeat(120))).unwrap(), "pax");
+        assert_eq!(fs::read_link(root.join("link")).unwrap(), Path::new("run.sh"));
+        assert_eq!(fs::metadata(root.join("run.sh")).unwrap().permissions().mode() & 0o777, 0o755);
+    }
+
+    #[test]
+    fn test_read_tar_round_trips_writer() {
+        let long = format!("root/{}/{}", "a".repeat(60), "c".repeat(120));
+        let mut tar = TarWriter::new(Vec::new());
+        tar.append_dir("root", 0o755).unwrap();
+        tar.append_file("root/run.sh", 0o755, b"#!/bin/sh\n").unwrap();
+        tar.append_file(&long, 0o644, b"pax").unwrap();
+        tar.append_symlink("root/link", "run.sh").unwrap();
+        let bytes = tar.finish().unwrap();
+
+        let entries = read_tar(&bytes).unwrap();
+        assert_eq!(entries, vec![
+            TarEntry { path: "root".into(), mode: 0o755, kind: EntryKind::Dir },
+            TarEntry { path: "root/run.sh".into(), mode: 0o755, kind: EntryKind::File(b"#!/bin/sh\n".to_vec()) },
+            TarEntry { path: long, mode: 0o644, kind: EntryKind::File(b"pax".to_vec()) },
+            TarEntry { path: "root/link".into(), mode: 0o777, kind: EntryKind::Symlink("run.sh".into()) },
+        ]);
+
+        let mut corrupt = bytes.clone();
+        corrupt[0] ^= 1;
+        assert!(read_tar(&corrupt).is_err());
+    }
+
+    #[test]
+    fn test_unpack_never_writes_outside_dest() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        fs::write(&outside, "untouched").unwrap();
+        let entry = |path: &str, kind: EntryKind| TarEntry { path: path.into(), mode: 0o644, kind };
+        let file = |path: &str| entry(path, EntryKind::File(b"evil".to_vec()));
+        let link = |path: &str, target: &str| entry(path, EntryKind::Symlink(target.into()));
+
+        let crafted = [
+            // A link followed by a file of the same name would write through it
+            vec![link("s/x", "y"), file("s/x")],
+            vec![link("s/x", outside.to_str().unwrap())],
+            vec![link("s/x", "../../outside")],
+            vec![link("s/a/x", "../../outside")],
+            // `..` through an earlier link climbs further than it reads
+            vec![link("s/a/b", ".."), link("s/c", "a/b/../../outside")],
+            vec![link("s/a/b", ".."), link("s/c", "a/b/..")],
+            // Writing through a link unpacked earlier
+            vec![link("s/d", "."), file("s/d/x")],
+            vec![entry("s/e", EntryKind::Dir), link("s/e/f", "."), entry("s/e/f/g", EntryKind::Dir)],
+        ];
+        for (i, entries) in crafted.iter().enumerate() {
+            let dest = dir.path().join(format!("dest{}", i)).join("inner");
+            assert!(unpack_entries(entries, "s", &dest).is_err(), "case {}", i);
+        }
+        assert_eq!(fs::read_to_string(&outside).unwrap(), "untouched");
+
+        let dest = dir.path().join("ok");
+        let entries = vec![
+            entry("s/bin", EntryKind::Dir),
+            TarEntry { path: "s/bin/tool".into(), mode: 0o4755, kind: EntryKind::File(b"#!/bin/sh\n".to_vec()) },
+            link("s/tool", "bin/tool"),
+            link("s/bin/again", "./tool"),
+        ];
+        unpack_entries(&entries, "s", &dest).unwrap();
+        let mode = fs::metadata(dest.join("bin/tool")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o7777, 0o755);
+        assert_eq!(fs::read_link(dest.join("tool")).unwrap(), Path::new("bin/tool"));
+        assert!(unpack_entries(&[link("s/lib/tool", "../bin/tool")], "s", &dir.path().join("up")).is_err());
+    }
+}
@@ -0,0 +1,213 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use similar::{ChangeTag, TextDiff};
+
+use crate::container::{PackageSource, PackageSpec};
+use crate::core::hash::SNAPSHOT_LOCKFILES;
+use crate::core::snapshot::SnapshotMetadata;
+use crate::error::{Result, ErrorContext};
+
+/// Version files a snapshot may hold, by toolchain
+pub(crate) const TOOLCHAIN_FILES: &[(&str, &str)] = &[
+    ("node", "node_version"),
+    ("rust", "rust_version"),
+    ("python", "python_version"),
+];
+
+/// What a snapshot pins, keyed by name
+#[derive(Debug, Default)]
+pub struct SnapshotContents {
+    pub packages: BTreeMap<String, String>,
+    pub toolchains: BTreeMap<String, String>,
+    pub environment: BTreeMap<String, String>,
+    pub lockfiles: BTreeMap<String, String>,
+}
+
+impl SnapshotContents {
+    /// Read a snapshot's manifest, toolchain version files and lockfiles
+    pub fn load(snapshot_dir: &Path) -> Result<Self> {
+        let mut contents = Self::default();
+        if let Some(metadata) = SnapshotMetadata::load(snapshot_dir)? {
+            contents.packages = metadata.packages.iter().map(|p| (p.name.clone(), package_version(p))).collect();
+            contents.toolchains = metadata.toolchains;
+            contents.environment = metadata.environment;
+        }
+        for (toolchain, file) in TOOLCHAIN_FILES {
+            if let Ok(version) = fs::read_to_string(snapshot_dir.join(file)) {
+                contents.toolchains.insert(toolchain.to_string(), version.trim().to_string());
+            }
+        }
+        for file in SNAPSHOT_LOCKFILES {
+            let path = snapshot_dir.join(file);
+            if path.exists() {
+                let content = fs::read_to_string(&path).with_io_context(|| format!("reading {}", path.display()))?;
+                contents.lockfiles.insert(file.to_string(), content);
+            }
+        }
+        Ok(contents)
+    }
+}
+
+/// How a package's version is shown: its pinned version, GitHub rev or URL
+fn package_version(spec: &PackageSpec) -> String {
+    match &spec.source {
+        PackageSource::Nixpkgs => spec.version.clone().unwrap_or_else(|| "latest".to_string()),
+        PackageSource::GitHub { repo, rev } => format!("github:{}@{}", repo, rev),
+        PackageSource::Url(url) => url.clone(),
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// One named entry that differs between two snapshots
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Change {
+    pub name: String,
+    pub kind: ChangeKind,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// A lockfile that differs, as a unified diff
+#[derive(Debug, Clone, Serialize)]
+pub struct LockfileDiff {
+    pub file: String,
+    pub added: usize,
+    pub removed: usize,
+    pub diff: String,
+}
+
+/// Everything that differs between two snapshots
+#[derive(Debug, Clone, Serialize)]
+pub struct SnapshotDiff {
+    pub from: String,
+    pub to: String,
+    pub packages: Vec<Change>,
+    pub toolchains: Vec<Change>,
+    pub environment: Vec<Change>,
+    pub lockfiles: Vec<LockfileDiff>,
+}
+
+impl SnapshotDiff {
+    /// Compare the snapshots in `from_dir` and `to_dir`, labelled by their hashes
+    pub fn between(from: &str, from_dir: &Path, to: &str, to_dir: &Path) -> Result<Self> {
+        let old = SnapshotContents::load(from_dir)?;
+        let new = SnapshotContents::load(to_dir)?;
+        Ok(Self {
+            from: from.to_string(),
+            to: to.to_string(),
+            packages: diff_maps(&old.packages, &new.packages),
+            toolchains: diff_maps(&old.toolchains, &new.toolchains),
+            environment: diff_maps(&old.environment, &new.environment),
+            lockfiles: diff_lockfiles(&old.lockfiles, &new.lockfiles),
+        })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.packages.is_empty() && self.toolchains.is_empty() && self.environment.is_empty() && self.lockfiles.is_empty()
+    }
+}
+
+fn diff_maps(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<Change> {
+    let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+    names.sort();
+    names.dedup();
+    names
+        .into_iter()
+        .filter_map(|name| {
+            let (before, after) = (old.get(name), new.get(name));
+            let kind = match (before, after) {
+                (None, Some(_)) => ChangeKind::Added,
+                (Some(_), None) => ChangeKind::Removed,
+                (Some(a), Some(b)) if a != b => ChangeKind::Changed,
+                _ => return None,
+            };
+            Some(Change { name: name.clone(), kind, old: before.cloned(), new: after.cloned() })
+        })
+        .collect()
+}
+
+fn diff_lockfiles(old: &BTreeMap<String, String>, new: &BTreeMap<String, String>) -> Vec<LockfileDiff> {
+    // Keep the hashing order rather than sorting by name
+    SNAPSHOT_LOCKFILES
+        .iter()
+        .filter_map(|file| {
+            let before = old.get(*file).map(String::as_str).unwrap_or_default();
+            let after = new.get(*file).map(String::as_str).unwrap_or_default();
+            if before == after {
+                return None;
+            }
+            let text = TextDiff::from_lines(before, after);
+            let (mut added, mut removed) = (0, 0);
+            for change in text.iter_all_changes() {
+                match change.tag() {
+                    ChangeTag::Insert => added += 1,
+                    ChangeTag::Delete => removed += 1,
+                    ChangeTag::Equal => {}
+                }
+            }
+            let diff = text
+                .unified_diff()
+                .context_radius(3)
+                .header(&format!("a/{}", file), &format!("b/{}", file))
+                .to_string();
+            Some(LockfileDiff { file: file.to_string(), added, removed, diff })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_diff() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::create_dir_all(&a).unwrap();
+        fs::create_dir_all(&b).unwrap();
+        fs::write(a.join("Cargo.lock"), "serde 1.0\nrand 0.8\n").unwrap();
+        fs::write(b.join("Cargo.lock"), "serde 1.0\nrand 0.9\n").unwrap();
+        fs::write(a.join("mix.lock"), "%{}\n").unwrap();
+        fs::write(a.join("node_version"), "18\n").unwrap();
+        fs::write(b.join("node_version"), "20\n").unwrap();
+
+        SnapshotMetadata {
+            packages: vec![PackageSpec::parse("jq@1.6"), PackageSpec::parse("git")],
+            environment: BTreeMap::from([("EDITOR".to_string(), "vim".to_string())]),
+            ..Default::default()
+        }
+        .save(&a)
+        .unwrap();
+        SnapshotMetadata {
+            packages: vec![PackageSpec::parse("jq@1.7"), PackageSpec::parse("curl")],
+            ..Default::default()
+        }
+        .save(&b)
+        .unwrap();
+
+        let diff = SnapshotDiff::between("a", &a, "b", &b).unwrap();
+        let kinds: Vec<_> = diff.packages.iter().map(|c| (c.name.as_str(), c.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![("curl", ChangeKind::Added), ("git", ChangeKind::Removed), ("jq", ChangeKind::Changed)]
+        );
+        assert_eq!(diff.toolchains[0].new.as_deref(), Some("20"));
+        assert_eq!(diff.environment[0].kind, ChangeKind::Removed);
+
+        let files: Vec<_> = diff.lockfiles.iter().map(|l| l.file.as_str()).collect();
+        assert_eq!(files, vec!["Cargo.lock", "mix.lock"]);
+        assert_eq!((diff.lockfiles[0].added, diff.lockfiles[0].removed), (1, 1));
+        assert!(diff.lockfiles[0].diff.contains("-rand 0.8\n+rand 0.9\n"));
+        assert!(SnapshotDiff::between("a", &a, "a", &a).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,228 @@
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::archive::{read_tar, tree_entries, unpack_entries, EntryKind, TarWriter};
+use crate::core::hash::compute_snapshot_hash;
+use crate::core::snapshot::SnapshotManager;
+use crate::core::symlink::create_or_update_symlink;
+use crate::error::{Result, SfcError, ErrorContext};
+
+/// Snapshot archive layout version written to the header
+pub const SNAPSHOT_ARCHIVE_FORMAT: u32 = 1;
+
+/// First entry of every snapshot archive
+const HEADER_PATH: &str = "sfc-snapshot.json";
+/// The snapshot tree, manifest included
+const SNAPSHOT_DIR: &str = "snapshot";
+/// Store directory kind for imported snapshots
+const IMPORT_KIND: &str = "snapshot-imported";
+const ZSTD_LEVEL: i32 = 19;
+
+/// What a snapshot archive (`.tar.zst`) says about the snapshot it holds
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotArchiveHeader {
+    pub format: u32,
+    pub container: String,
+    /// Content hash the unpacked snapshot must reproduce
+    pub hash: String,
+    pub exported_at: DateTime<Utc>,
+}
+
+/// A snapshot brought in by [`SnapshotManager::import_snapshot`]
+#[derive(Debug)]
+pub struct ImportedSnapshot {
+    pub header: SnapshotArchiveHeader,
+    pub path: PathBuf,
+    pub alias: String,
+    /// The store already held this snapshot, so its directory was reused
+    pub reused: bool,
+}
+
+fn invalid(reason: impl Into<String>) -> SfcError {
+    SfcError::Validation {
+        field: "snapshot archive".to_string(),
+        value: String::new(),
+        reason: reason.into(),
+    }
+}
+
+/// Aliases live directly in `links/`, next to `<name>-stable` and the temps
+fn validate_alias(alias: &str) -> Result<()> {
+    let ok = !alias.is_empty()
+        && !alias.starts_with('.')
+        && alias.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !ok {
+        return Err(SfcError::Validation {
+            field: "alias".to_string(),
+            value: alias.to_string(),
+            reason: "use letters, digits, '.', '-' and '_'".to_string(),
+        });
+    }
+    Ok(())
+}
+
+impl SnapshotManager {
+    /// Write `container`'s snapshot `reference` to `out` as a zstd-compressed
+    /// tar, keeping symlinks, modes and the manifest
+    pub fn export_snapshot(&self, container: &str, reference: &str, out: &Path) -> Result<SnapshotArchiveHeader> {
+        let hash = self.resolve(container, reference)?;
+        let snapshot_dir = self.find_snapshot_by_hash(&hash)?;
+        let header = SnapshotArchiveHeader {
+            format: SNAPSHOT_ARCHIVE_FORMAT,
+            container: container.to_string(),
+            hash,
+            exported_at: Utc::now(),
+        };
+        let header_json = serde_json::to_vec_pretty(&header).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize snapshot header: {}", e),
+            source: None,
+        })?;
+
+        let mtime = header.exported_at.timestamp().max(0) as u64;
+        let mut tar = TarWriter::new(Vec::new()).with_mtime(mtime);
+        tar.append_file(HEADER_PATH, 0o644, &header_json)
+            .with_io_context(|| "archiving snapshot header".to_string())?;
+        for entry in tree_entries(&snapshot_dir, SNAPSHOT_DIR)? {
+            tar.append_entry(&entry)
+                .with_io_context(|| format!("archiving {}", entry.path))?;
+        }
+        let bytes = tar.finish().with_io_context(|| "finishing snapshot archive".to_string())?;
+
+        let file = fs::File::create(out).with_io_context(|| format!("creating {}", out.display()))?;
+        let mut encoder = zstd::Encoder::new(file, ZSTD_LEVEL)
+            .with_io_context(|| format!("compressing {}", out.display()))?;
+        encoder.include_checksum(true)
+            .with_io_context(|| format!("compressing {}", out.display()))?;
+        encoder.write_all(&bytes)
+            .and_then(|_| encoder.finish())
+            .with_io_context(|| format!("writing {}", out.display()))?;
+        Ok(header)
+    }
+
+    /// Unpack a snapshot archive into the store, check that it reproduces
+    /// its content hash, and link it as `links/<alias>` (by default
+    /// `<container>-<hash prefix>`). An existing alias is never replaced.
+    pub fn import_snapshot(&self, archive: &Path, alias: Option<&str>) -> Result<ImportedSnapshot> {
+        let file = fs::File::open(archive).with_io_context(|| format!("opening {}", archive.display()))?;
+        let data = zstd::decode_all(file).with_io_context(|| format!("decompressing {}", archive.display()))?;
+        let mut entries = read_tar(&data)?;
+        if entries.first().map(|e| e.path.as_str()) != Some(HEADER_PATH) {
+            return Err(invalid(format!("not an sfc snapshot archive (no {})", HEADER_PATH)));
+        }
+        let EntryKind::File(raw) = entries.remove(0).kind else {
+            return Err(invalid(format!("{} is not a file", HEADER_PATH)));
+        };
+        let header: SnapshotArchiveHeader = serde_json::from_slice(&raw)
+            .map_err(|e| invalid(format!("invalid header: {}", e)))?;
+        if header.format > SNAPSHOT_ARCHIVE_FORMAT {
+            return Err(invalid(format!(
+                "archive format {} is newer than this sfc supports ({})",
+                header.format, SNAPSHOT_ARCHIVE_FORMAT
+            )));
+        }
+        if let Some(stray) = entries.iter().find(|e| e.path != SNAPSHOT_DIR && !e.path.starts_with("snapshot/")) {
+            return Err(invalid(format!("unexpected entry '{}'", stray.path)));
+        }
+
+        let alias = match alias {
+            Some(alias) => alias.to_string(),
+            None => format!("{}-{}", header.container, &header.hash[..12.min(header.hash.len())]),
+        };
+        validate_alias(&alias)?;
+        let link = self.workspace_root.join("links").join(&alias);
+        if fs::symlink_metadata(&link).is_ok() {
+            return Err(SfcError::AlreadyExists {
+                resource: "alias".to_string(),
+                identifier: alias,
+            });
+        }
+
+        let existing = self.find_snapshot_by_hash(&header.hash).ok();
+        let unpacked = self.create_snapshot(IMPORT_KIND)?;
+        let verified = unpack_entries(&entries, SNAPSHOT_DIR, &unpacked)
+            .and_then(|_| compute_snapshot_hash(&unpacked))
+            .and_then(|actual| match actual == header.hash {
+                true => Ok(()),
+                false => Err(invalid(format!(
+                    "content hash {} does not match the archive's {}",
+                    &actual[..12],
+                    &header.hash[..12.min(header.hash.len())]
+                ))),
+            });
+        if let Err(e) = verified {
+            let _ = fs::remove_dir_all(&unpacked);
+            return Err(e);
+        }
+
+        let (path, reused) = match existing {
+            Some(path) => {
+                fs::remove_dir_all(&unpacked).with_io_context(|| format!("removing {}", unpacked.display()))?;
+                (path, true)
+            }
+            None => (unpacked, false),
+        };
+        let target = Path::new("../store").join(path.file_name().unwrap_or_default());
+        create_or_update_symlink(&target, &link)?;
+        Ok(ImportedSnapshot { header, path, alias, reused })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+
+    #[test]
+    fn test_snapshot_export_import_round_trip() {
+        let ws = tempfile::tempdir().unwrap();
+        let manager = SnapshotManager::new(ws.path());
+        let snapshot = manager.create_snapshot("snapshot-000").unwrap();
+        fs::write(snapshot.join("Gemfile.lock"), "rails (7.1)\n").unwrap();
+        fs::write(snapshot.join("sfc-metadata.toml"), "created_by = \"test\"\n").unwrap();
+        fs::create_dir_all(snapshot.join("bin")).unwrap();
+        fs::write(snapshot.join("bin/tool"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(snapshot.join("bin/tool"), fs::Permissions::from_mode(0o755)).unwrap();
+        symlink("bin/tool", snapshot.join("tool")).unwrap();
+        let hash = compute_snapshot_hash(&snapshot).unwrap();
+        fs::create_dir_all(ws.path().join("links")).unwrap();
+        symlink(Path::new("../store").join(snapshot.file_name().unwrap()), ws.path().join("links/web-stable")).unwrap();
+
+        let archive = ws.path().join("web.tar.zst");
+        assert_eq!(manager.export_snapshot("web", "stable", &archive).unwrap().hash, hash);
+
+        // Into a fresh workspace: a new store dir, linked under the alias
+        let other = tempfile::tempdir().unwrap();
+        let imported = SnapshotManager::new(other.path()).import_snapshot(&archive, Some("web-backup")).unwrap();
+        assert!(!imported.reused);
+        assert_eq!(compute_snapshot_hash(&imported.path).unwrap(), hash);
+        assert_eq!(fs::read_link(imported.path.join("tool")).unwrap(), Path::new("bin/tool"));
+        let mode = fs::metadata(imported.path.join("bin/tool")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+        assert!(other.path().join("links/web-backup").join("sfc-metadata.toml").is_file());
+        assert!(SnapshotManager::new(other.path()).import_snapshot(&archive, Some("web-backup")).is_err());
+
+        // Back into the original workspace: the existing store dir is reused
+        let again = manager.import_snapshot(&archive, None).unwrap();
+        assert!(again.reused);
+        assert_eq!(again.alias, format!("web-{}", &hash[..12]));
+        assert_eq!(again.path.canonicalize().unwrap(), snapshot.canonicalize().unwrap());
+
+        // An archive whose header names another hash is refused
+        let mut entries = read_tar(&zstd::decode_all(fs::File::open(&archive).unwrap()).unwrap()).unwrap();
+        let EntryKind::File(raw) = &mut entries[0].kind else { unreachable!() };
+        *raw = String::from_utf8_lossy(raw).replacen(&hash, &"0".repeat(64), 1).into_bytes();
+        let mut tar = TarWriter::new(Vec::new());
+        for entry in &entries {
+            tar.append_entry(entry).unwrap();
+        }
+        let forged = ws.path().join("forged.tar.zst");
+        fs::write(&forged, zstd::encode_all(&tar.finish().unwrap()[..], 3).unwrap()).unwrap();
+        let err = manager.import_snapshot(&forged, Some("forged")).unwrap_err().to_string();
+        assert!(err.contains("does not match"), "{}", err);
+        assert!(!ws.path().join("links/forged").exists());
+    }
+}
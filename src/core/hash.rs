@@ -5,66 +5,41 @@ use serde::{Deserialize, Serialize};
 
 use crate::error::{Result, ErrorContext};
 
-/// Compute a stable hash for a snapshot directory from known lockfiles and metadata
+/// Lockfiles that make up a snapshot's identity, in hashing order. The first
+/// three come first so snapshots holding only those keep their original hash.
+pub const SNAPSHOT_LOCKFILES: &[&str] = &[
+    "requirements.txt",
+    "rockspec.lock",
+    "Cargo.lock",
+    "package-lock.json",
+    "Gemfile.lock",
+    "composer.lock",
+    "pubspec.lock",
+    "mix.lock",
+];
+
+/// Metadata files hashed after the lockfiles
+pub const SNAPSHOT_METADATA_FILES: &[&str] = &[
+    "sfc-metadata.toml",
+    "container.toml",
+    "toolchain.toml",
+];
+
+/// Compute a stable hash for a snapshot directory from known lockfiles and
+/// metadata. Only file names and contents count, so a copied snapshot (or one
+/// unpacked on another machine) keeps its hash.
 pub fn compute_snapshot_hash(snapshot_dir: &Path) -> Result<String> {
     let mut hasher = Sha256::new();
-    
-    // Include snapshot directory name for uniqueness
-    if let Some(dir_name) = snapshot_dir.file_name() {
-        hasher.update(dir_name.to_string_lossy().as_bytes());
-    }
-    
-    // Hash known lockfiles in a deterministic order
-    let lockfiles = [
-        "requirements.txt",
-        "package-lock.json", 
-        "Cargo.lock",
-        "rockspec.lock",
-        "Gemfile.lock",
-        "composer.lock",
-        "pubspec.lock",
-        "mix.lock",
-    ];
-    
-    for lockfile in lockfiles.iter() {
-        let lockfile_path = snapshot_dir.join(lockfile);
-        if lockfile_path.exists() {
-            // Hash the filename first
-            hasher.update(lockfile.as_bytes());
-            
-            // Then hash the file contents
-            let bytes = fs::read(&lockfile_path)
-                .with_io_context(|| format!("reading lockfile {}", lockfile_path.display()))?;
-            hasher.update(&bytes);
-        }
-    }
-    
-    // Hash metadata files if they exist
-    let metadata_files = [
-        "sfc-metadata.toml",
-        "container.toml", 
-        "toolchain.toml",
-    ];
-    
-    for metadata_file in metadata_files.iter() {
-        let metadata_path = snapshot_dir.join(metadata_file);
-        if metadata_path.exists() {
-            hasher.update(metadata_file.as_bytes());
-            let bytes = fs::read(&metadata_path)
-                .with_io_context(|| format!("reading metadata file {}", metadata_path.display()))?;
+    for file in SNAPSHOT_LOCKFILES.iter().chain(SNAPSHOT_METADATA_FILES) {
+        let path = snapshot_dir.join(file);
+        if path.exists() {
+            // Hash the filename first, then the contents
+            hasher.update(file.as_bytes());
+            let bytes = fs::read(&path)
+                .with_io_context(|| format!("reading {}", path.display()))?;
             hasher.update(&bytes);
         }
     }
-    
-    // Include modification time of the directory itself
-    if let Ok(metadata) = fs::metadata(snapshot_dir) {
-        if let Ok(modified) = metadata.modified() {
-            if let Ok(duration) = modified.duration_since(std::time::UNIX_EPOCH) {
-                hasher.update(&duration.as_secs().to_le_bytes());
-            }
-        }
-    }
-    
     let digest = hasher.finalize();
     Ok(format!("{:x}", digest))
 }
@@ -0,0 +1,460 @@
+use std::fs;
+use std::os::unix::fs as unix_fs;
+use std::path::{Path, PathBuf};
+use chrono::Utc;
+
+use crate::container::ContainerConfig;
+use crate::core::tags::TagStore;
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::history::{History, Operation};
+use crate::sfc::{create_or_update_symlink, link_alias_to_store, list_temps, temp_alias, unlink_alias_from_links, validate_name};
+
+/// Undo steps for a multi-step workspace change, replayed in reverse on failure
+#[derive(Default)]
+struct Journal {
+    undo: Vec<Box<dyn FnOnce()>>,
+}
+
+impl Journal {
+    fn push(&mut self, step: impl FnOnce() + 'static) {
+        self.undo.push(Box::new(step));
+    }
+
+    fn rollback(self) {
+        for step in self.undo.into_iter().rev() {
+            step();
+        }
+    }
+
+    /// Remember `path`'s current content (or absence) so rollback restores it
+    fn preserve(&mut self, path: &Path) {
+        let path = path.to_path_buf();
+        let original = fs::read(&path).ok();
+        self.push(move || match original {
+            Some(content) => { let _ = fs::write(&path, content); }
+            None => { let _ = fs::remove_file(&path); }
+        });
+    }
+}
+
+/// A `links/` alias belonging to a container, e.g. `app-stable` or `app-temp-exp1`
+struct Alias {
+    name: String,
+    suffix: String,
+    /// Target relative to `links/`, always `../store/<dir>`
+    target: PathBuf,
+}
+
+/// Names of every container with a config
+fn container_names(root: &Path) -> Result<Vec<String>> {
+    let dir = root.join(".sfc").join("containers");
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let entries = fs::read_dir(&dir).with_io_context(|| format!("reading {}", dir.display()))?;
+    Ok(entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "toml"))
+        .filter_map(|path| path.file_stem().map(|s| s.to_string_lossy().to_string()))
+        .collect())
+}
+
+fn container_aliases(root: &Path, container: &str) -> Result<Vec<Alias>> {
+    let stable = format!("{}-stable", container);
+    let stable_path = root.join("links").join(&stable);
+    let mut links = Vec::new();
+    if stable_path.is_symlink() {
+        // Stow-managed aliases point into .sfc/stow-pkgs; resolve to the store dir
+        if let Ok(resolved) = fs::canonicalize(&stable_path) {
+            links.push((stable, resolved));
+        }
+    }
+    links.extend(list_temps(root, container)?.into_iter().map(|temp| (temp.alias, temp.path)));
+
+    // `app-temp-` also prefixes the aliases of a container named `app-temp-x`
+    let longer: Vec<String> = container_names(root)?
+        .into_iter()
+        .filter(|other| other.len() > container.len() && other.starts_with(&format!("{}-", container)))
+        .collect();
+    let owned_elsewhere = |alias: &str| {
+        longer.iter().any(|other| alias == format!("{}-stable", other) || alias.starts_with(&temp_alias(other, "")))
+    };
+
+    let prefix = format!("{}-", container);
+    let mut aliases = Vec::new();
+    for (name, resolved) in links {
+        if owned_elsewhere(&name) {
+            continue;
+        }
+        let Some(store_dir) = resolved.file_name() else { continue };
+        aliases.push(Alias {
+            suffix: name[prefix.len()..].to_string(),
+            target: Path::new("../store").join(store_dir),
+            name,
+        });
+    }
+    aliases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(aliases)
+}
+
+fn config_path(root: &Path, name: &str) -> PathBuf {
+    root.join(".sfc").join("containers").join(format!("{}.toml", name))
+}
+
+/// Point absolute paths into `containers/<from>/` (e.g. PATH) at `containers/<to>/`
+fn retarget_paths(config: &mut ContainerConfig, root: &Path, from: &str, to: &str) {
+    let old_prefix = root.join("containers").join(from).to_string_lossy().to_string();
+    let new_prefix = root.join("containers").join(to).to_string_lossy().to_string();
+    for value in config.environment.values_mut() {
+        let parts: Vec<String> = value
+            .split(':')
+            .map(|part| match part.strip_prefix(&old_prefix) {
+                Some(rest) if rest.is_empty() || rest.starts_with('/') => format!("{}{}", new_prefix, rest),
+                _ => part.to_string(),
+            })
+            .collect();
+        *value = parts.join(":");
+    }
+}
+
+fn check_names(root: &Path, from: &str, to: &str) -> Result<()> {
+    validate_name(to)?;
+    if !ContainerConfig::exists(root, from) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: from.to_string(),
+        });
+    }
+    if ContainerConfig::exists(root, to) || !container_aliases(root, to)?.is_empty() {
+        return Err(SfcError::AlreadyExists {
+            resource: "container".to_string(),
+            identifier: to.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Copy a directory tree, recreating symlinks instead of following them
+fn copy_tree(src: &Path, dst: &Path) -> Result<()> {
+    fs::create_dir_all(dst).with_io_context(|| format!("creating {}", dst.display()))?;
+    let entries = fs::read_dir(src).with_io_context(|| format!("reading {}", src.display()))?;
+    for entry in entries {
+        let entry = entry.with_io_context(|| format!("reading {}", src.display()))?;
+        let from = entry.path();
+        let to = dst.join(entry.file_name());
+        let file_type = entry.file_type().with_io_context(|| format!("inspecting {}", from.display()))?;
+        if file_type.is_symlink() {
+            let target = fs::read_link(&from).with_io_context(|| format!("reading link {}", from.display()))?;
+            unix_fs::symlink(&target, &to).with_io_context(|| format!("linking {}", to.display()))?;
+        } else if file_type.is_dir() {
+            copy_tree(&from, &to)?;
+        } else {
+            fs::copy(&from, &to).with_io_context(|| format!("copying {}", from.display()))?;
+        }
+    }
+    Ok(())
+}
+
+/// Fork `src` into a new container `dst`: config, history lineage, tags and the
+/// container tree are copied; snapshots are shared by linking the same store dirs.
+/// Returns the aliases created for `dst`.
+pub fn clone_container(root: &Path, src: &str, dst: &str) -> Result<Vec<String>> {
+    check_names(root, src, dst)?;
+    let mut journal = Journal::default();
+    match clone_steps(root, src, dst, &mut journal) {
+        Ok(aliases) => Ok(aliases),
+        Err(e) => {
+            journal.rollback();
+            Err(e)
+        }
+    }
+}
+
+fn clone_steps(root: &Path, src: &str, dst: &str, journal: &mut Journal) -> Result<Vec<String>> {
+    let src_dir = root.join("containers").join(src);
+    let dst_dir = root.join("containers").join(dst);
+    if src_dir.is_dir() {
+        let undo_dir = dst_dir.clone();
+        journal.push(move || { let _ = fs::remove_dir_all(&undo_dir); });
+        copy_tree(&src_dir, &dst_dir)?;
+        if dst_dir.join("stable").is_symlink() {
+            create_or_update_symlink(Path::new("../../links").join(format!("{}-stable", dst)), dst_dir.join("stable"))?;
+        }
+    }
+
+    let mut created = Vec::new();
+    for alias in container_aliases(root, src)? {
+        let new_alias = format!("{}-{}", dst, alias.suffix);
+        link_alias_to_store(root, &new_alias, &alias.target)?;
+        let (undo_root, undo_alias) = (root.to_path_buf(), new_alias.clone());
+        journal.push(move || {
+            let _ = unlink_alias_from_links(&undo_root, &undo_alias);
+            let _ = fs::remove_dir_all(undo_root.join(".sfc").join("stow-pkgs").join(&undo_alias));
+        });
+        created.push(new_alias);
+    }
+
+    let mut config = ContainerConfig::load(root, src)?;
+    config.name = dst.to_string();
+    config.created_at = Utc::now();
+    retarget_paths(&mut config, root, src, dst);
+    journal.preserve(&config_path(root, dst));
+    config.save(root)?;
+
+    let history_file = root.join(".sfc").join("history.json");
+    journal.preserve(&history_file);
+    let mut history = History::load(root)?;
+    history.copy_lineage(src, dst);
+    history.add_entry(&config, Operation::Clone { source: src.to_string() }, format!("Cloned from {}", src))?;
+
+    let tags = TagStore::new(root);
+    journal.preserve(tags.path());
+    tags.copy_container(src, dst)?;
+
+    Ok(created)
+}
+
+/// Rename a container everywhere it is referenced: its directory, `links/`
+/// aliases and their stow packages, its config (and `extends` in other configs),
+/// `.sfc/current`, history, tags, project binding and task cache. Any failure restores the old state.
+pub fn rename_container(root: &Path, old: &str, new: &str) -> Result<()> {
+    check_names(root, old, new)?;
+    let mut journal = Journal::default();
+    match rename_steps(root, old, new, &mut journal) {
+        Ok(()) => Ok(()),
+        Err(e) => {
+            journal.rollback();
+            Err(e)
+        }
+    }
+}
+
+fn rename_steps(root: &Path, old: &str, new: &str, journal: &mut Journal) -> Result<()> {
+    let old_dir = root.join("containers").join(old);
+    let new_dir = root.join("containers").join(new);
+    if old_dir.is_dir() {
+        fs::rename(&old_dir, &new_dir)
+            .with_io_context(|| format!("renaming {} to {}", old_dir.display(), new_dir.display()))?;
+        let (from, to) = (new_dir.clone(), old_dir.clone());
+        journal.push(move || { let _ = fs::rename(&from, &to); });
+
+        let stable = new_dir.join("stable");
+        if stable.is_symlink() {
+            create_or_update_symlink(Path::new("../../links").join(format!("{}-stable", new)), &stable)?;
+            let old_target = Path::new("../../links").join(format!("{}-stable", old));
+            journal.push(move || { let _ = create_or_update_symlink(old_target, &stable); });
+        }
+    }
+
+    let stow_pkgs = root.join(".sfc").join("stow-pkgs");
+    for alias in container_aliases(root, old)? {
+        let new_alias = format!("{}-{}", new, alias.suffix);
+        link_alias_to_store(root, &new_alias, &alias.target)?;
+        let (undo_root, undo_alias) = (root.to_path_buf(), new_alias.clone());
+        journal.push(move || {
+            let _ = unlink_alias_from_links(&undo_root, &undo_alias);
+            let _ = fs::remove_dir_all(undo_root.join(".sfc").join("stow-pkgs").join(&undo_alias));
+        });
+
+        unlink_alias_from_links(root, &alias.name)?;
+        let old_pkg = stow_pkgs.join(&alias.name);
+        if old_pkg.exists() {
+            fs::remove_dir_all(&old_pkg)
+                .with_io_context(|| format!("removing stow package {}", old_pkg.display()))?;
+        }
+        let (undo_root, undo_alias, undo_target) = (root.to_path_buf(), alias.name.clone(), alias.target.clone());
+        journal.push(move || { let _ = link_alias_to_store(&undo_root, &undo_alias, &undo_target); });
+    }
+
+    // Configs: this container's own, then anything that extends it
+    let old_config = config_path(root, old);
+    if old_config.exists() {
+        let mut config = ContainerConfig::load(root, old)?;
+        config.name = new.to_string();
+        retarget_paths(&mut config, root, old, new);
+        journal.preserve(&config_path(root, new));
+        config.save(root)?;
+        journal.preserve(&old_config);
+        fs::remove_file(&old_config)
+            .with_io_context(|| format!("removing {}", old_config.display()))?;
+    }
+
+    let config_dir = root.join(".sfc").join("containers");
+    if config_dir.exists() {
+        let entries = fs::read_dir(&config_dir)
+            .with_io_context(|| format!("reading {}", config_dir.display()))?;
+        for entry in entries.flatten() {
+            let path = entry.path();
+            let Some(name) = path.file_stem().map(|s| s.to_string_lossy().to_string()) else { continue };
+            if path.extension().is_none_or(|ext| ext != "toml") {
+                continue;
+            }
+            let mut config = ContainerConfig::load(root, &name)?;
+            if !config.extends.iter().any(|base| base == old) {
+                continue;
+            }
+            for base in &mut config.extends {
+                if base == old {
+                    *base = new.to_string();
+                }
+            }
+            journal.preserve(&path);
+            config.save(root)?;
+        }
+    }
+
+    let current = root.join(".sfc").join("current");
+    if fs::read_to_string(&current).is_ok_and(|c| c.trim() == old) {
+        journal.preserve(&current);
+        fs::write(&current, new).with_io_context(|| format!("writing {}", current.display()))?;
+    }
+
+    let history_file = root.join(".sfc").join("history.json");
+    journal.preserve(&history_file);
+    let mut history = History::load(root)?;
+    history.rename_container(old, new);
+    if ContainerConfig::exists(root, new) {
+        let config = ContainerConfig::load(root, new)?;
+        history.add_entry(&config, Operation::Rename { from: old.to_string() }, format!("Renamed from {}", old))?;
+    } else if history_file.exists() {
+        history.save()?;
+    }
+
+    let tags = TagStore::new(root);
+    if tags.path().exists() {
+        journal.preserve(tags.path());
+        tags.rename_container(old, new)?;
+    }
+
+    let projects = root.join(".sfc").join("projects");
+    if projects.join(old).exists() {
+        fs::rename(projects.join(old), projects.join(new))
+            .with_io_context(|| "renaming project binding".to_string())?;
+        let (from, to) = (projects.join(new), projects.join(old));
+        journal.push(move || { let _ = fs::rename(&from, &to); });
+    }
+
+    let cache_dir = root.join(".sfc").join("cache").join("tasks");
+    if cache_dir.join(old).exists() {
+        fs::rename(cache_dir.join(old), cache_dir.join(new))
+            .with_io_context(|| "renaming task cache".to_string())?;
+        let (from, to) = (cache_dir.join(new), cache_dir.join(old));
+        journal.push(move || { let _ = fs::rename(&from, &to); });
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace_with(name: &str) -> tempfile::TempDir {
+        let ws = tempfile::tempdir().unwrap();
+        let root = ws.path();
+        fs::create_dir_all(root.join("store").join("abc123-snapshot-000")).unwrap();
+        fs::create_dir_all(root.join("links")).unwrap();
+        fs::create_dir_all(root.join("containers").join(name).join("local").join("bin")).unwrap();
+        unix_fs::symlink("../store/abc123-snapshot-000", root.join("links").join(format!("{}-stable", name))).unwrap();
+        unix_fs::symlink(
+            Path::new("../../links").join(format!("{}-stable", name)),
+            root.join("containers").join(name).join("stable"),
+        ).unwrap();
+
+        let mut config = ContainerConfig::new(name.to_string());
+        let bin = root.join("containers").join(name).join("local").join("bin");
+        config.environment.insert("PATH".to_string(), format!("{}:/usr/bin", bin.display()));
+        config.save(root).unwrap();
+        fs::write(root.join(".sfc").join("current"), name).unwrap();
+        ws
+    }
+
+    #[test]
+    fn test_rename_moves_every_reference() {
+        let ws = workspace_with("app");
+        let root = ws.path();
+        let mut child = ContainerConfig::new("child".to_string());
+        child.extends = vec!["app".to_string()];
+        child.save(root).unwrap();
+
+        rename_container(root, "app", "web").unwrap();
+
+        assert!(!root.join("containers").join("app").exists());
+        assert!(root.join("containers").join("web").join("stable").join(".").exists());
+        assert!(!root.join("links").join("app-stable").is_symlink());
+        assert_eq!(
+            fs::canonicalize(root.join("links").join("web-stable")).unwrap(),
+            fs::canonicalize(root.join("store").join("abc123-snapshot-000")).unwrap(),
+        );
+        assert!(!config_path(root, "app").exists());
+        let config = ContainerConfig::load(root, "web").unwrap();
+        assert!(config.environment["PATH"].contains("containers/web/local/bin"));
+        assert_eq!(ContainerConfig::load(root, "child").unwrap().extends, vec!["web".to_string()]);
+        assert_eq!(fs::read_to_string(root.join(".sfc").join("current")).unwrap(), "web");
+        let history = History::load(root).unwrap();
+        let entries = history.get_container_history("web");
+        assert!(matches!(&entries.last().unwrap().operation, Operation::Rename { from } if from == "app"));
+    }
+
+    #[test]
+    fn test_clone_shares_snapshots_and_refuses_existing_names() {
+        let ws = workspace_with("app");
+        let root = ws.path();
+
+        let aliases = clone_container(root, "app", "fork").unwrap();
+        assert_eq!(aliases, vec!["fork-stable".to_string()]);
+        assert_eq!(
+            fs::canonicalize(root.join("links").join("fork-stable")).unwrap(),
+            fs::canonicalize(root.join("links").join("app-stable")).unwrap(),
+        );
+        assert!(root.join("containers").join("fork").join("local").join("bin").is_dir());
+        assert!(root.join("links").join("app-stable").is_symlink());
+
+        let history = History::load(root).unwrap();
+        assert_eq!(history.get_container_history("fork").len(), 1);
+
+        assert!(matches!(clone_container(root, "app", "fork"), Err(SfcError::AlreadyExists { .. })));
+        assert!(matches!(rename_container(root, "nope", "x"), Err(SfcError::NotFound { .. })));
+    }
+
+    /// Link a temp of `container` called `name` to the same store dir as its stable
+    fn add_temp(root: &Path, container: &str, name: &str) {
+        unix_fs::symlink("../store/abc123-snapshot-000", root.join("links").join(temp_alias(container, name))).unwrap();
+    }
+
+    #[test]
+    fn test_rename_moves_named_temps() {
+        let ws = workspace_with("web");
+        let root = ws.path();
+        add_temp(root, "web", "exp1");
+        add_temp(root, "web", "20250101120000-2");
+
+        rename_container(root, "web", "site").unwrap();
+
+        assert!(!root.join("links").join("web-temp-exp1").is_symlink());
+        assert!(!root.join("links").join("web-temp-20250101120000-2").is_symlink());
+        let names: Vec<String> = list_temps(root, "site").unwrap().into_iter().map(|t| t.name).collect();
+        assert_eq!(names.len(), 2);
+        assert!(names.contains(&"exp1".to_string()) && names.contains(&"20250101120000-2".to_string()));
+        assert!(list_temps(root, "web").unwrap().is_empty());
+
+        // A leftover named temp keeps its name from being reused
+        add_temp(root, "old", "exp1");
+        assert!(matches!(rename_container(root, "site", "old"), Err(SfcError::AlreadyExists { .. })));
+    }
+
+    #[test]
+    fn test_clone_copies_named_temps_but_not_longer_containers() {
+        let ws = workspace_with("app");
+        let root = ws.path();
+        add_temp(root, "app", "exp1");
+        ContainerConfig::new("app-temp-x".to_string()).save(root).unwrap();
+        unix_fs::symlink("../store/abc123-snapshot-000", root.join("links").join("app-temp-x-stable")).unwrap();
+
+        let aliases = clone_container(root, "app", "fork").unwrap();
+        assert_eq!(aliases, vec!["fork-stable".to_string(), "fork-temp-exp1".to_string()]);
+        assert_eq!(list_temps(root, "fork").unwrap()[0].name, "exp1");
+        assert!(list_temps(root, "app").unwrap().iter().any(|t| t.name == "exp1"));
+    }
+}
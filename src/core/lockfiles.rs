@@ -0,0 +1,172 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::core::hash::SNAPSHOT_LOCKFILES;
+use crate::error::{Result, ErrorContext};
+use crate::system::shell::find_marker;
+
+/// Stand-ins older versions seeded into new snapshots; never written into a project
+const PLACEHOLDERS: &[(&str, &str)] = &[
+    ("requirements.txt", "# pinned python deps\n"),
+    ("rockspec.lock", "# pinned luarocks deps\n"),
+    ("Cargo.lock", "# pinned cargo lock placeholder\n"),
+    ("package-lock.json", "{\n  \"name\": \"sfc-container\",\n  \"lockfileVersion\": 2\n}\n"),
+];
+
+/// Where a container's project binding is kept
+fn binding_path(workspace_root: &Path, container: &str) -> PathBuf {
+    workspace_root.join(".sfc").join("projects").join(container)
+}
+
+/// The project directory bound to a container with [`bind_project`], if any
+pub fn project_dir(workspace_root: &Path, container: &str) -> Result<Option<PathBuf>> {
+    let path = binding_path(workspace_root, container);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read_to_string(&path).with_io_context(|| format!("reading {}", path.display()))?;
+    let dir = content.trim();
+    Ok((!dir.is_empty()).then(|| PathBuf::from(dir)))
+}
+
+/// Bind a container to the project directory whose lockfiles its snapshots track
+pub fn bind_project(workspace_root: &Path, container: &str, dir: &Path) -> Result<PathBuf> {
+    let dir = dir.canonicalize().with_io_context(|| format!("resolving {}", dir.display()))?;
+    let path = binding_path(workspace_root, container);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_io_context(|| format!("creating {}", parent.display()))?;
+    }
+    fs::write(&path, format!("{}\n", dir.display())).with_io_context(|| format!("writing {}", path.display()))?;
+    Ok(dir)
+}
+
+/// The directory `sfc bind` offers by default: the one whose `.sfc-container`
+/// marker names `container` when `cwd` is inside it, else `cwd` itself
+pub fn default_project_dir(container: &str, cwd: &Path) -> PathBuf {
+    match find_marker(cwd) {
+        Some((dir, bound)) if bound == container => dir,
+        _ => cwd.to_path_buf(),
+    }
+}
+
+/// Copy the project's lockfiles into a snapshot. Lockfiles the project lacks
+/// are left as the snapshot had them. Returns the lockfiles captured.
+pub fn capture_lockfiles(project_dir: &Path, snapshot_dir: &Path) -> Result<Vec<String>> {
+    let mut captured = Vec::new();
+    for file in SNAPSHOT_LOCKFILES {
+        let src = project_dir.join(file);
+        if src.is_file() {
+            let dst = snapshot_dir.join(file);
+            fs::copy(&src, &dst).with_io_context(|| format!("capturing {}", src.display()))?;
+            captured.push(file.to_string());
+        }
+    }
+    Ok(captured)
+}
+
+/// What restoring a snapshot's lockfiles did to a project
+#[derive(Debug, Default)]
+pub struct LockfileRestore {
+    pub restored: Vec<String>,
+    pub removed: Vec<String>,
+    /// Edited since the replaced snapshot was captured, so left alone
+    pub kept: Vec<String>,
+}
+
+/// Write `snapshot_dir`'s lockfiles back into `project_dir`. Lockfiles that no
+/// longer match `current` (the snapshot being replaced) hold work it never
+/// captured, and are kept unless `force` is set.
+pub fn restore_lockfiles(
+    snapshot_dir: &Path,
+    project_dir: &Path,
+    current: Option<&Path>,
+    force: bool,
+) -> Result<LockfileRestore> {
+    let mut outcome = LockfileRestore::default();
+    for file in SNAPSHOT_LOCKFILES {
+        let target = read_lockfile(snapshot_dir, file)?;
+        let path = project_dir.join(file);
+        let existing = read_lockfile(project_dir, file)?;
+        if existing == target {
+            continue;
+        }
+        let captured = match current {
+            Some(dir) => read_lockfile(dir, file)?,
+            None => None,
+        };
+        if existing.is_some() && existing != captured && !force {
+            outcome.kept.push(file.to_string());
+            continue;
+        }
+        match target {
+            Some(content) => {
+                fs::write(&path, content).with_io_context(|| format!("writing {}", path.display()))?;
+                outcome.restored.push(file.to_string());
+            }
+            None if existing.is_some() => {
+                fs::remove_file(&path).with_io_context(|| format!("removing {}", path.display()))?;
+                outcome.removed.push(file.to_string());
+            }
+            None => {}
+        }
+    }
+    Ok(outcome)
+}
+
+/// A lockfile's bytes, treating missing files and old placeholders as absent
+fn read_lockfile(dir: &Path, file: &str) -> Result<Option<Vec<u8>>> {
+    let path = dir.join(file);
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let content = fs::read(&path).with_io_context(|| format!("reading {}", path.display()))?;
+    let placeholder = PLACEHOLDERS.iter().any(|(name, text)| *name == file && text.as_bytes() == content);
+    Ok((!placeholder).then_some(content))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_capture_and_restore_lockfiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let (project, old, new) = (dir.path().join("src"), dir.path().join("old"), dir.path().join("new"));
+        for d in [&project, &old, &new] {
+            fs::create_dir_all(d).unwrap();
+        }
+        fs::write(old.join("Cargo.lock"), "# pinned cargo lock placeholder\n").unwrap();
+        fs::write(project.join("Gemfile.lock"), "rails (7.1)\n").unwrap();
+        fs::write(project.join("mix.lock"), "%{jason: 1.4}\n").unwrap();
+        assert_eq!(capture_lockfiles(&project, &old).unwrap(), vec!["Gemfile.lock", "mix.lock"]);
+        assert!(old.join("Cargo.lock").exists());
+
+        fs::write(new.join("Gemfile.lock"), "rails (7.0)\n").unwrap();
+        fs::write(new.join("rockspec.lock"), "# pinned luarocks deps\n").unwrap();
+        fs::write(project.join("mix.lock"), "%{jason: 1.5}\n").unwrap();
+        let outcome = restore_lockfiles(&new, &project, Some(&old), false).unwrap();
+        assert_eq!(outcome.restored, vec!["Gemfile.lock"]);
+        assert_eq!(outcome.kept, vec!["mix.lock"]);
+        assert!(!project.join("rockspec.lock").exists());
+        assert_eq!(fs::read_to_string(project.join("Gemfile.lock")).unwrap(), "rails (7.0)\n");
+
+        let forced = restore_lockfiles(&new, &project, Some(&old), true).unwrap();
+        assert_eq!(forced.removed, vec!["mix.lock"]);
+    }
+
+    #[test]
+    fn test_project_binding() {
+        let ws = tempfile::tempdir().unwrap();
+        let project = ws.path().join("work").join("app");
+        fs::create_dir_all(project.join("nested")).unwrap();
+        assert_eq!(project_dir(ws.path(), "app").unwrap(), None);
+        let bound = bind_project(ws.path(), "app", &project).unwrap();
+        assert_eq!(project_dir(ws.path(), "app").unwrap(), Some(bound));
+        assert_eq!(project_dir(ws.path(), "other").unwrap(), None);
+
+        let nested = project.join("nested");
+        assert_eq!(default_project_dir("app", &nested), nested);
+        fs::write(project.join(".sfc-container"), "app\n").unwrap();
+        assert_eq!(default_project_dir("app", &nested), project);
+    }
+}
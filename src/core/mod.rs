@@ -2,8 +2,22 @@ pub mod workspace;
 pub mod snapshot;
 pub mod symlink;
 pub mod hash;
+pub mod task;
+pub mod lifecycle;
+pub mod archive;
+pub mod tags;
+pub mod diff;
+pub mod lockfiles;
+pub mod export;
 
 pub use workspace::{WorkspaceManager, ensure_workspace_layout};
-pub use snapshot::{SnapshotManager, SnapshotInfo, create_snapshot_dir};
+pub use snapshot::{SnapshotManager, SnapshotInfo, SnapshotMetadata, create_snapshot_dir};
 pub use symlink::{SymlinkManager, create_or_update_symlink};
 pub use hash::{compute_snapshot_hash, compute_content_hash};
+pub use task::{TaskSpec, TaskRunner, TaskOutcome};
+pub use lifecycle::{clone_container, rename_container};
+pub use tags::TagStore;
+pub use diff::{SnapshotDiff, ChangeKind};
+pub use export::{ImportedSnapshot, SnapshotArchiveHeader};
+pub use lockfiles::{bind_project, capture_lockfiles, default_project_dir, restore_lockfiles, project_dir, LockfileRestore};
+pub use archive::{read_tar, safe_path, tree_entries, unpack_entries, EntryKind, TarEntry, TarWriter};
@@ -1,12 +1,14 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use chrono::{DateTime, Utc};
 use rand::{distributions::Alphanumeric, Rng};
 use serde::{Deserialize, Serialize};
 
+use crate::container::{ContainerConfig, PackageSpec};
 use crate::error::{Result, SfcError, ErrorContext};
 use crate::core::hash::compute_snapshot_hash;
+use crate::core::tags::{split_relative, TagStore};
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SnapshotInfo {
@@ -14,9 +16,12 @@ pub struct SnapshotInfo {
     pub timestamp: DateTime<Utc>,
     pub description: String,
     pub is_active: bool,
-    pub packages: Vec<PackageInfo>,
-    pub toolchains: HashMap<String, String>,
+    pub packages: Vec<PackageSpec>,
+    pub toolchains: BTreeMap<String, String>,
     pub container_name: String,
+    /// From the snapshot's manifest; `None` for snapshots made before it existed
+    pub created_by: Option<String>,
+    pub parent: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -36,8 +41,67 @@ pub struct ShareInfo {
     pub description: String,
 }
 
+/// Manifest kept in a snapshot as `sfc-metadata.toml`. It is part of the
+/// snapshot hash, so two snapshots with the same lockfiles still differ.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SnapshotMetadata {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub created_by: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Hash of the snapshot this one was made from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub packages: Vec<PackageSpec>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub toolchains: BTreeMap<String, String>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub environment: BTreeMap<String, String>,
+}
+
+impl SnapshotMetadata {
+    pub const FILE: &'static str = "sfc-metadata.toml";
+
+    /// Manifest for a snapshot of `config` made now by the current user
+    pub fn new(config: &ContainerConfig, parent: Option<String>) -> Self {
+        Self {
+            created_by: whoami::username(),
+            created_at: Some(Utc::now()),
+            parent,
+            packages: config.packages.clone(),
+            toolchains: config.toolchains.clone(),
+            environment: config.environment.clone().into_iter().collect(),
+        }
+    }
+
+    /// Read a snapshot's manifest, if it has one
+    pub fn load(snapshot_dir: &Path) -> Result<Option<Self>> {
+        let path = snapshot_dir.join(Self::FILE);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_io_context(|| format!("reading {}", path.display()))?;
+        toml::from_str(&content).map(Some).map_err(|e| SfcError::Config {
+            message: format!("invalid snapshot manifest: {}", e),
+            path: Some(path),
+        })
+    }
+
+    pub fn save(&self, snapshot_dir: &Path) -> Result<()> {
+        let path = snapshot_dir.join(Self::FILE);
+        let content = toml::to_string_pretty(self).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize snapshot manifest: {}", e),
+            source: None,
+        })?;
+        fs::write(&path, content).with_io_context(|| format!("writing {}", path.display()))
+    }
+}
+
 pub struct SnapshotManager {
-    workspace_root: PathBuf,
+    pub(crate) workspace_root: PathBuf,
 }
 
 impl SnapshotManager {
@@ -119,8 +183,10 @@ impl SnapshotManager {
         self.get_snapshot_hash_from_link(&stable_link)
     }
     
-    /// Find snapshot by hash prefix
+    /// Find snapshot by hash or hash prefix. A prefix matching more than one
+    /// snapshot is an error.
     pub fn find_snapshot_by_hash(&self, hash: &str) -> Result<PathBuf> {
+        check_prefix_length(hash)?;
         let store_dir = self.workspace_root.join("store");
         
         if !store_dir.exists() {
@@ -133,6 +199,9 @@ impl SnapshotManager {
         let entries = fs::read_dir(&store_dir)
             .with_io_context(|| format!("reading store directory {}", store_dir.display()))?;
         
+        // Copies of one snapshot share a hash, so only distinct hashes are ambiguous
+        let mut found: Option<(PathBuf, String)> = None;
+        let mut others = Vec::new();
         for entry in entries {
             let entry = entry
                 .with_io_context(|| "reading store entry".to_string())?;
@@ -144,18 +213,147 @@ impl SnapshotManager {
                 let snapshot_path = entry.path();
                 let snapshot_hash = compute_snapshot_hash(&snapshot_path)?;
                 
-                if snapshot_hash.starts_with(hash) {
-                    return Ok(snapshot_path);
+                if !snapshot_hash.starts_with(hash) {
+                    continue;
+                }
+                match &found {
+                    None => found = Some((snapshot_path, snapshot_hash)),
+                    Some((_, first)) if *first != snapshot_hash => others.push(snapshot_hash),
+                    Some(_) => {}
                 }
             }
         }
         
-        Err(SfcError::NotFound {
+        match found {
+            Some((_, first)) if !others.is_empty() => {
+                others.insert(0, first);
+                Err(ambiguous(hash, &others))
+            }
+            Some((path, _)) => Ok(path),
+            None => Err(SfcError::NotFound {
+                resource: "snapshot".to_string(),
+                identifier: hash.to_string(),
+            }),
+        }
+    }
+    
+    /// Resolve a container's snapshot reference to a full hash. The reference
+    /// is one of its tags, `stable` or a hash prefix, optionally followed by
+    /// `~N` to step N snapshots further back.
+    pub fn resolve(&self, container_name: &str, reference: &str) -> Result<String> {
+        let (base, steps) = split_relative(reference)?;
+        let hash = if let Some(hash) = TagStore::new(&self.workspace_root).get(container_name, base)? {
+            hash
+        } else if base == "stable" {
+            self.get_current_snapshot_hash(container_name)?
+        } else if is_hash_prefix(base) {
+            self.resolve_prefix(container_name, &base.to_lowercase())?
+        } else {
+            return Err(SfcError::NotFound {
+                resource: "snapshot".to_string(),
+                identifier: format!("{} in {}", reference, container_name),
+            });
+        };
+        let mut hash = hash;
+        for _ in 0..steps {
+            hash = self.parent_of(container_name, &hash)?.ok_or_else(|| SfcError::NotFound {
+                resource: "snapshot".to_string(),
+                identifier: format!("{} ({} has no parent)", reference, &hash[..12.min(hash.len())]),
+            })?;
+        }
+        Ok(hash)
+    }
+
+    /// The one snapshot of the container's whose hash starts with `prefix`
+    fn resolve_prefix(&self, container_name: &str, prefix: &str) -> Result<String> {
+        match_prefix(&self.container_hashes(container_name)?, prefix)?.ok_or_else(|| SfcError::NotFound {
             resource: "snapshot".to_string(),
-            identifier: hash.to_string(),
+            identifier: format!("{} in {}", prefix, container_name),
         })
     }
-    
+
+    /// Hashes of a container's snapshots: the ones its links and tags name,
+    /// and every snapshot those were made from, as their manifests record
+    fn container_hashes(&self, container_name: &str) -> Result<BTreeSet<String>> {
+        let mut pending: Vec<String> = self
+            .list_container_snapshots(container_name)?
+            .into_iter()
+            .map(|s| s.hash)
+            .collect();
+        pending.extend(TagStore::new(&self.workspace_root).for_container(container_name)?.into_values());
+        let mut hashes = BTreeSet::new();
+        while let Some(hash) = pending.pop() {
+            if !hashes.insert(hash.clone()) {
+                continue;
+            }
+            // Ancestors may have been cleaned from the store since
+            let Ok(dir) = self.find_snapshot_by_hash(&hash) else { continue };
+            if let Some(parent) = SnapshotMetadata::load(&dir)?.and_then(|m| m.parent) {
+                pending.push(parent);
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// The snapshot `hash` was made from: the parent its manifest records,
+    /// or for snapshots without one, the next older of the container's snapshots
+    fn parent_of(&self, container_name: &str, hash: &str) -> Result<Option<String>> {
+        if let Some(metadata) = SnapshotMetadata::load(&self.find_snapshot_by_hash(hash)?)? {
+            return Ok(metadata.parent);
+        }
+        // Newest first, so the parent is the entry after this one
+        let snapshots = self.list_container_snapshots(container_name)?;
+        Ok(snapshots
+            .iter()
+            .position(|s| s.hash == hash)
+            .and_then(|position| snapshots.get(position + 1))
+            .map(|s| s.hash.clone()))
+    }
+
+    /// Write a snapshot's manifest from the container's effective config.
+    /// Call it once, when the snapshot is made: the manifest is hashed, so
+    /// writing it again would give the snapshot a new hash.
+    pub fn record_metadata(&self, container_name: &str, snapshot_dir: &Path, parent: Option<String>) -> Result<SnapshotMetadata> {
+        let config = if ContainerConfig::exists(&self.workspace_root, container_name) {
+            ContainerConfig::load_effective(&self.workspace_root, container_name)?
+        } else {
+            ContainerConfig::new(container_name.to_string())
+        };
+        let metadata = SnapshotMetadata::new(&config, parent);
+        metadata.save(snapshot_dir)?;
+        Ok(metadata)
+    }
+
+    /// Resolve a reference without a container at hand: `container:ref`, a
+    /// tag only one container has, or a hash prefix
+    pub fn resolve_any(&self, reference: &str) -> Result<String> {
+        if let Some((container_name, rest)) = reference.split_once(':') {
+            return self.resolve(container_name, rest);
+        }
+        let (base, steps) = split_relative(reference)?;
+        let owners = TagStore::new(&self.workspace_root).find(base)?;
+        match owners.as_slice() {
+            [(container_name, _)] => self.resolve(container_name, reference),
+            [] if steps == 0 && is_hash_prefix(base) => {
+                compute_snapshot_hash(&self.find_snapshot_by_hash(&base.to_lowercase())?)
+            }
+            [] => Err(SfcError::NotFound {
+                resource: "snapshot".to_string(),
+                identifier: reference.to_string(),
+            }),
+            _ => Err(SfcError::Validation {
+                field: "snapshot".to_string(),
+                value: reference.to_string(),
+                reason: format!(
+                    "tag '{}' exists in {}; use <container>:{}",
+                    base,
+                    owners.iter().map(|(c, _)| c.as_str()).collect::<Vec<_>>().join(", "),
+                    reference
+                ),
+            }),
+        }
+    }
+
     /// Generate sharing information for a snapshot
     pub fn generate_share_info(&self, container_name: &str, hash: &str) -> Result<ShareInfo> {
         let snapshot_path = self.find_snapshot_by_hash(hash)?;
@@ -261,11 +459,6 @@ impl SnapshotManager {
         Ok(new_snapshot)
     }
     
-    /// Seed default lockfiles in a snapshot
-    pub fn seed_lockfiles(&self, snapshot_dir: &Path) -> Result<()> {
-        seed_lockfiles(snapshot_dir)
-    }
-    
     // Helper methods
     
     fn get_snapshot_hash_from_link(&self, link_path: &Path) -> Result<String> {
@@ -313,13 +506,16 @@ impl SnapshotManager {
             return Ok(None);
         }
         
-        // Get timestamp from directory metadata
-        let metadata = fs::metadata(snapshot_path)
-            .with_io_context(|| format!("getting metadata for snapshot {}", snapshot_path.display()))?;
-        
-        let timestamp = metadata.modified()
-            .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-            .into();
+        // Prefer the manifest's creation time over the directory's mtime
+        let manifest = SnapshotMetadata::load(snapshot_path)?.unwrap_or_default();
+        let timestamp = match manifest.created_at {
+            Some(created_at) => created_at,
+            None => fs::metadata(snapshot_path)
+                .with_io_context(|| format!("getting metadata for snapshot {}", snapshot_path.display()))?
+                .modified()
+                .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
+                .into(),
+        };
         
         // Generate description
         let description = if Some(hash) == current_hash {
@@ -333,9 +529,11 @@ impl SnapshotManager {
             timestamp,
             description,
             is_active: Some(hash) == current_hash,
-            packages: Vec::new(), // Would be populated from container config
-            toolchains: HashMap::new(), // Would be populated from snapshot
+            packages: manifest.packages,
+            toolchains: manifest.toolchains,
             container_name: container_name.to_string(),
+            created_by: Some(manifest.created_by).filter(|user| !user.is_empty()),
+            parent: manifest.parent,
         }))
     }
     
@@ -419,6 +617,46 @@ impl SnapshotManager {
     }
 }
 
+/// Shortest hash prefix accepted in place of a full snapshot hash
+pub const MIN_HASH_PREFIX: usize = 7;
+
+fn is_hash_prefix(reference: &str) -> bool {
+    !reference.is_empty() && reference.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn check_prefix_length(prefix: &str) -> Result<()> {
+    if prefix.len() < MIN_HASH_PREFIX {
+        return Err(SfcError::Validation {
+            field: "snapshot".to_string(),
+            value: prefix.to_string(),
+            reason: format!("hash prefixes need at least {} characters", MIN_HASH_PREFIX),
+        });
+    }
+    Ok(())
+}
+
+/// The hash among `hashes` that `prefix` picks out, if any
+fn match_prefix(hashes: &BTreeSet<String>, prefix: &str) -> Result<Option<String>> {
+    check_prefix_length(prefix)?;
+    let matches: Vec<String> = hashes.iter().filter(|hash| hash.starts_with(prefix)).cloned().collect();
+    match matches.as_slice() {
+        [] => Ok(None),
+        [hash] => Ok(Some(hash.clone())),
+        _ => Err(ambiguous(prefix, &matches)),
+    }
+}
+
+fn ambiguous(prefix: &str, hashes: &[String]) -> SfcError {
+    SfcError::Validation {
+        field: "snapshot".to_string(),
+        value: prefix.to_string(),
+        reason: format!(
+            "prefix matches {}; use more characters",
+            hashes.iter().map(|h| &h[..12.min(h.len())]).collect::<Vec<_>>().join(", ")
+        ),
+    }
+}
+
 /// Create a new snapshot directory in the workspace store
 pub fn create_snapshot_dir(workspace_root: &Path, kind: &str) -> Result<PathBuf> {
     let store = workspace_root.join("store");
@@ -441,22 +679,62 @@ pub fn create_snapshot_dir(workspace_root: &Path, kind: &str) -> Result<PathBuf>
     Ok(dir)
 }
 
-/// Seed default lockfiles in a snapshot directory
-pub fn seed_lockfiles(snapshot_dir: &Path) -> Result<()> {
-    let lockfiles = vec![
-        ("requirements.txt", "# pinned python deps\n"),
-        ("rockspec.lock", "# pinned luarocks deps\n"),
-        ("Cargo.lock", "# pinned cargo lock placeholder\n"),
-        ("package-lock.json", "{\n  \"name\": \"sfc-container\",\n  \"lockfileVersion\": 2\n}\n"),
-    ];
-    
-    for (filename, content) in &lockfiles {
-        let file_path = snapshot_dir.join(filename);
-        if !file_path.exists() {
-            fs::write(&file_path, content)
-                .with_io_context(|| format!("creating lockfile {}", file_path.display()))?;
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn test_manifest_fills_snapshot_info_and_parent_references() {
+        let ws = tempfile::tempdir().unwrap();
+        let root = ws.path();
+        let manager = SnapshotManager::new(root);
+        let mut config = ContainerConfig::new("web".to_string());
+        config.packages.push(PackageSpec::parse("jq@1.7"));
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        config.save(root).unwrap();
+
+        let first = manager.create_snapshot("snapshot-000").unwrap();
+        fs::write(first.join("Cargo.lock"), "serde 1.0\n").unwrap();
+        manager.record_metadata("web", &first, None).unwrap();
+        let first_hash = compute_snapshot_hash(&first).unwrap();
+        let second = manager.copy_snapshot(&first_hash, "snapshot-temp").unwrap();
+        manager.record_metadata("web", &second, Some(first_hash.clone())).unwrap();
+        let second_hash = compute_snapshot_hash(&second).unwrap();
+        assert_ne!(first_hash, second_hash);
+
+        fs::create_dir_all(root.join("links")).unwrap();
+        symlink(Path::new("../store").join(second.file_name().unwrap()), root.join("links/web-stable")).unwrap();
+        let snapshots = manager.list_container_snapshots("web").unwrap();
+        assert_eq!(snapshots.len(), 1);
+        assert_eq!(snapshots[0].packages[0].name, "jq");
+        assert_eq!(snapshots[0].toolchains["node"], "20");
+        assert_eq!(snapshots[0].parent.as_deref(), Some(first_hash.as_str()));
+        assert!(snapshots[0].created_by.is_some());
+
+        assert_eq!(manager.resolve("web", "stable").unwrap(), second_hash);
+        assert_eq!(manager.resolve("web", "stable~1").unwrap(), first_hash);
+        assert!(manager.resolve("web", "stable~2").is_err());
+        TagStore::new(root).tag("web", "release", &second_hash).unwrap();
+        assert_eq!(manager.resolve_any("release~").unwrap(), first_hash);
+
+        // Prefixes: long enough, and only among the container's own snapshots
+        assert_eq!(manager.resolve("web", &first_hash[..7]).unwrap(), first_hash);
+        assert!(manager.resolve("web", &first_hash[..6]).is_err());
+        let other = manager.create_snapshot("snapshot-000").unwrap();
+        fs::write(other.join("Gemfile.lock"), "rails (7.1)\n").unwrap();
+        let other_hash = compute_snapshot_hash(&other).unwrap();
+        assert!(manager.resolve("web", &other_hash[..12]).is_err());
+        assert_eq!(compute_snapshot_hash(&manager.find_snapshot_by_hash(&other_hash[..12]).unwrap()).unwrap(), other_hash);
+    }
+
+    #[test]
+    fn test_hash_prefix_must_be_unique_and_long_enough() {
+        let hashes: BTreeSet<String> = ["abcdef0123", "abcdef0456", "0123456789"].iter().map(|h| h.to_string()).collect();
+        assert_eq!(match_prefix(&hashes, "0123456").unwrap(), Some("0123456789".to_string()));
+        assert_eq!(match_prefix(&hashes, "abcdef01").unwrap(), Some("abcdef0123".to_string()));
+        assert_eq!(match_prefix(&hashes, "fedcba9").unwrap(), None);
+        assert!(match_prefix(&hashes, "abcdef0").unwrap_err().to_string().contains("abcdef0123, abcdef0456"));
+        assert!(match_prefix(&hashes, "012345").is_err());
     }
-    
-    Ok(())
 }
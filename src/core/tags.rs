@@ -0,0 +1,202 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::error::{Result, SfcError, ErrorContext};
+
+/// Tags for each container: container → tag → full snapshot hash
+type Tags = BTreeMap<String, BTreeMap<String, String>>;
+
+/// Named snapshots, stored in `.sfc/tags.toml`:
+///
+/// ```toml
+/// [web]
+/// release-1 = "cf1493c3ef93..."
+/// ```
+pub struct TagStore {
+    path: PathBuf,
+}
+
+impl TagStore {
+    pub fn new(workspace_root: &Path) -> Self {
+        Self {
+            path: workspace_root.join(".sfc").join("tags.toml"),
+        }
+    }
+
+    /// Location of the tags file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn load(&self) -> Result<Tags> {
+        if !self.path.exists() {
+            return Ok(Tags::new());
+        }
+        let content = fs::read_to_string(&self.path)
+            .with_io_context(|| format!("reading {}", self.path.display()))?;
+        toml::from_str(&content).map_err(|e| SfcError::Config {
+            message: format!("invalid tags file: {}", e),
+            path: Some(self.path.clone()),
+        })
+    }
+
+    fn save(&self, tags: &Tags) -> Result<()> {
+        let content = toml::to_string_pretty(tags).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize tags: {}", e),
+            source: None,
+        })?;
+        if let Some(parent) = self.path.parent() {
+            fs::create_dir_all(parent).with_io_context(|| format!("creating {}", parent.display()))?;
+        }
+        fs::write(&self.path, content).with_io_context(|| format!("writing {}", self.path.display()))
+    }
+
+    /// Point `container`'s tag `name` at `hash`, returning the hash it
+    /// pointed at before (if any)
+    pub fn tag(&self, container: &str, name: &str, hash: &str) -> Result<Option<String>> {
+        validate_tag(name)?;
+        let mut tags = self.load()?;
+        let previous = tags
+            .entry(container.to_string())
+            .or_default()
+            .insert(name.to_string(), hash.to_string());
+        self.save(&tags)?;
+        Ok(previous)
+    }
+
+    /// Tags of one container, by name
+    pub fn for_container(&self, container: &str) -> Result<BTreeMap<String, String>> {
+        Ok(self.load()?.remove(container).unwrap_or_default())
+    }
+
+    /// Tag names pointing at `hash`
+    pub fn names_for(&self, container: &str, hash: &str) -> Result<Vec<String>> {
+        Ok(self
+            .for_container(container)?
+            .into_iter()
+            .filter(|(_, tagged)| tagged == hash)
+            .map(|(name, _)| name)
+            .collect())
+    }
+
+    /// Hash tagged `name` in `container`
+    pub fn get(&self, container: &str, name: &str) -> Result<Option<String>> {
+        Ok(self.for_container(container)?.remove(name))
+    }
+
+    /// Containers with a tag called `name`, and the hash each one points at
+    pub fn find(&self, name: &str) -> Result<Vec<(String, String)>> {
+        Ok(self
+            .load()?
+            .into_iter()
+            .filter_map(|(container, mut tags)| tags.remove(name).map(|hash| (container, hash)))
+            .collect())
+    }
+
+    /// Drop `container`'s tags on `hash`, returning their names
+    pub fn remove_hash(&self, container: &str, hash: &str) -> Result<Vec<String>> {
+        let mut tags = self.load()?;
+        let Some(own) = tags.get_mut(container) else { return Ok(Vec::new()) };
+        let removed: Vec<String> = own
+            .iter()
+            .filter(|(_, tagged)| tagged.as_str() == hash)
+            .map(|(name, _)| name.clone())
+            .collect();
+        if removed.is_empty() {
+            return Ok(removed);
+        }
+        own.retain(|_, tagged| tagged != hash);
+        if own.is_empty() {
+            tags.remove(container);
+        }
+        self.save(&tags)?;
+        Ok(removed)
+    }
+
+    /// Give `dst` a copy of `src`'s tags
+    pub fn copy_container(&self, src: &str, dst: &str) -> Result<()> {
+        let mut tags = self.load()?;
+        let Some(own) = tags.get(src).cloned() else { return Ok(()) };
+        tags.insert(dst.to_string(), own);
+        self.save(&tags)
+    }
+
+    /// Move `old`'s tags to `new`
+    pub fn rename_container(&self, old: &str, new: &str) -> Result<()> {
+        let mut tags = self.load()?;
+        let Some(own) = tags.remove(old) else { return Ok(()) };
+        tags.insert(new.to_string(), own);
+        self.save(&tags)
+    }
+}
+
+/// Tag names must not be mistaken for hash prefixes or relative references
+pub fn validate_tag(name: &str) -> Result<()> {
+    let reason = if name.is_empty() {
+        Some("tag names cannot be empty")
+    } else if !name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_')) {
+        Some("use letters, digits, '.', '-' and '_'")
+    } else if name.starts_with('.') || name.starts_with('-') {
+        Some("tag names cannot start with '.' or '-'")
+    } else if name.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some("tag names cannot look like a snapshot hash")
+    } else if name == "stable" {
+        Some("'stable' always names the current stable snapshot")
+    } else {
+        None
+    };
+    match reason {
+        Some(reason) => Err(SfcError::Validation {
+            field: "tag".to_string(),
+            value: name.to_string(),
+            reason: reason.to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// Split `base~N` into `base` and `N`; a bare `~` means `~1`, as in git
+pub fn split_relative(reference: &str) -> Result<(&str, usize)> {
+    let Some((base, back)) = reference.split_once('~') else {
+        return Ok((reference, 0));
+    };
+    let steps = if back.is_empty() {
+        1
+    } else {
+        back.parse().map_err(|_| SfcError::Validation {
+            field: "snapshot".to_string(),
+            value: reference.to_string(),
+            reason: "expected <ref>~<number>".to_string(),
+        })?
+    };
+    Ok((base, steps))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tag_store_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = TagStore::new(dir.path());
+        assert_eq!(store.tag("web", "release", "aaa").unwrap(), None);
+        assert_eq!(store.tag("web", "release", "bbb").unwrap(), Some("aaa".to_string()));
+        store.tag("web", "before", "aaa").unwrap();
+        assert_eq!(store.names_for("web", "bbb").unwrap(), vec!["release"]);
+
+        store.rename_container("web", "site").unwrap();
+        assert_eq!(store.get("site", "release").unwrap(), Some("bbb".to_string()));
+        assert_eq!(store.find("before").unwrap(), vec![("site".to_string(), "aaa".to_string())]);
+        assert_eq!(store.remove_hash("site", "aaa").unwrap(), vec!["before"]);
+        assert!(store.get("web", "release").unwrap().is_none());
+
+        assert!(validate_tag("cafe12").is_err());
+        assert!(validate_tag("v1~2").is_err());
+        assert!(validate_tag("stable").is_err());
+        assert_eq!(split_relative("stable~2").unwrap(), ("stable", 2));
+        assert_eq!(split_relative("stable~").unwrap(), ("stable", 1));
+        assert!(split_relative("stable~x").is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::thread;
+use owo_colors::OwoColorize;
+use serde::{Deserialize, Serialize};
+
+use crate::container::{exit_code, ContainerConfig};
+use crate::core::hash::compute_content_hash;
+use crate::error::{Result, SfcError, ErrorContext};
+
+/// A named command defined under `[tasks]` in a container config
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct TaskSpec {
+    /// Command line, run with `sh -c` in the container environment
+    pub command: String,
+    /// Working directory relative to the project root
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Extra environment variables for this task only
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    /// Tasks that must succeed before this one starts
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub depends_on: Vec<String>,
+    /// Files or directories whose contents key the result cache (none = always run)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub inputs: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TaskOutcome {
+    Ran,
+    Cached,
+}
+
+/// Runs container tasks with dependency ordering, parallelism and input-hash caching
+pub struct TaskRunner {
+    container: ContainerConfig,
+    workspace_root: PathBuf,
+    project_root: PathBuf,
+    jobs: usize,
+    force: bool,
+}
+
+impl TaskRunner {
+    pub fn new<P: AsRef<Path>>(container: ContainerConfig, workspace_root: P, project_root: P) -> Self {
+        let jobs = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+        Self {
+            container,
+            workspace_root: workspace_root.as_ref().to_path_buf(),
+            project_root: project_root.as_ref().to_path_buf(),
+            jobs,
+            force: false,
+        }
+    }
+
+    /// Maximum number of tasks running at once
+    pub fn with_jobs(mut self, jobs: usize) -> Self {
+        self.jobs = jobs.max(1);
+        self
+    }
+
+    /// Ignore cached results and run every task
+    pub fn with_force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Run `target` and everything it depends on
+    pub fn run(&self, target: &str) -> Result<Vec<(String, TaskOutcome)>> {
+        let order = plan(&self.container.tasks, target)?;
+
+        let mut remaining: HashMap<&str, usize> = HashMap::new();
+        let mut dependents: HashMap<&str, Vec<&str>> = HashMap::new();
+        for name in &order {
+            let spec = &self.container.tasks[name];
+            remaining.insert(name, spec.depends_on.len());
+            for dep in &spec.depends_on {
+                dependents.entry(dep.as_str()).or_default().push(name);
+            }
+        }
+
+        let mut ready: VecDeque<&str> = order.iter()
+            .map(String::as_str)
+            .filter(|n| remaining[n] == 0)
+            .collect();
+        let mut keys: HashMap<String, String> = HashMap::new();
+        let mut results = Vec::new();
+        let mut failure: Option<SfcError> = None;
+
+        thread::scope(|scope| {
+            let (tx, rx) = mpsc::channel();
+            let mut running = 0;
+
+            loop {
+                while failure.is_none() && running < self.jobs {
+                    let Some(name) = ready.pop_front() else { break };
+                    let spec = &self.container.tasks[name];
+                    let dep_keys: Vec<String> = spec.depends_on.iter()
+                        .map(|d| keys[d].clone())
+                        .collect();
+                    let tx = tx.clone();
+                    running += 1;
+                    scope.spawn(move || {
+                        let result = self.run_one(name, spec, &dep_keys);
+                        let _ = tx.send((name, result));
+                    });
+                }
+
+                if running == 0 {
+                    break;
+                }
+
+                let Ok((name, result)) = rx.recv() else { break };
+                running -= 1;
+                match result {
+                    Ok((outcome, key)) => {
+                        keys.insert(name.to_string(), key);
+                        results.push((name.to_string(), outcome));
+                        for dependent in dependents.get(name).into_iter().flatten() {
+                            let count = remaining.get_mut(dependent).unwrap();
+                            *count -= 1;
+                            if *count == 0 {
+                                ready.push_back(dependent);
+                            }
+                        }
+                    }
+                    Err(e) => {
+                        if failure.is_none() {
+                            failure = Some(e);
+                        }
+                    }
+                }
+            }
+        });
+
+        match failure {
+            Some(e) => Err(e),
+            None => Ok(results),
+        }
+    }
+
+    fn run_one(&self, name: &str, spec: &TaskSpec, dep_keys: &[String]) -> Result<(TaskOutcome, String)> {
+        let key = self.cache_key(spec, dep_keys)?;
+        let cache_file = self.cache_file(name);
+
+        if !self.force && !spec.inputs.is_empty() {
+            if let Ok(previous) = fs::read_to_string(&cache_file) {
+                if previous.trim() == key {
+                    println!("{} {} {}", "✓".green(), name.cyan(), "(cached)".dimmed());
+                    return Ok((TaskOutcome::Cached, key));
+                }
+            }
+        }
+
+        println!("{} {} {}", "▶".blue(), name.cyan().bold(), spec.command.dimmed());
+
+        let cwd = match &spec.cwd {
+            Some(dir) => self.project_root.join(dir),
+            None => self.project_root.clone(),
+        };
+        let mut cmd = self.container.command(&self.workspace_root, "sh")
+            .with_io_context(|| format!("preparing task '{}'", name))?;
+        cmd.arg("-c").arg(&spec.command).current_dir(&cwd).envs(&spec.env);
+
+        let status = cmd.status()
+            .with_io_context(|| format!("running task '{}' in {}", name, cwd.display()))?;
+
+        if !status.success() {
+            let code = exit_code(&status);
+            eprintln!("{} {} {}", "✗".red(), name.red().bold(), format!("(exit {})", code).dimmed());
+            return Err(SfcError::Command {
+                command: spec.command.clone(),
+                exit_code: Some(code),
+                stderr: String::new(),
+            });
+        }
+
+        if !spec.inputs.is_empty() {
+            if let Some(parent) = cache_file.parent() {
+                fs::create_dir_all(parent)
+                    .with_io_context(|| format!("creating task cache {}", parent.display()))?;
+            }
+            fs::write(&cache_file, &key)
+                .with_io_context(|| format!("writing task cache {}", cache_file.display()))?;
+        }
+
+        println!("{} {}", "✓".green(), name.cyan());
+        Ok((TaskOutcome::Ran, key))
+    }
+
+    /// Hash of everything that determines a task's result
+    fn cache_key(&self, spec: &TaskSpec, dep_keys: &[String]) -> Result<String> {
+        let mut content = Vec::new();
+        content.extend_from_slice(spec.command.as_bytes());
+        content.push(0);
+        content.extend_from_slice(spec.cwd.as_deref().unwrap_or("").as_bytes());
+        content.push(0);
+
+        let env: BTreeMap<_, _> = spec.env.iter().collect();
+        for (k, v) in env {
+            content.extend_from_slice(format!("{}={}\0", k, v).as_bytes());
+        }
+        for key in dep_keys {
+            content.extend_from_slice(key.as_bytes());
+            content.push(0);
+        }
+
+        let mut files = Vec::new();
+        for input in &spec.inputs {
+            collect_files(&self.project_root.join(input), &mut files)?;
+        }
+        files.sort();
+        for file in files {
+            let bytes = fs::read(&file)
+                .with_io_context(|| format!("reading task input {}", file.display()))?;
+            let rel = file.strip_prefix(&self.project_root).unwrap_or(&file);
+            content.extend_from_slice(rel.to_string_lossy().as_bytes());
+            content.push(0);
+            content.extend_from_slice(compute_content_hash(&bytes).as_bytes());
+        }
+
+        Ok(compute_content_hash(&content))
+    }
+
+    /// Cached key for `task`, kept per project: one container may serve
+    /// several checkouts, and a run in one says nothing about the others
+    fn cache_file(&self, task: &str) -> PathBuf {
+        let project = compute_content_hash(self.project_root.to_string_lossy().as_bytes());
+        self.workspace_root
+            .join(".sfc")
+            .join("cache")
+            .join("tasks")
+            .join(&self.container.name)
+            .join(&project[..16])
+            .join(task)
+    }
+}
+
+/// Dependency-ordered list of tasks needed to run `target`
+pub fn plan(tasks: &BTreeMap<String, TaskSpec>, target: &str) -> Result<Vec<String>> {
+    fn visit(
+        tasks: &BTreeMap<String, TaskSpec>,
+        name: &str,
+        stack: &mut Vec<String>,
+        order: &mut Vec<String>,
+    ) -> Result<()> {
+        if order.iter().any(|n| n == name) {
+            return Ok(());
+        }
+        if stack.iter().any(|n| n == name) {
+            stack.push(name.to_string());
+            return Err(SfcError::Validation {
+                field: "depends_on".to_string(),
+                value: stack.join(" -> "),
+                reason: "task dependencies form a cycle".to_string(),
+            });
+        }
+        let spec = tasks.get(name).ok_or_else(|| SfcError::NotFound {
+            resource: "task".to_string(),
+            identifier: name.to_string(),
+        })?;
+
+        stack.push(name.to_string());
+        for dep in &spec.depends_on {
+            visit(tasks, dep, stack, order)?;
+        }
+        stack.pop();
+        order.push(name.to_string());
+        Ok(())
+    }
+
+    let mut order = Vec::new();
+    visit(tasks, target, &mut Vec::new(), &mut order)?;
+    Ok(order)
+}
+
+fn collect_files(path: &Path, files: &mut Vec<PathBuf>) -> Result<()> {
+    if path.is_file() {
+        files.push(path.to_path_buf());
+    } else if path.is_dir() {
+        let entries = fs::read_dir(path)
+            .with_io_context(|| format!("reading task input directory {}", path.display()))?;
+        for entry in entries {
+            let entry = entry.with_io_context(|| "reading task input entry".to_string())?;
+            collect_files(&entry.path(), files)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(command: &str, deps: &[&str]) -> TaskSpec {
+        TaskSpec {
+            command: command.to_string(),
+            depends_on: deps.iter().map(|d| d.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_plan_orders_dependencies_first() {
+        let mut tasks = BTreeMap::new();
+        tasks.insert("test".to_string(), task("true", &["build", "lint"]));
+        tasks.insert("build".to_string(), task("true", &["fetch"]));
+        tasks.insert("lint".to_string(), task("true", &["fetch"]));
+        tasks.insert("fetch".to_string(), task("true", &[]));
+
+        let order = plan(&tasks, "test").unwrap();
+        assert_eq!(order, vec!["fetch", "build", "lint", "test"]);
+    }
+
+    #[test]
+    fn test_plan_rejects_cycles_and_unknown_tasks() {
+        let mut tasks = BTreeMap::new();
+        tasks.insert("a".to_string(), task("true", &["b"]));
+        tasks.insert("b".to_string(), task("true", &["a"]));
+        tasks.insert("c".to_string(), task("true", &["missing"]));
+
+        assert!(matches!(plan(&tasks, "a"), Err(SfcError::Validation { .. })));
+        assert!(matches!(plan(&tasks, "c"), Err(SfcError::NotFound { .. })));
+    }
+
+    #[test]
+    fn test_run_caches_on_unchanged_inputs() {
+        let ws = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join("input.txt"), "one").unwrap();
+
+        let mut container = ContainerConfig::new("demo".to_string());
+        container.tasks.insert("gen".to_string(), TaskSpec {
+            command: "cat input.txt >> out.txt".to_string(),
+            inputs: vec!["input.txt".to_string()],
+            ..Default::default()
+        });
+
+        let runner = TaskRunner::new(container, ws.path(), project.path());
+        assert_eq!(runner.run("gen").unwrap()[0].1, TaskOutcome::Ran);
+        assert_eq!(runner.run("gen").unwrap()[0].1, TaskOutcome::Cached);
+
+        fs::write(project.path().join("input.txt"), "two").unwrap();
+        assert_eq!(runner.run("gen").unwrap()[0].1, TaskOutcome::Ran);
+        assert_eq!(fs::read_to_string(project.path().join("out.txt")).unwrap(), "onetwo");
+
+        // Another checkout with the same inputs has never run the task
+        let other = tempfile::tempdir().unwrap();
+        fs::write(other.path().join("input.txt"), "two").unwrap();
+        let other_runner = TaskRunner::new(runner.container.clone(), ws.path(), other.path());
+        assert_eq!(other_runner.run("gen").unwrap()[0].1, TaskOutcome::Ran);
+        assert_eq!(runner.run("gen").unwrap()[0].1, TaskOutcome::Cached);
+    }
+}
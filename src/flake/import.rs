@@ -0,0 +1,729 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+use super::parser::{self, AttrKey, Binding, Expr, StrPart};
+use super::{package_for_attr, FlakeRef, DEFAULT_NIXPKGS};
+use crate::config::{ImportReport, Unmapped};
+
+/// mkShell arguments that hold packages
+const PACKAGE_ATTRS: &[&str] = &["packages", "buildInputs", "nativeBuildInputs", "propagatedBuildInputs"];
+/// mkShell arguments that aren't environment variables
+const SHELL_ATTRS: &[&str] = &["name", "shellHook", "inputsFrom", "env", "hardeningDisable", "stdenv", "passthru", "meta"];
+
+/// Names a package set is usually bound to
+const PACKAGE_SETS: &[&str] = &["pkgs", "nixpkgs", "final", "prev"];
+
+/// How deep let-bound names are followed, so cyclic bindings terminate
+const MAX_DEPTH: usize = 16;
+
+/// A dev shell read from an existing `flake.nix`
+#[derive(Debug, Clone)]
+pub struct FlakeImport {
+    /// Packages, toolchains and env for the new container, plus what was skipped
+    pub report: ImportReport,
+    /// The flake's nixpkgs input, when it isn't sfc's default
+    pub nixpkgs: Option<String>,
+    /// Name of the imported shell under `devShells`
+    pub shell: String,
+    /// Other dev shells the flake defines
+    pub other_shells: Vec<String>,
+}
+
+struct Input {
+    url: String,
+    flake: bool,
+}
+
+/// Names in scope at a point in the expression
+#[derive(Clone, Copy)]
+enum Frame<'a> {
+    Let(&'a [Binding]),
+    With,
+}
+
+struct Shell<'a> {
+    name: String,
+    args: &'a [Binding],
+    scope: Vec<Frame<'a>>,
+}
+
+/// Value bound to `name` by the innermost `let`/`rec` that defines it
+fn lookup<'a>(scope: &[Frame<'a>], name: &str) -> Option<&'a Expr> {
+    scope.iter().rev().find_map(|frame| match frame {
+        Frame::Let(bindings) => bindings.iter().find_map(|binding| match binding {
+            Binding::Attr(path, value) if path.len() == 1 && path[0].name() == Some(name) => Some(value),
+            _ => None,
+        }),
+        Frame::With => None,
+    })
+}
+
+/// Attribute paths of nested attribute sets, e.g. `inputs.nixpkgs.url`
+fn flatten<'a>(bindings: &'a [Binding], prefix: &[String], out: &mut Vec<(Vec<String>, &'a Expr)>) {
+    for binding in bindings {
+        let Binding::Attr(keys, value) = binding else { continue };
+        let Some(names) = keys.iter().map(|k| k.name().map(String::from)).collect::<Option<Vec<_>>>() else {
+            continue;
+        };
+        let path = [prefix, &names].concat();
+        match value {
+            Expr::Attrs { rec: false, bindings } => flatten(bindings, &path, out),
+            _ => out.push((path, value)),
+        }
+    }
+}
+
+/// The function and arguments of `f a b c`
+fn apply_chain(expr: &Expr) -> (&Expr, Vec<&Expr>) {
+    let mut args = Vec::new();
+    let mut head = expr;
+    while let Expr::Apply(func, arg) = head {
+        args.push(arg.as_ref());
+        head = func;
+    }
+    args.reverse();
+    (head, args)
+}
+
+/// Root identifier and attribute path of `a.b.${c}.d`
+fn select_path(expr: &Expr) -> Option<(&str, Vec<&AttrKey>)> {
+    match expr {
+        Expr::Ident(name) => Some((name, Vec::new())),
+        Expr::Select { expr, path, default: None } => {
+            let (root, mut keys) = select_path(expr)?;
+            keys.extend(path);
+            Some((root, keys))
+        }
+        _ => None,
+    }
+}
+
+/// Last attribute name of a function such as `pkgs.mkShell`
+fn function_name(expr: &Expr) -> Option<&str> {
+    let (root, keys) = select_path(expr)?;
+    match keys.last() {
+        Some(key) => key.name(),
+        None => Some(root),
+    }
+}
+
+/// Short rendering of an expression for messages
+fn describe(expr: &Expr) -> String {
+    if let Some((root, keys)) = select_path(expr) {
+        let mut out = root.to_string();
+        for key in keys {
+            out.push('.');
+            out.push_str(key.name().unwrap_or("${...}"));
+        }
+        return out;
+    }
+    match expr {
+        Expr::Apply(..) => {
+            let (head, _) = apply_chain(expr);
+            format!("{} ...", describe(head))
+        }
+        Expr::Str(_) => "string".to_string(),
+        Expr::List(_) => "[ ... ]".to_string(),
+        Expr::If(..) => "if ... then ... else ...".to_string(),
+        _ => "expression".to_string(),
+    }
+}
+
+fn is_system(name: &str) -> bool {
+    name.ends_with("-linux") || name.ends_with("-darwin")
+}
+
+/// `devShells.<system>.<name>` and `devShell.<system>` as a shell name
+fn shell_name(path: &[String]) -> Option<String> {
+    let Some(start) = path.iter().rposition(|key| key == "devShells" || key == "devShell") else {
+        return path.is_empty().then(|| "default".to_string());
+    };
+    let rest: Vec<&str> = path[start + 1..].iter().map(String::as_str).filter(|key| !is_system(key)).collect();
+    Some(if rest.is_empty() { "default".to_string() } else { rest.join(".") })
+}
+
+/// Collect every `mkShell { ... }` reachable from `expr`
+fn find_shells<'a>(expr: &'a Expr, path: &mut Vec<String>, scope: &mut Vec<Frame<'a>>, shells: &mut Vec<Shell<'a>>, depth: usize) {
+    if depth > MAX_DEPTH {
+        return;
+    }
+    let frames = scope.len();
+    match expr {
+        Expr::Apply(func, arg) => {
+            if matches!(function_name(func), Some("mkShell" | "mkShellNoCC")) {
+                if let (Expr::Attrs { bindings, .. }, Some(name)) = (arg.as_ref(), shell_name(path)) {
+                    if !shells.iter().any(|shell| shell.name == name) {
+                        shells.push(Shell { name, args: bindings, scope: scope.clone() });
+                    }
+                    return;
+                }
+            }
+            find_shells(func, path, scope, shells, depth);
+            find_shells(arg, path, scope, shells, depth);
+        }
+        Expr::Attrs { rec, bindings } => {
+            if *rec {
+                scope.push(Frame::Let(bindings));
+            }
+            for binding in bindings {
+                if let Binding::Attr(keys, value) = binding {
+                    let pushed = keys.iter().filter_map(AttrKey::name).map(|key| path.push(key.to_string())).count();
+                    find_shells(value, path, scope, shells, depth);
+                    path.truncate(path.len() - pushed);
+                }
+            }
+        }
+        Expr::Let(bindings, body) => {
+            scope.push(Frame::Let(bindings));
+            find_shells(body, path, scope, shells, depth);
+        }
+        Expr::With(_, body) => {
+            scope.push(Frame::With);
+            find_shells(body, path, scope, shells, depth);
+        }
+        Expr::Lambda(body) | Expr::Assert(_, body) => find_shells(body, path, scope, shells, depth),
+        Expr::If(_, then, otherwise) => {
+            find_shells(then, path, scope, shells, depth);
+            find_shells(otherwise, path, scope, shells, depth);
+        }
+        Expr::BinOp("//", lhs, rhs) => {
+            find_shells(lhs, path, scope, shells, depth);
+            find_shells(rhs, path, scope, shells, depth);
+        }
+        // `devShells.default = shell;` with `shell` bound in a let
+        Expr::Ident(name) if shell_name(path).is_some() => {
+            if let Some(value) = lookup(scope, name) {
+                find_shells(value, path, scope, shells, depth + 1);
+            }
+        }
+        _ => {}
+    }
+    scope.truncate(frames);
+}
+
+/// Reads one shell's arguments into an [`ImportReport`]
+struct Reader<'a> {
+    source: String,
+    inputs: &'a BTreeMap<String, Input>,
+    report: ImportReport,
+}
+
+impl<'a> Reader<'a> {
+    fn skip(&mut self, entry: impl Into<String>, reason: impl Into<String>) {
+        self.report.unmapped.push(Unmapped {
+            source: self.source.clone(),
+            entry: entry.into(),
+            reason: reason.into(),
+        });
+    }
+
+    fn add_package(&mut self, spec: String) {
+        if !self.report.template.packages.contains(&spec) {
+            self.report.template.packages.push(spec);
+        }
+    }
+
+    /// A nixpkgs attribute; versioned nodejs becomes the node toolchain,
+    /// which the flake generator renders back as the same attribute
+    fn add_attr(&mut self, attr: &str) {
+        match package_for_attr(attr) {
+            (name, Some(version)) if name == "nodejs" => {
+                self.report.template.toolchains.insert("node".to_string(), version);
+            }
+            (name, Some(version)) => self.add_package(format!("{}@{}", name, version)),
+            (name, None) => self.add_package(name),
+        }
+    }
+
+    fn read_shell(&mut self, shell: &Shell<'a>) {
+        let mut entries = Vec::new();
+        flatten(shell.args, &[], &mut entries);
+        for (path, value) in entries {
+            let key = path.join(".");
+            match path[0].as_str() {
+                attr if PACKAGE_ATTRS.contains(&attr) && path.len() == 1 => {
+                    let mut scope = shell.scope.clone();
+                    self.packages(value, &mut scope, 0);
+                }
+                "shellHook" => self.shell_hook(value),
+                "env" if path.len() == 2 => self.env(&path[1], value),
+                "inputsFrom" => self.skip(key, "build inputs of other derivations aren't imported"),
+                attr if SHELL_ATTRS.contains(&attr) => {}
+                _ if path.len() == 1 => self.env(&key, value),
+                _ => self.skip(key, "not an environment variable"),
+            }
+        }
+        for binding in shell.args {
+            if let Binding::Inherit(_, names) = binding {
+                self.skip(format!("inherit {}", names.join(" ")), "inherited values aren't followed");
+            }
+        }
+    }
+
+    fn env(&mut self, key: &str, value: &Expr) {
+        if key.starts_with("SFC_") {
+            return;
+        }
+        match value.as_str() {
+            Some(text) => {
+                self.report.template.environment.insert(key.to_string(), text);
+            }
+            None if matches!(value, Expr::Str(_)) => self.skip(key, "value interpolates Nix expressions"),
+            None => self.skip(key, "not a string"),
+        }
+    }
+
+    /// `export NAME=value` lines; anything depending on the shell's own
+    /// environment stays out
+    fn shell_hook(&mut self, value: &Expr) {
+        let Expr::Str(parts) = value else {
+            self.skip("shellHook", "not a string");
+            return;
+        };
+        // Interpolations become NUL so lines using them can be recognised
+        let text: String = parts.iter().map(|part| match part {
+            StrPart::Lit(text) => text.as_str(),
+            StrPart::Interp(_) => "\0",
+        }).collect();
+
+        let mut other = 0;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') || line.starts_with("echo ") {
+                continue;
+            }
+            let Some((key, raw)) = line.strip_prefix("export ").and_then(|rest| rest.trim().split_once('=')) else {
+                other += 1;
+                continue;
+            };
+            if key.starts_with("SFC_") {
+                continue;
+            }
+            let unquoted = raw
+                .strip_prefix('"').and_then(|v| v.strip_suffix('"'))
+                .or_else(|| raw.strip_prefix('\'').and_then(|v| v.strip_suffix('\'')))
+                .unwrap_or(raw);
+            if key == "PATH" || unquoted.contains(['$', '`', '\0']) {
+                self.skip(format!("export {}", key), "depends on the shell environment");
+            } else {
+                self.report.template.environment.insert(key.to_string(), unquoted.to_string());
+            }
+        }
+        if other > 0 {
+            self.skip("shellHook", format!("{} other line(s) not imported", other));
+        }
+    }
+
+    /// A package list: `[ ... ]`, `a ++ b`, `with pkgs; [ ... ]` or a let-bound name
+    fn packages(&mut self, expr: &'a Expr, scope: &mut Vec<Frame<'a>>, depth: usize) {
+        if depth > MAX_DEPTH {
+            return;
+        }
+        let frames = scope.len();
+        match expr {
+            Expr::List(items) => {
+                for item in items {
+                    self.package(item, scope, depth);
+                }
+            }
+            Expr::BinOp("++", lhs, rhs) => {
+                self.packages(lhs, scope, depth);
+                self.packages(rhs, scope, depth);
+            }
+            Expr::With(_, body) => {
+                scope.push(Frame::With);
+                self.packages(body, scope, depth);
+            }
+            Expr::Let(bindings, body) => {
+                scope.push(Frame::Let(bindings));
+                self.packages(body, scope, depth);
+            }
+            Expr::Ident(name) => match lookup(scope, name) {
+                Some(value) => self.packages(value, scope, depth + 1),
+                None => self.skip(name.as_str(), "unknown package list"),
+            },
+            Expr::Apply(..) => {
+                let (head, _) = apply_chain(expr);
+                match function_name(head) {
+                    Some("optional" | "optionals") => self.skip(describe(expr), "conditional packages aren't imported"),
+                    _ => self.skip(describe(expr), "computed package list"),
+                }
+            }
+            other => self.skip(describe(other), "not a package list"),
+        }
+        scope.truncate(frames);
+    }
+
+    /// One element of a package list
+    fn package(&mut self, expr: &'a Expr, scope: &mut Vec<Frame<'a>>, depth: usize) {
+        match expr {
+            Expr::Ident(name) => {
+                if let Some(value) = lookup(scope, name) {
+                    if depth < MAX_DEPTH {
+                        match value {
+                            Expr::List(_) | Expr::BinOp("++", ..) => self.packages(value, scope, depth + 1),
+                            _ => self.package(value, scope, depth + 1),
+                        }
+                    }
+                } else if scope.iter().any(|frame| matches!(frame, Frame::With)) {
+                    self.add_attr(name);
+                } else {
+                    self.skip(name.as_str(), "not bound to a package");
+                }
+            }
+            Expr::Select { .. } => self.selected_package(expr, scope),
+            Expr::Apply(..) => self.applied_package(expr, scope),
+            other => self.skip(describe(other), "not a package"),
+        }
+    }
+
+    /// `pkgs.ripgrep`, `nixpkgs.legacyPackages.${system}.git` or
+    /// `inputs.tool.packages.${system}.default`
+    fn selected_package(&mut self, expr: &Expr, scope: &[Frame<'a>]) {
+        let Some((root, keys)) = select_path(expr) else {
+            self.skip(describe(expr), "not a package");
+            return;
+        };
+        let input = match root {
+            "inputs" => keys.first().and_then(|key| key.name()),
+            name if name != "nixpkgs" && self.inputs.contains_key(name) => Some(name),
+            _ => None,
+        };
+        if let Some(input) = input {
+            self.input_package(input, &describe(expr));
+            return;
+        }
+
+        // The attribute is whatever follows the package set (`${system}`,
+        // `legacyPackages.x86_64-linux`, `pkgs`); under `with pkgs;` an
+        // unbound root such as `nodePackages` is part of it
+        let marker = keys.iter().rposition(|key| key.name().is_none_or(|name| is_system(name) || name == "legacyPackages"));
+        let in_with = scope.iter().any(|frame| matches!(frame, Frame::With));
+        let root_is_attr = marker.is_none() && in_with && !PACKAGE_SETS.contains(&root) && lookup(scope, root).is_none();
+        let start = marker.map_or(0, |index| index + 1);
+        let attr: Option<Vec<&str>> = root_is_attr.then_some(Some(root)).into_iter()
+            .chain(keys[start..].iter().map(|key| key.name()))
+            .collect();
+        match attr {
+            Some(attr) if !attr.is_empty() => self.add_attr(&attr.join(".")),
+            _ => self.skip(describe(expr), "not a package"),
+        }
+    }
+
+    /// The default package of a flake input, as a GitHub package
+    fn input_package(&mut self, input: &str, entry: &str) {
+        let Some(url) = self.inputs.get(input).map(|input| input.url.as_str()) else {
+            self.skip(entry, format!("no input named {}", input));
+            return;
+        };
+        match FlakeRef::parse(url) {
+            Ok(FlakeRef::GitHub { owner, repo, reference }) => {
+                let spec = match reference {
+                    Some(reference) => format!("github:{}/{}@{}", owner, repo, reference),
+                    None => format!("github:{}/{}", owner, repo),
+                };
+                self.add_package(spec);
+            }
+            _ => self.skip(entry, format!("input {} ({}) isn't a GitHub flake", input, url)),
+        }
+    }
+
+    /// `python3.withPackages (...)` keeps the interpreter, and
+    /// `runCommand` over a non-flake input becomes a URL package again
+    fn applied_package(&mut self, expr: &Expr, scope: &[Frame<'a>]) {
+        let (head, args) = apply_chain(expr);
+        match function_name(head) {
+            Some("withPackages") => {
+                if let Expr::Select { expr: base, path, default: None } = head {
+                    let base = match &path[..path.len() - 1] {
+                        [] => base.as_ref().clone(),
+                        keys => Expr::Select { expr: base.clone(), path: keys.to_vec(), default: None },
+                    };
+                    match &base {
+                        Expr::Ident(name) => self.add_attr(name),
+                        _ => self.selected_package(&base, scope),
+                    }
+                }
+                self.skip(describe(expr), "extra modules aren't imported");
+            }
+            Some("runCommand" | "runCommandLocal" | "runCommandNoCC") => {
+                match args.iter().find_map(|arg| self.file_input(arg)) {
+                    Some(url) => self.add_package(url),
+                    None => self.skip(describe(expr), "custom derivation"),
+                }
+            }
+            _ => self.skip(describe(expr), "function call"),
+        }
+    }
+
+    /// URL of a `flake = false` input interpolated into a build script
+    fn file_input(&self, arg: &Expr) -> Option<String> {
+        let Expr::Str(parts) = arg else { return None };
+        parts.iter().find_map(|part| {
+            let StrPart::Interp(expr) = part else { return None };
+            let (root, keys) = select_path(expr)?;
+            let name = match root {
+                "inputs" => keys.first()?.name()?,
+                name => name,
+            };
+            self.inputs.get(name).filter(|input| !input.flake).map(|input| input.url.clone())
+        })
+    }
+}
+
+impl FlakeImport {
+    /// Read a dev shell from the text of a flake (or `shell.nix`). `shell`
+    /// names an entry of `devShells`; without it `default` is used, or the
+    /// only shell there is.
+    pub fn parse(source: &str, origin: &Path, shell: Option<&str>) -> Result<Self> {
+        let file_name = file_label(origin);
+        let root = parser::parse(source).with_context(|| format!("parsing {}", origin.display()))?;
+
+        let mut top = Vec::new();
+        if let Expr::Attrs { bindings, .. } = &root {
+            flatten(bindings, &[], &mut top);
+        }
+        let mut inputs = BTreeMap::new();
+        for (path, value) in &top {
+            if let [first, name, field] = &path[..] {
+                if first != "inputs" {
+                    continue;
+                }
+                let input = inputs.entry(name.clone()).or_insert(Input { url: String::new(), flake: true });
+                match (field.as_str(), value) {
+                    ("url", value) => input.url = value.as_str().unwrap_or_default(),
+                    ("flake", Expr::Ident(flag)) => input.flake = flag != "false",
+                    _ => {}
+                }
+            }
+        }
+
+        let outputs = top.iter().find(|(path, _)| path == &["outputs"]).map_or(&root, |(_, value)| *value);
+        let mut shells = Vec::new();
+        find_shells(outputs, &mut Vec::new(), &mut Vec::new(), &mut shells, 0);
+        let names: Vec<String> = shells.iter().map(|s| s.name.clone()).collect();
+        let chosen = match shell {
+            Some(wanted) => shells.iter().position(|s| s.name == wanted).ok_or_else(|| match names.is_empty() {
+                true => anyhow!("{} defines no dev shells", file_name),
+                false => anyhow!("no dev shell '{}' in {} (available: {})", wanted, file_name, names.join(", ")),
+            })?,
+            None => match shells.iter().position(|s| s.name == "default") {
+                Some(index) => index,
+                None if shells.len() == 1 => 0,
+                None if shells.is_empty() => bail!("no mkShell dev shell found in {}", file_name),
+                None => bail!("{} defines several dev shells ({}); choose one with --shell", file_name, names.join(", ")),
+            },
+        };
+
+        let mut reader = Reader { source: file_name, inputs: &inputs, report: ImportReport::default() };
+        reader.read_shell(&shells[chosen]);
+        let mut report = reader.report;
+        report.sources.push(origin.to_path_buf());
+        if let Some((_, description)) = top.iter().find(|(path, _)| path == &["description"]) {
+            report.template.description = description.as_str().unwrap_or_default();
+        }
+
+        let nixpkgs = match inputs.get("nixpkgs").map(|input| input.url.as_str()) {
+            None | Some(DEFAULT_NIXPKGS) => None,
+            Some(url) if FlakeRef::parse(url).is_ok() => Some(url.to_string()),
+            Some(url) => {
+                report.unmapped.push(Unmapped {
+                    source: file_label(origin),
+                    entry: format!("inputs.nixpkgs ({})", url),
+                    reason: "unsupported flake reference, using the default nixpkgs".to_string(),
+                });
+                None
+            }
+        };
+
+        let shell = shells[chosen].name.clone();
+        let other_shells = names.into_iter().filter(|name| *name != shell).collect();
+        Ok(Self { report, nixpkgs, shell, other_shells })
+    }
+
+    /// Read `flake.nix` from a directory, or the given Nix file
+    pub fn from_path(path: &Path, shell: Option<&str>) -> Result<Self> {
+        let file = if path.is_dir() { path.join("flake.nix") } else { path.to_path_buf() };
+        let source = fs::read_to_string(&file).with_context(|| format!("reading {}", file.display()))?;
+        Self::parse(&source, &file, shell)
+    }
+}
+
+/// How the source file is named in messages
+fn file_label(origin: &Path) -> String {
+    origin.file_name().map_or_else(|| origin.display().to_string(), |n| n.to_string_lossy().into_owned())
+}
+
+/// Clone URL and branch for a git flake reference, if `source` is one
+fn git_source(source: &str) -> Option<(String, Option<String>)> {
+    if let Some(rest) = source.strip_prefix("github:") {
+        let mut parts = rest.splitn(3, '/');
+        let (owner, repo) = (parts.next()?, parts.next()?);
+        return Some((format!("https://github.com/{}/{}.git", owner, repo), parts.next().map(String::from)));
+    }
+    let url = source.strip_prefix("git+").unwrap_or(source);
+    let (url, reference) = match url.split_once("?ref=") {
+        Some((url, reference)) => (url, Some(reference.to_string())),
+        None => (url, None),
+    };
+    let is_git = source.starts_with("git+")
+        || url.ends_with(".git")
+        || url.starts_with("git@")
+        || url.starts_with("ssh://");
+    is_git.then(|| (url.to_string(), reference))
+}
+
+/// Local path of the flake named by `source`: an existing file or
+/// directory, or a git repository (`github:owner/repo[/ref]`, `*.git`,
+/// `git+<url>[?ref=<ref>]`) cloned into the workspace cache
+pub fn checkout_source(workspace: &Path, source: &str) -> Result<PathBuf> {
+    let path = Path::new(source);
+    if path.exists() {
+        return Ok(path.to_path_buf());
+    }
+    let Some((url, reference)) = git_source(source) else {
+        bail!("'{}' is neither a local path nor a git repository", source);
+    };
+
+    let key = format!("{:x}", Sha256::digest(source.as_bytes()));
+    let checkout = workspace.join(".sfc").join("cache").join("imports").join(&key[..16]);
+    if checkout.exists() {
+        fs::remove_dir_all(&checkout).with_context(|| format!("removing {}", checkout.display()))?;
+    }
+    fs::create_dir_all(checkout.parent().unwrap_or(workspace))?;
+
+    let mut clone = Command::new("git");
+    clone.args(["clone", "--quiet", "--depth", "1"]);
+    if let Some(reference) = &reference {
+        clone.args(["--branch", reference]);
+    }
+    let output = clone.arg(&url).arg(&checkout).output().context("running git clone")?;
+    if !output.status.success() {
+        bail!("git clone {} failed: {}", url, String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(checkout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLAKE: &str = r#"
+    {
+      description = "Team dev environment";
+
+      inputs = {
+        nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+        flake-utils.url = "github:numtide/flake-utils";
+        my-tool.url = "github:owner/my-tool/v1.2.0";
+        cli = { url = "https://example.com/dl/cli"; flake = false; };
+      };
+
+      outputs = { self, nixpkgs, flake-utils, ... }@inputs:
+        flake-utils.lib.eachDefaultSystem (system:
+          let
+            pkgs = nixpkgs.legacyPackages.${system};
+            common = with pkgs; [ git jq ];
+          in {
+            devShells.default = pkgs.mkShell {
+              packages = common ++ (with pkgs; [
+                nodejs_20
+                python312
+                nodePackages.pnpm
+                (python3.withPackages (ps: [ ps.requests ]))
+              ]) ++ [
+                inputs.my-tool.packages.${system}.default
+                (pkgs.runCommand "cli" { } "install -Dm755 ${inputs.cli} $out/bin/cli")
+              ] ++ pkgs.lib.optionals pkgs.stdenv.isLinux [ pkgs.inotify-tools ];
+              buildInputs = [ pkgs.openssl ];
+              RUST_LOG = "debug";
+              env.DATABASE_URL = "postgres://localhost/dev";
+              shellHook = ''
+                export NODE_ENV=development
+                export PATH="$PWD/bin:$PATH"
+                export SFC_CONTAINER="web"
+                echo "welcome"
+                source .env
+              '';
+            };
+            devShells.docs = pkgs.mkShell { packages = [ pkgs.mdbook ]; };
+          });
+    }
+    "#;
+
+    #[test]
+    fn test_import_default_shell() {
+        let import = FlakeImport::parse(FLAKE, Path::new("flake.nix"), None).unwrap();
+        let template = &import.report.template;
+        assert_eq!(import.shell, "default");
+        assert_eq!(import.other_shells, ["docs"]);
+        assert_eq!(import.nixpkgs.as_deref(), Some("github:NixOS/nixpkgs/nixos-24.05"));
+        assert_eq!(template.description, "Team dev environment");
+        assert_eq!(template.packages, [
+            "git",
+            "jq",
+            "python3@3.12",
+            "nodePackages.pnpm",
+            "python3",
+            "github:owner/my-tool@v1.2.0",
+            "https://example.com/dl/cli",
+            "openssl",
+        ]);
+        assert_eq!(template.toolchains.get("node").map(String::as_str), Some("20"));
+        assert_eq!(template.environment.get("RUST_LOG").map(String::as_str), Some("debug"));
+        assert_eq!(template.environment.get("DATABASE_URL").map(String::as_str), Some("postgres://localhost/dev"));
+        assert_eq!(template.environment.get("NODE_ENV").map(String::as_str), Some("development"));
+        assert!(!template.environment.contains_key("PATH"));
+        assert!(!template.environment.contains_key("SFC_CONTAINER"));
+
+        let skipped: Vec<&str> = import.report.unmapped.iter().map(|u| u.entry.as_str()).collect();
+        assert!(skipped.contains(&"export PATH"), "{:?}", skipped);
+        assert!(skipped.contains(&"shellHook"), "{:?}", skipped);
+        assert!(skipped.iter().any(|s| s.starts_with("python3.withPackages")), "{:?}", skipped);
+        assert!(skipped.iter().any(|s| s.starts_with("pkgs.lib.optionals")), "{:?}", skipped);
+    }
+
+    #[test]
+    fn test_import_named_shell_and_errors() {
+        let import = FlakeImport::parse(FLAKE, Path::new("flake.nix"), Some("docs")).unwrap();
+        assert_eq!(import.report.template.packages, ["mdbook"]);
+
+        let err = FlakeImport::parse(FLAKE, Path::new("flake.nix"), Some("ci")).unwrap_err();
+        assert!(err.to_string().contains("available: default, docs"), "{}", err);
+    }
+
+    #[test]
+    fn test_import_round_trips_generated_flake() {
+        let mut config = crate::container::ContainerConfig::new("web".to_string());
+        config.nixpkgs = Some("github:NixOS/nixpkgs/nixos-24.05".to_string());
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        config.environment.insert("NODE_ENV".to_string(), "development".to_string());
+        for spec in ["ripgrep", "python@3.12", "github:owner/my-tool@v1.2.0", "https://example.com/dl/cli"] {
+            config.add_package(crate::container::PackageSpec::parse(spec)).unwrap();
+        }
+        let nix = super::super::FlakeConfig::from_container(&config).to_nix();
+
+        let import = FlakeImport::parse(&nix, Path::new("flake.nix"), None).unwrap();
+        let template = &import.report.template;
+        assert_eq!(import.nixpkgs, config.nixpkgs);
+        assert_eq!(template.toolchains.get("node").map(String::as_str), Some("20"));
+        assert_eq!(template.environment.get("NODE_ENV").map(String::as_str), Some("development"));
+        for spec in ["ripgrep", "python3@3.12", "github:owner/my-tool@v1.2.0", "https://example.com/dl/cli"] {
+            assert!(template.packages.iter().any(|p| p == spec), "{} missing from {:?}", spec, template.packages);
+        }
+        assert!(import.report.unmapped.is_empty(), "{:?}", import.report.unmapped);
+    }
+
+    #[test]
+    fn test_git_source() {
+        assert_eq!(git_source("github:owner/repo"), Some(("https://github.com/owner/repo.git".to_string(), None)));
+        assert_eq!(git_source("github:owner/repo/dev"), Some(("https://github.com/owner/repo.git".to_string(), Some("dev".to_string()))));
+        assert_eq!(git_source("git+https://host/team/env?ref=main"), Some(("https://host/team/env".to_string(), Some("main".to_string()))));
+        assert_eq!(git_source("git@host:team/env.git"), Some(("git@host:team/env.git".to_string(), None)));
+        assert_eq!(git_source("./missing"), None);
+    }
+}
@@ -0,0 +1,437 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use super::nar::nar_hash;
+
+/// nixpkgs input used when a container doesn't pin one
+pub const DEFAULT_NIXPKGS: &str = "github:NixOS/nixpkgs/nixos-unstable";
+
+const ARCHIVE_EXTENSIONS: &[&str] = &[".tar.gz", ".tgz", ".tar.xz", ".txz", ".tar.bz2", ".tbz2", ".tar.zst", ".tar", ".zip"];
+
+/// The flake reference forms sfc writes into `flake.nix`
+#[derive(Debug, Clone, PartialEq)]
+pub enum FlakeRef {
+    /// `github:owner/repo[/ref-or-rev]`
+    GitHub { owner: String, repo: String, reference: Option<String> },
+    /// `https://.../source.tar.gz`, unpacked
+    Tarball { url: String },
+    /// `file+https://...`, a single file
+    File { url: String },
+}
+
+fn is_rev(s: &str) -> bool {
+    s.len() == 40 && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+impl FlakeRef {
+    pub fn parse(reference: &str) -> Result<Self> {
+        if let Some(rest) = reference.strip_prefix("github:") {
+            let mut parts = rest.splitn(3, '/');
+            let (Some(owner), Some(repo)) = (parts.next(), parts.next()) else {
+                return Err(anyhow!("invalid GitHub flake reference '{}': expected github:owner/repo[/ref]", reference));
+            };
+            if owner.is_empty() || repo.is_empty() {
+                return Err(anyhow!("invalid GitHub flake reference '{}': expected github:owner/repo[/ref]", reference));
+            }
+            return Ok(FlakeRef::GitHub {
+                owner: owner.to_string(),
+                repo: repo.to_string(),
+                reference: parts.next().filter(|r| !r.is_empty()).map(str::to_string),
+            });
+        }
+        if let Some(url) = reference.strip_prefix("tarball+") {
+            return Ok(FlakeRef::Tarball { url: url.to_string() });
+        }
+        if let Some(url) = reference.strip_prefix("file+") {
+            return Ok(FlakeRef::File { url: url.to_string() });
+        }
+        if reference.starts_with("https://") || reference.starts_with("http://") {
+            let path = reference.split(['?', '#']).next().unwrap_or(reference);
+            return Ok(if ARCHIVE_EXTENSIONS.iter().any(|ext| path.ends_with(ext)) {
+                FlakeRef::Tarball { url: reference.to_string() }
+            } else {
+                FlakeRef::File { url: reference.to_string() }
+            });
+        }
+        Err(anyhow!(
+            "unsupported flake reference '{}': use github:owner/repo[/ref] or an http(s) URL",
+            reference
+        ))
+    }
+
+    /// The `original` attribute of this input's lock node
+    pub fn original(&self) -> Map<String, Value> {
+        let value = match self {
+            FlakeRef::GitHub { owner, repo, reference } => {
+                let mut original = json!({ "owner": owner, "repo": repo, "type": "github" });
+                match reference {
+                    Some(r) if is_rev(r) => original["rev"] = json!(r),
+                    Some(r) => original["ref"] = json!(r),
+                    None => {}
+                }
+                original
+            }
+            FlakeRef::Tarball { url } => json!({ "type": "tarball", "url": url }),
+            FlakeRef::File { url } => json!({ "type": "file", "url": url }),
+        };
+        match value {
+            Value::Object(map) => map,
+            _ => unreachable!("lock attributes are objects"),
+        }
+    }
+}
+
+impl std::fmt::Display for FlakeRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FlakeRef::GitHub { owner, repo, reference: Some(r) } => write!(f, "github:{}/{}/{}", owner, repo, r),
+            FlakeRef::GitHub { owner, repo, reference: None } => write!(f, "github:{}/{}", owner, repo),
+            // Bare URLs only read back as tarballs with an archive extension
+            FlakeRef::Tarball { url } if FlakeRef::parse(url).ok() == Some(self.clone()) => write!(f, "{}", url),
+            FlakeRef::Tarball { url } => write!(f, "tarball+{}", url),
+            FlakeRef::File { url } => write!(f, "file+{}", url),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LockNode {
+    /// Some(false) for inputs that aren't flakes
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub flake: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inputs: Option<BTreeMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub locked: Option<Map<String, Value>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original: Option<Map<String, Value>>,
+}
+
+impl LockNode {
+    /// Short description of what is pinned: the revision, else the NAR hash
+    pub fn pin(&self) -> String {
+        let locked = self.locked.as_ref();
+        let field = |key: &str| locked.and_then(|l| l.get(key)).and_then(Value::as_str);
+        match (field("rev"), field("narHash")) {
+            (Some(rev), _) => rev.chars().take(12).collect(),
+            (None, Some(hash)) => hash.to_string(),
+            (None, None) => "unlocked".to_string(),
+        }
+    }
+}
+
+/// A change made to a lock file
+#[derive(Debug, Clone, PartialEq)]
+pub struct LockChange {
+    pub input: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// `flake.lock`, version 7
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FlakeLock {
+    pub nodes: BTreeMap<String, LockNode>,
+    pub root: String,
+    pub version: u32,
+}
+
+impl Default for FlakeLock {
+    fn default() -> Self {
+        let root = LockNode { inputs: Some(BTreeMap::new()), ..LockNode::default() };
+        Self {
+            nodes: BTreeMap::from([("root".to_string(), root)]),
+            root: "root".to_string(),
+            version: 7,
+        }
+    }
+}
+
+impl FlakeLock {
+    /// Read `path`, or start empty when it is missing or isn't a v7 lock
+    pub fn load_or_default(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        match serde_json::from_str::<Self>(&content) {
+            Ok(lock) if lock.version == 7 && lock.nodes.contains_key(&lock.root) => Ok(lock),
+            _ => Ok(Self::default()),
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self)?;
+        fs::write(path, content + "\n").with_context(|| format!("writing {}", path.display()))?;
+        Ok(())
+    }
+
+    /// Lock node for a direct input of the flake
+    pub fn input(&self, name: &str) -> Option<&LockNode> {
+        let key = self.nodes.get(&self.root)?.inputs.as_ref()?.get(name)?;
+        self.nodes.get(key)
+    }
+
+    /// Keep entries whose input still exists with the same reference and
+    /// drop the rest. Nothing is fetched.
+    pub fn retain(&mut self, inputs: &BTreeMap<String, FlakeRef>) -> Vec<LockChange> {
+        let mut changes = Vec::new();
+        let names: Vec<String> = self.root_inputs().keys().cloned().collect();
+        for name in names {
+            let current = inputs.get(&name).map(FlakeRef::original);
+            let node = self.input(&name).cloned().unwrap_or_default();
+            if current.is_none() || node.original != current {
+                changes.push(LockChange { input: name.clone(), old: Some(node.pin()), new: None });
+                self.unlink(&name);
+            }
+        }
+        changes
+    }
+
+    /// Lock every input that isn't locked yet, or all of them with
+    /// `update_all`. `fetch` returns the `locked` attributes for a reference.
+    pub fn update<F>(&mut self, inputs: &BTreeMap<String, FlakeRef>, update_all: bool, mut fetch: F) -> Result<Vec<LockChange>>
+    where
+        F: FnMut(&FlakeRef) -> Result<Map<String, Value>>,
+    {
+        let mut changes = self.retain(inputs);
+        for (name, reference) in inputs {
+            let previous = self.input(name).cloned();
+            if previous.is_some() && !update_all {
+                continue;
+            }
+            let locked = fetch(reference).with_context(|| format!("locking input '{}' ({})", name, reference))?;
+            let node = LockNode {
+                flake: None,
+                inputs: None,
+                locked: Some(locked),
+                original: Some(reference.original()),
+            };
+            if previous.as_ref().map(|p| &p.locked) == Some(&node.locked) {
+                continue;
+            }
+            // An entry dropped by `retain` above and locked again is an update
+            let dropped = changes
+                .iter()
+                .position(|c| c.input == *name)
+                .and_then(|i| changes.remove(i).old);
+            let old = previous.map(|p| p.pin()).or(dropped);
+            changes.push(LockChange { input: name.clone(), old, new: Some(node.pin()) });
+            self.nodes.insert(name.clone(), node);
+            self.root_inputs().insert(name.clone(), name.clone());
+        }
+        changes.sort_by(|a, b| a.input.cmp(&b.input));
+        Ok(changes)
+    }
+
+    fn root_inputs(&mut self) -> &mut BTreeMap<String, String> {
+        let root = self.root.clone();
+        self.nodes.entry(root).or_default().inputs.get_or_insert_with(BTreeMap::new)
+    }
+
+    fn unlink(&mut self, name: &str) {
+        if let Some(key) = self.root_inputs().remove(name) {
+            self.nodes.remove(&key);
+        }
+    }
+}
+
+fn which(command: &str) -> bool {
+    Command::new("which")
+        .arg(command)
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Resolve `reference` to `locked` attributes. Uses `nix flake prefetch` when
+/// nix is installed, otherwise downloads and hashes the source itself.
+pub fn fetch_locked(reference: &FlakeRef) -> Result<Map<String, Value>> {
+    if which("nix") {
+        nix_prefetch(reference)
+    } else {
+        native_prefetch(reference)
+    }
+}
+
+fn nix_prefetch(reference: &FlakeRef) -> Result<Map<String, Value>> {
+    let output = Command::new("nix")
+        .args(["--extra-experimental-features", "nix-command flakes", "flake", "prefetch", "--json"])
+        .arg(reference.to_string())
+        .output()
+        .context("running nix flake prefetch")?;
+    if !output.status.success() {
+        return Err(anyhow!("nix flake prefetch failed: {}", String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    let result: Value = serde_json::from_slice(&output.stdout).context("parsing nix flake prefetch output")?;
+    match result.get("locked") {
+        Some(Value::Object(locked)) => Ok(locked.clone()),
+        _ => Err(anyhow!("nix flake prefetch did not report locked attributes (nix 2.19 or newer is required)")),
+    }
+}
+
+/// Scratch directory removed on drop
+struct Scratch(PathBuf);
+
+impl Scratch {
+    fn new() -> Result<Self> {
+        let suffix: u64 = rand::random();
+        let dir = std::env::temp_dir().join(format!("sfc-fetch-{:016x}", suffix));
+        fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        Ok(Self(dir))
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn run(cmd: &mut Command, what: &str) -> Result<String> {
+    let output = cmd.output().with_context(|| format!("running {}", what))?;
+    if !output.status.success() {
+        return Err(anyhow!("{} failed: {}", what, String::from_utf8_lossy(&output.stderr).trim()));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn download(url: &str, dest: &Path) -> Result<()> {
+    run(Command::new("curl").args(["-fsSL", "-o"]).arg(dest).arg(url), &format!("curl {}", url))?;
+    Ok(())
+}
+
+/// Unpack an archive the way Nix does: a single top-level directory is
+/// stripped, and lastModified is the newest mtime in the archive
+fn unpack(archive: &Path, dest: &Path) -> Result<(PathBuf, i64)> {
+    fs::create_dir_all(dest)?;
+    run(Command::new("tar").arg("-xf").arg(archive).arg("-C").arg(dest), "tar")?;
+    let entries: Vec<_> = fs::read_dir(dest)?.flatten().collect();
+    let root = match entries.as_slice() {
+        [only] if only.file_type()?.is_dir() => only.path(),
+        _ => dest.to_path_buf(),
+    };
+    Ok((root.clone(), newest_mtime(&root)?))
+}
+
+fn newest_mtime(path: &Path) -> Result<i64> {
+    use std::os::unix::fs::MetadataExt;
+    let meta = fs::symlink_metadata(path)?;
+    let mut newest = meta.mtime();
+    if meta.is_dir() {
+        for entry in fs::read_dir(path)?.flatten() {
+            newest = newest.max(newest_mtime(&entry.path())?);
+        }
+    }
+    Ok(newest)
+}
+
+fn native_prefetch(reference: &FlakeRef) -> Result<Map<String, Value>> {
+    let scratch = Scratch::new()?;
+    let archive = scratch.0.join("source");
+    let value = match reference {
+        FlakeRef::GitHub { owner, repo, reference } => {
+            let rev = match reference {
+                Some(r) if is_rev(r) => r.clone(),
+                other => {
+                    let wanted = other.as_deref().unwrap_or("HEAD");
+                    let url = format!("https://github.com/{}/{}", owner, repo);
+                    let refs = run(Command::new("git").args(["ls-remote", &url, wanted]), "git ls-remote")?;
+                    refs.split_whitespace()
+                        .next()
+                        .ok_or_else(|| anyhow!("'{}' not found in {}", wanted, url))?
+                        .to_string()
+                }
+            };
+            download(&format!("https://github.com/{}/{}/archive/{}.tar.gz", owner, repo, rev), &archive)?;
+            let (root, last_modified) = unpack(&archive, &scratch.0.join("unpacked"))?;
+            json!({
+                "lastModified": last_modified,
+                "narHash": nar_hash(&root)?,
+                "owner": owner,
+                "repo": repo,
+                "rev": rev,
+                "type": "github",
+            })
+        }
+        FlakeRef::Tarball { url } => {
+            download(url, &archive)?;
+            let (root, last_modified) = unpack(&archive, &scratch.0.join("unpacked"))?;
+            json!({ "lastModified": last_modified, "narHash": nar_hash(&root)?, "type": "tarball", "url": url })
+        }
+        FlakeRef::File { url } => {
+            download(url, &archive)?;
+            json!({ "narHash": nar_hash(&archive)?, "type": "file", "url": url })
+        }
+    };
+    match value {
+        Value::Object(map) => Ok(map),
+        _ => unreachable!("lock attributes are objects"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn github_locked(rev: &str) -> Map<String, Value> {
+        match json!({ "lastModified": 1700000000, "narHash": "sha256-AAAA", "owner": "NixOS", "repo": "nixpkgs", "rev": rev, "type": "github" }) {
+            Value::Object(map) => map,
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_original() {
+        let nixpkgs = FlakeRef::parse(DEFAULT_NIXPKGS).unwrap();
+        assert_eq!(
+            Value::Object(nixpkgs.original()),
+            json!({ "owner": "NixOS", "ref": "nixos-unstable", "repo": "nixpkgs", "type": "github" })
+        );
+        let pinned = FlakeRef::parse(&format!("github:NixOS/nixpkgs/{}", "a".repeat(40))).unwrap();
+        assert_eq!(pinned.original()["rev"], json!("a".repeat(40)));
+        assert!(matches!(FlakeRef::parse("https://x.org/src.tar.gz").unwrap(), FlakeRef::Tarball { .. }));
+        assert!(matches!(FlakeRef::parse("https://x.org/tool").unwrap(), FlakeRef::File { .. }));
+        for reference in ["file+https://x.org/a.tgz", "tarball+https://x.org/archive", "https://x.org/a.tgz"] {
+            assert_eq!(FlakeRef::parse(reference).unwrap().to_string(), reference);
+        }
+        assert!(FlakeRef::parse("git+ssh://host/repo").is_err());
+    }
+
+    #[test]
+    fn test_update_keeps_pins_unless_asked() {
+        let mut inputs = BTreeMap::new();
+        inputs.insert("nixpkgs".to_string(), FlakeRef::parse(DEFAULT_NIXPKGS).unwrap());
+
+        let mut lock = FlakeLock::default();
+        let changes = lock.update(&inputs, false, |_| Ok(github_locked(&"1".repeat(40)))).unwrap();
+        assert_eq!(changes, vec![LockChange { input: "nixpkgs".into(), old: None, new: Some("1".repeat(12)) }]);
+
+        // Already locked: the fetcher isn't consulted
+        let changes = lock.update(&inputs, false, |_| panic!("should not fetch")).unwrap();
+        assert!(changes.is_empty());
+
+        let changes = lock.update(&inputs, true, |_| Ok(github_locked(&"2".repeat(40)))).unwrap();
+        assert_eq!(changes[0].old.as_deref(), Some("111111111111"));
+        assert_eq!(lock.input("nixpkgs").unwrap().locked.as_ref().unwrap()["rev"], json!("2".repeat(40)));
+
+        // Serialized form is a v7 lock with a root node
+        let value = serde_json::to_value(&lock).unwrap();
+        assert_eq!(value["version"], 7);
+        assert_eq!(value["nodes"]["root"]["inputs"]["nixpkgs"], "nixpkgs");
+        assert_eq!(value["nodes"]["nixpkgs"]["original"]["ref"], "nixos-unstable");
+
+        // Changing the pin invalidates the entry without fetching
+        inputs.insert("nixpkgs".to_string(), FlakeRef::parse("github:NixOS/nixpkgs/nixos-24.05").unwrap());
+        let changes = lock.retain(&inputs);
+        assert_eq!(changes.len(), 1);
+        assert!(lock.input("nixpkgs").is_none());
+        assert_eq!(lock.nodes.len(), 1);
+    }
+}
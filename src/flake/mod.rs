@@ -0,0 +1,445 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::container::{ContainerConfig, PackageSource};
+use crate::system::shell::ShellKind;
+
+mod import;
+pub mod lock;
+mod nar;
+mod nixpkgs;
+pub mod parser;
+
+pub use import::{checkout_source, FlakeImport};
+pub use lock::{FlakeLock, FlakeRef, LockChange, DEFAULT_NIXPKGS};
+pub use nixpkgs::{nixpkgs_attr, package_for_attr};
+
+/// Systems every generated flake provides a dev shell for
+pub const DEFAULT_SYSTEMS: &[&str] = &["x86_64-linux", "aarch64-linux", "x86_64-darwin", "aarch64-darwin"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakeConfig {
+    pub description: String,
+    pub inputs: FlakeInputs,
+    pub outputs: FlakeOutputs,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakeInputs {
+    pub nixpkgs: FlakeInput,
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, FlakeInput>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakeInput {
+    pub url: String,
+    /// false for plain sources (URL downloads) that aren't flakes themselves
+    pub flake: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlakeOutputs {
+    pub packages: std::collections::HashMap<String, String>,
+    pub shell: ShellConfig,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ShellConfig {
+    /// Nix expressions, evaluated with `pkgs`, `system` and `inputs` in scope
+    pub packages: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    /// Emitted as comments: what couldn't be expressed exactly
+    pub notes: Vec<String>,
+    pub shell_hook: String,
+}
+
+const NIX_KEYWORDS: &[&str] = &["assert", "else", "if", "in", "inherit", "let", "or", "rec", "then", "with"];
+
+fn is_nix_ident(s: &str) -> bool {
+    let mut chars = s.chars();
+    let valid_start = chars.next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_');
+    valid_start
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '\'' | '-'))
+        && !NIX_KEYWORDS.contains(&s)
+}
+
+/// Contents of a double-quoted Nix string
+fn nix_escape(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace("${", "\\${")
+        .replace('\n', "\\n")
+}
+
+fn nix_string(s: &str) -> String {
+    format!("\"{}\"", nix_escape(s))
+}
+
+/// Attribute name, quoted when it isn't a plain identifier
+fn nix_attr(s: &str) -> String {
+    if is_nix_ident(s) { s.to_string() } else { nix_string(s) }
+}
+
+/// `pkgs.<attr>`, where a dotted name such as `nodePackages.pnpm` is a nested attribute
+fn pkgs_attr(attr: &str) -> String {
+    let path: Vec<String> = attr.split('.').map(nix_attr).collect();
+    format!("pkgs.{}", path.join("."))
+}
+
+/// Input names the generated flake already uses
+const RESERVED_INPUTS: &[&str] = &["nixpkgs", "self"];
+
+/// Input name for a package: identifier characters only. Packages that map
+/// to a reserved or already-taken name (`my-tool` and `my_tool`) get a suffix
+/// from a hash of their own name, so the result doesn't depend on order.
+fn input_name(package: &str, taken: &BTreeMap<String, FlakeInput>) -> String {
+    let name: String = package
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '_' { c } else { '_' })
+        .collect();
+    let name = if is_nix_ident(&name) { name } else { format!("_{}", name) };
+    let free = |candidate: &str| !RESERVED_INPUTS.contains(&candidate) && !taken.contains_key(candidate);
+    if free(&name) {
+        return name;
+    }
+    let suffixed = format!("{}_{}", name, &crate::core::compute_content_hash(package.as_bytes())[..8]);
+    (1..)
+        .map(|n| if n == 1 { suffixed.clone() } else { format!("{}_{}", suffixed, n) })
+        .find(|candidate| free(candidate))
+        .unwrap_or(suffixed)
+}
+
+fn push_unique(list: &mut Vec<String>, expr: String) {
+    if !list.contains(&expr) {
+        list.push(expr);
+    }
+}
+
+impl FlakeConfig {
+    pub fn from_container(container: &ContainerConfig) -> Self {
+        let mut inputs = FlakeInputs {
+            nixpkgs: FlakeInput {
+                url: container.nixpkgs.clone().unwrap_or_else(|| DEFAULT_NIXPKGS.to_string()),
+                flake: true,
+            },
+            extra: BTreeMap::new(),
+        };
+        let mut packages = Vec::new();
+        let mut notes = Vec::new();
+
+        // Toolchains come from nixpkgs too, and win over a package for the same tool
+        let mut toolchain_attrs = Vec::new();
+        for (tool, version) in &container.toolchains {
+            match tool.as_str() {
+                "node" => {
+                    let (attr, exact) = nixpkgs_attr("nodejs", Some(version));
+                    if !exact {
+                        notes.push(format!("node {}: no versioned nixpkgs attribute, using {}", version, attr));
+                    }
+                    toolchain_attrs.push("nodejs");
+                    push_unique(&mut packages, format!("pkgs.{}", nix_attr(&attr)));
+                }
+                "npm" => notes.push(format!("npm {}: nodejs ships its bundled npm", version)),
+                "rust" => {
+                    if version != "stable" {
+                        notes.push(format!("rust {}: nixpkgs has one rustc per revision; pin nixpkgs to choose it", version));
+                    }
+                    toolchain_attrs.extend(["rustc", "cargo"]);
+                    push_unique(&mut packages, "pkgs.rustc".to_string());
+                    push_unique(&mut packages, "pkgs.cargo".to_string());
+                }
+                other => notes.push(format!("toolchain {} {}: not available from nixpkgs, left out", other, version)),
+            }
+        }
+
+        for pkg in &container.packages {
+            match &pkg.source {
+                PackageSource::Nixpkgs => {
+                    let (base, _) = nixpkgs_attr(&pkg.name, None);
+                    if toolchain_attrs.contains(&base.as_str()) {
+                        notes.push(format!("{}: provided by the {} toolchain", pkg.name, base));
+                        continue;
+                    }
+                    let (attr, exact) = nixpkgs_attr(&pkg.name, pkg.version.as_deref());
+                    if let (Some(version), false) = (&pkg.version, exact) {
+                        notes.push(format!("{}@{}: no versioned nixpkgs attribute, using {}", pkg.name, version, attr));
+                    }
+                    push_unique(&mut packages, pkgs_attr(&attr));
+                }
+                PackageSource::GitHub { repo, rev } => {
+                    // The rev may be a branch or tag, so it goes in the reference
+                    // and the lock file records the commit
+                    let input = input_name(&pkg.name, &inputs.extra);
+                    inputs.extra.insert(input.clone(), FlakeInput {
+                        url: format!("github:{}/{}", repo, rev),
+                        flake: true,
+                    });
+                    push_unique(&mut packages, format!("inputs.{}.packages.${{system}}.default", input));
+                }
+                PackageSource::Url(url) => {
+                    let input = input_name(&pkg.name, &inputs.extra);
+                    let unpacked = matches!(FlakeRef::parse(url), Ok(FlakeRef::Tarball { .. }));
+                    inputs.extra.insert(input.clone(), FlakeInput {
+                        url: url.clone(),
+                        flake: false,
+                    });
+                    // Archives are linked in as a package tree, single files become a binary
+                    let build = if unpacked {
+                        format!("ln -s ${{inputs.{}}} $out", input)
+                    } else {
+                        format!("install -Dm755 ${{inputs.{}}} $out/bin/'{}'", input, nix_escape(&pkg.name.replace('\'', "")))
+                    };
+                    push_unique(&mut packages, format!("(pkgs.runCommand {} {{ }} \"{}\")", nix_string(&pkg.name), build));
+                }
+            }
+        }
+
+        // PATH and sfc's own variables only make sense on this host
+        let env = container.environment.iter()
+            .filter(|(key, _)| key.as_str() != "PATH" && !key.starts_with("SFC_"))
+            .map(|(key, value)| (key.clone(), value.clone()))
+            .collect();
+
+        let names: Vec<&str> = container.packages.iter().map(|p| p.name.as_str()).collect();
+        // mkShell runs the hook with bash: single-quote everything interpolated
+        let quote = |text: &str| ShellKind::Bash.quote(&text.replace('\n', " "));
+        let shell_hook = format!(
+            "echo {}\necho {}\nexport SFC_CONTAINER={}\n",
+            quote(&format!("🚀 Entering {} container", container.name)),
+            quote(&format!("📦 Packages: {}", names.join(", "))),
+            quote(&container.name)
+        );
+
+        Self {
+            description: format!("SFC container: {}", container.name),
+            inputs,
+            outputs: FlakeOutputs {
+                packages: std::collections::HashMap::new(),
+                shell: ShellConfig {
+                    packages,
+                    env,
+                    notes,
+                    shell_hook,
+                },
+            },
+        }
+    }
+
+    /// flake.nix with a dev shell for each of `DEFAULT_SYSTEMS`
+    pub fn to_nix(&self) -> String {
+        let shell = &self.outputs.shell;
+        let mut out = String::new();
+        out.push_str("{\n");
+        out.push_str(&format!("  description = {};\n\n", nix_string(&self.description)));
+
+        out.push_str("  inputs = {\n");
+        out.push_str(&format!("    nixpkgs.url = {};\n", nix_string(&self.inputs.nixpkgs.url)));
+        for (name, input) in &self.inputs.extra {
+            if input.flake {
+                out.push_str(&format!("    {}.url = {};\n", nix_attr(name), nix_string(&input.url)));
+            } else {
+                out.push_str(&format!("    {} = {{\n", nix_attr(name)));
+                out.push_str(&format!("      url = {};\n", nix_string(&input.url)));
+                out.push_str("      flake = false;\n");
+                out.push_str("    };\n");
+            }
+        }
+        out.push_str("  };\n\n");
+
+        let systems: Vec<String> = DEFAULT_SYSTEMS.iter().map(|s| nix_string(s)).collect();
+        out.push_str("  outputs = { self, nixpkgs, ... }@inputs:\n");
+        out.push_str("    let\n");
+        out.push_str(&format!("      systems = [ {} ];\n", systems.join(" ")));
+        out.push_str("      forAllSystems = f: nixpkgs.lib.genAttrs systems (system: f system nixpkgs.legacyPackages.${system});\n");
+        out.push_str("    in\n");
+        out.push_str("    {\n");
+        out.push_str("      devShells = forAllSystems (system: pkgs: {\n");
+        out.push_str("        default = pkgs.mkShell {\n");
+        for note in &shell.notes {
+            out.push_str(&format!("          # {}\n", note.replace('\n', " ")));
+        }
+        if shell.packages.is_empty() {
+            out.push_str("          packages = [ ];\n");
+        } else {
+            out.push_str("          packages = [\n");
+            for package in &shell.packages {
+                out.push_str(&format!("            {}\n", package));
+            }
+            out.push_str("          ];\n");
+        }
+        if !shell.env.is_empty() {
+            out.push_str("\n          env = {\n");
+            for (key, value) in &shell.env {
+                out.push_str(&format!("            {} = {};\n", nix_attr(key), nix_string(value)));
+            }
+            out.push_str("          };\n");
+        }
+        // Indented string: '' and ${ are escaped by prefixing ''
+        out.push_str("\n          shellHook = ''\n");
+        for line in shell.shell_hook.lines() {
+            let line = line.replace("''", "'''").replace("${", "''${");
+            out.push_str(&format!("            {}\n", line));
+        }
+        out.push_str("          '';\n");
+        out.push_str("        };\n");
+        out.push_str("      });\n");
+        out.push_str("    };\n");
+        out.push_str("}\n");
+        out
+    }
+
+    /// Every input by name, parsed
+    pub fn input_refs(&self) -> Result<BTreeMap<String, FlakeRef>> {
+        std::iter::once(("nixpkgs", &self.inputs.nixpkgs))
+            .chain(self.inputs.extra.iter().map(|(name, input)| (name.as_str(), input)))
+            .map(|(name, input)| Ok((name.to_string(), FlakeRef::parse(&input.url)?)))
+            .collect()
+    }
+
+    /// Write flake.nix and drop lock entries for inputs that changed. Nothing is
+    /// fetched here; `lock` pins new inputs.
+    pub fn save(&self, workspace: &Path, container_name: &str) -> Result<Vec<LockChange>> {
+        let flake_dir = workspace.join("containers").join(container_name);
+        fs::create_dir_all(&flake_dir)?;
+        
+        let flake_nix = flake_dir.join("flake.nix");
+        fs::write(&flake_nix, self.to_nix())?;
+        
+        let lock_path = flake_dir.join("flake.lock");
+        let mut lock = FlakeLock::load_or_default(&lock_path)?;
+        let changes = lock.retain(&self.input_refs()?);
+        lock.save(&lock_path)?;
+        Ok(changes)
+    }
+
+    /// Inputs that `flake.lock` doesn't pin yet
+    pub fn unlocked_inputs(&self, workspace: &Path, container_name: &str) -> Result<Vec<String>> {
+        let lock_path = workspace.join("containers").join(container_name).join("flake.lock");
+        let lock = FlakeLock::load_or_default(&lock_path)?;
+        Ok(self.input_refs()?.into_keys().filter(|name| lock.input(name).is_none()).collect())
+    }
+
+    /// Write flake.nix and pin every unlocked input, or all of them with
+    /// `update_all`, in flake.lock
+    pub fn lock(&self, workspace: &Path, container_name: &str, update_all: bool) -> Result<Vec<LockChange>> {
+        let mut changes = self.save(workspace, container_name)?;
+        let lock_path = workspace.join("containers").join(container_name).join("flake.lock");
+        let mut lock = FlakeLock::load_or_default(&lock_path)?;
+        let updated = lock
+            .update(&self.input_refs()?, update_all, lock::fetch_locked)
+            .with_context(|| format!("locking flake inputs for '{}'", container_name))?;
+        for (name, _) in self.inputs.extra.iter().filter(|(_, input)| !input.flake) {
+            if let Some(node) = lock.nodes.get_mut(name) {
+                node.flake = Some(false);
+            }
+        }
+        lock.save(&lock_path)?;
+
+        // An input dropped by `save` and locked again shows up once
+        for change in updated {
+            changes.retain(|c| c.input != change.input);
+            changes.push(change);
+        }
+        changes.sort_by(|a, b| a.input.cmp(&b.input));
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSpec;
+
+    /// Compare against `tests/golden/<name>`; `UPDATE_GOLDEN=1` rewrites it
+    fn assert_golden(name: &str, actual: &str) {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(name);
+        if std::env::var_os("UPDATE_GOLDEN").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, actual).unwrap();
+        }
+        let expected = fs::read_to_string(&path).unwrap_or_else(|_| panic!("missing golden file {}", path.display()));
+        assert_eq!(actual, expected, "{} is out of date (rerun with UPDATE_GOLDEN=1)", name);
+    }
+
+    #[test]
+    fn test_flake_minimal_golden() {
+        let config = ContainerConfig::new("empty".to_string());
+        assert_golden("flake_minimal.nix", &FlakeConfig::from_container(&config).to_nix());
+    }
+
+    #[test]
+    fn test_flake_full_golden() {
+        let mut config = ContainerConfig::new("web".to_string());
+        config.nixpkgs = Some("github:NixOS/nixpkgs/nixos-24.05".to_string());
+        for spec in [
+            "node@18",
+            "python@3.12",
+            "ripgrep@14",
+            "github:owner/my-tool@v1.2.0",
+            "https://example.com/dl/cli",
+            "https://example.com/src/data.tar.gz",
+        ] {
+            config.add_package(PackageSpec::parse(spec)).unwrap();
+        }
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        config.toolchains.insert("npm".to_string(), "10".to_string());
+        config.toolchains.insert("rust".to_string(), "1.78".to_string());
+        config.environment.insert("NODE_ENV".to_string(), "development".to_string());
+        config.environment.insert("GREETING".to_string(), "say \"hi\" ${USER}".to_string());
+        config.environment.insert("PATH".to_string(), "/host/bin".to_string());
+
+        let flake = FlakeConfig::from_container(&config);
+        assert_golden("flake_full.nix", &flake.to_nix());
+
+        let refs = flake.input_refs().unwrap();
+        assert!(matches!(refs["data_tar_gz"], FlakeRef::Tarball { .. }));
+        assert!(matches!(refs["cli"], FlakeRef::File { .. }));
+        assert_eq!(refs["my_tool"].to_string(), "github:owner/my-tool/v1.2.0");
+    }
+
+    #[test]
+    fn test_input_names_never_collide() {
+        let mut config = ContainerConfig::new("tools".to_string());
+        for spec in ["github:owner/my-tool@v1", "github:other/my_tool@v2", "github:owner/nixpkgs@main", "https://example.com/self"] {
+            config.add_package(PackageSpec::parse(spec)).unwrap();
+        }
+        let flake = FlakeConfig::from_container(&config);
+        let names: Vec<&String> = flake.inputs.extra.keys().collect();
+        assert_eq!(names.len(), 4);
+        assert!(names.iter().all(|n| !RESERVED_INPUTS.contains(&n.as_str())));
+        let refs = flake.input_refs().unwrap();
+        assert_eq!(refs["my_tool"].to_string(), "github:owner/my-tool/v1");
+        let suffixed = names.iter().find(|n| n.starts_with("my_tool_")).unwrap();
+        assert_eq!(refs[suffixed.as_str()].to_string(), "github:other/my_tool/v2");
+        assert_eq!(FlakeConfig::from_container(&config).inputs.extra.keys().collect::<Vec<_>>(), names);
+    }
+
+    #[test]
+    fn test_shell_hook_prints_names_literally() {
+        let mut config = ContainerConfig::new("web".to_string());
+        let hostile = "x\"$(touch pwned)`id`${HOME}'q''";
+        config.add_package(PackageSpec::from_name(hostile)).unwrap();
+        let flake = FlakeConfig::from_container(&config);
+
+        let dir = tempfile::tempdir().unwrap();
+        let output = std::process::Command::new("bash")
+            .args(["-c", &flake.outputs.shell.shell_hook])
+            .current_dir(dir.path())
+            .output()
+            .unwrap();
+        let printed = String::from_utf8(output.stdout).unwrap();
+        assert_eq!(printed, format!("🚀 Entering web container\n📦 Packages: {}\n", hostile));
+        assert!(!dir.path().join("pwned").exists());
+
+        // The Nix indented string escapes '' and ${ so the hook reaches bash unchanged
+        let nix = flake.to_nix();
+        let start = nix.find("shellHook = ").unwrap() + "shellHook = ".len();
+        let end = nix[start..].find("\n          '';").unwrap() + start + "\n          ''".len();
+        let parsed = parser::parse(&nix[start..end]).unwrap().as_str();
+        assert_eq!(parsed.as_deref(), Some(flake.outputs.shell.shell_hook.as_str()));
+    }
+}
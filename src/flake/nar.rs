@@ -0,0 +1,122 @@
+use std::fs;
+use std::io::{self, Write};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+use sha2::{Digest, Sha256};
+
+/// Nix archive serialization of `path`, streamed into `out`. Directory entries
+/// are sorted by name and only the owner-executable bit of files is kept, so
+/// this matches `nix hash path` for the same tree.
+pub fn write_nar<W: Write>(path: &Path, out: &mut W) -> io::Result<()> {
+    put_str(out, b"nix-archive-1")?;
+    write_node(path, out)
+}
+
+fn write_node<W: Write>(path: &Path, out: &mut W) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path)?;
+    put_str(out, b"(")?;
+    put_str(out, b"type")?;
+    if meta.file_type().is_symlink() {
+        put_str(out, b"symlink")?;
+        put_str(out, b"target")?;
+        put_str(out, fs::read_link(path)?.as_os_str().as_encoded_bytes())?;
+    } else if meta.is_dir() {
+        put_str(out, b"directory")?;
+        let mut entries: Vec<_> = fs::read_dir(path)?.collect::<io::Result<_>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for entry in entries {
+            put_str(out, b"entry")?;
+            put_str(out, b"(")?;
+            put_str(out, b"name")?;
+            put_str(out, entry.file_name().as_encoded_bytes())?;
+            put_str(out, b"node")?;
+            write_node(&entry.path(), out)?;
+            put_str(out, b")")?;
+        }
+    } else {
+        put_str(out, b"regular")?;
+        if meta.permissions().mode() & 0o100 != 0 {
+            put_str(out, b"executable")?;
+            put_str(out, b"")?;
+        }
+        put_str(out, b"contents")?;
+        put_str(out, &fs::read(path)?)?;
+    }
+    put_str(out, b")")
+}
+
+/// Length-prefixed, zero-padded to a multiple of 8 bytes
+fn put_str<W: Write>(out: &mut W, bytes: &[u8]) -> io::Result<()> {
+    out.write_all(&(bytes.len() as u64).to_le_bytes())?;
+    out.write_all(bytes)?;
+    let pad = (8 - bytes.len() % 8) % 8;
+    out.write_all(&[0u8; 8][..pad])
+}
+
+struct HashWriter(Sha256);
+
+impl Write for HashWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// `narHash` of `path` in SRI form (`sha256-<base64>`)
+pub fn nar_hash(path: &Path) -> io::Result<String> {
+    let mut hasher = HashWriter(Sha256::new());
+    write_nar(path, &mut hasher)?;
+    Ok(format!("sha256-{}", base64(&hasher.0.finalize())))
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |acc, (i, &b)| acc | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nar_layout_and_hash() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("hello");
+        fs::write(&file, "hi").unwrap();
+
+        let mut nar = Vec::new();
+        write_nar(&file, &mut nar).unwrap();
+        let mut expected = Vec::new();
+        for s in ["nix-archive-1", "(", "type", "regular", "contents", "hi", ")"] {
+            put_str(&mut expected, s.as_bytes()).unwrap();
+        }
+        assert_eq!(nar, expected);
+        assert_eq!(nar.len() % 8, 0);
+
+        // Executable bit and entry order both affect the hash
+        let before = nar_hash(dir.path()).unwrap();
+        fs::set_permissions(&file, fs::Permissions::from_mode(0o755)).unwrap();
+        assert_ne!(nar_hash(dir.path()).unwrap(), before);
+        assert!(before.starts_with("sha256-") && before.len() == "sha256-".len() + 44);
+
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"foob"), "Zm9vYg==");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
@@ -0,0 +1,118 @@
+/// Canonical nixpkgs name for common aliases
+fn base_attr(name: &str) -> &str {
+    match name {
+        "node" | "nodejs" => "nodejs",
+        "python" | "python3" => "python3",
+        "java" | "openjdk" | "jdk" => "jdk",
+        "golang" | "go" => "go",
+        "postgres" | "postgresql" => "postgresql",
+        "dotnet" | "dotnet-sdk" => "dotnet-sdk",
+        other => other,
+    }
+}
+
+/// Numeric components of a version such as `20`, `3.12.1`, `18.x` or `^1.22`
+fn numeric_parts(version: &str) -> Vec<u32> {
+    version
+        .trim_start_matches(|c: char| !c.is_ascii_digit())
+        .split('.')
+        .map_while(|part| part.parse().ok())
+        .collect()
+}
+
+/// nixpkgs attribute for `name` at `version`, and whether that attribute
+/// actually carries the requested version. nixpkgs only keeps versioned
+/// attributes for some packages (e.g. `nodejs_20`, `python312`); for the rest
+/// the version is decided by the nixpkgs pin.
+pub fn nixpkgs_attr(name: &str, version: Option<&str>) -> (String, bool) {
+    let base = base_attr(name);
+    let Some(version) = version.filter(|v| !matches!(*v, "latest" | "stable" | "*")) else {
+        return (base.to_string(), true);
+    };
+    let parts = numeric_parts(version);
+    let (major, minor) = (parts.first().copied(), parts.get(1).copied());
+
+    let attr = match (base, major, minor) {
+        ("nodejs", Some(major), _) => format!("nodejs_{}", major),
+        ("python3", Some(3), Some(minor)) => format!("python3{}", minor),
+        ("python3", Some(2), _) => "python2".to_string(),
+        ("go", Some(major), Some(minor)) => format!("go_{}_{}", major, minor),
+        ("ruby", Some(major), Some(minor)) => format!("ruby_{}_{}", major, minor),
+        ("php", Some(major), Some(minor)) => format!("php{}{}", major, minor),
+        ("lua", Some(major), Some(minor)) => format!("lua{}_{}", major, minor),
+        ("elixir", Some(major), Some(minor)) => format!("elixir_{}_{}", major, minor),
+        ("jdk", Some(major), _) => format!("jdk{}", major),
+        ("gcc", Some(major), _) => format!("gcc{}", major),
+        ("postgresql" | "erlang" | "llvm" | "clang" | "lld", Some(major), _) => format!("{}_{}", base, major),
+        ("dotnet-sdk", Some(major), _) => format!("dotnet-sdk_{}", major),
+        _ => return (base.to_string(), false),
+    };
+    (attr, true)
+}
+
+/// `digits` if the string is a non-empty run of ASCII digits
+fn number(digits: &str) -> Option<&str> {
+    (!digits.is_empty() && digits.bytes().all(|c| c.is_ascii_digit())).then_some(digits)
+}
+
+/// sfc package name and version for a nixpkgs attribute; the inverse of
+/// [`nixpkgs_attr`] for the versioned attributes it produces
+pub fn package_for_attr(attr: &str) -> (String, Option<String>) {
+    let major_minor = |rest: &str, sep: &str| -> Option<String> {
+        let (major, minor) = rest.split_once(sep)?;
+        Some(format!("{}.{}", number(major)?, number(minor)?))
+    };
+    let versioned = [
+        ("nodejs_", attr.strip_prefix("nodejs_").and_then(number).map(String::from)),
+        ("python3", attr.strip_prefix("python3").and_then(number).map(|minor| format!("3.{}", minor))),
+        ("go", attr.strip_prefix("go_").and_then(|rest| major_minor(rest, "_"))),
+        ("ruby", attr.strip_prefix("ruby_").and_then(|rest| major_minor(rest, "_"))),
+        ("lua", attr.strip_prefix("lua").and_then(|rest| major_minor(rest, "_"))),
+        ("elixir", attr.strip_prefix("elixir_").and_then(|rest| major_minor(rest, "_"))),
+        ("php", attr.strip_prefix("php").filter(|rest| rest.len() == 2).and_then(number).map(|v| format!("{}.{}", &v[..1], &v[1..]))),
+        ("jdk", attr.strip_prefix("jdk").and_then(number).map(String::from)),
+        ("gcc", attr.strip_prefix("gcc").and_then(number).map(String::from)),
+        ("dotnet-sdk", attr.strip_prefix("dotnet-sdk_").and_then(number).map(String::from)),
+    ];
+    for (name, version) in versioned {
+        if let Some(version) = version {
+            return (name.trim_end_matches('_').to_string(), Some(version));
+        }
+    }
+    for name in ["postgresql", "erlang", "llvm", "clang", "lld"] {
+        if let Some(major) = attr.strip_prefix(name).and_then(|rest| rest.strip_prefix('_')).and_then(number) {
+            return (name.to_string(), Some(major.to_string()));
+        }
+    }
+    (attr.to_string(), None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versioned_attrs() {
+        assert_eq!(nixpkgs_attr("node", Some("20")), ("nodejs_20".to_string(), true));
+        assert_eq!(nixpkgs_attr("nodejs", Some("18.x")), ("nodejs_18".to_string(), true));
+        assert_eq!(nixpkgs_attr("python", Some("3.12.1")), ("python312".to_string(), true));
+        assert_eq!(nixpkgs_attr("go", Some("^1.22")), ("go_1_22".to_string(), true));
+        assert_eq!(nixpkgs_attr("java", Some("21")), ("jdk21".to_string(), true));
+        assert_eq!(nixpkgs_attr("postgres", Some("16")), ("postgresql_16".to_string(), true));
+        assert_eq!(nixpkgs_attr("python3", None), ("python3".to_string(), true));
+        assert_eq!(nixpkgs_attr("ripgrep", Some("14")), ("ripgrep".to_string(), false));
+        assert_eq!(nixpkgs_attr("go", Some("1")), ("go".to_string(), false));
+    }
+
+    #[test]
+    fn test_package_for_attr_round_trips() {
+        for attr in ["nodejs_20", "python312", "go_1_22", "ruby_3_3", "php83", "lua5_4", "elixir_1_16", "jdk21", "gcc13", "postgresql_16", "llvm_17", "dotnet-sdk_8"] {
+            let (name, version) = package_for_attr(attr);
+            assert!(version.is_some(), "{}", attr);
+            assert_eq!(nixpkgs_attr(&name, version.as_deref()), (attr.to_string(), true));
+        }
+        assert_eq!(package_for_attr("ripgrep"), ("ripgrep".to_string(), None));
+        assert_eq!(package_for_attr("python3"), ("python3".to_string(), None));
+        assert_eq!(package_for_attr("gccgo"), ("gccgo".to_string(), None));
+    }
+}
@@ -0,0 +1,740 @@
+//! A restricted Nix expression parser. It covers the syntax found in
+//! `flake.nix` files (attribute sets, lets, lambdas, `with`, strings with
+//! interpolation, operators) well enough to read a dev shell's packages and
+//! environment; nothing is evaluated.
+
+use anyhow::{anyhow, Result};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Ident(String),
+    Str(Vec<StrPart>),
+    /// Numbers, paths and bare URIs, kept as written
+    Literal(String),
+    List(Vec<Expr>),
+    Attrs { rec: bool, bindings: Vec<Binding> },
+    Select { expr: Box<Expr>, path: Vec<AttrKey>, default: Option<Box<Expr>> },
+    HasAttr(Box<Expr>, Vec<AttrKey>),
+    Apply(Box<Expr>, Box<Expr>),
+    Lambda(Box<Expr>),
+    Let(Vec<Binding>, Box<Expr>),
+    With(Box<Expr>, Box<Expr>),
+    If(Box<Expr>, Box<Expr>, Box<Expr>),
+    Assert(Box<Expr>, Box<Expr>),
+    BinOp(&'static str, Box<Expr>, Box<Expr>),
+    Not(Box<Expr>),
+    Neg(Box<Expr>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum StrPart {
+    Lit(String),
+    Interp(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum AttrKey {
+    Name(String),
+    Dynamic(Expr),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Binding {
+    Attr(Vec<AttrKey>, Expr),
+    Inherit(Option<Expr>, Vec<String>),
+}
+
+impl Expr {
+    /// The string value if this is a string without interpolation
+    pub fn as_str(&self) -> Option<String> {
+        match self {
+            Expr::Str(parts) => parts.iter().map(|part| match part {
+                StrPart::Lit(text) => Some(text.as_str()),
+                StrPart::Interp(_) => None,
+            }).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl AttrKey {
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            AttrKey::Name(name) => Some(name),
+            AttrKey::Dynamic(_) => None,
+        }
+    }
+}
+
+const KEYWORDS: &[&str] = &["let", "in", "rec", "with", "inherit", "if", "then", "else", "assert", "or"];
+
+/// Binary operators as (token, precedence, right associative), loosest first
+const BINARY_OPS: &[(&str, u8, bool)] = &[
+    ("->", 1, true),
+    ("||", 2, false),
+    ("&&", 3, false),
+    ("==", 4, false),
+    ("!=", 4, false),
+    ("<=", 5, false),
+    (">=", 5, false),
+    ("<", 5, false),
+    (">", 5, false),
+    ("//", 6, true),
+    ("+", 8, false),
+    ("-", 8, false),
+    ("*", 9, false),
+    ("/", 9, false),
+    ("++", 10, true),
+];
+const HAS_ATTR_PREC: u8 = 11;
+
+/// Parse a complete Nix expression
+pub fn parse(source: &str) -> Result<Expr> {
+    let mut parser = Parser { src: source.as_bytes(), pos: 0 };
+    let expr = parser.expr()?;
+    parser.skip_ws();
+    if parser.pos < parser.src.len() {
+        return Err(parser.error("unexpected input after expression"));
+    }
+    Ok(expr)
+}
+
+fn is_ident_start(c: u8) -> bool {
+    c.is_ascii_alphabetic() || c == b'_'
+}
+
+fn is_ident_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'_' | b'\'' | b'-')
+}
+
+fn is_uri_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || b"%/?:@&=+$,-_.!~*'".contains(&c)
+}
+
+fn is_path_char(c: u8) -> bool {
+    c.is_ascii_alphanumeric() || matches!(c, b'.' | b'_' | b'-' | b'+' | b'/')
+}
+
+struct Parser<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> anyhow::Error {
+        let before = &self.src[..self.pos.min(self.src.len())];
+        let line = before.iter().filter(|&&c| c == b'\n').count() + 1;
+        let column = before.iter().rev().take_while(|&&c| c != b'\n').count() + 1;
+        anyhow!("{} at line {}, column {}", message, line, column)
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, token: &str) -> bool {
+        self.src[self.pos..].starts_with(token.as_bytes())
+    }
+
+    fn skip_ws(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_ascii_whitespace() => self.pos += 1,
+                Some(b'#') => {
+                    while self.peek().is_some_and(|c| c != b'\n') {
+                        self.pos += 1;
+                    }
+                }
+                Some(b'/') if self.peek_at(1) == Some(b'*') => {
+                    self.pos += 2;
+                    while self.pos < self.src.len() && !self.starts_with("*/") {
+                        self.pos += 1;
+                    }
+                    self.pos = (self.pos + 2).min(self.src.len());
+                }
+                _ => return,
+            }
+        }
+    }
+
+    /// Consume `token` (after whitespace) if it comes next
+    fn eat(&mut self, token: &str) -> bool {
+        self.skip_ws();
+        if self.starts_with(token) {
+            self.pos += token.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, token: &str) -> Result<()> {
+        if self.eat(token) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", token)))
+        }
+    }
+
+    /// The identifier or keyword at the cursor, without consuming it
+    fn peek_word(&mut self) -> Option<&str> {
+        self.skip_ws();
+        if !self.peek().is_some_and(is_ident_start) {
+            return None;
+        }
+        let end = self.src[self.pos..].iter().position(|&c| !is_ident_char(c)).map_or(self.src.len(), |n| self.pos + n);
+        std::str::from_utf8(&self.src[self.pos..end]).ok()
+    }
+
+    fn eat_keyword(&mut self, keyword: &str) -> bool {
+        if self.peek_word() == Some(keyword) {
+            self.pos += keyword.len();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn ident(&mut self) -> Result<String> {
+        match self.peek_word() {
+            Some(word) if !KEYWORDS.contains(&word) => {
+                let word = word.to_string();
+                self.pos += word.len();
+                Ok(word)
+            }
+            _ => Err(self.error("expected identifier")),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr> {
+        if self.eat_keyword("let") {
+            let bindings = self.bindings("in")?;
+            if !self.eat_keyword("in") {
+                return Err(self.error("expected 'in'"));
+            }
+            return Ok(Expr::Let(bindings, Box::new(self.expr()?)));
+        }
+        if self.eat_keyword("with") {
+            let scope = self.expr()?;
+            self.expect(";")?;
+            return Ok(Expr::With(Box::new(scope), Box::new(self.expr()?)));
+        }
+        if self.eat_keyword("assert") {
+            let cond = self.expr()?;
+            self.expect(";")?;
+            return Ok(Expr::Assert(Box::new(cond), Box::new(self.expr()?)));
+        }
+        if self.eat_keyword("if") {
+            let cond = self.expr()?;
+            if !self.eat_keyword("then") {
+                return Err(self.error("expected 'then'"));
+            }
+            let then = self.expr()?;
+            if !self.eat_keyword("else") {
+                return Err(self.error("expected 'else'"));
+            }
+            return Ok(Expr::If(Box::new(cond), Box::new(then), Box::new(self.expr()?)));
+        }
+        if let Some(body) = self.lambda()? {
+            return Ok(body);
+        }
+        self.binary(0)
+    }
+
+    /// `x: body`, `{ a, b ? 1, ... }: body` and the `@` forms
+    fn lambda(&mut self) -> Result<Option<Expr>> {
+        self.skip_ws();
+        let start = self.pos;
+        if let Some(len) = self.peek_word().filter(|w| !KEYWORDS.contains(w)).map(str::len) {
+            self.pos += len;
+            let is_lambda = self.peek() == Some(b':') && !self.peek_at(1).is_some_and(is_uri_char);
+            if is_lambda {
+                self.pos += 1;
+                return Ok(Some(Expr::Lambda(Box::new(self.expr()?))));
+            }
+            if self.eat("@") && self.pattern().is_ok() && self.eat(":") {
+                return Ok(Some(Expr::Lambda(Box::new(self.expr()?))));
+            }
+        }
+        self.pos = start;
+        if self.peek() == Some(b'{') && self.pattern().is_ok() {
+            if self.eat("@") {
+                self.ident()?;
+            }
+            if self.eat(":") {
+                return Ok(Some(Expr::Lambda(Box::new(self.expr()?))));
+            }
+        }
+        self.pos = start;
+        Ok(None)
+    }
+
+    /// A `{ a, b ? default, ... }` argument pattern
+    fn pattern(&mut self) -> Result<()> {
+        self.expect("{")?;
+        loop {
+            if self.eat("}") {
+                return Ok(());
+            }
+            if !self.eat("...") {
+                self.ident()?;
+                if self.eat("?") {
+                    self.expr()?;
+                }
+            }
+            if !self.eat(",") {
+                return self.expect("}");
+            }
+        }
+    }
+
+    fn peek_binary_op(&mut self) -> Option<(&'static str, u8, bool)> {
+        self.skip_ws();
+        // Longest match first, so `//` and `++` win over `/` and `+`
+        let mut ops: Vec<_> = BINARY_OPS.to_vec();
+        ops.sort_by_key(|(token, _, _)| std::cmp::Reverse(token.len()));
+        ops.into_iter().find(|(token, _, _)| self.starts_with(token))
+    }
+
+    fn binary(&mut self, min_prec: u8) -> Result<Expr> {
+        let mut lhs = self.unary()?;
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(b'?') && HAS_ATTR_PREC >= min_prec {
+                self.pos += 1;
+                lhs = Expr::HasAttr(Box::new(lhs), self.attr_path()?);
+                continue;
+            }
+            let Some((token, prec, right)) = self.peek_binary_op() else {
+                return Ok(lhs);
+            };
+            if prec < min_prec {
+                return Ok(lhs);
+            }
+            self.pos += token.len();
+            let rhs = self.binary(if right { prec } else { prec + 1 })?;
+            lhs = Expr::BinOp(token, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr> {
+        if self.eat("!") {
+            return Ok(Expr::Not(Box::new(self.binary(7)?)));
+        }
+        self.skip_ws();
+        if self.peek() == Some(b'-') && self.peek_at(1) != Some(b'>') {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.application()
+    }
+
+    fn application(&mut self) -> Result<Expr> {
+        let mut expr = self.select()?;
+        while self.starts_operand() {
+            expr = Expr::Apply(Box::new(expr), Box::new(self.select()?));
+        }
+        Ok(expr)
+    }
+
+    /// Whether the next token can begin an argument of a function application
+    fn starts_operand(&mut self) -> bool {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'(' | b'[' | b'{' | b'"') => true,
+            Some(b'<') => self.peek_at(1).is_some_and(is_ident_start),
+            Some(b'\'') => self.peek_at(1) == Some(b'\''),
+            Some(b'.') => self.peek_at(1) == Some(b'/') || self.starts_with("../"),
+            Some(b'~') => self.peek_at(1) == Some(b'/'),
+            Some(c) if c.is_ascii_digit() => true,
+            Some(c) if is_ident_start(c) => !self.peek_word().is_some_and(|w| KEYWORDS.contains(&w) && w != "rec" && w != "let"),
+            _ => false,
+        }
+    }
+
+    fn select(&mut self) -> Result<Expr> {
+        let expr = self.primary()?;
+        self.skip_ws();
+        if self.peek() != Some(b'.') || self.peek_at(1) == Some(b'.') {
+            return Ok(expr);
+        }
+        self.pos += 1;
+        let path = self.attr_path()?;
+        let default = if self.eat_keyword("or") { Some(Box::new(self.select()?)) } else { None };
+        Ok(Expr::Select { expr: Box::new(expr), path, default })
+    }
+
+    fn attr_path(&mut self) -> Result<Vec<AttrKey>> {
+        let mut path = vec![self.attr_key()?];
+        loop {
+            self.skip_ws();
+            if self.peek() == Some(b'.') && self.peek_at(1) != Some(b'.') {
+                self.pos += 1;
+                path.push(self.attr_key()?);
+            } else {
+                return Ok(path);
+            }
+        }
+    }
+
+    fn attr_key(&mut self) -> Result<AttrKey> {
+        self.skip_ws();
+        match self.peek() {
+            Some(b'"') => {
+                self.pos += 1;
+                let parts = self.string()?;
+                let key = Expr::Str(parts);
+                Ok(key.as_str().map(AttrKey::Name).unwrap_or(AttrKey::Dynamic(key)))
+            }
+            Some(b'$') if self.peek_at(1) == Some(b'{') => {
+                self.pos += 2;
+                let key = self.expr()?;
+                self.expect("}")?;
+                Ok(AttrKey::Dynamic(key))
+            }
+            // Keywords are valid attribute names after a dot (`lib.or`)
+            _ => match self.peek_word() {
+                Some(word) => {
+                    let word = word.to_string();
+                    self.pos += word.len();
+                    Ok(AttrKey::Name(word))
+                }
+                None => Err(self.error("expected attribute name")),
+            },
+        }
+    }
+
+    fn primary(&mut self) -> Result<Expr> {
+        self.skip_ws();
+        let Some(c) = self.peek() else {
+            return Err(self.error("unexpected end of input"));
+        };
+        match c {
+            b'(' => {
+                self.pos += 1;
+                let expr = self.expr()?;
+                self.expect(")")?;
+                Ok(expr)
+            }
+            b'[' => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                while !self.eat("]") {
+                    if self.pos >= self.src.len() {
+                        return Err(self.error("unterminated list"));
+                    }
+                    items.push(self.select()?);
+                }
+                Ok(Expr::List(items))
+            }
+            b'{' => {
+                self.pos += 1;
+                let bindings = self.bindings("}")?;
+                self.expect("}")?;
+                Ok(Expr::Attrs { rec: false, bindings })
+            }
+            b'"' => {
+                self.pos += 1;
+                Ok(Expr::Str(self.string()?))
+            }
+            b'\'' if self.peek_at(1) == Some(b'\'') => {
+                self.pos += 2;
+                Ok(Expr::Str(self.indented_string()?))
+            }
+            b'<' => {
+                let end = self.src[self.pos..].iter().position(|&c| c == b'>').ok_or_else(|| self.error("unterminated search path"))?;
+                let text = String::from_utf8_lossy(&self.src[self.pos..=self.pos + end]).into_owned();
+                self.pos += end + 1;
+                Ok(Expr::Literal(text))
+            }
+            b'.' | b'/' | b'~' => Ok(Expr::Literal(self.take_while(is_path_char, 1))),
+            c if c.is_ascii_digit() => Ok(Expr::Literal(self.take_while(|c| c.is_ascii_digit() || c == b'.', 0))),
+            _ => {
+                if self.eat_keyword("rec") {
+                    self.expect("{")?;
+                    let bindings = self.bindings("}")?;
+                    self.expect("}")?;
+                    return Ok(Expr::Attrs { rec: true, bindings });
+                }
+                if self.peek_word() == Some("let") {
+                    return self.expr();
+                }
+                let start = self.pos;
+                let name = self.ident()?;
+                if self.peek() == Some(b':') && self.peek_at(1).is_some_and(is_uri_char) {
+                    self.pos = start;
+                    return Ok(Expr::Literal(self.take_while(is_uri_char, 0)));
+                }
+                Ok(Expr::Ident(name))
+            }
+        }
+    }
+
+    /// Consume characters matching `pred`, always taking the first `min` bytes
+    fn take_while(&mut self, pred: impl Fn(u8) -> bool, min: usize) -> String {
+        let start = self.pos;
+        self.pos += min;
+        while self.peek().is_some_and(&pred) {
+            self.pos += 1;
+        }
+        String::from_utf8_lossy(&self.src[start..self.pos]).into_owned()
+    }
+
+    /// Bindings up to (not including) the `end` keyword or token
+    fn bindings(&mut self, end: &str) -> Result<Vec<Binding>> {
+        let mut bindings = Vec::new();
+        loop {
+            self.skip_ws();
+            let at_end = match end {
+                "in" => self.peek_word() == Some("in"),
+                _ => self.starts_with(end),
+            };
+            if at_end || self.pos >= self.src.len() {
+                return Ok(bindings);
+            }
+            if self.eat_keyword("inherit") {
+                let from = if self.eat("(") {
+                    let from = self.expr()?;
+                    self.expect(")")?;
+                    Some(from)
+                } else {
+                    None
+                };
+                let mut names = Vec::new();
+                while !self.eat(";") {
+                    match self.attr_key()? {
+                        AttrKey::Name(name) => names.push(name),
+                        AttrKey::Dynamic(_) => return Err(self.error("dynamic attribute in inherit")),
+                    }
+                }
+                bindings.push(Binding::Inherit(from, names));
+                continue;
+            }
+            let path = self.attr_path()?;
+            self.expect("=")?;
+            let value = self.expr()?;
+            self.expect(";")?;
+            bindings.push(Binding::Attr(path, value));
+        }
+    }
+
+    /// Body of a `"..."` string, after the opening quote
+    fn string(&mut self) -> Result<Vec<StrPart>> {
+        let mut parts = Vec::new();
+        let mut text = Vec::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.error("unterminated string")),
+                Some(b'"') => {
+                    self.pos += 1;
+                    break;
+                }
+                Some(b'\\') => {
+                    let escaped = self.peek_at(1).ok_or_else(|| self.error("unterminated string"))?;
+                    text.push(match escaped {
+                        b'n' => b'\n',
+                        b't' => b'\t',
+                        b'r' => b'\r',
+                        other => other,
+                    });
+                    self.pos += 2;
+                }
+                Some(b'$') if self.peek_at(1) == Some(b'{') => {
+                    self.interpolation(&mut parts, &mut text)?;
+                }
+                Some(c) => {
+                    text.push(c);
+                    self.pos += 1;
+                }
+            }
+        }
+        flush(&mut parts, &mut text);
+        Ok(parts)
+    }
+
+    /// Body of a `''...''` string, after the opening quotes, with the
+    /// common indentation removed
+    fn indented_string(&mut self) -> Result<Vec<StrPart>> {
+        let mut parts = Vec::new();
+        let mut text = Vec::new();
+        loop {
+            if self.starts_with("'''") {
+                text.extend_from_slice(b"''");
+                self.pos += 3;
+            } else if self.starts_with("''$") {
+                text.push(b'$');
+                self.pos += 3;
+            } else if self.starts_with("''\\") {
+                let escaped = self.peek_at(3).ok_or_else(|| self.error("unterminated string"))?;
+                text.push(match escaped {
+                    b'n' => b'\n',
+                    b't' => b'\t',
+                    b'r' => b'\r',
+                    other => other,
+                });
+                self.pos += 4;
+            } else if self.starts_with("''") {
+                self.pos += 2;
+                break;
+            } else if self.starts_with("${") {
+                self.interpolation(&mut parts, &mut text)?;
+            } else {
+                let c = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                text.push(c);
+                self.pos += 1;
+            }
+        }
+        flush(&mut parts, &mut text);
+        Ok(strip_indentation(parts))
+    }
+
+    fn interpolation(&mut self, parts: &mut Vec<StrPart>, text: &mut Vec<u8>) -> Result<()> {
+        flush(parts, text);
+        self.pos += 2;
+        parts.push(StrPart::Interp(self.expr()?));
+        self.expect("}")
+    }
+}
+
+fn flush(parts: &mut Vec<StrPart>, text: &mut Vec<u8>) {
+    if !text.is_empty() {
+        parts.push(StrPart::Lit(String::from_utf8_lossy(text).into_owned()));
+        text.clear();
+    }
+}
+
+/// Apply the `''` string rules: drop the smallest indentation of the
+/// non-blank lines, and a first line that is only whitespace
+fn strip_indentation(parts: Vec<StrPart>) -> Vec<StrPart> {
+    // Indentation of each line, where interpolations count as content
+    let mut indent: Option<usize> = None;
+    let mut current = Some(0);
+    for part in &parts {
+        match part {
+            StrPart::Lit(text) => {
+                for c in text.chars() {
+                    match (c, current) {
+                        ('\n', _) => current = Some(0),
+                        (' ', Some(n)) => current = Some(n + 1),
+                        (_, Some(n)) => {
+                            indent = Some(indent.map_or(n, |i| i.min(n)));
+                            current = None;
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            StrPart::Interp(_) => {
+                if let Some(n) = current.take() {
+                    indent = Some(indent.map_or(n, |i| i.min(n)));
+                }
+            }
+        }
+    }
+    let indent = indent.unwrap_or(0);
+
+    let mut out = Vec::new();
+    let mut at_line_start = true;
+    let mut first_line = true;
+    for part in parts {
+        match part {
+            StrPart::Lit(text) => {
+                let mut kept = String::new();
+                let mut column = 0;
+                for c in text.chars() {
+                    if c == '\n' {
+                        if first_line && kept.trim().is_empty() {
+                            kept.clear();
+                        } else {
+                            kept.push('\n');
+                        }
+                        first_line = false;
+                        at_line_start = true;
+                        column = 0;
+                        continue;
+                    }
+                    if at_line_start && c == ' ' && column < indent {
+                        column += 1;
+                        continue;
+                    }
+                    at_line_start = false;
+                    kept.push(c);
+                }
+                if !kept.is_empty() {
+                    out.push(StrPart::Lit(kept));
+                }
+            }
+            interp => {
+                at_line_start = false;
+                first_line = false;
+                out.push(interp);
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ident(name: &str) -> Expr {
+        Expr::Ident(name.to_string())
+    }
+
+    #[test]
+    fn test_parse_flake_shapes() {
+        let source = r#"
+            {
+              description = "demo";
+              inputs.nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+              outputs = { self, nixpkgs, ... }@inputs:
+                let pkgs = nixpkgs.legacyPackages.x86_64-linux; /* comment */
+                in {
+                  devShells.x86_64-linux.default = pkgs.mkShell {
+                    packages = with pkgs; [ git nodejs_20 ] ++ [ (python3.withPackages (ps: [ ps.requests ])) ];
+                    FOO = "bar ${pkgs.hello}";
+                    shellHook = ''
+                      export A=1
+                        echo ''${HOME}
+                    '';
+                  };
+                };
+            }
+        "#;
+        let Expr::Attrs { bindings, .. } = parse(source).unwrap() else {
+            panic!("expected attribute set");
+        };
+        assert_eq!(bindings.len(), 3);
+        let Binding::Attr(path, value) = &bindings[1] else { panic!() };
+        assert_eq!(path.iter().filter_map(AttrKey::name).collect::<Vec<_>>(), ["inputs", "nixpkgs", "url"]);
+        assert_eq!(value.as_str().as_deref(), Some("github:NixOS/nixpkgs/nixos-24.05"));
+
+        let Binding::Attr(_, Expr::Lambda(body)) = &bindings[2] else { panic!() };
+        let Expr::Let(_, body) = body.as_ref() else { panic!() };
+        let Expr::Attrs { bindings, .. } = body.as_ref() else { panic!() };
+        let Binding::Attr(_, Expr::Apply(func, arg)) = &bindings[0] else { panic!() };
+        assert!(matches!(func.as_ref(), Expr::Select { path, .. } if path[0] == AttrKey::Name("mkShell".into())));
+        let Expr::Attrs { bindings, .. } = arg.as_ref() else { panic!() };
+
+        let Binding::Attr(_, Expr::With(scope, list)) = &bindings[0] else { panic!() };
+        assert_eq!(scope.as_ref(), &ident("pkgs"));
+        let Expr::BinOp("++", lhs, _) = list.as_ref() else { panic!() };
+        assert_eq!(lhs.as_ref(), &Expr::List(vec![ident("git"), ident("nodejs_20")]));
+
+        let Binding::Attr(_, hook) = &bindings[2] else { panic!() };
+        assert_eq!(hook.as_str().as_deref(), Some("export A=1\n  echo ${HOME}\n"));
+    }
+
+    #[test]
+    fn test_parse_errors_report_position() {
+        let err = parse("{ a = 1; b = ; }").unwrap_err().to_string();
+        assert!(err.contains("line 1"), "{}", err);
+        assert!(parse("{ url = github:owner/repo; f = x: x + 1; g = a: b: a // b; }").is_ok());
+        assert!(parse("{ a = if x ? y then \"\\\"q\\\"\" else -1; }").is_ok());
+    }
+}
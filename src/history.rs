@@ -1,3 +1,4 @@
+use std::collections::BTreeMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 use anyhow::{anyhow, Context, Result};
@@ -15,6 +16,9 @@ pub struct HistoryEntry {
     pub message: String,
     pub operation: Operation,
     pub parent_hash: Option<String>,
+    /// Hash of each container this one extends, as of this entry
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extends: BTreeMap<String, String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,6 +29,9 @@ pub enum Operation {
     ModifyPackage { name: String, old_version: Option<String>, new_version: Option<String> },
     Promote,
     Rollback { target_hash: String },
+    Clone { source: String },
+    Rename { from: String },
+    Pull { remote: String },
 }
 
 #[derive(Debug)]
@@ -65,6 +72,15 @@ impl History {
             .find(|e| e.container_name == container.name)
             .map(|e| e.hash.clone());
 
+        let mut extends = BTreeMap::new();
+        for base in &container.extends {
+            let base_hash = match self.entries.iter().rev().find(|e| &e.container_name == base) {
+                Some(entry) => entry.hash.clone(),
+                None => ContainerConfig::load(&self.workspace, base)?.compute_hash()?,
+            };
+            extends.insert(base.clone(), base_hash);
+        }
+
         let entry = HistoryEntry {
             hash: hash.clone(),
             container_name: container.name.clone(),
@@ -72,6 +88,7 @@ impl History {
             message,
             operation,
             parent_hash,
+            extends,
         };
 
         self.entries.push(entry);
@@ -86,6 +103,29 @@ impl History {
             .collect()
     }
 
+    /// Give `dst` a copy of `src`'s entries. Hashes are kept, so both
+    /// containers share the same ancestry.
+    pub fn copy_lineage(&mut self, src: &str, dst: &str) {
+        let copies: Vec<HistoryEntry> = self.entries
+            .iter()
+            .filter(|e| e.container_name == src)
+            .map(|e| HistoryEntry { container_name: dst.to_string(), ..e.clone() })
+            .collect();
+        self.entries.extend(copies);
+    }
+
+    /// Move every entry, and every `extends` reference, from `old` to `new`
+    pub fn rename_container(&mut self, old: &str, new: &str) {
+        for entry in &mut self.entries {
+            if entry.container_name == old {
+                entry.container_name = new.to_string();
+            }
+            if let Some(hash) = entry.extends.remove(old) {
+                entry.extends.insert(new.to_string(), hash);
+            }
+        }
+    }
+
     pub fn find_by_hash(&self, hash: &str) -> Option<&HistoryEntry> {
         self.entries.iter().find(|e| e.hash.starts_with(hash))
     }
@@ -120,6 +160,9 @@ impl History {
             Operation::ModifyPackage { name, .. } => format!("MODIFY {}", name).yellow().to_string(),
             Operation::Promote => "PROMOTE".blue().to_string(),
             Operation::Rollback { .. } => "ROLLBACK".magenta().to_string(),
+            Operation::Clone { source } => format!("CLONE {}", source).green().to_string(),
+            Operation::Rename { from } => format!("RENAME {}", from).blue().to_string(),
+            Operation::Pull { remote } => format!("PULL {}", remote).cyan().to_string(),
         };
 
         println!("{} {} [{}] {} - {}", 
@@ -128,6 +171,9 @@ impl History {
                  entry.container_name.cyan(),
                  op_str,
                  entry.message);
+        for (base, hash) in &entry.extends {
+            println!("         {} {}@{}", "extends".dimmed(), base.cyan(), (&hash[..8.min(hash.len())]).bright_yellow());
+        }
     }
 
     pub fn visualize_graph(&self, container_name: Option<&str>) -> Result<()> {
@@ -176,6 +222,9 @@ impl History {
             Operation::ModifyPackage { .. } => hash_short.yellow().to_string(),
             Operation::Promote => hash_short.blue().to_string(),
             Operation::Rollback { .. } => hash_short.magenta().to_string(),
+            Operation::Clone { .. } => hash_short.green().to_string(),
+            Operation::Rename { .. } => hash_short.blue().to_string(),
+            Operation::Pull { .. } => hash_short.cyan().to_string(),
         };
 
         println!("{}{}{} {} {}", 
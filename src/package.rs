@@ -29,7 +29,24 @@ impl PackageManager {
 
     pub fn add_package(&self, container: &mut ContainerConfig, package_spec: &str) -> Result<String> {
         let spec = self.parse_package_spec(package_spec)?;
-        
+        self.install_spec(container, spec)
+    }
+
+    /// Install every package a container records, e.g. after importing a
+    /// share. Returns the specs that failed and why.
+    pub fn install_all(&self, container: &mut ContainerConfig) -> Vec<(String, anyhow::Error)> {
+        let mut failed = Vec::new();
+        for spec in container.packages.clone() {
+            let label = spec.to_spec_string();
+            if let Err(e) = self.install_spec(container, spec) {
+                failed.push((label, e));
+            }
+        }
+        failed
+    }
+
+    /// Install an already-resolved spec exactly as given (source, rev, channel)
+    pub fn install_spec(&self, container: &mut ContainerConfig, spec: PackageSpec) -> Result<String> {
         // Dramatic installation header
         self.print_installation_header(&spec);
         
@@ -348,47 +365,7 @@ impl PackageManager {
     }
 
     fn parse_package_spec(&self, spec: &str) -> Result<PackageSpec> {
-        if spec.contains("github:") {
-            // GitHub source: github:owner/repo@rev
-            let parts: Vec<&str> = spec.split('@').collect();
-            let repo = parts[0].strip_prefix("github:").unwrap_or(parts[0]);
-            let rev = parts.get(1).unwrap_or(&"main").to_string();
-            
-            let name = repo.split('/').last().unwrap_or(repo).to_string();
-            
-            return Ok(PackageSpec {
-                name,
-                version: None,
-                channel: None,
-                source: PackageSource::GitHub { 
-                    repo: repo.to_string(), 
-                    rev 
-                },
-            });
-        }
-
-        if spec.starts_with("http") {
-            // URL source
-            let name = spec.split('/').last().unwrap_or(spec).to_string();
-            return Ok(PackageSpec {
-                name,
-                version: None,
-                channel: None,
-                source: PackageSource::Url(spec.to_string()),
-            });
-        }
-
-        // Default nixpkgs source: package@version
-        let parts: Vec<&str> = spec.split('@').collect();
-        let name = parts[0].to_string();
-        let version = parts.get(1).map(|v| v.to_string());
-
-        Ok(PackageSpec {
-            name,
-            version,
-            channel: Some("stable".to_string()),
-            source: PackageSource::Nixpkgs,
-        })
+        Ok(PackageSpec::parse(spec))
     }
 
     fn install_package_real(&self, spec: &PackageSpec, pkg_dir: &std::path::Path) -> Result<bool> {
@@ -427,34 +404,8 @@ impl PackageManager {
             return Err(anyhow!("nix not available"));
         }
 
-        // Fix Nix package naming - versions don't work with @ syntax in Nix
-        let package_name = match &spec.version {
-            Some(version) => {
-                // For Node.js, map versions to Nix packages
-                match spec.name.as_str() {
-                    "nodejs" | "node" => {
-                        match version.as_str() {
-                            "18" | "18.17.0" | "18.x" => "nodejs_18".to_string(),
-                            "20" | "20.5.0" | "20.x" => "nodejs_20".to_string(),
-                            "16" | "16.x" => "nodejs_16".to_string(),
-                            "14" | "14.x" => "nodejs_14".to_string(),
-                            _ => "nodejs".to_string(), // Default to latest
-                        }
-                    }
-                    "python3" | "python" => {
-                        match version.as_str() {
-                            "3.11" | "3.11.0" => "python311".to_string(),
-                            "3.10" | "3.10.0" => "python310".to_string(),
-                            "3.9" | "3.9.0" => "python39".to_string(),
-                            "3.8" | "3.8.0" => "python38".to_string(),
-                            _ => "python3".to_string(), // Default to latest
-                        }
-                    }
-                    _ => spec.name.clone(), // For other packages, ignore version
-                }
-            }
-            None => spec.name.clone(),
-        };
+        // Versions don't work with @ syntax in Nix; use the versioned attribute
+        let (package_name, _) = crate::flake::nixpkgs_attr(&spec.name, spec.version.as_deref());
 
         let display_name = if let Some(version) = &spec.version {
             format!("{}@{}", spec.name, version)
@@ -1760,6 +1711,20 @@ impl PackageManager {
             .unwrap_or(false)
     }
 
+    /// Package name a system backend uses for `package` (as in `sfc add`)
+    pub fn backend_package_name(&self, backend: &crate::system::platform::PackageManager, package: &str) -> String {
+        use crate::system::platform::PackageManager as Backend;
+        match backend {
+            Backend::Apt | Backend::AptGet => self.map_to_apt_name(package),
+            Backend::Yum => self.map_to_yum_name(package),
+            Backend::Dnf => self.map_to_dnf_name(package),
+            Backend::Pacman => self.map_to_pacman_name(package),
+            Backend::Zypper => self.map_to_zypper_name(package),
+            Backend::Homebrew => self.map_to_brew_name_with_version(package, None),
+            _ => package.to_string(),
+        }
+    }
+
     fn map_to_apt_name(&self, package: &str) -> String {
         match package {
             "nodejs" | "node" => "nodejs".to_string(),
@@ -1,3 +1,4 @@
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::{Path, PathBuf};
 use std::process::Command;
@@ -7,8 +8,8 @@ use anyhow::{anyhow, Context, Result};
 use rand::{distributions::Alphanumeric, Rng};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use sha2::{Digest, Sha256};
 use owo_colors::OwoColorize;
+use chrono::{DateTime, Utc};
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct WorkspaceMeta {
@@ -110,22 +111,7 @@ fn read_lines_trimmed(path: &Path) -> Result<Vec<String>> {
 
 /// Compute a stable hash for a snapshot directory from known lockfiles.
 pub fn compute_snapshot_hash(snapshot_dir: &Path) -> Result<String> {
-    let mut hasher = Sha256::new();
-    let lockfiles = [
-        "requirements.txt",
-        "rockspec.lock",
-        "Cargo.lock",
-    ];
-    for lf in lockfiles.iter() {
-        let p = snapshot_dir.join(lf);
-        if p.exists() {
-            hasher.update(lf.as_bytes());
-            let bytes = fs::read(&p).unwrap_or_default();
-            hasher.update(&bytes);
-        }
-    }
-    let digest = hasher.finalize();
-    Ok(format!("{:x}", digest))
+    Ok(crate::core::compute_snapshot_hash(snapshot_dir)?)
 }
 
 #[derive(Debug, Default)]
@@ -158,9 +144,8 @@ pub fn build_change_message(old_snapshot: Option<&Path>, new_snapshot: &Path, ol
         (None, Some(nh)) => lines.push(format!("Switching to generation {}", &nh[..12.min(nh.len())])),
         _ => {}
     }
-    let files = ["requirements.txt", "rockspec.lock", "Cargo.lock"];
     let mut any = false;
-    for f in files.iter() {
+    for f in crate::core::hash::SNAPSHOT_LOCKFILES {
         if let Some(sum) = summarize_lockfile(old_snapshot, new_snapshot, f)? {
             any = true;
             lines.push(format!("{}:", sum.file));
@@ -500,22 +485,6 @@ pub fn create_snapshot_dir(root: &Path, kind: &str) -> Result<PathBuf> {
     Ok(dir)
 }
 
-pub fn seed_lockfiles(snapshot_dir: &Path) -> Result<()> {
-    let req = snapshot_dir.join("requirements.txt");
-    if !req.exists() {
-        fs::write(&req, b"# pinned python deps\n")?;
-    }
-    let rock = snapshot_dir.join("rockspec.lock");
-    if !rock.exists() {
-        fs::write(&rock, b"# pinned luarocks deps\n")?;
-    }
-    let cargo_lock = snapshot_dir.join("Cargo.lock");
-    if !cargo_lock.exists() {
-        fs::write(&cargo_lock, b"# pinned cargo lock placeholder\n")?;
-    }
-    Ok(())
-}
-
 pub fn resolve_stable_snapshot(root: &Path, name: &str) -> Result<PathBuf> {
     let stable_alias = root.join("links").join(format!("{}-stable", name));
     if !stable_alias.exists() {
@@ -531,7 +500,7 @@ pub fn resolve_stable_snapshot(root: &Path, name: &str) -> Result<PathBuf> {
 }
 
 pub fn copy_lockfiles(from: &Path, to: &Path) -> Result<()> {
-    for fname in ["requirements.txt", "rockspec.lock", "Cargo.lock"] {
+    for fname in crate::core::hash::SNAPSHOT_LOCKFILES {
         let src = from.join(fname);
         let dst = to.join(fname);
         if src.exists() {
@@ -541,22 +510,165 @@ pub fn copy_lockfiles(from: &Path, to: &Path) -> Result<()> {
     Ok(())
 }
 
-pub fn find_latest_temp_alias(root: &Path, name: &str) -> Result<Option<String>> {
-    let prefix = format!("{}-temp-", name);
-    let mut temps: Vec<(String, PathBuf)> = vec![];
-    for entry in fs::read_dir(root.join("links"))? {
+/// The project directory whose lockfiles `name`'s snapshots track: the one
+/// bound with `sfc bind`, else the container's own `src/`, if it exists
+pub fn container_project_dir(root: &Path, name: &str) -> Result<Option<PathBuf>> {
+    let dir = match crate::core::project_dir(root, name)? {
+        Some(bound) => bound,
+        None => root.join("containers").join(name).join("src"),
+    };
+    Ok(dir.is_dir().then_some(dir))
+}
+
+/// Capture `name`'s project lockfiles into a snapshot. Leaves the snapshot
+/// alone when there is no project directory.
+pub fn capture_project_lockfiles(root: &Path, name: &str, snapshot_dir: &Path) -> Result<Vec<String>> {
+    match container_project_dir(root, name)? {
+        Some(dir) => Ok(crate::core::capture_lockfiles(&dir, snapshot_dir)?),
+        None => Ok(Vec::new()),
+    }
+}
+
+/// What capturing `name`'s project lockfiles would make of a snapshot, staged
+/// in a scratch copy of its top-level files so the snapshot itself is left
+/// untouched. The caller removes the returned directory.
+pub fn preview_project_lockfiles(root: &Path, name: &str, snapshot_dir: &Path) -> Result<(PathBuf, Vec<String>)> {
+    let preview = root.join(".sfc").join("cache").join("previews")
+        .join(format!("{}-{}", name, std::process::id()));
+    if preview.exists() {
+        fs::remove_dir_all(&preview).with_context(|| format!("remove {}", preview.display()))?;
+    }
+    fs::create_dir_all(&preview).with_context(|| format!("create {}", preview.display()))?;
+    let staged = (|| -> Result<Vec<String>> {
+        for entry in fs::read_dir(snapshot_dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                fs::copy(entry.path(), preview.join(entry.file_name()))
+                    .with_context(|| format!("copy {}", entry.path().display()))?;
+            }
+        }
+        capture_project_lockfiles(root, name, &preview)
+    })();
+    match staged {
+        Ok(captured) => Ok((preview, captured)),
+        Err(e) => {
+            let _ = fs::remove_dir_all(&preview);
+            Err(e)
+        }
+    }
+}
+
+/// A temp snapshot, linked as `links/<container>-temp-<name>`
+#[derive(Debug, Clone)]
+pub struct TempSnapshot {
+    pub name: String,
+    pub alias: String,
+    /// The store directory the alias points at
+    pub path: PathBuf,
+    pub created: Option<DateTime<Utc>>,
+}
+
+pub fn temp_alias(container: &str, name: &str) -> String {
+    format!("{}-temp-{}", container, name)
+}
+
+/// `container`'s temps, oldest first
+pub fn list_temps(root: &Path, container: &str) -> Result<Vec<TempSnapshot>> {
+    let prefix = temp_alias(container, "");
+    let links = root.join("links");
+    if !links.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut temps = Vec::new();
+    for entry in fs::read_dir(&links)? {
         let entry = entry?;
-        let fname = entry.file_name().to_string_lossy().to_string();
-        if fname.starts_with(&prefix) && entry.path().is_symlink() {
-            temps.push((fname, entry.path()));
+        let alias = entry.file_name().to_string_lossy().to_string();
+        let Some(name) = alias.strip_prefix(&prefix) else { continue };
+        let Ok(path) = entry.path().canonicalize() else { continue };
+        if !entry.path().is_symlink() {
+            continue;
+        }
+        // The manifest knows when the temp was made; older temps only have the link's mtime
+        let created = crate::core::SnapshotMetadata::load(&path)
+            .ok()
+            .flatten()
+            .and_then(|m| m.created_at)
+            .or_else(|| fs::symlink_metadata(entry.path()).and_then(|m| m.modified()).ok().map(DateTime::<Utc>::from));
+        temps.push(TempSnapshot { name: name.to_string(), alias, path, created });
+    }
+    temps.sort_by(|a, b| a.created.cmp(&b.created).then_with(|| a.name.cmp(&b.name)));
+    Ok(temps)
+}
+
+pub fn find_latest_temp_alias(root: &Path, name: &str) -> Result<Option<String>> {
+    Ok(list_temps(root, name)?.pop().map(|t| t.alias))
+}
+
+/// The temp `given` names (by name or full alias). Without a name the
+/// container must have exactly one temp, so the wrong experiment is never
+/// picked by accident.
+pub fn resolve_temp(root: &Path, container: &str, given: Option<&str>) -> Result<TempSnapshot> {
+    let mut temps = list_temps(root, container)?;
+    let names = || temps.iter().map(|t| t.name.as_str()).collect::<Vec<_>>().join(", ");
+    match given {
+        Some(given) => match temps.iter().position(|t| t.name == given || t.alias == given) {
+            Some(i) => Ok(temps.swap_remove(i)),
+            None if temps.is_empty() => Err(anyhow!("no temp '{}' for {} (it has no temps)", given, container)),
+            None => Err(anyhow!("no temp '{}' for {}; its temps are: {}", given, container, names())),
+        },
+        None => match temps.len() {
+            0 => Err(anyhow!("no temp snapshots for {}", container)),
+            1 => Ok(temps.remove(0)),
+            _ => Err(anyhow!("{} has several temps ({}); say which one", container, names())),
+        },
+    }
+}
+
+/// What a shell inside a temp gets on top of its container's environment
+#[derive(Debug, Default)]
+pub struct TempShellEnv {
+    pub env: HashMap<String, String>,
+    /// Prepended to PATH, first wins
+    pub bins: Vec<PathBuf>,
+    /// Toolchain versions the temp pins
+    pub toolchains: BTreeMap<String, String>,
+    /// Pinned toolchains that aren't installed in the shared toolchain homes
+    pub missing: Vec<String>,
+}
+
+/// Point the shared toolchain homes at `temp`, pinned to the versions it
+/// recorded: its Volta node image goes first on PATH and `RUSTUP_TOOLCHAIN`
+/// selects its rust
+pub fn temp_shell_env(root: &Path, container: &str, temp: &TempSnapshot) -> Result<TempShellEnv> {
+    let (envs, tc_root) = build_toolchain_env_for_workspace(root);
+    let mut shell = TempShellEnv::default();
+    shell.env.extend(envs.into_iter().filter(|(k, _)| *k != "PATH").map(|(k, v)| (k.to_string(), v)));
+    shell.env.insert("SFC_TEMP".to_string(), temp.name.clone());
+    shell.env.insert("SFC_SNAPSHOT".to_string(), temp.path.to_string_lossy().to_string());
+    shell.env.insert("SFC_TEMP_ALIAS".to_string(), temp_alias(container, &temp.name));
+
+    shell.toolchains = crate::core::diff::SnapshotContents::load(&temp.path)?.toolchains;
+    if let Some(version) = shell.toolchains.get("node") {
+        let image = tc_root.join("volta/tools/image/node").join(version).join("bin");
+        if image.is_dir() {
+            shell.bins.push(image);
+        } else {
+            shell.missing.push(format!("node@{}", version));
         }
     }
-    temps.sort_by(|a, b| {
-        let am = a.1.metadata().and_then(|m| m.modified()).ok();
-        let bm = b.1.metadata().and_then(|m| m.modified()).ok();
-        bm.cmp(&am)
-    });
-    Ok(temps.first().map(|(s, _)| s.clone()))
+    if let Some(version) = shell.toolchains.get("rust") {
+        shell.env.insert("RUSTUP_TOOLCHAIN".to_string(), version.clone());
+        // rustup names toolchain dirs `<version>-<host triple>`
+        let installed = fs::read_dir(tc_root.join("rustup/toolchains"))
+            .map(|entries| entries.flatten().any(|e| e.file_name().to_string_lossy().starts_with(version.as_str())))
+            .unwrap_or(false);
+        if !installed {
+            shell.missing.push(format!("rust@{}", version));
+        }
+    }
+    shell.bins.push(tc_root.join("volta/bin"));
+    shell.bins.push(tc_root.join("cargo/bin"));
+    Ok(shell)
 }
 
 pub fn try_remove_store_if_orphan(root: &Path, rel: &Path) -> Result<()> {
@@ -585,33 +697,7 @@ pub fn try_remove_store_if_orphan(root: &Path, rel: &Path) -> Result<()> {
 
 // ==== NEW FEATURES: System Binary Switching, Snapshots, and Sharing ====
 
-use chrono::{DateTime, Utc};
-use std::collections::HashMap;
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SnapshotInfo {
-    pub hash: String,
-    pub timestamp: DateTime<Utc>,
-    pub description: String,
-    pub is_active: bool,
-    pub packages: Vec<PackageInfo>,
-    pub toolchains: HashMap<String, String>,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PackageInfo {
-    pub name: String,
-    pub version: Option<String>,
-    pub source: String,
-}
-
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ShareInfo {
-    pub packages: Vec<PackageInfo>,
-    pub toolchains: HashMap<String, String>,
-    pub hash: String,
-    pub timestamp: DateTime<Utc>,
-}
+pub use crate::core::SnapshotInfo;
 
 /// Switch system binaries to use container binaries
 pub fn switch_system_binaries(container_bin: &Path, force: bool) -> Result<()> {
@@ -691,40 +777,7 @@ pub fn restore_system_binaries() -> Result<()> {
 
 /// List all snapshots for a container
 pub fn list_container_snapshots(workspace: &Path, container_name: &str) -> Result<Vec<SnapshotInfo>> {
-    let mut snapshots = Vec::new();
-    let store_dir = workspace.join("store");
-    let links_dir = workspace.join("links");
-    
-    // Get current active snapshot
-    let stable_link = links_dir.join(format!("{}-stable", container_name));
-    let current_hash = if stable_link.exists() {
-        get_snapshot_hash_from_link(&stable_link)?
-    } else {
-        String::new()
-    };
-    
-    // Scan store directory for snapshots
-    if let Ok(entries) = fs::read_dir(&store_dir) {
-        for entry in entries.flatten() {
-            if entry.file_type()?.is_dir() {
-                let dir_name = entry.file_name().to_string_lossy().to_string();
-                if dir_name.contains("-snapshot-") {
-                    let snapshot_path = entry.path();
-                    let hash = compute_snapshot_hash(&snapshot_path)?;
-                    
-                    // Check if this snapshot belongs to this container by looking for links
-                    if let Some(snapshot_info) = create_snapshot_info(workspace, container_name, &hash, &snapshot_path, &current_hash)? {
-                        snapshots.push(snapshot_info);
-                    }
-                }
-            }
-        }
-    }
-    
-    // Sort by timestamp (newest first)
-    snapshots.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-    
-    Ok(snapshots)
+    Ok(crate::core::SnapshotManager::new(workspace).list_container_snapshots(container_name)?)
 }
 
 /// Get the current snapshot hash for a container
@@ -737,31 +790,6 @@ pub fn get_current_snapshot_hash(workspace: &Path, container_name: &str) -> Resu
     get_snapshot_hash_from_link(&stable_link)
 }
 
-/// Generate sharing information for a snapshot
-pub fn generate_share_info(workspace: &Path, container_name: &str, hash: &str) -> Result<ShareInfo> {
-    // Find the snapshot directory
-    let snapshot_path = find_snapshot_by_hash(workspace, hash)?;
-    
-    // Load container configuration
-    let container_config_path = workspace.join(".sfc").join("containers").join(format!("{}.toml", container_name));
-    let packages = if container_config_path.exists() {
-        let config_content = fs::read_to_string(&container_config_path)?;
-        parse_packages_from_config(&config_content)?
-    } else {
-        Vec::new()
-    };
-    
-    // Get toolchain information (simplified)
-    let toolchains = get_snapshot_toolchains(&snapshot_path)?;
-    
-    Ok(ShareInfo {
-        packages,
-        toolchains,
-        hash: hash.to_string(),
-        timestamp: Utc::now(),
-    })
-}
-
 /// Delete a specific snapshot
 pub fn delete_snapshot(workspace: &Path, container_name: &str, hash: &str) -> Result<()> {
     let store_dir = workspace.join("store");
@@ -807,128 +835,33 @@ fn get_snapshot_hash_from_link(link_path: &Path) -> Result<String> {
     compute_snapshot_hash(&abs_target)
 }
 
-fn create_snapshot_info(
-    workspace: &Path, 
-    container_name: &str, 
-    hash: &str, 
-    snapshot_path: &Path,
-    current_hash: &str
-) -> Result<Option<SnapshotInfo>> {
-    let links_dir = workspace.join("links");
-    let container_prefix = format!("{}-", container_name);
-    
-    // Check if any link points to this snapshot
-    let mut found_link = false;
-    if let Ok(entries) = fs::read_dir(&links_dir) {
-        for entry in entries.flatten() {
-            let link_name = entry.file_name().to_string_lossy().to_string();
-            if link_name.starts_with(&container_prefix) && entry.path().is_symlink() {
-                if let Ok(target) = fs::read_link(entry.path()) {
-                    let abs_target = entry.path().parent().unwrap().join(target);
-                    if let Ok(canonical) = abs_target.canonicalize() {
-                        if canonical == snapshot_path {
-                            found_link = true;
-                            break;
-                        }
-                    }
-                }
-            }
-        }
-    }
-    
-    if !found_link {
-        return Ok(None);
-    }
-    
-    // Get timestamp from directory metadata
-    let metadata = fs::metadata(snapshot_path)?;
-    let timestamp = metadata.modified()
-        .unwrap_or(std::time::SystemTime::UNIX_EPOCH)
-        .into();
-    
-    // Generate description
-    let description = if hash == current_hash {
-        "current stable".to_string()
-    } else {
-        "snapshot".to_string()
-    };
-    
-    Ok(Some(SnapshotInfo {
-        hash: hash.to_string(),
-        timestamp,
-        description,
-        is_active: hash == current_hash,
-        packages: Vec::new(), // Would be populated from container config
-        toolchains: HashMap::new(), // Would be populated from snapshot
-    }))
-}
-
 pub fn find_snapshot_by_hash(workspace: &Path, hash: &str) -> Result<PathBuf> {
-    let store_dir = workspace.join("store");
-    
-    if let Ok(entries) = fs::read_dir(&store_dir) {
-        for entry in entries.flatten() {
-            if entry.file_type()?.is_dir() {
-                let snapshot_path = entry.path();
-                let snapshot_hash = compute_snapshot_hash(&snapshot_path)?;
-                
-                if snapshot_hash.starts_with(hash) {
-                    return Ok(snapshot_path);
-                }
-            }
-        }
-    }
-    
-    Err(anyhow!("Snapshot with hash '{}' not found", hash))
+    Ok(crate::core::SnapshotManager::new(workspace).find_snapshot_by_hash(hash)?)
 }
 
-fn parse_packages_from_config(config_content: &str) -> Result<Vec<PackageInfo>> {
-    // Simple TOML parsing for packages
-    // In a real implementation, this would use the proper container config structures
-    let mut packages = Vec::new();
-    
-    for line in config_content.lines() {
-        if line.trim().starts_with("name = ") {
-            if let Some(name) = extract_toml_string_value(line) {
-                packages.push(PackageInfo {
-                    name,
-                    version: None,
-                    source: "nixpkgs".to_string(),
-                });
-            }
-        }
-    }
-    
-    Ok(packages)
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-fn extract_toml_string_value(line: &str) -> Option<String> {
-    if let Some(start) = line.find('"') {
-        if let Some(end) = line.rfind('"') {
-            if start < end {
-                return Some(line[start + 1..end].to_string());
-            }
+    #[test]
+    fn test_lockfiles_come_from_src_unless_bound() {
+        let ws = tempfile::tempdir().unwrap();
+        let root = ws.path();
+        let (snapshot, project) = (root.join("snapshot"), root.join("project"));
+        let src = root.join("containers").join("web").join("src");
+        for dir in [&snapshot, &project, &src] {
+            fs::create_dir_all(dir).unwrap();
         }
-    }
-    None
-}
+        assert!(capture_project_lockfiles(root, "api", &snapshot).unwrap().is_empty());
 
-fn get_snapshot_toolchains(snapshot_path: &Path) -> Result<HashMap<String, String>> {
-    let mut toolchains = HashMap::new();
-    
-    // Check for common toolchain indicators
-    if snapshot_path.join("node_version").exists() {
-        if let Ok(version) = fs::read_to_string(snapshot_path.join("node_version")) {
-            toolchains.insert("node".to_string(), version.trim().to_string());
-        }
-    }
-    
-    if snapshot_path.join("rust_version").exists() {
-        if let Ok(version) = fs::read_to_string(snapshot_path.join("rust_version")) {
-            toolchains.insert("rust".to_string(), version.trim().to_string());
-        }
+        fs::write(src.join("Gemfile.lock"), "rails (7.1)\n").unwrap();
+        assert_eq!(capture_project_lockfiles(root, "web", &snapshot).unwrap(), vec!["Gemfile.lock"]);
+        assert_eq!(fs::read_to_string(snapshot.join("Gemfile.lock")).unwrap(), "rails (7.1)\n");
+
+        fs::write(project.join("Gemfile.lock"), "rails (7.2)\n").unwrap();
+        crate::core::bind_project(root, "web", &project).unwrap();
+        assert_eq!(container_project_dir(root, "web").unwrap(), Some(project.canonicalize().unwrap()));
+        capture_project_lockfiles(root, "web", &snapshot).unwrap();
+        assert_eq!(fs::read_to_string(snapshot.join("Gemfile.lock")).unwrap(), "rails (7.2)\n");
     }
-    
-    Ok(toolchains)
 }
-
@@ -0,0 +1,320 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::core::{compute_snapshot_hash, read_tar, safe_path, tree_entries, unpack_entries, EntryKind, TarEntry, TarWriter};
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::sharing::signing::{canonical_json, KeyPair, ShareSignature};
+use crate::sharing::ShareInfo;
+
+/// Bundle layout version written to the manifest
+pub const BUNDLE_FORMAT: u32 = 1;
+
+/// First entry of every bundle
+pub const MANIFEST_PATH: &str = "sfc-bundle.json";
+const SHARE_PATH: &str = "share.json";
+/// Snapshot tree (lockfiles, toolchain version files)
+pub const SNAPSHOT_DIR: &str = "snapshot";
+/// The container's flake.nix and flake.lock
+pub const FLAKE_DIR: &str = "flake";
+/// Installed package artifacts, only with `--artifacts`
+pub const PACKAGES_DIR: &str = "packages";
+
+/// Table of contents of a bundle, checked entry by entry on import
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    pub format: u32,
+    pub container: String,
+    /// Content hash of the snapshot under `snapshot/`
+    pub hash: String,
+    pub created_at: DateTime<Utc>,
+    /// sha256 of every regular file, by path in the bundle
+    pub files: BTreeMap<String, String>,
+    /// Symlink targets, by path in the bundle
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub symlinks: BTreeMap<String, String>,
+    /// Signature over the rest of the manifest, which in turn pins every file
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ShareSignature>,
+}
+
+/// A share bundle (`.sfcb`): an uncompressed tar holding the manifest, the
+/// share JSON, the snapshot tree, the container's flake lockfiles and
+/// optionally its installed packages
+pub struct Bundle {
+    pub manifest: BundleManifest,
+    pub share: ShareInfo,
+    entries: Vec<TarEntry>,
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn invalid(reason: impl Into<String>) -> SfcError {
+    SfcError::Validation {
+        field: "bundle".to_string(),
+        value: String::new(),
+        reason: reason.into(),
+    }
+}
+
+impl BundleManifest {
+    fn describe(container: &str, hash: &str, created_at: DateTime<Utc>, entries: &[TarEntry]) -> Self {
+        let mut files = BTreeMap::new();
+        let mut symlinks = BTreeMap::new();
+        for entry in entries {
+            match &entry.kind {
+                EntryKind::File(data) => {
+                    files.insert(entry.path.clone(), sha256_hex(data));
+                }
+                EntryKind::Symlink(target) => {
+                    symlinks.insert(entry.path.clone(), target.clone());
+                }
+                EntryKind::Dir => {}
+            }
+        }
+        Self {
+            format: BUNDLE_FORMAT,
+            container: container.to_string(),
+            hash: hash.to_string(),
+            created_at,
+            files,
+            symlinks,
+            signature: None,
+        }
+    }
+
+    /// The bytes a signature covers: the canonical manifest without its signature
+    pub fn signed_bytes(&self) -> Result<Vec<u8>> {
+        canonical_json(&Self { signature: None, ..self.clone() })
+    }
+}
+
+impl Bundle {
+    /// Collect a bundle for `share`, whose snapshot lives in `snapshot_dir`
+    /// and whose container directory is `container_dir`
+    pub fn create(share: ShareInfo, snapshot_dir: &Path, container_dir: &Path, artifacts: bool) -> Result<Self> {
+        let hash = compute_snapshot_hash(snapshot_dir)?;
+        let share_json = serde_json::to_string_pretty(&share).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize share info: {}", e),
+            source: None,
+        })?;
+
+        let mut entries = vec![TarEntry {
+            path: SHARE_PATH.to_string(),
+            mode: 0o644,
+            kind: EntryKind::File(share_json.into_bytes()),
+        }];
+        entries.extend(tree_entries(snapshot_dir, SNAPSHOT_DIR)?);
+        let flake_files: Vec<_> = ["flake.nix", "flake.lock"]
+            .iter()
+            .map(|name| container_dir.join(name))
+            .filter(|path| path.is_file())
+            .collect();
+        if !flake_files.is_empty() {
+            entries.push(TarEntry { path: FLAKE_DIR.to_string(), mode: 0o755, kind: EntryKind::Dir });
+            for path in flake_files {
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                entries.extend(tree_entries(&path, &format!("{}/{}", FLAKE_DIR, name))?);
+            }
+        }
+        let packages = container_dir.join("packages");
+        if artifacts && packages.is_dir() {
+            entries.extend(tree_entries(&packages, PACKAGES_DIR)?);
+        }
+
+        let manifest = BundleManifest::describe(&share.container_name, &hash, Utc::now(), &entries);
+        Ok(Self { manifest, share, entries })
+    }
+
+    /// Sign the manifest with `key`
+    pub fn sign(&mut self, key: &KeyPair) -> Result<()> {
+        self.manifest.signature = Some(key.sign(&self.manifest.signed_bytes()?));
+        Ok(())
+    }
+
+    /// Write the bundle to `path`; the same contents always produce the same bytes
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let manifest = serde_json::to_vec_pretty(&self.manifest).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize bundle manifest: {}", e),
+            source: None,
+        })?;
+        let mtime = self.manifest.created_at.timestamp().max(0) as u64;
+        let mut tar = TarWriter::new(Vec::new()).with_mtime(mtime);
+        tar.append_file(MANIFEST_PATH, 0o644, &manifest)
+            .with_io_context(|| "archiving bundle manifest".to_string())?;
+        for entry in &self.entries {
+            tar.append_entry(entry)
+                .with_io_context(|| format!("archiving {}", entry.path))?;
+        }
+        let bytes = tar.finish().with_io_context(|| "finishing bundle".to_string())?;
+        fs::write(path, bytes).with_io_context(|| format!("writing bundle {}", path.display()))
+    }
+
+    /// Read a bundle and check every entry against its manifest
+    pub fn read(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_io_context(|| format!("reading bundle {}", path.display()))?;
+        Self::parse(&data)
+    }
+
+    /// Parse bundle bytes and check every entry against the manifest
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut entries = read_tar(data)?;
+        if entries.first().map(|e| e.path.as_str()) != Some(MANIFEST_PATH) {
+            return Err(invalid(format!("not an sfc bundle (no {})", MANIFEST_PATH)));
+        }
+        let EntryKind::File(raw) = entries.remove(0).kind else {
+            return Err(invalid(format!("{} is not a file", MANIFEST_PATH)));
+        };
+        let manifest: BundleManifest = serde_json::from_slice(&raw)
+            .map_err(|e| invalid(format!("invalid manifest: {}", e)))?;
+        if manifest.format > BUNDLE_FORMAT {
+            return Err(invalid(format!(
+                "bundle format {} is newer than this sfc supports ({})",
+                manifest.format, BUNDLE_FORMAT
+            )));
+        }
+
+        let described = BundleManifest::describe(&manifest.container, &manifest.hash, manifest.created_at, &entries);
+        for entry in &entries {
+            safe_path(&entry.path)?;
+            let top = entry.path.split('/').next().unwrap_or_default();
+            if ![SHARE_PATH, SNAPSHOT_DIR, FLAKE_DIR, PACKAGES_DIR].contains(&top) {
+                return Err(invalid(format!("unexpected entry '{}'", entry.path)));
+            }
+        }
+        for (path, sha) in &manifest.files {
+            match described.files.get(path) {
+                Some(actual) if actual == sha => {}
+                Some(_) => return Err(invalid(format!("{} does not match its checksum", path))),
+                None => return Err(invalid(format!("{} is listed in the manifest but missing", path))),
+            }
+        }
+        if let Some(extra) = described.files.keys().find(|path| !manifest.files.contains_key(*path)) {
+            return Err(invalid(format!("{} is not listed in the manifest", extra)));
+        }
+        if described.symlinks != manifest.symlinks {
+            return Err(invalid("symlinks differ from the manifest"));
+        }
+
+        let share = entries
+            .iter()
+            .find_map(|entry| match (&entry.kind, entry.path.as_str()) {
+                (EntryKind::File(data), SHARE_PATH) => Some(data),
+                _ => None,
+            })
+            .ok_or_else(|| invalid(format!("missing {}", SHARE_PATH)))?;
+        let share: ShareInfo = serde_json::from_slice(share)
+            .map_err(|e| invalid(format!("invalid {}: {}", SHARE_PATH, e)))?;
+        if share.hash != manifest.hash {
+            return Err(invalid("share hash does not match the manifest"));
+        }
+
+        Ok(Self { manifest, share, entries })
+    }
+
+    /// Whether the bundle carries anything under `dir`
+    pub fn contains(&self, dir: &str) -> bool {
+        let prefix = format!("{}/", dir);
+        self.entries.iter().any(|e| e.path.starts_with(&prefix))
+    }
+
+    /// Unpack the entries under `dir` into `dest`, keeping modes and
+    /// symlinks. Returns the number of files written.
+    pub fn unpack(&self, dir: &str, dest: &Path) -> Result<usize> {
+        unpack_entries(&self.entries, dir, dest)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::os::unix::fs::{symlink, PermissionsExt};
+    use crate::sharing::snapshot::ShareMetadata;
+
+    fn share(hash: &str) -> ShareInfo {
+        ShareInfo {
+            hash: hash.to_string(),
+            container_name: "web".to_string(),
+            description: "snapshot".to_string(),
+            timestamp: Utc::now(),
+            packages: Vec::new(),
+            toolchains: Default::default(),
+            environment: Default::default(),
+            nixpkgs: None,
+            metadata: ShareMetadata {
+                sfc_version: env!("CARGO_PKG_VERSION").to_string(),
+                platform_os: std::env::consts::OS.to_string(),
+                platform_arch: std::env::consts::ARCH.to_string(),
+                created_by: "test".to_string(),
+                shared_at: Utc::now(),
+            },
+            signature: None,
+        }
+    }
+
+    /// Bundle a one-lockfile snapshot, returning its hash and bytes
+    pub(crate) fn sample_bundle(dir: &Path) -> (String, Vec<u8>) {
+        let snapshot = dir.join("store/sample-snapshot-000");
+        fs::create_dir_all(&snapshot).unwrap();
+        fs::write(snapshot.join("Cargo.lock"), "# sample\n").unwrap();
+        let hash = compute_snapshot_hash(&snapshot).unwrap();
+        let path = dir.join("sample.sfcb");
+        Bundle::create(share(&hash), &snapshot, &dir.join("containers/sample"), false).unwrap().write(&path).unwrap();
+        (hash, fs::read(path).unwrap())
+    }
+
+    #[test]
+    fn test_bundle_round_trip_and_tamper_detection() {
+        let dir = tempfile::tempdir().unwrap();
+        let snapshot = dir.path().join("store/abc-snapshot-000");
+        fs::create_dir_all(&snapshot).unwrap();
+        fs::write(snapshot.join("Cargo.lock"), "# lock\n").unwrap();
+        fs::write(snapshot.join("node_version"), "20\n").unwrap();
+        symlink("Cargo.lock", snapshot.join("lock-link")).unwrap();
+        let container = dir.path().join("containers/web");
+        fs::create_dir_all(container.join("packages/bin")).unwrap();
+        fs::write(container.join("flake.nix"), "{ }\n").unwrap();
+        fs::write(container.join("packages/bin/tool"), "#!/bin/sh\n").unwrap();
+        fs::set_permissions(container.join("packages/bin/tool"), fs::Permissions::from_mode(0o755)).unwrap();
+
+        let hash = compute_snapshot_hash(&snapshot).unwrap();
+        let bundle = Bundle::create(share(&hash), &snapshot, &container, true).unwrap();
+        let path = dir.path().join("web.sfcb");
+        bundle.write(&path).unwrap();
+
+        let read = Bundle::read(&path).unwrap();
+        assert_eq!(read.manifest, bundle.manifest);
+        assert!(read.contains(FLAKE_DIR) && read.contains(PACKAGES_DIR));
+
+        let out = dir.path().join("out");
+        assert_eq!(read.unpack(SNAPSHOT_DIR, &out).unwrap(), 2);
+        assert_eq!(compute_snapshot_hash(&out).unwrap(), hash);
+        assert_eq!(fs::read_link(out.join("lock-link")).unwrap(), Path::new("Cargo.lock"));
+        read.unpack(PACKAGES_DIR, &out.join("packages")).unwrap();
+        let mode = fs::metadata(out.join("packages/bin/tool")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o755);
+
+        // Flip a byte of the lockfile's contents: the checksum catches it
+        let mut bytes = fs::read(&path).unwrap();
+        let at = bytes.windows(7).position(|w| w == b"# lock\n").unwrap();
+        bytes[at + 2] = b'L';
+        fs::write(&path, &bytes).unwrap();
+        let err = Bundle::read(&path).err().unwrap().to_string();
+        assert!(err.contains("snapshot/Cargo.lock"), "{}", err);
+    }
+
+    #[test]
+    fn test_safe_path() {
+        assert!(safe_path("snapshot/Cargo.lock").is_ok());
+        assert!(safe_path("../etc/passwd").is_err());
+        assert!(safe_path("/etc/passwd").is_err());
+        assert!(safe_path("./snapshot").is_err());
+        assert!(safe_path("").is_err());
+    }
+}
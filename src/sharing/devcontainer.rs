@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::container::{ContainerConfig, PackageSource, Removals};
+use crate::error::{Result, SfcError};
+use crate::sharing::DockerExporter;
+
+const FEATURES: &str = "ghcr.io/devcontainers/features";
+
+/// Files for a `.devcontainer/` directory
+#[derive(Debug, Clone)]
+pub struct DevcontainerExport {
+    pub devcontainer_json: String,
+    pub dockerfile: String,
+    /// Name of the container config written next to them
+    pub config_file: String,
+    pub config_toml: String,
+    pub notes: Vec<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct DevcontainerJson {
+    name: String,
+    build: Build,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    features: BTreeMap<String, BTreeMap<String, String>>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    container_env: BTreeMap<String, String>,
+    post_create_command: String,
+}
+
+#[derive(Serialize)]
+struct Build {
+    dockerfile: String,
+}
+
+/// Dev container feature (and its version option) covering a package
+fn feature_for(package: &str) -> Option<(&'static str, &'static str)> {
+    let feature = match package {
+        "node" | "nodejs" => ("node:1", "version"),
+        "python" | "python3" => ("python:1", "version"),
+        "go" | "golang" => ("go:1", "version"),
+        "java" | "jdk" | "openjdk" => ("java:1", "version"),
+        "ruby" => ("ruby:1", "version"),
+        "rust" | "rustc" | "cargo" => ("rust:1", "version"),
+        "php" => ("php:1", "version"),
+        "dotnet" => ("dotnet:2", "version"),
+        "terraform" => ("terraform:1", "version"),
+        "kubectl" => ("kubectl-helm-minikube:1", "version"),
+        "helm" => ("kubectl-helm-minikube:1", "helm"),
+        "docker" => ("docker-in-docker:2", "version"),
+        _ => return None,
+    };
+    Some(feature)
+}
+
+/// Builds `.devcontainer/` contents with the container's TOML as the source of truth
+pub struct DevcontainerExporter {
+    workspace: PathBuf,
+}
+
+impl DevcontainerExporter {
+    pub fn new<P: AsRef<Path>>(workspace: P) -> Self {
+        Self { workspace: workspace.as_ref().to_path_buf() }
+    }
+
+    /// Toolchains and well-known packages become features; the remaining
+    /// packages go into the Dockerfile and `sfc apply` records the rest.
+    pub fn export(&self, config: &ContainerConfig, base: Option<&str>, snapshot: Option<&str>) -> Result<DevcontainerExport> {
+        let docker = DockerExporter::new(&self.workspace);
+        let mut notes = Vec::new();
+        let mut features: BTreeMap<String, BTreeMap<String, String>> = BTreeMap::new();
+        let mut add_feature = |id: &str, option: &str, version: &str| {
+            features
+                .entry(format!("{}/{}", FEATURES, id))
+                .or_default()
+                .insert(option.to_string(), version.to_string());
+        };
+
+        for (tool, version) in &config.toolchains {
+            match tool.as_str() {
+                // The rust feature takes "latest" or a version, not a channel name
+                "rust" if version == "stable" => add_feature("rust:1", "version", "latest"),
+                "node" | "rust" => add_feature(&format!("{}:1", tool), "version", version),
+                "npm" => notes.push(format!("npm@{}: the node feature ships the npm bundled with node", version)),
+                other => notes.push(format!("toolchain '{}': no matching feature, left out", other)),
+            }
+        }
+
+        let mut image = config.clone();
+        image.toolchains.clear();
+        image.environment.clear();
+        image.packages.retain(|pkg| {
+            let Some((id, option)) = feature_for(&pkg.name).filter(|_| matches!(pkg.source, PackageSource::Nixpkgs)) else {
+                return true;
+            };
+            let version = pkg.version.as_deref().unwrap_or("latest");
+            // An explicit toolchain version wins over a package of the same tool
+            let key = format!("{}/{}", FEATURES, id);
+            if !features.get(&key).is_some_and(|options| options.contains_key(option)) {
+                features.entry(key).or_default().insert(option.to_string(), version.to_string());
+            }
+            false
+        });
+
+        let dockerfile = docker.dockerfile(&image, base, snapshot);
+        notes.extend(dockerfile.notes);
+
+        let (env, skipped) = docker.portable_env(config);
+        notes.extend(skipped);
+        let container_env: BTreeMap<String, String> = env.into_iter().collect();
+
+        // The config replayed inside the dev container: self-contained, no host paths
+        let mut portable = config.clone();
+        portable.extends.clear();
+        portable.remove = Removals::default();
+        portable.environment = container_env.clone().into_iter().collect();
+        let config_file = format!("{}.sfc.toml", config.name);
+        let config_toml = toml::to_string_pretty(&portable).map_err(|e| SfcError::Config {
+            message: format!("Failed to serialize container config: {}", e),
+            path: None,
+        })?;
+
+        let json = DevcontainerJson {
+            name: config.name.clone(),
+            build: Build { dockerfile: "Dockerfile".to_string() },
+            features,
+            container_env,
+            post_create_command: format!(
+                "if command -v sfc >/dev/null 2>&1; then sfc apply .devcontainer/{} --no-install; else echo 'sfc not found; skipping sfc apply'; fi",
+                config_file
+            ),
+        };
+        let devcontainer_json = serde_json::to_string_pretty(&json).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize devcontainer.json: {}", e),
+            source: None,
+        })?;
+
+        Ok(DevcontainerExport {
+            devcontainer_json: devcontainer_json + "\n",
+            dockerfile: dockerfile.dockerfile,
+            config_file,
+            config_toml,
+            notes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSpec;
+
+    #[test]
+    fn test_features_env_and_post_create() {
+        let ws = tempfile::tempdir().unwrap();
+        let mut config = ContainerConfig::new("web".to_string());
+        config.extends.push("base".to_string());
+        config.add_package(PackageSpec::parse("python3@3.12")).unwrap();
+        config.add_package(PackageSpec::parse("jq")).unwrap();
+        config.add_package(PackageSpec::parse("node@18")).unwrap();
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        config.toolchains.insert("rust".to_string(), "stable".to_string());
+        config.environment.insert("NODE_ENV".to_string(), "development".to_string());
+        config.environment.insert("PATH".to_string(), "/host/bin".to_string());
+
+        let export = DevcontainerExporter::new(ws.path()).export(&config, Some("debian:12"), None).unwrap();
+        let json: serde_json::Value = serde_json::from_str(&export.devcontainer_json).unwrap();
+        let features = &json["features"];
+        assert_eq!(features["ghcr.io/devcontainers/features/node:1"]["version"], "20");
+        assert_eq!(features["ghcr.io/devcontainers/features/rust:1"]["version"], "latest");
+        assert_eq!(features["ghcr.io/devcontainers/features/python:1"]["version"], "3.12");
+        assert_eq!(json["containerEnv"], serde_json::json!({ "NODE_ENV": "development" }));
+        assert!(json["postCreateCommand"].as_str().unwrap().contains("sfc apply .devcontainer/web.sfc.toml"));
+
+        assert!(export.dockerfile.contains("install -y --no-install-recommends jq \\"));
+        assert!(!export.dockerfile.contains("python3"));
+        assert!(!export.dockerfile.contains("ENV NODE_ENV"));
+
+        let replayed: ContainerConfig = toml::from_str(&export.config_toml).unwrap();
+        assert!(replayed.extends.is_empty());
+        assert!(!replayed.environment.contains_key("PATH"));
+        assert_eq!(replayed.packages.len(), 3);
+    }
+}
@@ -0,0 +1,353 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::container::{ContainerConfig, PackageSource};
+use crate::core::archive::TarWriter;
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::package::PackageManager;
+use crate::system::platform::{detect_architecture, detect_distro, Architecture, PackageManager as Backend};
+
+/// A generated Dockerfile and whatever didn't make it into it
+#[derive(Debug, Clone)]
+pub struct DockerExport {
+    pub base_image: String,
+    pub dockerfile: String,
+    pub notes: Vec<String>,
+}
+
+/// Builds Docker/OCI artifacts from a container's config and snapshot
+pub struct DockerExporter {
+    workspace: PathBuf,
+}
+
+/// Guess the package manager inside an explicitly chosen base image
+fn backend_for_image(image: &str) -> Backend {
+    let name = image.rsplit('/').next().unwrap_or(image);
+    if name.starts_with("alpine") {
+        Backend::Apk
+    } else if ["fedora", "rockylinux", "almalinux", "centos", "ubi"].iter().any(|d| name.starts_with(d)) {
+        Backend::Dnf
+    } else if name.starts_with("archlinux") {
+        Backend::Pacman
+    } else if image.contains("opensuse") {
+        Backend::Zypper
+    } else {
+        Backend::AptGet
+    }
+}
+
+fn install_command(backend: &Backend, packages: &[String]) -> String {
+    let list = packages.join(" ");
+    match backend {
+        Backend::Apk => format!("apk add --no-cache {}", list),
+        Backend::Dnf => format!("dnf install -y {} \\\n    && dnf clean all", list),
+        Backend::Yum => format!("yum install -y {} \\\n    && yum clean all", list),
+        Backend::Pacman => format!("pacman -Syu --noconfirm {} \\\n    && pacman -Scc --noconfirm", list),
+        Backend::Zypper => format!("zypper --non-interactive install {} \\\n    && zypper clean --all", list),
+        _ => format!(
+            "apt-get update \\\n    && DEBIAN_FRONTEND=noninteractive apt-get install -y --no-install-recommends {} \\\n    && rm -rf /var/lib/apt/lists/*",
+            list
+        ),
+    }
+}
+
+/// Double-quoted Dockerfile value; `$` is escaped so nothing expands at build time
+fn quote(value: &str) -> String {
+    let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('$', "\\$");
+    format!("\"{}\"", escaped)
+}
+
+fn oci_architecture() -> &'static str {
+    match detect_architecture() {
+        Architecture::Aarch64 => "arm64",
+        Architecture::X86 => "386",
+        _ => "amd64",
+    }
+}
+
+fn to_json(value: &serde_json::Value) -> Result<Vec<u8>> {
+    serde_json::to_vec(value).map_err(|e| SfcError::Generic {
+        message: format!("Failed to serialize OCI metadata: {}", e),
+        source: None,
+    })
+}
+
+fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+impl DockerExporter {
+    pub fn new<P: AsRef<Path>>(workspace: P) -> Self {
+        Self { workspace: workspace.as_ref().to_path_buf() }
+    }
+
+    /// Generate a Dockerfile for `config` (normally the effective config).
+    /// Without `base`, the image follows the host distribution.
+    pub fn dockerfile(&self, config: &ContainerConfig, base: Option<&str>, snapshot: Option<&str>) -> DockerExport {
+        let (base_image, backend) = match base {
+            Some(image) => (image.to_string(), backend_for_image(image)),
+            None => match detect_distro() {
+                Some(distro) => (distro.base_image(), distro.package_manager()),
+                None => ("debian:bookworm-slim".to_string(), Backend::AptGet),
+            },
+        };
+        let names = PackageManager::new(self.workspace.clone());
+        let mut notes = Vec::new();
+        let mut out = String::new();
+
+        out.push_str("# syntax=docker/dockerfile:1\n");
+        out.push_str(&format!("# Generated by sfc from container '{}'\n", config.name));
+        out.push_str(&format!("FROM {}\n\n", base_image));
+        out.push_str(&format!("LABEL org.opencontainers.image.title={}", quote(&config.name)));
+        if let Some(hash) = snapshot {
+            out.push_str(&format!(" \\\n      dev.sfc.snapshot={}", quote(hash)));
+        }
+        out.push_str("\n\n");
+
+        // System packages, named the way the image's package manager expects
+        let toolchain = |key: &str| config.toolchains.get(key).filter(|v| !v.is_empty());
+        let wants_volta = toolchain("node").is_some() || toolchain("npm").is_some();
+        let wants_rustup = toolchain("rust").is_some();
+        let mut system = Vec::new();
+        if wants_volta || wants_rustup {
+            system.extend(["ca-certificates".to_string(), "curl".to_string()]);
+            if backend == Backend::Apk {
+                system.push("bash".to_string());
+            }
+        }
+        let mut fetched = Vec::new();
+        for pkg in &config.packages {
+            match &pkg.source {
+                PackageSource::Nixpkgs => {
+                    let name = names.backend_package_name(&backend, &pkg.name);
+                    if let Some(version) = &pkg.version {
+                        notes.push(format!("{}@{}: installed at the distribution's version", pkg.name, version));
+                    }
+                    if !system.contains(&name) {
+                        system.push(name);
+                    }
+                }
+                PackageSource::GitHub { repo, rev } => {
+                    fetched.push(format!("ADD https://github.com/{}.git#{} /opt/sfc/{}", repo, rev, pkg.name));
+                }
+                PackageSource::Url(url) => {
+                    fetched.push(format!("ADD {} /opt/sfc/{}/", url, pkg.name));
+                }
+            }
+        }
+        if !system.is_empty() {
+            out.push_str(&format!("RUN {}\n\n", install_command(&backend, &system)));
+        }
+        if !fetched.is_empty() {
+            out.push_str(&fetched.join("\n"));
+            out.push_str("\n\n");
+        }
+
+        // Toolchains, installed the same way `setup_toolchains` does (Volta, rustup)
+        if wants_volta {
+            let mut installs = Vec::new();
+            if let Some(v) = toolchain("node") {
+                installs.push(format!("node@{}", v));
+            }
+            if let Some(v) = toolchain("npm") {
+                installs.push(format!("npm@{}", v));
+            }
+            out.push_str("ENV VOLTA_HOME=/opt/volta \\\n    PATH=/opt/volta/bin:$PATH\n");
+            out.push_str(&format!(
+                "RUN curl -fsSL https://get.volta.sh | bash -s -- --skip-setup \\\n    && volta install {}\n\n",
+                installs.join(" ")
+            ));
+        }
+        if let Some(rust) = toolchain("rust") {
+            out.push_str("ENV RUSTUP_HOME=/opt/rustup \\\n    CARGO_HOME=/opt/cargo \\\n    PATH=/opt/cargo/bin:$PATH\n");
+            out.push_str(&format!(
+                "RUN curl -fsSL https://sh.rustup.rs | sh -s -- -y --no-modify-path --default-toolchain {}\n\n",
+                rust
+            ));
+        }
+        for key in config.toolchains.keys().filter(|k| !["node", "npm", "rust"].contains(&k.as_str())) {
+            notes.push(format!("toolchain '{}': no installer in sfc, left out", key));
+        }
+
+        let (env, skipped) = self.portable_env(config);
+        notes.extend(skipped);
+        let lines: Vec<String> = env.iter().map(|(key, value)| format!("{}={}", key, quote(value))).collect();
+        if !lines.is_empty() {
+            out.push_str(&format!("ENV {}\n\n", lines.join(" \\\n    ")));
+        }
+
+        let shell = if backend == Backend::Apk { "sh" } else { "bash" };
+        out.push_str("WORKDIR /workspace\n");
+        out.push_str(&format!("CMD [\"{}\"]\n", shell));
+
+        DockerExport { base_image, dockerfile: out, notes }
+    }
+
+    /// Environment that means the same inside an image, sorted by key.
+    /// PATH and anything pointing into the workspace only make sense on this host.
+    pub fn portable_env(&self, config: &ContainerConfig) -> (Vec<(String, String)>, Vec<String>) {
+        let workspace = self.workspace.to_string_lossy();
+        let mut env: Vec<_> = config.environment.iter().collect();
+        env.sort();
+        let mut kept = Vec::new();
+        let mut notes = Vec::new();
+        for (key, value) in env {
+            if key == "PATH" || value.contains(workspace.as_ref()) {
+                notes.push(format!("env {}: refers to host paths, left out", key));
+            } else {
+                kept.push((key.clone(), value.clone()));
+            }
+        }
+        (kept, notes)
+    }
+
+    /// Write an OCI image layout tarball holding the container's `local/`
+    /// tree as a single `/usr/local` layer. Returns the manifest digest.
+    pub fn write_oci_layout(&self, config: &ContainerConfig, snapshot: Option<&str>, out: &Path) -> Result<String> {
+        let local_dir = self.workspace.join("containers").join(&config.name).join("local");
+        let mtime = config.created_at.timestamp().max(0) as u64;
+
+        let mut layer = TarWriter::new(Vec::new()).with_mtime(mtime);
+        layer.append_dir("usr", 0o755).with_io_context(|| "building layer".to_string())?;
+        if local_dir.is_dir() {
+            layer.append_tree(&local_dir, "usr/local")?;
+        } else {
+            layer.append_dir("usr/local", 0o755).with_io_context(|| "building layer".to_string())?;
+        }
+        let layer = layer.finish().with_io_context(|| "building layer".to_string())?;
+        let layer_digest = sha256_hex(&layer);
+
+        // Same filtering as the Dockerfile: host paths mean nothing in the image
+        let (portable, _) = self.portable_env(config);
+        let mut env: Vec<String> = portable.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        env.push("PATH=/usr/local/sbin:/usr/local/bin:/usr/sbin:/usr/bin:/sbin:/bin".to_string());
+        env.sort();
+
+        let mut labels = json!({ "org.opencontainers.image.title": config.name });
+        if let Some(hash) = snapshot {
+            labels["dev.sfc.snapshot"] = json!(hash);
+        }
+        let image_config = to_json(&json!({
+            "created": config.created_at.to_rfc3339(),
+            "architecture": oci_architecture(),
+            "os": "linux",
+            "config": { "Env": env, "Labels": labels },
+            "rootfs": { "type": "layers", "diff_ids": [format!("sha256:{}", layer_digest)] },
+        }))?;
+        let config_digest = sha256_hex(&image_config);
+
+        let manifest = to_json(&json!({
+            "schemaVersion": 2,
+            "mediaType": "application/vnd.oci.image.manifest.v1+json",
+            "config": {
+                "mediaType": "application/vnd.oci.image.config.v1+json",
+                "digest": format!("sha256:{}", config_digest),
+                "size": image_config.len(),
+            },
+            "layers": [{
+                "mediaType": "application/vnd.oci.image.layer.v1.tar",
+                "digest": format!("sha256:{}", layer_digest),
+                "size": layer.len(),
+            }],
+        }))?;
+        let manifest_digest = sha256_hex(&manifest);
+
+        let index = to_json(&json!({
+            "schemaVersion": 2,
+            "manifests": [{
+                "mediaType": "application/vnd.oci.image.manifest.v1+json",
+                "digest": format!("sha256:{}", manifest_digest),
+                "size": manifest.len(),
+                "annotations": { "org.opencontainers.image.ref.name": "latest" },
+            }],
+        }))?;
+
+        let mut blobs = vec![
+            (layer_digest, layer),
+            (config_digest, image_config),
+            (manifest_digest.clone(), manifest),
+        ];
+        blobs.sort_by(|a, b| a.0.cmp(&b.0));
+
+        let file = fs::File::create(out).with_io_context(|| format!("creating {}", out.display()))?;
+        let mut tar = TarWriter::new(std::io::BufWriter::new(file)).with_mtime(mtime);
+        let write = |tar: &mut TarWriter<_>| -> std::io::Result<()> {
+            tar.append_file("oci-layout", 0o644, br#"{"imageLayoutVersion":"1.0.0"}"#)?;
+            tar.append_file("index.json", 0o644, &index)?;
+            tar.append_dir("blobs", 0o755)?;
+            tar.append_dir("blobs/sha256", 0o755)?;
+            for (digest, bytes) in &blobs {
+                tar.append_file(&format!("blobs/sha256/{}", digest), 0o644, bytes)?;
+            }
+            Ok(())
+        };
+        write(&mut tar)
+            .and_then(|_| tar.finish().map(|_| ()))
+            .with_io_context(|| format!("writing {}", out.display()))?;
+
+        Ok(format!("sha256:{}", manifest_digest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSpec;
+
+    #[test]
+    fn test_dockerfile_from_config() {
+        let ws = tempfile::tempdir().unwrap();
+        let mut config = ContainerConfig::new("api".to_string());
+        config.add_package(PackageSpec::parse("docker")).unwrap();
+        config.add_package(PackageSpec::parse("ripgrep@14")).unwrap();
+        config.add_package(PackageSpec::parse("github:owner/tool@v1")).unwrap();
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        config.environment.insert("GREETING".to_string(), "say \"hi\" to $USER".to_string());
+        let host_path = ws.path().join("containers/api/local/bin").to_string_lossy().to_string();
+        config.environment.insert("TOOL_HOME".to_string(), host_path.clone());
+        config.environment.insert("PATH".to_string(), host_path);
+
+        let export = DockerExporter::new(ws.path()).dockerfile(&config, Some("ubuntu:24.04"), Some("abc123"));
+        let file = &export.dockerfile;
+        assert!(file.contains("FROM ubuntu:24.04\n"));
+        assert!(file.contains("apt-get install -y --no-install-recommends ca-certificates curl docker.io ripgrep \\"));
+        assert!(file.contains("ADD https://github.com/owner/tool.git#v1 /opt/sfc/tool"));
+        assert!(file.contains("volta install node@20\n"));
+        assert!(file.contains(r#"ENV GREETING="say \"hi\" to \$USER""#));
+        assert!(file.contains(r#"dev.sfc.snapshot="abc123""#));
+        assert!(!file.contains("TOOL_HOME"));
+        assert_eq!(export.notes.len(), 3);
+
+        let alpine = DockerExporter::new(ws.path()).dockerfile(&config, Some("alpine:3.20"), None);
+        assert!(alpine.dockerfile.contains("apk add --no-cache ca-certificates curl bash docker ripgrep\n"));
+        assert!(alpine.dockerfile.ends_with("CMD [\"sh\"]\n"));
+    }
+
+    #[test]
+    fn test_oci_image_env_leaves_out_host_paths() {
+        let ws = tempfile::tempdir().unwrap();
+        let mut config = ContainerConfig::new("api".to_string());
+        config.environment.insert("GREETING".to_string(), "hi".to_string());
+        let host_path = ws.path().join("containers/api/local/bin").to_string_lossy().to_string();
+        config.environment.insert("TOOL_HOME".to_string(), host_path);
+
+        let out = ws.path().join("api.oci.tar");
+        DockerExporter::new(ws.path()).write_oci_layout(&config, None, &out).unwrap();
+        let entries = crate::core::read_tar(&fs::read(&out).unwrap()).unwrap();
+        let image_config: serde_json::Value = entries
+            .iter()
+            .filter_map(|e| match &e.kind {
+                crate::core::EntryKind::File(bytes) => serde_json::from_slice::<serde_json::Value>(bytes).ok(),
+                _ => None,
+            })
+            .find(|v| v.get("rootfs").is_some())
+            .unwrap();
+        let env: Vec<&str> = image_config["config"]["Env"].as_array().unwrap().iter().map(|v| v.as_str().unwrap()).collect();
+        assert!(env.contains(&"GREETING=hi"));
+        assert!(env.iter().all(|e| !e.starts_with("TOOL_HOME=")));
+        assert!(env.iter().any(|e| e.starts_with("PATH=/usr/local/sbin")));
+    }
+}
@@ -1,5 +1,19 @@
 pub mod snapshot;
 pub mod flake;
+pub mod docker;
+pub mod devcontainer;
+pub mod sbom;
+pub mod remote;
+pub mod bundle;
+pub mod signing;
+pub mod registry;
 
 pub use snapshot::{ShareManager, ShareInfo, share_snapshot, recreate_from_share};
-pub use flake::{FlakeManager, generate_nix_flake};
\ No newline at end of file
+pub use flake::{FlakeManager, generate_nix_flake};
+pub use docker::{DockerExporter, DockerExport};
+pub use devcontainer::{DevcontainerExporter, DevcontainerExport};
+pub use sbom::{Inventory, SbomFormat};
+pub use remote::{FlakeRemote, PullOutcome, PushOutcome};
+pub use bundle::{Bundle, BundleManifest};
+pub use signing::{KeyPair, KeyStore, PublicKey, ShareSignature, Verdict};
+pub use registry::{BundleRef, RegistryClient, RegistryEntry, RegistryServer, RegistryStore};
@@ -0,0 +1,769 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::sharing::Bundle;
+
+/// Port used when a registry URL doesn't name one
+pub const DEFAULT_PORT: u16 = 7373;
+/// Tag used when a reference doesn't name one
+pub const DEFAULT_TAG: &str = "latest";
+
+const SCHEME: &str = "registry://";
+/// Environment variable holding the token publishers send and `serve` expects
+pub const TOKEN_ENV: &str = "SFC_REGISTRY_TOKEN";
+/// Largest request or response body accepted
+const MAX_BODY: usize = 512 * 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(60);
+/// Connections answered at once; more get a 503
+const MAX_CONNECTIONS: usize = 64;
+/// How much of a refused request's body is read so the client still sees the answer
+const MAX_DISCARD: usize = 1024 * 1024;
+const DISCARD_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A bundle in a registry: `name[:tag]` or a snapshot hash (prefix)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BundleRef {
+    Tag { name: String, tag: String },
+    Hash(String),
+}
+
+impl BundleRef {
+    pub fn parse(reference: &str) -> Result<Self> {
+        if let Some(hash) = reference.strip_prefix("sha256:") {
+            return Self::hash(hash);
+        }
+        let (name, tag) = reference.split_once(':').unwrap_or((reference, DEFAULT_TAG));
+        if !reference.contains(':') && name.len() >= 12 && name.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Self::hash(name);
+        }
+        check_component("name", name)?;
+        check_component("tag", tag)?;
+        Ok(BundleRef::Tag { name: name.to_string(), tag: tag.to_string() })
+    }
+
+    fn hash(hash: &str) -> Result<Self> {
+        if hash.len() < 12 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(SfcError::Validation {
+                field: "hash".to_string(),
+                value: hash.to_string(),
+                reason: "expected at least 12 hex characters".to_string(),
+            });
+        }
+        Ok(BundleRef::Hash(hash.to_lowercase()))
+    }
+
+    fn api_path(&self) -> String {
+        match self {
+            BundleRef::Tag { name, tag } => format!("/v1/bundles/{}/{}", name, tag),
+            BundleRef::Hash(hash) => format!("/v1/blobs/{}", hash),
+        }
+    }
+}
+
+impl fmt::Display for BundleRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BundleRef::Tag { name, tag } => write!(f, "{}:{}", name, tag),
+            BundleRef::Hash(hash) => write!(f, "sha256:{}", hash),
+        }
+    }
+}
+
+fn check_component(field: &str, value: &str) -> Result<()> {
+    let valid = !value.is_empty()
+        && !value.starts_with('.')
+        && value.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+    if !valid {
+        return Err(SfcError::Validation {
+            field: field.to_string(),
+            value: value.to_string(),
+            reason: "use letters, digits, '.', '-' and '_'".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Whether `source` names a registry rather than a snapshot hash or file
+pub fn is_registry_url(source: &str) -> bool {
+    source.starts_with(SCHEME) || source.starts_with("http://")
+}
+
+/// A tag as listed by a registry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RegistryEntry {
+    pub name: String,
+    pub tag: String,
+    pub hash: String,
+}
+
+/// Bundles on disk: `blobs/<hash>.sfcb` plus `index.json` mapping name → tag → hash
+pub struct RegistryStore {
+    dir: PathBuf,
+}
+
+type Index = BTreeMap<String, BTreeMap<String, String>>;
+
+impl RegistryStore {
+    pub fn new(dir: &Path) -> Result<Self> {
+        fs::create_dir_all(dir.join("blobs")).with_io_context(|| format!("creating registry {}", dir.display()))?;
+        Ok(Self { dir: dir.to_path_buf() })
+    }
+
+    fn blob_path(&self, hash: &str) -> PathBuf {
+        self.dir.join("blobs").join(format!("{}.sfcb", hash))
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.dir.join("index.json")
+    }
+
+    fn load_index(&self) -> Result<Index> {
+        let path = self.index_path();
+        if !path.exists() {
+            return Ok(Index::new());
+        }
+        let content = fs::read_to_string(&path).with_io_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&content).map_err(|e| SfcError::Config {
+            message: format!("invalid registry index: {}", e),
+            path: Some(path),
+        })
+    }
+
+    fn save_index(&self, index: &Index) -> Result<()> {
+        let path = self.index_path();
+        let content = serde_json::to_string_pretty(index).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize registry index: {}", e),
+            source: None,
+        })?;
+        write_atomic(&path, content.as_bytes())
+    }
+
+    /// Store a bundle under `name:tag`, moving the tag if it already exists.
+    /// The bundle is fully checked first.
+    pub fn publish(&self, name: &str, tag: &str, data: &[u8]) -> Result<RegistryEntry> {
+        check_component("name", name)?;
+        check_component("tag", tag)?;
+        let bundle = Bundle::parse(data)?;
+        let hash = bundle.manifest.hash;
+        check_component("hash", &hash)?;
+
+        let blob = self.blob_path(&hash);
+        if !blob.exists() {
+            write_atomic(&blob, data)?;
+        }
+        let mut index = self.load_index()?;
+        index.entry(name.to_string()).or_default().insert(tag.to_string(), hash.clone());
+        self.save_index(&index)?;
+        Ok(RegistryEntry { name: name.to_string(), tag: tag.to_string(), hash })
+    }
+
+    /// Full hash of the bundle a reference points at
+    pub fn resolve(&self, reference: &BundleRef) -> Result<String> {
+        let not_found = || SfcError::NotFound {
+            resource: "bundle".to_string(),
+            identifier: reference.to_string(),
+        };
+        match reference {
+            BundleRef::Tag { name, tag } => {
+                self.load_index()?.get(name).and_then(|tags| tags.get(tag)).cloned().ok_or_else(not_found)
+            }
+            BundleRef::Hash(prefix) => {
+                let blobs = self.dir.join("blobs");
+                let mut matches = Vec::new();
+                for entry in fs::read_dir(&blobs).with_io_context(|| format!("reading {}", blobs.display()))? {
+                    let name = entry.with_io_context(|| format!("reading {}", blobs.display()))?.file_name();
+                    let name = name.to_string_lossy();
+                    if let Some(hash) = name.strip_suffix(".sfcb").filter(|hash| hash.starts_with(prefix.as_str())) {
+                        matches.push(hash.to_string());
+                    }
+                }
+                match matches.len() {
+                    0 => Err(not_found()),
+                    1 => Ok(matches.remove(0)),
+                    _ => Err(SfcError::Validation {
+                        field: "hash".to_string(),
+                        value: prefix.clone(),
+                        reason: format!("matches {} bundles; use a longer prefix", matches.len()),
+                    }),
+                }
+            }
+        }
+    }
+
+    pub fn fetch(&self, reference: &BundleRef) -> Result<Vec<u8>> {
+        let blob = self.blob_path(&self.resolve(reference)?);
+        fs::read(&blob).with_io_context(|| format!("reading {}", blob.display()))
+    }
+
+    pub fn list(&self) -> Result<Vec<RegistryEntry>> {
+        Ok(self
+            .load_index()?
+            .into_iter()
+            .flat_map(|(name, tags)| {
+                tags.into_iter().map(move |(tag, hash)| RegistryEntry { name: name.clone(), tag, hash })
+            })
+            .collect())
+    }
+}
+
+fn write_atomic(path: &Path, data: &[u8]) -> Result<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, data).with_io_context(|| format!("writing {}", tmp.display()))?;
+    fs::rename(&tmp, path).with_io_context(|| format!("replacing {}", path.display()))
+}
+
+/// An HTTP response from the registry server
+pub struct Response {
+    pub status: u16,
+    pub content_type: &'static str,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    fn json<T: Serialize>(status: u16, value: &T) -> Self {
+        Self {
+            status,
+            content_type: "application/json",
+            body: serde_json::to_vec_pretty(value).unwrap_or_default(),
+        }
+    }
+
+    fn error(status: u16, message: impl fmt::Display) -> Self {
+        Self::json(status, &serde_json::json!({ "error": message.to_string() }))
+    }
+
+    fn bundle(body: Vec<u8>) -> Self {
+        Self { status: 200, content_type: "application/octet-stream", body }
+    }
+}
+
+/// Serves a [`RegistryStore`] over HTTP:
+///
+/// - `GET /v1/bundles` lists every name:tag
+/// - `PUT /v1/bundles/<name>/<tag>` publishes a bundle
+/// - `GET /v1/bundles/<name>/<tag>` fetches by tag
+/// - `GET /v1/blobs/<hash>` fetches by snapshot hash (prefix)
+///
+/// Fetching is open to anyone who can reach the server. Publishing needs
+/// `Authorization: Bearer <token>` matching [`RegistryServer::with_token`],
+/// and is refused outright when the server has no token; either way the
+/// body is only read once the request is allowed. Traffic is plain HTTP, so
+/// the token is only as private as the network it crosses.
+pub struct RegistryServer {
+    store: RegistryStore,
+    token: Option<String>,
+    /// Publishes rewrite the index, so they take turns
+    publishing: Mutex<()>,
+    active: AtomicUsize,
+    max_connections: usize,
+}
+
+impl RegistryServer {
+    pub fn new(store: RegistryStore) -> Self {
+        Self {
+            store,
+            token: None,
+            publishing: Mutex::new(()),
+            active: AtomicUsize::new(0),
+            max_connections: MAX_CONNECTIONS,
+        }
+    }
+
+    /// Accept publishes that carry `token`; `None` keeps the server read-only
+    pub fn with_token(mut self, token: Option<String>) -> Self {
+        self.token = token.filter(|t| !t.is_empty());
+        self
+    }
+
+    /// Answer requests until the listener is closed, each connection on its
+    /// own thread and at most [`MAX_CONNECTIONS`] at once. Failed accepts and
+    /// requests are logged through `log` rather than stopping the server.
+    pub fn serve(&self, listener: TcpListener, log: impl Fn(&str) + Sync) -> Result<()> {
+        let log = &log;
+        thread::scope(|scope| {
+            for stream in listener.incoming() {
+                let mut stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        log(&format!("accept failed: {}", e));
+                        continue;
+                    }
+                };
+                if self.active.fetch_add(1, Ordering::SeqCst) >= self.max_connections {
+                    self.active.fetch_sub(1, Ordering::SeqCst);
+                    stream.set_write_timeout(Some(Duration::from_secs(1))).ok();
+                    write_response(&mut stream, &Response::error(503, "registry is busy, try again later")).ok();
+                    log("- 503");
+                    continue;
+                }
+                scope.spawn(move || {
+                    self.answer(stream, log);
+                    self.active.fetch_sub(1, Ordering::SeqCst);
+                });
+            }
+        });
+        Ok(())
+    }
+
+    fn answer(&self, mut stream: TcpStream, log: &impl Fn(&str)) {
+        stream.set_read_timeout(Some(TIMEOUT)).ok();
+        stream.set_write_timeout(Some(TIMEOUT)).ok();
+        let (line, response) = match read_head(&mut stream) {
+            Ok(mut head) => {
+                let mut parts = head.start.split_whitespace();
+                let (method, path) = (parts.next().unwrap_or_default(), parts.next().unwrap_or_default());
+                let authorization = head.authorization.clone();
+                let response = match self.refuse(method, authorization.as_deref(), head.length) {
+                    Some(refusal) => {
+                        discard(&mut stream, &head);
+                        refusal
+                    }
+                    None => match read_body(&mut stream, std::mem::take(&mut head.rest), head.length) {
+                        Ok(body) => self.handle(method, path, authorization.as_deref(), &body),
+                        Err(e) => Response::error(400, e),
+                    },
+                };
+                (format!("{} {}", method, path), response)
+            }
+            Err(e) => ("-".to_string(), Response::error(400, e)),
+        };
+        log(&format!("{} {}", line, response.status));
+        // A client that went away mid-response doesn't stop the server
+        write_response(&mut stream, &response).ok();
+    }
+
+    /// Why a request with a `length`-byte body may not go ahead, decided
+    /// before the body is read
+    fn refuse(&self, method: &str, authorization: Option<&str>, length: usize) -> Option<Response> {
+        if self.token.is_none() && (method == "PUT" || length > 0) {
+            return Some(Response::error(403, "publishing is disabled; start the registry with --token"));
+        }
+        if method == "PUT" && !self.authorized(authorization) {
+            return Some(Response::error(401, format!("publishing needs a valid token (set {})", TOKEN_ENV)));
+        }
+        None
+    }
+
+    /// Whether an `Authorization` header value may publish
+    fn authorized(&self, authorization: Option<&str>) -> bool {
+        let given = authorization.and_then(|a| a.trim().strip_prefix("Bearer ")).map(str::trim);
+        let (Some(token), Some(given)) = (&self.token, given) else {
+            return false;
+        };
+        // Compare every byte so the time taken doesn't reveal the prefix matched
+        token.len() == given.len() && token.bytes().zip(given.bytes()).fold(0u8, |acc, (a, b)| acc | (a ^ b)) == 0
+    }
+
+    pub fn handle(&self, method: &str, path: &str, authorization: Option<&str>, body: &[u8]) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+        if let Some(refusal) = self.refuse(method, authorization, body.len()) {
+            return refusal;
+        }
+        let result = match (method, segments.as_slice()) {
+            ("GET", ["v1", "bundles"]) => self.store.list().map(|entries| Response::json(200, &entries)),
+            ("PUT", ["v1", "bundles", name, tag]) => {
+                let _turn = self.publishing.lock().unwrap_or_else(|e| e.into_inner());
+                self.store.publish(name, tag, body).map(|entry| Response::json(201, &entry))
+            }
+            ("GET", ["v1", "bundles", name, tag]) => BundleRef::parse(&format!("{}:{}", name, tag))
+                .and_then(|reference| self.store.fetch(&reference))
+                .map(Response::bundle),
+            ("GET", ["v1", "blobs", hash]) => BundleRef::hash(hash)
+                .and_then(|reference| self.store.fetch(&reference))
+                .map(Response::bundle),
+            (_, ["v1", "bundles"]) | (_, ["v1", "bundles", _, _]) | (_, ["v1", "blobs", _]) => {
+                return Response::error(405, format!("{} is not allowed here", method));
+            }
+            _ => return Response::error(404, format!("no such endpoint {}", path)),
+        };
+        result.unwrap_or_else(|e| match e {
+            SfcError::NotFound { .. } => Response::error(404, e),
+            SfcError::Validation { .. } => Response::error(400, e),
+            _ => Response::error(500, e),
+        })
+    }
+}
+
+/// Talks to a registry at `registry://host[:port]` (or `http://host:port`)
+#[derive(Debug, Clone)]
+pub struct RegistryClient {
+    /// `host:port`
+    pub address: String,
+    /// Sent when publishing; read from `SFC_REGISTRY_TOKEN` by [`RegistryClient::parse`]
+    pub token: Option<String>,
+}
+
+impl RegistryClient {
+    /// Split `registry://host[:port][/reference]` into a client and the
+    /// optional bundle reference
+    pub fn parse(url: &str) -> Result<(Self, Option<BundleRef>)> {
+        let rest = url
+            .strip_prefix(SCHEME)
+            .or_else(|| url.strip_prefix("http://"))
+            .unwrap_or(url);
+        let (host, reference) = match rest.split_once('/') {
+            Some((host, reference)) => (host, Some(reference.trim_matches('/'))),
+            None => (rest, None),
+        };
+        if host.is_empty() {
+            return Err(SfcError::Validation {
+                field: "registry".to_string(),
+                value: url.to_string(),
+                reason: "expected registry://host[:port][/name:tag]".to_string(),
+            });
+        }
+        let address = if host.contains(':') { host.to_string() } else { format!("{}:{}", host, DEFAULT_PORT) };
+        let reference = reference.filter(|r| !r.is_empty()).map(BundleRef::parse).transpose()?;
+        let token = std::env::var(TOKEN_ENV).ok().filter(|t| !t.is_empty());
+        Ok((Self { address, token }, reference))
+    }
+
+    /// `registry://host:port/<reference>`
+    pub fn url_for(&self, reference: &BundleRef) -> String {
+        format!("{}{}/{}", SCHEME, self.address, reference)
+    }
+
+    pub fn publish(&self, name: &str, tag: &str, data: &[u8]) -> Result<RegistryEntry> {
+        let body = self.request("PUT", &format!("/v1/bundles/{}/{}", name, tag), data)?;
+        self.decode(&body)
+    }
+
+    pub fn fetch(&self, reference: &BundleRef) -> Result<Vec<u8>> {
+        self.request("GET", &reference.api_path(), &[]).map_err(|e| match e {
+            SfcError::NotFound { resource, .. } => SfcError::NotFound { resource, identifier: reference.to_string() },
+            e => e,
+        })
+    }
+
+    pub fn list(&self) -> Result<Vec<RegistryEntry>> {
+        let body = self.request("GET", "/v1/bundles", &[])?;
+        self.decode(&body)
+    }
+
+    fn decode<T: for<'de> Deserialize<'de>>(&self, body: &[u8]) -> Result<T> {
+        serde_json::from_slice(body).map_err(|e| SfcError::Generic {
+            message: format!("unexpected response from registry {}: {}", self.address, e),
+            source: None,
+        })
+    }
+
+    fn request(&self, method: &str, path: &str, body: &[u8]) -> Result<Vec<u8>> {
+        let context = || format!("{} {} on registry {}", method, path, self.address);
+        let mut stream = TcpStream::connect(&self.address).with_io_context(context)?;
+        stream.set_read_timeout(Some(TIMEOUT)).ok();
+        stream.set_write_timeout(Some(TIMEOUT)).ok();
+        let authorization = match (&self.token, method) {
+            (Some(token), "PUT") => format!("Authorization: Bearer {}\r\n", token),
+            _ => String::new(),
+        };
+        let head = format!(
+            "{} {} HTTP/1.1\r\nHost: {}\r\n{}Content-Length: {}\r\nConnection: close\r\n\r\n",
+            method,
+            path,
+            self.address,
+            authorization,
+            body.len()
+        );
+        let sent = stream.write_all(head.as_bytes()).and_then(|_| stream.write_all(body)).with_io_context(context);
+        // A server that refuses the request answers without reading the body
+        let response = match read_message(&mut stream) {
+            Ok(response) => response,
+            Err(e) => return Err(sent.err().unwrap_or(e)),
+        };
+        let status: u16 = response.start.split_whitespace().nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+        if (200..300).contains(&status) {
+            return Ok(response.body);
+        }
+        let message = serde_json::from_slice::<serde_json::Value>(&response.body)
+            .ok()
+            .and_then(|v| v.get("error").and_then(|e| e.as_str()).map(str::to_string))
+            .unwrap_or_else(|| response.start.clone());
+        match status {
+            404 => Err(SfcError::NotFound { resource: "bundle".to_string(), identifier: path.to_string() }),
+            _ => Err(SfcError::Command {
+                command: format!("{} {}", method, path),
+                exit_code: Some(status as i32),
+                stderr: message,
+            }),
+        }
+    }
+}
+
+/// Where `sfc share --publish <url>` puts a container's bundle: the URL's
+/// `name:tag`, or `<container>:latest` when the URL names only the registry
+pub fn publish_target(url: &str, container: &str) -> Result<(RegistryClient, String, String)> {
+    match RegistryClient::parse(url)? {
+        (client, None) => Ok((client, container.to_string(), DEFAULT_TAG.to_string())),
+        (client, Some(BundleRef::Tag { name, tag })) => Ok((client, name, tag)),
+        (_, Some(BundleRef::Hash(_))) => Err(SfcError::Validation {
+            field: "publish".to_string(),
+            value: url.to_string(),
+            reason: "publish to a name:tag, not a hash".to_string(),
+        }),
+    }
+}
+
+/// Fetch `registry://host/<reference>` into the workspace cache and return
+/// the bundle's path. The bundle is checked before it is written.
+pub fn download(workspace: &Path, url: &str) -> Result<PathBuf> {
+    let (client, reference) = RegistryClient::parse(url)?;
+    let reference = reference.ok_or_else(|| SfcError::Validation {
+        field: "from".to_string(),
+        value: url.to_string(),
+        reason: "expected registry://host[:port]/name:tag".to_string(),
+    })?;
+    let data = client.fetch(&reference)?;
+    let hash = Bundle::parse(&data)?.manifest.hash;
+    if let BundleRef::Hash(prefix) = &reference {
+        if !hash.starts_with(prefix.as_str()) {
+            return Err(SfcError::Validation {
+                field: "hash".to_string(),
+                value: prefix.clone(),
+                reason: format!("registry returned snapshot {}", hash),
+            });
+        }
+    }
+    check_component("hash", &hash)?;
+
+    let dir = workspace.join(".sfc").join("cache").join("registry");
+    fs::create_dir_all(&dir).with_io_context(|| format!("creating {}", dir.display()))?;
+    let path = dir.join(format!("{}.sfcb", hash));
+    write_atomic(&path, &data)?;
+    Ok(path)
+}
+
+/// A request or response: its first line and body
+struct Message {
+    start: String,
+    body: Vec<u8>,
+}
+
+/// A message's first line and the headers the registry cares about, with
+/// whatever of the body arrived alongside them
+struct Head {
+    start: String,
+    authorization: Option<String>,
+    length: usize,
+    rest: Vec<u8>,
+}
+
+fn read_message(stream: &mut impl Read) -> Result<Message> {
+    let head = read_head(stream)?;
+    let start = head.start.clone();
+    let body = read_body(stream, head.rest, head.length)?;
+    Ok(Message { start, body })
+}
+
+fn read_head(stream: &mut impl Read) -> Result<Head> {
+    let mut data = Vec::new();
+    let mut buf = [0u8; 8192];
+    let head_end = loop {
+        if let Some(pos) = data.windows(4).position(|w| w == b"\r\n\r\n") {
+            break pos;
+        }
+        if data.len() > 64 * 1024 {
+            return Err(bad_http("header too large"));
+        }
+        let n = stream.read(&mut buf).with_io_context(|| "reading HTTP message".to_string())?;
+        if n == 0 {
+            return Err(bad_http("connection closed before the header ended"));
+        }
+        data.extend_from_slice(&buf[..n]);
+    };
+
+    let text = String::from_utf8_lossy(&data[..head_end]).to_string();
+    let mut lines = text.split("\r\n");
+    let start = lines.next().unwrap_or_default().to_string();
+    let headers: Vec<(&str, &str)> = lines.filter_map(|line| line.split_once(':')).collect();
+    let header = |wanted: &str| {
+        headers.iter().find(|(name, _)| name.trim().eq_ignore_ascii_case(wanted)).map(|(_, value)| value.trim())
+    };
+    let authorization = header("authorization").map(str::to_string);
+    let length = header("content-length")
+        .map(|value| value.parse::<usize>().map_err(|_| bad_http("invalid Content-Length")))
+        .transpose()?
+        .unwrap_or(0);
+    if length > MAX_BODY {
+        return Err(bad_http("body too large"));
+    }
+    let rest = data.split_off(head_end + 4);
+    Ok(Head { start, authorization, length, rest })
+}
+
+/// The rest of a body whose first bytes came in with the head
+fn read_body(stream: &mut impl Read, mut body: Vec<u8>, length: usize) -> Result<Vec<u8>> {
+    let mut buf = [0u8; 8192];
+    while body.len() < length {
+        let n = stream.read(&mut buf).with_io_context(|| "reading HTTP body".to_string())?;
+        if n == 0 {
+            return Err(bad_http("connection closed before the body ended"));
+        }
+        body.extend_from_slice(&buf[..n]);
+    }
+    body.truncate(length);
+    Ok(body)
+}
+
+/// Read and drop a little of a refused request's body, so closing the
+/// connection doesn't reset it before the client reads the answer. A client
+/// waiting for the answer before sending the body isn't waited on for long.
+fn discard(stream: &mut TcpStream, head: &Head) {
+    stream.set_read_timeout(Some(DISCARD_TIMEOUT)).ok();
+    let mut left = head.length.min(MAX_DISCARD).saturating_sub(head.rest.len());
+    let mut buf = [0u8; 8192];
+    while left > 0 {
+        match stream.read(&mut buf[..left.min(8192)]) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => left -= n,
+        }
+    }
+}
+
+fn write_response(stream: &mut impl Write, response: &Response) -> std::io::Result<()> {
+    let reason = match response.status {
+        200 => "OK",
+        201 => "Created",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        503 => "Service Unavailable",
+        _ => "Internal Server Error",
+    };
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        response.status,
+        reason,
+        response.content_type,
+        response.body.len()
+    )?;
+    stream.write_all(&response.body)?;
+    stream.flush()
+}
+
+fn bad_http(reason: &str) -> SfcError {
+    SfcError::Validation {
+        field: "http".to_string(),
+        value: String::new(),
+        reason: reason.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sharing::bundle::tests::sample_bundle;
+
+    #[test]
+    fn test_bundle_ref_parse() {
+        assert_eq!(
+            BundleRef::parse("web").unwrap(),
+            BundleRef::Tag { name: "web".to_string(), tag: "latest".to_string() }
+        );
+        assert_eq!(
+            BundleRef::parse("web:stable").unwrap(),
+            BundleRef::Tag { name: "web".to_string(), tag: "stable".to_string() }
+        );
+        assert_eq!(BundleRef::parse("ABCDEF012345").unwrap(), BundleRef::Hash("abcdef012345".to_string()));
+        assert!(BundleRef::parse("../etc:passwd").is_err());
+
+        let (client, reference) = RegistryClient::parse("registry://box/web:v1").unwrap();
+        assert_eq!(client.address, format!("box:{}", DEFAULT_PORT));
+        assert_eq!(client.url_for(&reference.unwrap()), format!("registry://box:{}/web:v1", DEFAULT_PORT));
+    }
+
+    #[test]
+    fn test_publish_and_fetch_over_http() {
+        let dir = tempfile::tempdir().unwrap();
+        let (hash, data) = sample_bundle(dir.path());
+        let server = RegistryServer::new(RegistryStore::new(&dir.path().join("registry")).unwrap())
+            .with_token(Some("s3cret".to_string()));
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("registry://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || server.serve(listener, |_| {}));
+
+        // A client that connects and says nothing doesn't hold up the others
+        let _idle = TcpStream::connect(url.trim_start_matches(SCHEME)).unwrap();
+        let (mut client, _) = RegistryClient::parse(&url).unwrap();
+        client.token = None;
+        assert!(matches!(client.publish("web", "stable", &data), Err(SfcError::Command { exit_code: Some(401), .. })));
+        client.token = Some("wrong!".to_string());
+        assert!(client.publish("web", "stable", &data).is_err());
+        assert!(client.list().unwrap().is_empty());
+        client.token = Some("s3cret".to_string());
+        let entry = client.publish("web", "stable", &data).unwrap();
+        assert_eq!(entry.hash, hash);
+        assert_eq!(client.list().unwrap(), vec![entry]);
+
+        assert_eq!(client.fetch(&BundleRef::parse("web:stable").unwrap()).unwrap(), data);
+        assert_eq!(client.fetch(&BundleRef::parse(&hash[..12]).unwrap()).unwrap(), data);
+        assert!(matches!(client.fetch(&BundleRef::parse("web:missing").unwrap()), Err(SfcError::NotFound { .. })));
+        assert!(client.publish("web", "bad", b"not a bundle").is_err());
+
+        let path = download(dir.path(), &format!("{}/web:stable", url)).unwrap();
+        assert_eq!(fs::read(path).unwrap(), data);
+
+        // Without a token the server is read-only
+        let server = RegistryServer::new(RegistryStore::new(&dir.path().join("registry")).unwrap());
+        let refused = server.handle("PUT", "/v1/bundles/web/next", Some("Bearer s3cret"), &data);
+        assert_eq!(refused.status, 403);
+        assert_eq!(server.handle("GET", "/v1/bundles/web/stable", None, &[]).status, 200);
+    }
+
+    /// Send a request head alone and return the status of the answer
+    fn status_for_head(address: &str, head: &str) -> u16 {
+        let mut stream = TcpStream::connect(address).unwrap();
+        stream.write_all(head.as_bytes()).unwrap();
+        let response = read_message(&mut stream).unwrap();
+        response.start.split_whitespace().nth(1).unwrap().parse().unwrap()
+    }
+
+    #[test]
+    fn test_refuses_before_reading_the_body_and_when_busy() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = || RegistryStore::new(&dir.path().join("registry")).unwrap();
+        let serve = |server: RegistryServer| {
+            let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+            let address = listener.local_addr().unwrap().to_string();
+            std::thread::spawn(move || server.serve(listener, |_| {}));
+            address
+        };
+        // The body is never sent; an answer means the server didn't wait for it
+        let huge = MAX_BODY - 1;
+        let publishing = serve(RegistryServer::new(store()).with_token(Some("s3cret".to_string())));
+        let put = format!("PUT /v1/bundles/web/v1 HTTP/1.1\r\nContent-Length: {}\r\n\r\n", huge);
+        assert_eq!(status_for_head(&publishing, &put), 401);
+        let read_only = serve(RegistryServer::new(store()));
+        let get = format!("GET /v1/bundles HTTP/1.1\r\nContent-Length: {}\r\n\r\n", huge);
+        assert_eq!(status_for_head(&read_only, &get), 403);
+
+        let mut server = RegistryServer::new(store());
+        server.max_connections = 1;
+        let busy = serve(server);
+        let idle = TcpStream::connect(&busy).unwrap();
+        let list = "GET /v1/bundles HTTP/1.1\r\n\r\n";
+        assert_eq!(status_for_head(&busy, list), 503);
+        drop(idle);
+        let status = (0..50)
+            .map(|_| {
+                std::thread::sleep(Duration::from_millis(20));
+                status_for_head(&busy, list)
+            })
+            .find(|status| *status != 503);
+        assert_eq!(status, Some(200));
+    }
+}
@@ -0,0 +1,432 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::container::{ContainerConfig, Removals};
+use crate::error::{Result, SfcError, ErrorContext};
+use crate::flake::FlakeConfig;
+use crate::history::{History, Operation};
+use crate::sfc::validate_name;
+
+const DEFAULT_BRANCH: &str = "main";
+
+/// What was last exchanged with a remote, per container
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct SyncRecord {
+    remote: String,
+    branch: String,
+    /// Remote commit that last touched `containers/<name>/`
+    commit: String,
+    /// Local config hash at that point
+    config_hash: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SyncState(BTreeMap<String, SyncRecord>);
+
+impl SyncState {
+    fn path(workspace: &Path) -> PathBuf {
+        workspace.join(".sfc").join("flake-sync.json")
+    }
+
+    fn load(workspace: &Path) -> Result<Self> {
+        let path = Self::path(workspace);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).with_io_context(|| format!("reading {}", path.display()))?;
+        serde_json::from_str(&content).map_err(|e| SfcError::Config {
+            message: format!("invalid sync state: {}", e),
+            path: Some(path),
+        })
+    }
+
+    fn save(&self, workspace: &Path) -> Result<()> {
+        let path = Self::path(workspace);
+        let content = serde_json::to_string_pretty(self).map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize sync state: {}", e),
+            source: None,
+        })?;
+        fs::write(&path, content).with_io_context(|| format!("writing {}", path.display()))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PushOutcome {
+    /// The remote already has this config
+    UpToDate,
+    Pushed { commit: String },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PullOutcome {
+    UpToDate,
+    /// The remote holds an earlier version from local history
+    LocalAhead,
+    Updated { commit: String, created: bool },
+}
+
+/// Container flakes kept in a git repository as `containers/<name>/` with
+/// `flake.nix`, `flake.lock` and `<name>.toml`. Works with any git remote,
+/// including a local bare repository.
+pub struct FlakeRemote {
+    workspace: PathBuf,
+    url: String,
+    branch: String,
+    checkout: PathBuf,
+}
+
+impl FlakeRemote {
+    pub fn new<P: AsRef<Path>>(workspace: P, url: &str, branch: Option<&str>) -> Self {
+        let workspace = workspace.as_ref().to_path_buf();
+        let key = format!("{:x}", Sha256::digest(url.as_bytes()));
+        let checkout = workspace.join(".sfc").join("cache").join("remotes").join(&key[..16]);
+        Self {
+            workspace,
+            url: url.to_string(),
+            branch: branch.unwrap_or(DEFAULT_BRANCH).to_string(),
+            checkout,
+        }
+    }
+
+    fn git(&self, args: &[&str]) -> Result<String> {
+        let output = Command::new("git")
+            .arg("-C")
+            .arg(&self.checkout)
+            .args(args)
+            .output()
+            .with_io_context(|| "running git".to_string())?;
+        if !output.status.success() {
+            return Err(SfcError::Command {
+                command: format!("git {}", args.join(" ")),
+                exit_code: output.status.code(),
+                stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Bring the cached clone to the remote branch tip (or an empty branch
+    /// when the remote doesn't have it yet)
+    fn sync_checkout(&self) -> Result<()> {
+        if !self.checkout.join(".git").exists() {
+            fs::create_dir_all(&self.checkout)
+                .with_io_context(|| format!("creating {}", self.checkout.display()))?;
+            self.git(&["init", "--quiet"])?;
+            self.git(&["remote", "add", "origin", &self.url])?;
+        }
+        self.git(&["remote", "set-url", "origin", &self.url])?;
+        self.git(&["fetch", "--quiet", "--prune", "origin"])?;
+
+        let remote_ref = format!("refs/remotes/origin/{}", self.branch);
+        if self.git(&["rev-parse", "--verify", "--quiet", &remote_ref]).is_ok() {
+            self.git(&["checkout", "--quiet", "--force", "-B", &self.branch, &remote_ref])?;
+        } else {
+            self.git(&["checkout", "--quiet", "--orphan", &format!("sfc-{}", self.branch)]).ok();
+            self.git(&["symbolic-ref", "HEAD", &format!("refs/heads/{}", self.branch)])?;
+            self.git(&["read-tree", "--empty"])?;
+        }
+        self.git(&["clean", "--quiet", "-fdx"])?;
+        Ok(())
+    }
+
+    fn container_dir(&self, name: &str) -> PathBuf {
+        self.checkout.join("containers").join(name)
+    }
+
+    /// Last remote commit touching `containers/<name>/`
+    fn last_commit(&self, name: &str) -> Result<Option<String>> {
+        if self.git(&["rev-parse", "--verify", "--quiet", "HEAD"]).is_err() {
+            return Ok(None);
+        }
+        let commit = self.git(&["log", "-1", "--format=%H", "--", &format!("containers/{}", name)])?;
+        Ok((!commit.is_empty()).then_some(commit))
+    }
+
+    fn remote_config(&self, name: &str) -> Result<Option<ContainerConfig>> {
+        let path = self.container_dir(name).join(format!("{}.toml", name));
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path).with_io_context(|| format!("reading {}", path.display()))?;
+        let mut config: ContainerConfig = toml::from_str(&content).map_err(|e| SfcError::Config {
+            message: format!("invalid container config in remote: {}", e),
+            path: Some(path),
+        })?;
+        config.name = name.to_string();
+        Ok(Some(config))
+    }
+
+    /// Names of the containers in the remote
+    pub fn list(&self) -> Result<Vec<String>> {
+        self.sync_checkout()?;
+        let dir = self.checkout.join("containers");
+        let mut names: Vec<String> = match fs::read_dir(&dir) {
+            Ok(entries) => entries
+                .flatten()
+                .filter(|e| e.path().is_dir())
+                .map(|e| e.file_name().to_string_lossy().to_string())
+                .filter(|name| validate_name(name).is_ok())
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        Ok(names)
+    }
+
+    /// Whether `hash` is an earlier state of `name` in local history
+    fn in_history(&self, name: &str, hash: &str) -> Result<bool> {
+        let history = History::load(&self.workspace)?;
+        Ok(history.get_container_history(name).iter().any(|e| e.hash == hash))
+    }
+
+    fn record(&self, name: &str, commit: &str, config_hash: &str) -> Result<()> {
+        let mut state = SyncState::load(&self.workspace)?;
+        state.0.insert(name.to_string(), SyncRecord {
+            remote: self.url.clone(),
+            branch: self.branch.clone(),
+            commit: commit.to_string(),
+            config_hash: config_hash.to_string(),
+        });
+        state.save(&self.workspace)
+    }
+
+    fn last_sync(&self, name: &str) -> Result<Option<SyncRecord>> {
+        let state = SyncState::load(&self.workspace)?;
+        Ok(state.0.get(name).filter(|r| r.remote == self.url && r.branch == self.branch).cloned())
+    }
+
+    /// Commit the container's flake and config under `containers/<name>/` and
+    /// push. Refuses when the remote copy changed since the last sync and
+    /// isn't an earlier local state, unless `force`.
+    pub fn push(&self, name: &str, force: bool) -> Result<PushOutcome> {
+        validate_name(name)?;
+        let config = shareable(&self.workspace, name)?;
+        let local_hash = config.compute_hash()?;
+        self.sync_checkout()?;
+
+        let remote_commit = self.last_commit(name)?;
+        if let (Some(commit), Some(remote)) = (&remote_commit, self.remote_config(name)?) {
+            let remote_hash = remote.compute_hash()?;
+            if remote_hash == local_hash {
+                self.record(name, commit, &local_hash)?;
+                return Ok(PushOutcome::UpToDate);
+            }
+            let synced = self.last_sync(name)?.is_some_and(|r| &r.commit == commit);
+            if !force && !synced && !self.in_history(name, &remote_hash)? {
+                return Err(SfcError::Container {
+                    name: name.to_string(),
+                    operation: "push".to_string(),
+                    reason: format!(
+                        "the remote copy changed since the last sync (commit {}); pull first or use --force",
+                        &commit[..12.min(commit.len())]
+                    ),
+                });
+            }
+        }
+
+        let dir = self.container_dir(name);
+        fs::create_dir_all(&dir).with_io_context(|| format!("creating {}", dir.display()))?;
+        let flake = FlakeConfig::from_container(&config);
+        fs::write(dir.join("flake.nix"), flake.to_nix())
+            .with_io_context(|| "writing flake.nix".to_string())?;
+        let lock = self.workspace.join("containers").join(name).join("flake.lock");
+        if lock.exists() {
+            fs::copy(&lock, dir.join("flake.lock")).with_io_context(|| "copying flake.lock".to_string())?;
+        }
+        let toml = toml::to_string_pretty(&config).map_err(|e| SfcError::Config {
+            message: format!("Failed to serialize container config: {}", e),
+            path: None,
+        })?;
+        fs::write(dir.join(format!("{}.toml", name)), toml)
+            .with_io_context(|| "writing container config".to_string())?;
+
+        let path = format!("containers/{}", name);
+        self.git(&["add", "--all", "--", &path])?;
+        let mut commit_args = identity_args(&self.checkout);
+        let message = format!("Update {} ({})", name, local_hash);
+        commit_args.extend(["commit", "--quiet", "-m", &message, "--", &path].map(String::from));
+        let commit_args: Vec<&str> = commit_args.iter().map(String::as_str).collect();
+        self.git(&commit_args)?;
+        self.git(&["push", "--quiet", "origin", &format!("HEAD:refs/heads/{}", self.branch)])?;
+
+        let commit = self.git(&["rev-parse", "HEAD"])?;
+        self.record(name, &commit, &local_hash)?;
+        Ok(PushOutcome::Pushed { commit })
+    }
+
+    /// Replace the local config (and flake files) of `name` with the remote's.
+    /// Refuses when both sides changed since the last sync, unless `force`.
+    /// `name` becomes a path in both the checkout and the workspace, so it
+    /// must be a valid container name.
+    pub fn pull(&self, name: &str, force: bool) -> Result<PullOutcome> {
+        validate_name(name)?;
+        self.sync_checkout()?;
+        let (Some(commit), Some(remote)) = (self.last_commit(name)?, self.remote_config(name)?) else {
+            return Err(SfcError::NotFound {
+                resource: format!("container in {}", self.url),
+                identifier: name.to_string(),
+            });
+        };
+        let remote_hash = remote.compute_hash()?;
+
+        let exists = ContainerConfig::exists(&self.workspace, name);
+        if exists {
+            let local_hash = shareable(&self.workspace, name)?.compute_hash()?;
+            if local_hash == remote_hash {
+                self.record(name, &commit, &local_hash)?;
+                return Ok(PullOutcome::UpToDate);
+            }
+            if !force && self.in_history(name, &remote_hash)? {
+                return Ok(PullOutcome::LocalAhead);
+            }
+            let unchanged = self.last_sync(name)?.is_some_and(|r| r.config_hash == local_hash);
+            if !force && !unchanged {
+                return Err(SfcError::Container {
+                    name: name.to_string(),
+                    operation: "pull".to_string(),
+                    reason: "the local container changed since the last sync; push it, or use --force to take the remote copy".to_string(),
+                });
+            }
+        }
+
+        remote.save(&self.workspace)?;
+        let local_dir = self.workspace.join("containers").join(name);
+        fs::create_dir_all(&local_dir).with_io_context(|| format!("creating {}", local_dir.display()))?;
+        for file in ["flake.nix", "flake.lock"] {
+            let src = self.container_dir(name).join(file);
+            if src.exists() {
+                fs::copy(&src, local_dir.join(file)).with_io_context(|| format!("copying {}", file))?;
+            }
+        }
+
+        let mut history = History::load(&self.workspace)?;
+        let operation = if exists { Operation::Pull { remote: self.url.clone() } } else { Operation::Create };
+        history.add_entry(&remote, operation, format!("Pull from {}", self.url))?;
+        self.record(name, &commit, &remote_hash)?;
+        Ok(PullOutcome::Updated { commit, created: !exists })
+    }
+}
+
+/// The config as shared: inherited settings folded in, so it stands alone
+fn shareable(workspace: &Path, name: &str) -> Result<ContainerConfig> {
+    if !ContainerConfig::exists(workspace, name) {
+        return Err(SfcError::NotFound {
+            resource: "container".to_string(),
+            identifier: name.to_string(),
+        });
+    }
+    let raw = ContainerConfig::load(workspace, name)?;
+    if raw.extends.is_empty() {
+        return Ok(raw);
+    }
+    let mut config = raw.effective(workspace)?;
+    config.extends.clear();
+    config.remove = Removals::default();
+    Ok(config)
+}
+
+/// `-c user.name=... -c user.email=...` when the repository has no identity
+fn identity_args(repo: &Path) -> Vec<String> {
+    let configured = Command::new("git")
+        .arg("-C")
+        .arg(repo)
+        .args(["config", "user.email"])
+        .output()
+        .is_ok_and(|o| o.status.success());
+    if configured {
+        return Vec::new();
+    }
+    let user = whoami::username();
+    vec![
+        "-c".to_string(),
+        format!("user.name={}", user),
+        "-c".to_string(),
+        format!("user.email={}@localhost", user),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSpec;
+
+    fn workspace() -> tempfile::TempDir {
+        let ws = tempfile::tempdir().unwrap();
+        fs::create_dir_all(ws.path().join(".sfc/containers")).unwrap();
+        ws
+    }
+
+    fn edit(ws: &Path, name: &str, package: &str) {
+        let mut config = ContainerConfig::load(ws, name).unwrap();
+        config.add_package(PackageSpec::parse(package)).unwrap();
+        config.save(ws).unwrap();
+        History::load(ws).unwrap()
+            .add_entry(&config, Operation::AddPackage { name: package.to_string(), version: None }, package.to_string())
+            .unwrap();
+    }
+
+    #[test]
+    fn test_push_pull_and_conflicts_with_bare_repo() {
+        let remote = tempfile::tempdir().unwrap();
+        let status = Command::new("git").args(["init", "--bare", "--quiet"]).arg(remote.path()).status();
+        if !status.is_ok_and(|s| s.success()) {
+            return;
+        }
+        let url = remote.path().to_string_lossy().to_string();
+        let (alice, bob) = (workspace(), workspace());
+
+        let mut config = ContainerConfig::new("api".to_string());
+        config.add_package(PackageSpec::parse("jq")).unwrap();
+        config.save(alice.path()).unwrap();
+        let alice_remote = FlakeRemote::new(alice.path(), &url, None);
+        assert!(matches!(alice_remote.push("api", false).unwrap(), PushOutcome::Pushed { .. }));
+        assert_eq!(alice_remote.push("api", false).unwrap(), PushOutcome::UpToDate);
+
+        // A fresh workspace gets the container, flake included
+        let bob_remote = FlakeRemote::new(bob.path(), &url, None);
+        assert_eq!(bob_remote.list().unwrap(), vec!["api".to_string()]);
+        assert!(matches!(bob_remote.pull("api", false).unwrap(), PullOutcome::Updated { created: true, .. }));
+        assert!(bob.path().join("containers/api/flake.nix").exists());
+        assert_eq!(ContainerConfig::load(bob.path(), "api").unwrap().packages.len(), 1);
+
+        // Bob changes and pushes; Alice had no local edits, so she fast-forwards
+        edit(bob.path(), "api", "ripgrep");
+        assert!(matches!(bob_remote.push("api", false).unwrap(), PushOutcome::Pushed { .. }));
+        assert!(matches!(alice_remote.pull("api", false).unwrap(), PullOutcome::Updated { created: false, .. }));
+        assert_eq!(ContainerConfig::load(alice.path(), "api").unwrap().packages.len(), 2);
+
+        // Both sides change: each direction is refused until forced
+        edit(alice.path(), "api", "fd");
+        edit(bob.path(), "api", "bat");
+        assert!(matches!(bob_remote.push("api", false).unwrap(), PushOutcome::Pushed { .. }));
+        assert!(matches!(alice_remote.push("api", false), Err(SfcError::Container { .. })));
+        assert!(matches!(alice_remote.pull("api", false), Err(SfcError::Container { .. })));
+        assert!(matches!(alice_remote.pull("api", true).unwrap(), PullOutcome::Updated { .. }));
+        let names: Vec<String> = ContainerConfig::load(alice.path(), "api").unwrap()
+            .packages.into_iter().map(|p| p.name).collect();
+        assert_eq!(names, vec!["bat", "jq", "ripgrep"]);
+
+        // Names that would leave containers/ are refused, and not listed
+        let stray = alice_remote.checkout.join("containers").join("bad name");
+        fs::create_dir_all(&stray).unwrap();
+        fs::write(stray.join("bad name.toml"), "name = \"x\"\n").unwrap();
+        alice_remote.git(&["add", "--all"]).unwrap();
+        let mut commit_args = identity_args(&alice_remote.checkout);
+        commit_args.extend(["commit", "--quiet", "-m", "stray"].map(String::from));
+        alice_remote.git(&commit_args.iter().map(String::as_str).collect::<Vec<_>>()).unwrap();
+        alice_remote.git(&["push", "--quiet", "origin", "HEAD:refs/heads/main"]).unwrap();
+        assert_eq!(bob_remote.list().unwrap(), vec!["api".to_string()]);
+        assert!(bob_remote.pull("bad name", false).is_err());
+        for name in ["../../escape", "../containers/api"] {
+            assert!(bob_remote.pull(name, true).is_err());
+            assert!(bob_remote.push(name, true).is_err());
+        }
+        assert!(!bob.path().join("escape.toml").exists());
+        assert!(!bob.path().parent().unwrap().join("escape.toml").exists());
+    }
+}
@@ -0,0 +1,560 @@
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::container::{ContainerConfig, PackageSource};
+use crate::core::diff::TOOLCHAIN_FILES;
+use crate::core::hash::{compute_snapshot_hash, SNAPSHOT_LOCKFILES};
+use crate::core::{SnapshotManager, SnapshotMetadata};
+use crate::error::{Result, SfcError, ErrorContext};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SbomFormat {
+    CycloneDx,
+    Spdx,
+}
+
+impl FromStr for SbomFormat {
+    type Err = SfcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "cyclonedx" | "cdx" => Ok(SbomFormat::CycloneDx),
+            "spdx" => Ok(SbomFormat::Spdx),
+            other => Err(SfcError::Validation {
+                field: "format".to_string(),
+                value: other.to_string(),
+                reason: "supported formats are cyclonedx and spdx".to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for SbomFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            SbomFormat::CycloneDx => "cyclonedx",
+            SbomFormat::Spdx => "spdx",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComponentKind {
+    Package,
+    Toolchain,
+    Lockfile,
+}
+
+/// One inventory entry
+#[derive(Debug, Clone, PartialEq)]
+pub struct Component {
+    pub kind: ComponentKind,
+    pub name: String,
+    /// Version as requested in the container config
+    pub requested: Option<String>,
+    /// Version actually installed, when sfc can tell
+    pub resolved: Option<String>,
+    /// nixpkgs, github, url, toolchain or lockfile
+    pub source: String,
+    pub purl: Option<String>,
+    pub download_url: Option<String>,
+    pub sha256: Option<String>,
+    pub nix_store_path: Option<String>,
+}
+
+impl Component {
+    fn version(&self) -> Option<&str> {
+        self.resolved.as_deref().or(self.requested.as_deref())
+    }
+}
+
+/// Everything sfc knows is in a container (or one of its snapshots)
+#[derive(Debug, Clone)]
+pub struct Inventory {
+    pub container: String,
+    pub snapshot: Option<String>,
+    pub components: Vec<Component>,
+}
+
+fn sha256_file(path: &Path) -> Result<String> {
+    let bytes = fs::read(path).with_io_context(|| format!("reading {}", path.display()))?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+/// Commit checked out in a git work tree, read without running git
+fn git_head(repo: &Path) -> Option<String> {
+    let git = repo.join(".git");
+    let head = fs::read_to_string(git.join("HEAD")).ok()?;
+    let head = head.trim();
+    let Some(reference) = head.strip_prefix("ref: ") else {
+        return Some(head.to_string());
+    };
+    if let Ok(sha) = fs::read_to_string(git.join(reference)) {
+        return Some(sha.trim().to_string());
+    }
+    let packed = fs::read_to_string(git.join("packed-refs")).ok()?;
+    packed
+        .lines()
+        .filter_map(|line| line.split_once(' '))
+        .find(|(_, name)| *name == reference)
+        .map(|(sha, _)| sha.to_string())
+}
+
+/// Store paths from a `nix profile` manifest, by package name
+fn nix_store_paths(profile: &Path) -> Vec<(String, String)> {
+    let Ok(content) = fs::read_to_string(profile.join("manifest.json")) else {
+        return Vec::new();
+    };
+    let Ok(manifest) = serde_json::from_str::<Value>(&content) else {
+        return Vec::new();
+    };
+    // Version 2 keeps elements in a list, version 3 in a map keyed by name
+    let elements: Vec<&Value> = match &manifest["elements"] {
+        Value::Array(items) => items.iter().collect(),
+        Value::Object(map) => map.values().collect(),
+        _ => Vec::new(),
+    };
+    let mut paths = Vec::new();
+    for element in elements {
+        let Some(store_path) = element["storePaths"].get(0).and_then(Value::as_str) else {
+            continue;
+        };
+        let attr = element["attrPath"].as_str().unwrap_or_default();
+        let name = attr.rsplit('.').next().filter(|n| !n.is_empty()).map(str::to_string);
+        if let Some(name) = name {
+            paths.push((name, store_path.to_string()));
+        }
+    }
+    paths
+}
+
+/// `/nix/store/<hash>-ripgrep-14.1.0` -> `14.1.0`
+fn store_path_version(store_path: &str, name: &str) -> Option<String> {
+    let base = store_path.rsplit('/').next()?;
+    let (_, rest) = base.split_once('-')?;
+    let version = rest.strip_prefix(name)?.strip_prefix('-')?;
+    (!version.is_empty()).then(|| version.to_string())
+}
+
+/// Installed versions under a Volta/rustup directory whose names start with `requested`
+fn installed_version(dir: &Path, requested: &str) -> Option<String> {
+    let mut versions: Vec<String> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .filter(|v| v == requested || v.starts_with(&format!("{}.", requested)) || v.starts_with(&format!("{}-", requested)))
+        .collect();
+    versions.sort();
+    versions.pop()
+}
+
+fn purl_escape(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| match c {
+            'A'..='Z' | 'a'..='z' | '0'..='9' | '.' | '-' | '_' | '~' | '/' => c.to_string(),
+            _ => format!("%{:02X}", c as u32),
+        })
+        .collect()
+}
+
+impl Inventory {
+    /// Collect from `config` (normally the effective config). With a snapshot
+    /// hash, packages, toolchains and lockfiles come from that snapshot.
+    pub fn collect(workspace: &Path, config: &ContainerConfig, snapshot: Option<&str>) -> Result<Self> {
+        let container_dir = workspace.join("containers").join(&config.name);
+        let pkg_dir = container_dir.join("packages");
+        let nix_paths = nix_store_paths(&pkg_dir.join("nix-profile"));
+        let mut components = Vec::new();
+
+        // A snapshot's manifest and version files say what it was taken with;
+        // snapshots made before manifests existed fall back to the config
+        let (snapshot_dir, packages, toolchains) = match snapshot {
+            Some(hash) => {
+                let dir = SnapshotManager::new(workspace).find_snapshot_by_hash(hash)?;
+                let (packages, mut toolchains) = match SnapshotMetadata::load(&dir)? {
+                    Some(manifest) => (manifest.packages, manifest.toolchains),
+                    None => (config.packages.clone(), config.toolchains.clone()),
+                };
+                for (name, file) in TOOLCHAIN_FILES {
+                    if let Ok(version) = fs::read_to_string(dir.join(file)) {
+                        toolchains.insert(name.to_string(), version.trim().to_string());
+                    }
+                }
+                (Some(dir), packages, toolchains)
+            }
+            None => (None, config.packages.clone(), config.toolchains.clone()),
+        };
+
+        for pkg in &packages {
+            let mut component = Component {
+                kind: ComponentKind::Package,
+                name: pkg.name.clone(),
+                requested: pkg.version.clone(),
+                resolved: None,
+                source: String::new(),
+                purl: None,
+                download_url: None,
+                sha256: None,
+                nix_store_path: None,
+            };
+            match &pkg.source {
+                PackageSource::Nixpkgs => {
+                    component.source = "nixpkgs".to_string();
+                    if let Some((_, path)) = nix_paths.iter().find(|(name, _)| *name == pkg.name) {
+                        component.resolved = store_path_version(path, &pkg.name);
+                        component.nix_store_path = Some(path.clone());
+                    }
+                    component.purl = Some(match component.version() {
+                        Some(v) => format!("pkg:nix/{}@{}", purl_escape(&pkg.name), purl_escape(v)),
+                        None => format!("pkg:nix/{}", purl_escape(&pkg.name)),
+                    });
+                }
+                PackageSource::GitHub { repo, rev } => {
+                    component.source = "github".to_string();
+                    component.requested = Some(rev.clone());
+                    component.resolved = git_head(&pkg_dir.join("github").join(repo.replace('/', "_")));
+                    component.download_url = Some(format!("https://github.com/{}", repo));
+                    let version = component.version().unwrap_or(rev).to_string();
+                    component.purl = Some(format!("pkg:github/{}@{}", repo.to_lowercase(), purl_escape(&version)));
+                }
+                PackageSource::Url(url) => {
+                    component.source = "url".to_string();
+                    component.download_url = Some(url.clone());
+                    let file = url.rsplit('/').next().unwrap_or("download");
+                    let downloaded = pkg_dir.join("downloads").join(file);
+                    if downloaded.is_file() {
+                        component.sha256 = Some(sha256_file(&downloaded)?);
+                    }
+                    component.purl = Some(format!(
+                        "pkg:generic/{}?download_url={}",
+                        purl_escape(&pkg.name),
+                        purl_escape(url)
+                    ));
+                }
+            }
+            components.push(component);
+        }
+
+        let tc_root = workspace.join(".sfc").join("toolchains");
+        for (name, version) in &toolchains {
+            let installed_in = match name.as_str() {
+                "node" | "npm" => Some(tc_root.join("volta/tools/image").join(name)),
+                "rust" => Some(tc_root.join("rustup/toolchains")),
+                _ => None,
+            };
+            let resolved = installed_in.and_then(|dir| installed_version(&dir, version));
+            let mut component = Component {
+                kind: ComponentKind::Toolchain,
+                name: name.clone(),
+                requested: Some(version.clone()),
+                resolved,
+                source: "toolchain".to_string(),
+                purl: None,
+                download_url: None,
+                sha256: None,
+                nix_store_path: None,
+            };
+            component.purl = component
+                .version()
+                .map(|v| format!("pkg:generic/{}@{}", purl_escape(name), purl_escape(v)));
+            components.push(component);
+        }
+
+        if let Some(dir) = &snapshot_dir {
+            for lockfile in SNAPSHOT_LOCKFILES {
+                let path = dir.join(lockfile);
+                if path.is_file() {
+                    components.push(Component {
+                        kind: ComponentKind::Lockfile,
+                        name: lockfile.to_string(),
+                        requested: None,
+                        resolved: None,
+                        source: "lockfile".to_string(),
+                        purl: None,
+                        download_url: None,
+                        sha256: Some(sha256_file(&path)?),
+                        nix_store_path: None,
+                    });
+                }
+            }
+        }
+
+        Ok(Self {
+            container: config.name.clone(),
+            snapshot: snapshot_dir.as_deref().map(compute_snapshot_hash).transpose()?,
+            components,
+        })
+    }
+
+    fn serial(&self) -> String {
+        let bytes: [u8; 16] = rand::random();
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        // Version 4, RFC 4122 variant
+        format!(
+            "{}-{}-4{}-{:x}{}-{}",
+            &hex[0..8],
+            &hex[8..12],
+            &hex[13..16],
+            8 | (bytes[8] & 0x3),
+            &hex[17..20],
+            &hex[20..32]
+        )
+    }
+
+    pub fn render(&self, format: SbomFormat, created: DateTime<Utc>) -> Value {
+        match format {
+            SbomFormat::CycloneDx => self.to_cyclonedx(created),
+            SbomFormat::Spdx => self.to_spdx(created),
+        }
+    }
+
+    /// CycloneDX 1.5 JSON
+    pub fn to_cyclonedx(&self, created: DateTime<Utc>) -> Value {
+        let root_ref = format!("container:{}", self.container);
+        let mut refs = Vec::new();
+        let components: Vec<Value> = self
+            .components
+            .iter()
+            .map(|c| {
+                let bom_ref = format!("{}:{}", c.source, c.name);
+                refs.push(bom_ref.clone());
+                let mut value = json!({
+                    "type": match c.kind {
+                        ComponentKind::Package => "library",
+                        ComponentKind::Toolchain => "application",
+                        ComponentKind::Lockfile => "file",
+                    },
+                    "bom-ref": bom_ref,
+                    "name": c.name,
+                });
+                if let Some(version) = c.version() {
+                    value["version"] = json!(version);
+                }
+                if let Some(purl) = &c.purl {
+                    value["purl"] = json!(purl);
+                }
+                if let Some(sha) = &c.sha256 {
+                    value["hashes"] = json!([{ "alg": "SHA-256", "content": sha }]);
+                }
+                if let Some(url) = &c.download_url {
+                    value["externalReferences"] = json!([{ "type": "distribution", "url": url }]);
+                }
+                let mut properties = vec![json!({ "name": "sfc:source", "value": c.source })];
+                if let Some(requested) = &c.requested {
+                    properties.push(json!({ "name": "sfc:requested-version", "value": requested }));
+                }
+                if let Some(path) = &c.nix_store_path {
+                    properties.push(json!({ "name": "nix:store-path", "value": path }));
+                }
+                value["properties"] = Value::Array(properties);
+                value
+            })
+            .collect();
+
+        let mut root = json!({ "type": "application", "bom-ref": root_ref, "name": self.container });
+        if let Some(hash) = &self.snapshot {
+            root["version"] = json!(hash);
+        }
+        json!({
+            "bomFormat": "CycloneDX",
+            "specVersion": "1.5",
+            "serialNumber": format!("urn:uuid:{}", self.serial()),
+            "version": 1,
+            "metadata": {
+                "timestamp": created.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "tools": { "components": [{ "type": "application", "name": "sfc", "version": env!("CARGO_PKG_VERSION") }] },
+                "component": root,
+            },
+            "components": components,
+            "dependencies": [{ "ref": root_ref, "dependsOn": refs }],
+        })
+    }
+
+    /// SPDX 2.3 JSON
+    pub fn to_spdx(&self, created: DateTime<Utc>) -> Value {
+        let spdx_id = |prefix: &str, name: &str| -> String {
+            let clean: String = name
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '-' })
+                .collect();
+            format!("SPDXRef-{}-{}", prefix, clean)
+        };
+        let root_id = spdx_id("Container", &self.container);
+
+        let mut root = json!({
+            "SPDXID": root_id,
+            "name": self.container,
+            "downloadLocation": "NOASSERTION",
+            "filesAnalyzed": false,
+            "primaryPackagePurpose": "CONTAINER",
+        });
+        if let Some(hash) = &self.snapshot {
+            root["versionInfo"] = json!(hash);
+        }
+        let mut packages = vec![root];
+        let mut relationships = vec![json!({
+            "spdxElementId": "SPDXRef-DOCUMENT",
+            "relationshipType": "DESCRIBES",
+            "relatedSpdxElement": root_id,
+        })];
+
+        for c in &self.components {
+            let (prefix, purpose) = match c.kind {
+                ComponentKind::Package => ("Package", "LIBRARY"),
+                ComponentKind::Toolchain => ("Toolchain", "APPLICATION"),
+                ComponentKind::Lockfile => ("Lockfile", "FILE"),
+            };
+            let id = spdx_id(prefix, &c.name);
+            let mut package = json!({
+                "SPDXID": id,
+                "name": c.name,
+                "downloadLocation": c.download_url.as_deref().unwrap_or("NOASSERTION"),
+                "filesAnalyzed": false,
+                "primaryPackagePurpose": purpose,
+                "licenseConcluded": "NOASSERTION",
+                "licenseDeclared": "NOASSERTION",
+                "copyrightText": "NOASSERTION",
+            });
+            if let Some(version) = c.version() {
+                package["versionInfo"] = json!(version);
+            }
+            if let Some(sha) = &c.sha256 {
+                package["checksums"] = json!([{ "algorithm": "SHA256", "checksumValue": sha }]);
+            }
+            if let Some(purl) = &c.purl {
+                package["externalRefs"] = json!([{
+                    "referenceCategory": "PACKAGE-MANAGER",
+                    "referenceType": "purl",
+                    "referenceLocator": purl,
+                }]);
+            }
+            let mut comment = format!("source: {}", c.source);
+            if let Some(requested) = &c.requested {
+                comment.push_str(&format!("; requested: {}", requested));
+            }
+            if let Some(path) = &c.nix_store_path {
+                comment.push_str(&format!("; nix store path: {}", path));
+            }
+            package["comment"] = json!(comment);
+            packages.push(package);
+            relationships.push(json!({
+                "spdxElementId": root_id,
+                "relationshipType": "CONTAINS",
+                "relatedSpdxElement": id,
+            }));
+        }
+
+        json!({
+            "spdxVersion": "SPDX-2.3",
+            "dataLicense": "CC0-1.0",
+            "SPDXID": "SPDXRef-DOCUMENT",
+            "name": format!("sfc-{}", self.container),
+            "documentNamespace": format!("https://spdx.org/spdxdocs/sfc-{}-{}", self.container, self.serial()),
+            "creationInfo": {
+                "created": created.to_rfc3339_opts(SecondsFormat::Secs, true),
+                "creators": [format!("Tool: sfc-{}", env!("CARGO_PKG_VERSION"))],
+            },
+            "packages": packages,
+            "relationships": relationships,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSpec;
+
+    fn fixture() -> (tempfile::TempDir, ContainerConfig) {
+        let ws = tempfile::tempdir().unwrap();
+        let pkgs = ws.path().join("containers/api/packages");
+
+        fs::create_dir_all(pkgs.join("nix-profile")).unwrap();
+        fs::write(
+            pkgs.join("nix-profile/manifest.json"),
+            r#"{"version":2,"elements":[{"attrPath":"legacyPackages.x86_64-linux.ripgrep","storePaths":["/nix/store/abc123-ripgrep-14.1.0"]}]}"#,
+        )
+        .unwrap();
+        let repo = pkgs.join("github/owner_tool/.git");
+        fs::create_dir_all(repo.join("refs/heads")).unwrap();
+        fs::write(repo.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        fs::write(repo.join("refs/heads/main"), "0123456789abcdef0123456789abcdef01234567\n").unwrap();
+        fs::create_dir_all(pkgs.join("downloads")).unwrap();
+        fs::write(pkgs.join("downloads/tool.tar.gz"), "payload").unwrap();
+        fs::create_dir_all(ws.path().join(".sfc/toolchains/volta/tools/image/node/20.11.1")).unwrap();
+
+        let mut config = ContainerConfig::new("api".to_string());
+        config.add_package(PackageSpec::parse("ripgrep@14")).unwrap();
+        config.add_package(PackageSpec::parse("github:owner/tool@main")).unwrap();
+        config.add_package(PackageSpec::parse("https://example.com/dl/tool.tar.gz")).unwrap();
+        config.toolchains.insert("node".to_string(), "20".to_string());
+        (ws, config)
+    }
+
+    #[test]
+    fn test_inventory_resolves_versions_and_digests() {
+        let (ws, config) = fixture();
+        let inventory = Inventory::collect(ws.path(), &config, None).unwrap();
+        let by_name = |name: &str| inventory.components.iter().find(|c| c.name == name).unwrap();
+
+        let rg = by_name("ripgrep");
+        assert_eq!(rg.resolved.as_deref(), Some("14.1.0"));
+        assert_eq!(rg.purl.as_deref(), Some("pkg:nix/ripgrep@14.1.0"));
+        let tool = by_name("tool");
+        assert_eq!(tool.resolved.as_deref(), Some("0123456789abcdef0123456789abcdef01234567"));
+        let download = inventory.components.iter().find(|c| c.source == "url").unwrap();
+        assert_eq!(download.sha256.as_deref(), Some(format!("{:x}", Sha256::digest(b"payload")).as_str()));
+        assert_eq!(by_name("node").resolved.as_deref(), Some("20.11.1"));
+    }
+
+    #[test]
+    fn test_cyclonedx_and_spdx_shapes() {
+        let (ws, config) = fixture();
+        let inventory = Inventory::collect(ws.path(), &config, None).unwrap();
+        let created = Utc::now();
+
+        let cdx = inventory.render(SbomFormat::CycloneDx, created);
+        assert_eq!(cdx["bomFormat"], "CycloneDX");
+        assert_eq!(cdx["components"].as_array().unwrap().len(), 4);
+        assert_eq!(cdx["dependencies"][0]["dependsOn"].as_array().unwrap().len(), 4);
+        let serial = cdx["serialNumber"].as_str().unwrap();
+        assert_eq!(serial.len(), "urn:uuid:".len() + 36);
+
+        let spdx = inventory.render(SbomFormat::Spdx, created);
+        assert_eq!(spdx["spdxVersion"], "SPDX-2.3");
+        let packages = spdx["packages"].as_array().unwrap();
+        assert_eq!(packages.len(), 5);
+        assert!(packages.iter().all(|p| p["SPDXID"].as_str().unwrap().starts_with("SPDXRef-")));
+        assert_eq!(spdx["relationships"].as_array().unwrap().len(), 5);
+        assert!(matches!("xml".parse::<SbomFormat>(), Err(SfcError::Validation { .. })));
+    }
+
+    #[test]
+    fn test_snapshot_inventory_comes_from_its_manifest() {
+        let (ws, mut config) = fixture();
+        config.save(ws.path()).unwrap();
+        let snapshots = SnapshotManager::new(ws.path());
+        let dir = snapshots.create_snapshot("snapshot-000").unwrap();
+        let mut manifest = SnapshotMetadata::new(&config, None);
+        manifest.packages.truncate(1);
+        manifest.save(&dir).unwrap();
+        fs::write(dir.join("node_version"), "18\n").unwrap();
+        fs::write(dir.join("mix.lock"), "%{jason: 1.4}\n").unwrap();
+        let hash = compute_snapshot_hash(&dir).unwrap();
+        config.add_package(PackageSpec::parse("jq")).unwrap();
+
+        let inventory = Inventory::collect(ws.path(), &config, Some(&hash[..12])).unwrap();
+        let names: Vec<_> = inventory.components.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["ripgrep", "node", "mix.lock"]);
+        assert_eq!(inventory.components[1].requested.as_deref(), Some("18"));
+        assert_eq!(inventory.snapshot.as_deref(), Some(hash.as_str()));
+    }
+}
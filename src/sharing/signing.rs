@@ -0,0 +1,382 @@
+use std::fs;
+use std::io::Write;
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::config::{SharePolicy, SharingConfig};
+use crate::error::{Result, SfcError, ErrorContext};
+
+/// Key name used when none is given and none is configured
+pub const DEFAULT_KEY: &str = "default";
+
+const KEY_TYPE: &str = "ed25519";
+
+/// Detached ed25519 signature over the canonical JSON of a share or bundle manifest
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShareSignature {
+    /// Signer's public key (hex)
+    pub key: String,
+    /// Signature bytes (hex)
+    pub signature: String,
+}
+
+/// A public key with the name it is known by locally
+#[derive(Debug, Clone, PartialEq)]
+pub struct PublicKey {
+    pub name: String,
+    /// Public key bytes (hex)
+    pub key: String,
+}
+
+impl PublicKey {
+    /// Short identifier shown in listings and messages
+    pub fn fingerprint(&self) -> String {
+        fingerprint(&self.key)
+    }
+
+    /// `ed25519 <hex> <name>`, the line format of `.pub` files and `trusted_keys`
+    pub fn to_line(&self) -> String {
+        format!("{} {} {}", KEY_TYPE, self.key, self.name)
+    }
+
+    fn parse_line(line: &str) -> Option<Self> {
+        let mut parts = line.split_whitespace();
+        let (kind, key) = (parts.next()?, parts.next()?);
+        let name = parts.collect::<Vec<_>>().join(" ");
+        (kind == KEY_TYPE && decode_key(key).is_ok()).then(|| Self { name, key: key.to_lowercase() })
+    }
+}
+
+/// A local signing key
+pub struct KeyPair {
+    pub name: String,
+    signing: SigningKey,
+}
+
+impl KeyPair {
+    pub fn public(&self) -> PublicKey {
+        PublicKey {
+            name: self.name.clone(),
+            key: to_hex(self.signing.verifying_key().as_bytes()),
+        }
+    }
+
+    pub fn sign(&self, message: &[u8]) -> ShareSignature {
+        ShareSignature {
+            key: self.public().key,
+            signature: to_hex(&self.signing.sign(message).to_bytes()),
+        }
+    }
+}
+
+/// Outcome of checking a share's signature against the trust store
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verdict {
+    Unsigned,
+    /// Valid signature from a key that is neither ours nor trusted
+    Untrusted { fingerprint: String },
+    Trusted { key: PublicKey },
+}
+
+impl Verdict {
+    pub fn describe(&self) -> String {
+        match self {
+            Verdict::Unsigned => "unsigned".to_string(),
+            Verdict::Untrusted { fingerprint } => format!("signed by untrusted key {}", fingerprint),
+            Verdict::Trusted { key } => format!("signed by {} ({})", key.name, key.fingerprint()),
+        }
+    }
+}
+
+impl SharePolicy {
+    /// Refuse a share whose verdict this policy doesn't accept
+    pub fn check(&self, verdict: &Verdict, what: &str) -> Result<()> {
+        let refused = match (self, verdict) {
+            (SharePolicy::Any, _) => false,
+            (SharePolicy::Signed, Verdict::Unsigned) => true,
+            (SharePolicy::Signed, _) => false,
+            (SharePolicy::Trusted, Verdict::Trusted { .. }) => false,
+            (SharePolicy::Trusted, _) => true,
+        };
+        if refused {
+            let hint = match verdict {
+                Verdict::Untrusted { .. } => " (see 'sfc keys trust')",
+                _ => "",
+            };
+            return Err(SfcError::Validation {
+                field: "signature".to_string(),
+                value: what.to_string(),
+                reason: format!("{} is {}, but the sharing policy is '{}'{}", what, verdict.describe(), self.as_str(), hint),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Signing keys in `.sfc/keys` and trusted public keys in `.sfc/trusted_keys`
+pub struct KeyStore {
+    keys_dir: PathBuf,
+    trusted_path: PathBuf,
+}
+
+impl KeyStore {
+    pub fn new(workspace: &Path) -> Self {
+        let sfc_dir = workspace.join(".sfc");
+        Self {
+            keys_dir: sfc_dir.join("keys"),
+            trusted_path: sfc_dir.join("trusted_keys"),
+        }
+    }
+
+    fn secret_path(&self, name: &str) -> PathBuf {
+        self.keys_dir.join(format!("{}.key", name))
+    }
+
+    fn public_path(&self, name: &str) -> PathBuf {
+        self.keys_dir.join(format!("{}.pub", name))
+    }
+
+    /// Generate a new key; the secret half is only readable by the owner
+    pub fn generate(&self, name: &str) -> Result<PublicKey> {
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+            return Err(SfcError::Validation {
+                field: "key name".to_string(),
+                value: name.to_string(),
+                reason: "use letters, digits, '-' and '_'".to_string(),
+            });
+        }
+        if self.secret_path(name).exists() {
+            return Err(SfcError::AlreadyExists {
+                resource: "key".to_string(),
+                identifier: name.to_string(),
+            });
+        }
+        fs::create_dir_all(&self.keys_dir)
+            .with_io_context(|| format!("creating key directory {}", self.keys_dir.display()))?;
+
+        let mut seed = [0u8; 32];
+        rand::thread_rng().fill(&mut seed);
+        let pair = KeyPair { name: name.to_string(), signing: SigningKey::from_bytes(&seed) };
+        let secret = self.secret_path(name);
+        // Owner-only from the start, and never through an existing file
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o600)
+            .open(&secret)
+            .with_io_context(|| format!("creating key {}", secret.display()))?;
+        writeln!(file, "{}", to_hex(&seed))
+            .with_io_context(|| format!("writing key {}", secret.display()))?;
+        let public = pair.public();
+        fs::write(self.public_path(name), format!("{}\n", public.to_line()))
+            .with_io_context(|| format!("writing public key for {}", name))?;
+        Ok(public)
+    }
+
+    /// Load a signing key by name
+    pub fn load(&self, name: &str) -> Result<KeyPair> {
+        let path = self.secret_path(name);
+        if !path.exists() {
+            return Err(SfcError::NotFound {
+                resource: "key".to_string(),
+                identifier: name.to_string(),
+            });
+        }
+        let content = fs::read_to_string(&path).with_io_context(|| format!("reading key {}", path.display()))?;
+        let seed = decode_key(content.trim()).map_err(|reason| SfcError::Config {
+            message: format!("invalid key {}: {}", name, reason),
+            path: Some(path.clone()),
+        })?;
+        Ok(KeyPair { name: name.to_string(), signing: SigningKey::from_bytes(&seed) })
+    }
+
+    /// The key to sign shares with: `explicit`, else the configured key, else
+    /// the default key if it has been generated
+    pub fn signer(&self, explicit: Option<&str>, config: &SharingConfig) -> Result<Option<KeyPair>> {
+        match explicit.or(config.signing_key.as_deref()) {
+            Some(name) => self.load(name).map(Some),
+            None if self.secret_path(DEFAULT_KEY).exists() => self.load(DEFAULT_KEY).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Our own public keys, by name
+    pub fn list(&self) -> Result<Vec<PublicKey>> {
+        let mut keys = Vec::new();
+        if !self.keys_dir.is_dir() {
+            return Ok(keys);
+        }
+        for entry in fs::read_dir(&self.keys_dir).with_io_context(|| format!("reading {}", self.keys_dir.display()))? {
+            let path = entry.with_io_context(|| format!("reading {}", self.keys_dir.display()))?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("key") {
+                continue;
+            }
+            let name = path.file_stem().unwrap_or_default().to_string_lossy().to_string();
+            keys.push(self.load(&name)?.public());
+        }
+        keys.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(keys)
+    }
+
+    /// Keys listed in the trust store
+    pub fn trusted(&self) -> Result<Vec<PublicKey>> {
+        if !self.trusted_path.exists() {
+            return Ok(Vec::new());
+        }
+        let content = fs::read_to_string(&self.trusted_path)
+            .with_io_context(|| format!("reading {}", self.trusted_path.display()))?;
+        Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .filter_map(PublicKey::parse_line)
+            .collect())
+    }
+
+    /// Add a key to the trust store. `key` is a hex public key, an
+    /// `ed25519 <hex> [name]` line, or a path to a `.pub` file.
+    pub fn trust(&self, key: &str, name: Option<&str>) -> Result<PublicKey> {
+        let line = if Path::new(key).is_file() {
+            fs::read_to_string(key).with_io_context(|| format!("reading public key {}", key))?
+        } else {
+            key.to_string()
+        };
+        let line = line.trim();
+        let mut public = PublicKey::parse_line(line)
+            .or_else(|| PublicKey::parse_line(&format!("{} {}", KEY_TYPE, line)))
+            .ok_or_else(|| SfcError::Validation {
+                field: "key".to_string(),
+                value: key.to_string(),
+                reason: "expected a 64-character hex ed25519 public key or a .pub file".to_string(),
+            })?;
+        if let Some(name) = name {
+            public.name = name.to_string();
+        }
+        if public.name.is_empty() {
+            public.name = public.fingerprint();
+        }
+
+        let mut trusted = self.trusted()?;
+        if let Some(existing) = trusted.iter().find(|k| k.key == public.key) {
+            return Ok(existing.clone());
+        }
+        trusted.push(public.clone());
+        if let Some(parent) = self.trusted_path.parent() {
+            fs::create_dir_all(parent).with_io_context(|| format!("creating {}", parent.display()))?;
+        }
+        let content: String = trusted.iter().map(|k| format!("{}\n", k.to_line())).collect();
+        fs::write(&self.trusted_path, content)
+            .with_io_context(|| format!("writing {}", self.trusted_path.display()))?;
+        Ok(public)
+    }
+
+    /// Check `signature` over `message`. A signature that doesn't verify is
+    /// always an error; otherwise the verdict says who (if anyone) signed.
+    pub fn verify(&self, signature: Option<&ShareSignature>, message: &[u8]) -> Result<Verdict> {
+        let Some(signature) = signature else {
+            return Ok(Verdict::Unsigned);
+        };
+        let bad = |reason: &str| SfcError::Validation {
+            field: "signature".to_string(),
+            value: fingerprint(&signature.key),
+            reason: reason.to_string(),
+        };
+        let key = decode_key(&signature.key)
+            .ok()
+            .and_then(|bytes| VerifyingKey::from_bytes(&bytes).ok())
+            .ok_or_else(|| bad("malformed public key"))?;
+        let bytes: [u8; 64] = from_hex(&signature.signature)
+            .and_then(|b| b.try_into().ok())
+            .ok_or_else(|| bad("malformed signature"))?;
+        key.verify_strict(message, &Signature::from_bytes(&bytes))
+            .map_err(|_| bad("signature does not match the signed content"))?;
+
+        let key = signature.key.to_lowercase();
+        let known = self.list()?.into_iter().chain(self.trusted()?).find(|k| k.key == key);
+        Ok(match known {
+            Some(key) => Verdict::Trusted { key },
+            None => Verdict::Untrusted { fingerprint: fingerprint(&key) },
+        })
+    }
+}
+
+/// Canonical JSON of `value`: compact, with object keys sorted
+pub fn canonical_json<T: Serialize>(value: &T) -> Result<Vec<u8>> {
+    serde_json::to_value(value)
+        .and_then(|value| serde_json::to_vec(&value))
+        .map_err(|e| SfcError::Generic {
+            message: format!("Failed to serialize signed content: {}", e),
+            source: Some(Box::new(e)),
+        })
+}
+
+fn fingerprint(key: &str) -> String {
+    let digest = format!("{:x}", Sha256::digest(key.to_lowercase().as_bytes()));
+    format!("SHA256:{}", &digest[..16])
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) || !s.is_ascii() {
+        return None;
+    }
+    (0..s.len()).step_by(2).map(|i| u8::from_str_radix(&s[i..i + 2], 16).ok()).collect()
+}
+
+fn decode_key(s: &str) -> std::result::Result<[u8; 32], &'static str> {
+    from_hex(s)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or("expected 64 hex characters")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_sign_verify_and_trust() {
+        let ours = TempDir::new().unwrap();
+        let theirs = TempDir::new().unwrap();
+        let signer = KeyStore::new(theirs.path());
+        let public = signer.generate("alice").unwrap();
+        let mode = fs::metadata(signer.secret_path("alice")).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(signer.generate("alice").is_err());
+        let signature = signer.load("alice").unwrap().sign(b"share");
+
+        // The signer's own keys count as trusted
+        assert!(matches!(signer.verify(Some(&signature), b"share").unwrap(), Verdict::Trusted { .. }));
+
+        let store = KeyStore::new(ours.path());
+        assert_eq!(store.verify(None, b"share").unwrap(), Verdict::Unsigned);
+        assert!(matches!(store.verify(Some(&signature), b"share").unwrap(), Verdict::Untrusted { .. }));
+        assert!(store.verify(Some(&signature), b"tampered").is_err());
+
+        store.trust(&theirs.path().join(".sfc/keys/alice.pub").to_string_lossy(), None).unwrap();
+        store.trust(&public.key, Some("again")).unwrap();
+        assert_eq!(store.trusted().unwrap().len(), 1);
+        match store.verify(Some(&signature), b"share").unwrap() {
+            Verdict::Trusted { key } => assert_eq!(key.name, "alice"),
+            other => panic!("expected trusted, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_policy() {
+        let untrusted = Verdict::Untrusted { fingerprint: "SHA256:x".to_string() };
+        assert!(SharePolicy::Any.check(&Verdict::Unsigned, "share").is_ok());
+        assert!(SharePolicy::Signed.check(&Verdict::Unsigned, "share").is_err());
+        assert!(SharePolicy::Signed.check(&untrusted, "share").is_ok());
+        assert!(SharePolicy::Trusted.check(&untrusted, "share").is_err());
+    }
+}
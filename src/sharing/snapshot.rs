@@ -1,36 +1,60 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::fs;
 use std::path::Path;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
-use crate::core::{SnapshotManager, WorkspaceManager};
+use crate::container::{ContainerConfig, PackageSpec};
+use crate::history::{History, Operation};
+use crate::core::{SnapshotManager, SnapshotMetadata, WorkspaceManager};
 use crate::error::{Result, SfcError, ErrorContext};
+use crate::sharing::bundle::{Bundle, BundleManifest, FLAKE_DIR, PACKAGES_DIR, SNAPSHOT_DIR};
+use crate::sharing::signing::{canonical_json, KeyPair, KeyStore, ShareSignature, Verdict};
 
 /// Manages sharing and recreation of snapshots
 pub struct ShareManager {
     workspace: WorkspaceManager,
     snapshot_manager: SnapshotManager,
+    keys: KeyStore,
+    signer: Option<KeyPair>,
 }
 
+/// Everything needed to recreate a container from one of its snapshots
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ShareInfo {
     pub hash: String,
     pub container_name: String,
     pub description: String,
     pub timestamp: DateTime<Utc>,
-    pub packages: Vec<PackageInfo>,
-    pub toolchains: HashMap<String, String>,
+    /// Package specs exactly as the container records them
+    pub packages: Vec<PackageSpec>,
+    /// Toolchain versions; the snapshot's version files win over the config
+    pub toolchains: BTreeMap<String, String>,
     pub environment: HashMap<String, String>,
+    /// The container's nixpkgs pin, if it has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nixpkgs: Option<String>,
     pub metadata: ShareMetadata,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<ShareSignature>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct PackageInfo {
-    pub name: String,
-    pub version: Option<String>,
-    pub source: String,
-    pub channel: Option<String>,
+impl ShareInfo {
+    /// The bytes a signature covers: the canonical share without its signature
+    pub fn signed_bytes(&self) -> Result<Vec<u8>> {
+        canonical_json(&Self { signature: None, ..self.clone() })
+    }
+
+    /// A container config named `name` recording the shared packages,
+    /// toolchains, environment and nixpkgs pin
+    pub fn to_container(&self, name: &str) -> ContainerConfig {
+        let mut config = ContainerConfig::new(name.to_string());
+        config.packages = self.packages.clone();
+        config.toolchains = self.toolchains.clone();
+        config.environment = self.environment.clone();
+        config.nixpkgs = self.nixpkgs.clone();
+        config
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -48,8 +72,23 @@ impl ShareManager {
         Self {
             workspace: workspace.clone(),
             snapshot_manager,
+            keys: KeyStore::new(&workspace.root),
+            signer: None,
         }
     }
+
+    /// Sign exported shares and bundles with `key`
+    pub fn with_signer(mut self, key: Option<KeyPair>) -> Self {
+        self.signer = key;
+        self
+    }
+
+    /// Verify a signature over `message` and apply the sharing policy
+    fn check_signature(&self, signature: Option<&ShareSignature>, message: &[u8], what: &str) -> Result<Verdict> {
+        let verdict = self.keys.verify(signature, message)?;
+        self.workspace.config.sharing.policy.check(&verdict, what)?;
+        Ok(verdict)
+    }
     
     /// Generate sharing information for a snapshot
     pub fn generate_share_info(&self, container_name: &str, hash: Option<&str>) -> Result<ShareInfo> {
@@ -59,28 +98,25 @@ impl ShareManager {
             self.snapshot_manager.get_current_snapshot_hash(container_name)?
         };
         
-        // Find the snapshot
+        // Find the snapshot; record its full hash even when given a prefix
         let snapshot_path = self.snapshot_manager.find_snapshot_by_hash(&snapshot_hash)?;
+        let snapshot_hash = crate::core::compute_snapshot_hash(&snapshot_path)?;
         
-        // Load container configuration
-        let container_config_path = self.workspace.root
-            .join(".sfc")
-            .join("containers")
-            .join(format!("{}.toml", container_name));
-        
-        let (packages, environment) = if container_config_path.exists() {
-            let config_content = fs::read_to_string(&container_config_path)
-                .with_io_context(|| format!("reading container config {}", container_config_path.display()))?;
-            
-            let packages = self.parse_packages_from_config(&config_content)?;
-            let environment = self.parse_environment_from_config(&config_content)?;
-            (packages, environment)
+        // Load the container configuration with everything it inherits, since
+        // the receiving machine may not have its parents
+        let config = if ContainerConfig::exists(&self.workspace.root, container_name) {
+            ContainerConfig::load_effective(&self.workspace.root, container_name)?
         } else {
-            (Vec::new(), HashMap::new())
+            ContainerConfig::new(container_name.to_string())
         };
         
-        // Get toolchain information
-        let toolchains = self.get_snapshot_toolchains(&snapshot_path)?;
+        // The snapshot's manifest says what it was taken with; only snapshots
+        // made before manifests existed fall back to the current config
+        let (packages, mut toolchains, environment) = match SnapshotMetadata::load(&snapshot_path)? {
+            Some(manifest) => (manifest.packages, manifest.toolchains, manifest.environment.into_iter().collect()),
+            None => (config.packages, config.toolchains, config.environment),
+        };
+        toolchains.extend(self.get_snapshot_toolchains(&snapshot_path)?);
         
         // Generate description
         let description = if Some(&snapshot_hash) == self.snapshot_manager.get_current_snapshot_hash(container_name).ok().as_ref() {
@@ -106,13 +142,48 @@ impl ShareManager {
             packages,
             toolchains,
             environment,
+            nixpkgs: config.nixpkgs,
             metadata,
+            signature: None,
         })
     }
     
+    /// Share info for a snapshot known only by its hash, taken from a
+    /// container with an alias (`<name>-stable`, `<name>-temp-...`) pointing at it
+    pub fn share_info_for_hash(&self, hash: &str) -> Result<ShareInfo> {
+        let containers = self.workspace.list_containers()?;
+        let links_dir = self.workspace.root.join("links");
+        let mut aliases: Vec<_> = fs::read_dir(&links_dir)
+            .with_io_context(|| format!("reading links directory {}", links_dir.display()))?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| (entry.file_name().to_string_lossy().to_string(), entry.path()))
+            .collect();
+        aliases.sort();
+
+        for (alias, link) in aliases {
+            if !link.is_dir() || !crate::core::compute_snapshot_hash(&link)?.starts_with(hash) {
+                continue;
+            }
+            let owner = containers
+                .iter()
+                .filter(|name| alias.starts_with(&format!("{}-", name)))
+                .max_by_key(|name| name.len());
+            if let Some(owner) = owner {
+                return self.generate_share_info(owner, Some(hash));
+            }
+        }
+        Err(SfcError::NotFound {
+            resource: "container for snapshot".to_string(),
+            identifier: hash.to_string(),
+        })
+    }
+
     /// Create a shareable export of a snapshot
     pub fn export_snapshot(&self, container_name: &str, hash: Option<&str>) -> Result<String> {
-        let share_info = self.generate_share_info(container_name, hash)?;
+        let mut share_info = self.generate_share_info(container_name, hash)?;
+        if let Some(key) = &self.signer {
+            share_info.signature = Some(key.sign(&share_info.signed_bytes()?));
+        }
         
         // Serialize to JSON for sharing
         serde_json::to_string_pretty(&share_info)
@@ -131,7 +202,8 @@ impl ShareManager {
                 source: Some(Box::new(e)),
             })?;
         
-        // Validate compatibility
+        // Validate signature and compatibility
+        self.check_signature(share_info.signature.as_ref(), &share_info.signed_bytes()?, "share")?;
         self.validate_share_compatibility(&share_info)?;
         
         // Create new snapshot
@@ -140,8 +212,9 @@ impl ShareManager {
         // Recreate lockfiles and metadata
         self.recreate_snapshot_content(&new_snapshot, &share_info)?;
         
-        // Create container configuration
+        // Create container configuration, then record it in the snapshot
         self.create_container_config(new_container_name, &share_info)?;
+        self.snapshot_manager.record_metadata(new_container_name, &new_snapshot, Some(share_info.hash.clone()))?;
         
         // Create container directory structure
         self.create_container_structure(new_container_name, &new_snapshot)?;
@@ -150,6 +223,62 @@ impl ShareManager {
         crate::core::compute_snapshot_hash(&new_snapshot)
     }
     
+    /// Write a `.sfcb` bundle of a snapshot to `out`; `artifacts` adds the
+    /// container's installed packages
+    pub fn export_bundle(&self, container_name: &str, hash: Option<&str>, out: &Path, artifacts: bool) -> Result<BundleManifest> {
+        let share_info = self.generate_share_info(container_name, hash)?;
+        let snapshot_path = self.snapshot_manager.find_snapshot_by_hash(&share_info.hash)?;
+        let container_dir = self.workspace.root.join("containers").join(container_name);
+        let mut bundle = Bundle::create(share_info, &snapshot_path, &container_dir, artifacts)?;
+        if let Some(key) = &self.signer {
+            bundle.sign(key)?;
+        }
+        bundle.write(out)?;
+        Ok(bundle.manifest)
+    }
+
+    /// Recreate a container from a bundle, checking its signature against the
+    /// sharing policy, every entry and the unpacked snapshot's hash. Returns
+    /// the snapshot hash and who signed the bundle.
+    pub fn import_bundle(&self, path: &Path, new_container_name: &str) -> Result<(String, Verdict)> {
+        let bundle = Bundle::read(path)?;
+        let verdict = self.check_signature(bundle.manifest.signature.as_ref(), &bundle.manifest.signed_bytes()?, "bundle")?;
+        self.validate_share_compatibility(&bundle.share)?;
+        let container_dir = self.workspace.root.join("containers").join(new_container_name);
+        if ContainerConfig::exists(&self.workspace.root, new_container_name) || container_dir.exists() {
+            return Err(SfcError::AlreadyExists {
+                resource: "container".to_string(),
+                identifier: new_container_name.to_string(),
+            });
+        }
+
+        let new_snapshot = self.snapshot_manager.create_snapshot("snapshot-bundle")?;
+        bundle.unpack(SNAPSHOT_DIR, &new_snapshot)?;
+        let hash = crate::core::compute_snapshot_hash(&new_snapshot)?;
+        if hash != bundle.manifest.hash {
+            fs::remove_dir_all(&new_snapshot).ok();
+            return Err(SfcError::Validation {
+                field: "bundle".to_string(),
+                value: path.display().to_string(),
+                reason: format!("snapshot hashes to {} instead of {}", &hash[..12], &bundle.manifest.hash[..12.min(bundle.manifest.hash.len())]),
+            });
+        }
+
+        self.create_container_config(new_container_name, &bundle.share)?;
+        self.create_container_structure(new_container_name, &new_snapshot)?;
+        if bundle.contains(FLAKE_DIR) {
+            bundle.unpack(FLAKE_DIR, &container_dir)?;
+        }
+        if bundle.contains(PACKAGES_DIR) {
+            bundle.unpack(PACKAGES_DIR, &container_dir.join("packages"))?;
+        }
+
+        let config = ContainerConfig::load(&self.workspace.root, new_container_name)?;
+        History::load(&self.workspace.root)?
+            .add_entry(&config, Operation::Create, format!("Imported from bundle {}", path.display()))?;
+        Ok((hash, verdict))
+    }
+
     /// Print share information in a user-friendly format
     pub fn format_share_info(&self, share_info: &ShareInfo) -> String {
         let mut output = String::new();
@@ -164,9 +293,7 @@ impl ShareManager {
         if !share_info.packages.is_empty() {
             output.push_str(&format!("📦 Included packages ({}):\n", share_info.packages.len()));
             for package in &share_info.packages {
-                let version = package.version.as_deref().unwrap_or("latest");
-                output.push_str(&format!("   • {} {} [{}]\n", 
-                    package.name, version, package.source));
+                output.push_str(&format!("   • {}\n", package.to_spec_string()));
             }
             output.push('\n');
         }
@@ -197,106 +324,8 @@ impl ShareManager {
     
     // Helper methods
     
-    fn parse_packages_from_config(&self, config_content: &str) -> Result<Vec<PackageInfo>> {
-        // Simple TOML parsing - in production, use proper TOML parsing
-        let mut packages = Vec::new();
-        
-        // This is a simplified parser - in production, use proper TOML deserialization
-        let mut in_packages_section = false;
-        let mut current_package = PackageInfo {
-            name: String::new(),
-            version: None,
-            source: "nixpkgs".to_string(),
-            channel: None,
-        };
-        
-        for line in config_content.lines() {
-            let line = line.trim();
-            
-            if line == "[[packages]]" {
-                if in_packages_section && !current_package.name.is_empty() {
-                    packages.push(current_package.clone());
-                }
-                in_packages_section = true;
-                current_package = PackageInfo {
-                    name: String::new(),
-                    version: None,
-                    source: "nixpkgs".to_string(),
-                    channel: None,
-                };
-            } else if in_packages_section {
-                if line.starts_with("name = ") {
-                    if let Some(name) = self.extract_toml_string_value(line) {
-                        current_package.name = name;
-                    }
-                } else if line.starts_with("version = ") {
-                    if let Some(version) = self.extract_toml_string_value(line) {
-                        current_package.version = Some(version);
-                    }
-                } else if line.starts_with("source = ") {
-                    if let Some(source) = self.extract_toml_string_value(line) {
-                        current_package.source = source;
-                    }
-                } else if line.starts_with("channel = ") {
-                    if let Some(channel) = self.extract_toml_string_value(line) {
-                        current_package.channel = Some(channel);
-                    }
-                } else if line.starts_with('[') && line != "[[packages]]" {
-                    // New section started
-                    if !current_package.name.is_empty() {
-                        packages.push(current_package.clone());
-                    }
-                    in_packages_section = false;
-                }
-            }
-        }
-        
-        // Add the last package if we were in a packages section
-        if in_packages_section && !current_package.name.is_empty() {
-            packages.push(current_package);
-        }
-        
-        Ok(packages)
-    }
-    
-    fn parse_environment_from_config(&self, config_content: &str) -> Result<HashMap<String, String>> {
-        let mut environment = HashMap::new();
-        
-        // Simple parser for [environment] section
-        let mut in_env_section = false;
-        
-        for line in config_content.lines() {
-            let line = line.trim();
-            
-            if line == "[environment]" {
-                in_env_section = true;
-            } else if line.starts_with('[') && line != "[environment]" {
-                in_env_section = false;
-            } else if in_env_section && line.contains('=') {
-                if let Some((key, value)) = line.split_once('=') {
-                    let key = key.trim().trim_matches('"');
-                    let value = value.trim().trim_matches('"');
-                    environment.insert(key.to_string(), value.to_string());
-                }
-            }
-        }
-        
-        Ok(environment)
-    }
-    
-    fn extract_toml_string_value(&self, line: &str) -> Option<String> {
-        if let Some(start) = line.find('"') {
-            if let Some(end) = line.rfind('"') {
-                if start < end {
-                    return Some(line[start + 1..end].to_string());
-                }
-            }
-        }
-        None
-    }
-    
-    fn get_snapshot_toolchains(&self, snapshot_path: &Path) -> Result<HashMap<String, String>> {
-        let mut toolchains = HashMap::new();
+    fn get_snapshot_toolchains(&self, snapshot_path: &Path) -> Result<BTreeMap<String, String>> {
+        let mut toolchains = BTreeMap::new();
         
         // Check for toolchain indicator files
         let toolchain_files = [
@@ -337,9 +366,7 @@ impl ShareManager {
     }
     
     fn recreate_snapshot_content(&self, snapshot_path: &Path, share_info: &ShareInfo) -> Result<()> {
-        // Create lockfiles based on packages
-        self.snapshot_manager.seed_lockfiles(snapshot_path)?;
-        
+        // Shares carry no lockfiles; the packages are reinstalled from the specs instead
         // Write toolchain version files
         for (toolchain, version) in &share_info.toolchains {
             let version_file = snapshot_path.join(format!("{}_version", toolchain));
@@ -362,44 +389,9 @@ impl ShareManager {
     }
     
     fn create_container_config(&self, container_name: &str, share_info: &ShareInfo) -> Result<()> {
-        // Create container configuration based on share info
-        let config_dir = self.workspace.root.join(".sfc").join("containers");
-        fs::create_dir_all(&config_dir)
-            .with_io_context(|| format!("creating container config directory {}", config_dir.display()))?;
-        
-        let config_file = config_dir.join(format!("{}.toml", container_name));
-        
-        // Generate TOML configuration
-        let mut config_content = String::new();
-        config_content.push_str(&format!("name = \"{}\"\n", container_name));
-        config_content.push_str(&format!("created_at = \"{}\"\n", Utc::now().to_rfc3339()));
-        config_content.push_str("shell = \"/bin/bash\"\n\n");
-        
-        // Add packages
-        for package in &share_info.packages {
-            config_content.push_str("[[packages]]\n");
-            config_content.push_str(&format!("name = \"{}\"\n", package.name));
-            if let Some(version) = &package.version {
-                config_content.push_str(&format!("version = \"{}\"\n", version));
-            }
-            config_content.push_str(&format!("source = \"{}\"\n", package.source));
-            if let Some(channel) = &package.channel {
-                config_content.push_str(&format!("channel = \"{}\"\n", channel));
-            }
-            config_content.push('\n');
-        }
-        
-        // Add environment variables
-        if !share_info.environment.is_empty() {
-            config_content.push_str("[environment]\n");
-            for (key, value) in &share_info.environment {
-                config_content.push_str(&format!("{} = \"{}\"\n", key, value));
-            }
-        }
-        
-        fs::write(&config_file, config_content)
-            .with_io_context(|| format!("writing container config to {}", config_file.display()))?;
-        
+        let config = share_info.to_container(container_name);
+        config.save(&self.workspace.root)?;
+        config.to_flake().save(&self.workspace.root, container_name)?;
         Ok(())
     }
     
@@ -436,3 +428,77 @@ pub fn recreate_from_share(workspace: &WorkspaceManager, share_data: &str, new_c
     let share_manager = ShareManager::new(&workspace);
     share_manager.import_snapshot(share_data, new_container_name)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::container::PackageSource;
+
+    #[test]
+    fn test_share_info_keeps_package_sources() {
+        let mut config = ContainerConfig::new("web".to_string());
+        config.packages = vec![
+            PackageSpec::parse("github:BurntSushi/ripgrep@14.1.0"),
+            PackageSpec::parse("https://example.com/tool.tar.gz"),
+            PackageSpec::parse("nodejs@20").with_channel("unstable"),
+        ];
+        config.toolchains.insert("rust".to_string(), "1.80".to_string());
+        config.environment.insert("FOO".to_string(), "bar".to_string());
+        config.nixpkgs = Some("github:NixOS/nixpkgs/nixos-24.05".to_string());
+
+        let mut share: ShareInfo = serde_json::from_value(serde_json::json!({
+            "hash": "abc",
+            "container_name": "web",
+            "description": "snapshot",
+            "timestamp": Utc::now(),
+            "packages": [],
+            "toolchains": {},
+            "environment": {},
+            "metadata": {
+                "sfc_version": "0.1.0",
+                "platform_os": "linux",
+                "platform_arch": "x86_64",
+                "created_by": "test",
+                "shared_at": Utc::now(),
+            },
+        }))
+        .unwrap();
+        share.packages = config.packages.clone();
+        share.toolchains = config.toolchains.clone();
+        share.environment = config.environment.clone();
+        share.nixpkgs = config.nixpkgs.clone();
+
+        let json = serde_json::to_string(&share).unwrap();
+        let restored = serde_json::from_str::<ShareInfo>(&json).unwrap().to_container("copy");
+        let specs: Vec<_> = restored.packages.iter().map(PackageSpec::to_spec_string).collect();
+        assert_eq!(specs, ["github:BurntSushi/ripgrep@14.1.0", "https://example.com/tool.tar.gz", "nodejs@20"]);
+        assert!(matches!(&restored.packages[0].source, PackageSource::GitHub { rev, .. } if rev == "14.1.0"));
+        assert_eq!(restored.packages[2].channel.as_deref(), Some("unstable"));
+        assert_eq!(restored.toolchains, config.toolchains);
+        assert_eq!(restored.environment, config.environment);
+        assert_eq!(restored.nixpkgs, config.nixpkgs);
+        assert_eq!(restored.name, "copy");
+    }
+
+    #[test]
+    fn test_share_info_describes_the_snapshot_not_the_current_config() {
+        let ws = tempfile::tempdir().unwrap();
+        let root = ws.path();
+        let mut config = ContainerConfig::new("web".to_string());
+        config.packages.push(PackageSpec::parse("jq@1.7"));
+        config.save(root).unwrap();
+        let snapshots = SnapshotManager::new(root);
+        let snapshot = snapshots.create_snapshot("snapshot-000").unwrap();
+        snapshots.record_metadata("web", &snapshot, None).unwrap();
+        let hash = crate::core::compute_snapshot_hash(&snapshot).unwrap();
+
+        config.packages.push(PackageSpec::parse("curl"));
+        config.environment.insert("FOO".to_string(), "bar".to_string());
+        config.save(root).unwrap();
+
+        let share = ShareManager::new(&WorkspaceManager::new(root).unwrap()).generate_share_info("web", Some(&hash)).unwrap();
+        let specs: Vec<_> = share.packages.iter().map(PackageSpec::to_spec_string).collect();
+        assert_eq!(specs, ["jq@1.7"]);
+        assert!(share.environment.is_empty());
+    }
+}
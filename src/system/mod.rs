@@ -1,5 +1,9 @@
 pub mod binary;
 pub mod platform;
+pub mod shell;
+pub mod sandbox;
 
 pub use binary::{BinaryManager, switch_system_binaries, restore_system_binaries};
-pub use platform::{detect_platform, detect_package_manager, PlatformInfo};
+pub use platform::{detect_platform, detect_package_manager, detect_distro, Distro, PlatformInfo};
+pub use shell::{ShellKind, HookState, hook_env, find_marker};
+pub use sandbox::{Isolation, SandboxOptions};
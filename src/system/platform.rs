@@ -169,6 +169,87 @@ impl PackageManager {
     }
 }
 
+/// Linux distribution as described by /etc/os-release
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Distro {
+    pub id: String,
+    pub version_id: String,
+    pub id_like: Vec<String>,
+}
+
+impl Distro {
+    pub fn parse_os_release(content: &str) -> Self {
+        let mut distro = Self { id: "linux".to_string(), version_id: String::new(), id_like: Vec::new() };
+        for line in content.lines() {
+            let Some((key, value)) = line.split_once('=') else { continue };
+            let value = value.trim().trim_matches('"').trim_matches('\'').to_string();
+            match key.trim() {
+                "ID" => distro.id = value,
+                "VERSION_ID" => distro.version_id = value,
+                "ID_LIKE" => distro.id_like = value.split_whitespace().map(str::to_string).collect(),
+                _ => {}
+            }
+        }
+        distro
+    }
+
+    fn is(&self, id: &str) -> bool {
+        self.id == id || self.id_like.iter().any(|like| like == id)
+    }
+
+    fn major(&self) -> &str {
+        self.version_id.split('.').next().unwrap_or_default()
+    }
+
+    /// The system package manager this distribution ships with
+    pub fn package_manager(&self) -> PackageManager {
+        if self.id == "alpine" {
+            PackageManager::Apk
+        } else if self.is("arch") {
+            PackageManager::Pacman
+        } else if self.is("suse") || self.id.starts_with("opensuse") {
+            PackageManager::Zypper
+        } else if self.is("fedora") || self.is("rhel") {
+            match self.major().parse::<u32>() {
+                Ok(major) if self.id != "fedora" && major < 8 => PackageManager::Yum,
+                _ => PackageManager::Dnf,
+            }
+        } else {
+            PackageManager::AptGet
+        }
+    }
+
+    /// Closest official container image, falling back to Debian
+    pub fn base_image(&self) -> String {
+        let version = &self.version_id;
+        match self.id.as_str() {
+            "ubuntu" | "debian" | "fedora" if !version.is_empty() => format!("{}:{}", self.id, version),
+            "alpine" if !version.is_empty() => {
+                let minor: Vec<&str> = version.split('.').take(2).collect();
+                format!("alpine:{}", minor.join("."))
+            }
+            "arch" | "archarm" => "archlinux:latest".to_string(),
+            "opensuse-leap" if !version.is_empty() => format!("opensuse/leap:{}", version),
+            "opensuse-tumbleweed" => "opensuse/tumbleweed:latest".to_string(),
+            "rocky" if !version.is_empty() => format!("rockylinux:{}", self.major()),
+            "almalinux" | "centos" | "rhel" if !version.is_empty() => format!("almalinux:{}", self.major()),
+            "ubuntu" | "debian" | "fedora" | "alpine" => format!("{}:latest", self.id),
+            _ => "debian:bookworm-slim".to_string(),
+        }
+    }
+}
+
+/// Detect the running Linux distribution (None elsewhere or without /etc/os-release)
+pub fn detect_distro() -> Option<Distro> {
+    if detect_os() != OperatingSystem::Linux {
+        return None;
+    }
+    std::fs::read_to_string("/etc/os-release")
+        .or_else(|_| std::fs::read_to_string("/usr/lib/os-release"))
+        .ok()
+        .map(|content| Distro::parse_os_release(&content))
+}
+
 /// Detect the current operating system
 pub fn detect_os() -> OperatingSystem {
     match std::env::consts::OS {
@@ -0,0 +1,292 @@
+use std::io;
+use std::path::Path;
+use std::process::Command;
+use serde::{Deserialize, Serialize};
+
+/// How a container's processes are separated from the host
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Isolation {
+    /// PATH/env changes only
+    #[default]
+    None,
+    /// Unprivileged user + mount namespaces (Linux only)
+    Sandbox,
+}
+
+impl Isolation {
+    pub fn is_none(&self) -> bool {
+        *self == Isolation::None
+    }
+}
+
+/// Settings for `isolation = "sandbox"`, read from the `[sandbox]` table
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SandboxOptions {
+    /// Remount the host read-only; only the container directory stays writable
+    #[serde(default)]
+    pub read_only_host: bool,
+    /// Keep host networking (`false` gives the sandbox an empty network namespace)
+    #[serde(default = "default_network")]
+    pub network: bool,
+}
+
+fn default_network() -> bool {
+    true
+}
+
+impl Default for SandboxOptions {
+    fn default() -> Self {
+        Self {
+            read_only_host: false,
+            network: true,
+        }
+    }
+}
+
+impl SandboxOptions {
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Arrange for `cmd` to start inside a sandbox for `container`. Containers in
+/// `inherited` stay visible (read through PATH) but don't get `/usr/local`.
+#[cfg(target_os = "linux")]
+pub fn apply(
+    cmd: &mut Command,
+    workspace: &Path,
+    container: &str,
+    inherited: &[String],
+    options: &SandboxOptions,
+) -> io::Result<()> {
+    use std::os::unix::process::CommandExt;
+
+    let plan = linux::SandboxPlan::new(workspace, container, inherited, options)?;
+    if options.read_only_host {
+        cmd.env("TMPDIR", plan.tmp_dir());
+    }
+    // SAFETY: `enter` only issues syscalls on data prepared before the fork
+    unsafe {
+        cmd.pre_exec(move || plan.enter());
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn apply(
+    _cmd: &mut Command,
+    _workspace: &Path,
+    container: &str,
+    _inherited: &[String],
+    _options: &SandboxOptions,
+) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("container '{}' uses isolation = \"sandbox\", which needs Linux namespaces", container),
+    ))
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use std::fs::{self, File};
+    use std::io;
+    use std::os::fd::{AsRawFd, IntoRawFd};
+    use std::path::{Path, PathBuf};
+    use nix::mount::{mount, MsFlags};
+    use nix::sched::{unshare, CloneFlags};
+    use nix::unistd::{close, dup2, getgid, getuid};
+
+    use super::SandboxOptions;
+
+    /// Everything the child needs, computed in the parent so the post-fork
+    /// side never allocates
+    pub struct SandboxPlan {
+        options: SandboxOptions,
+        uid_map: String,
+        gid_map: String,
+        local_dir: PathBuf,
+        tmp_dir: PathBuf,
+        containers_dir: PathBuf,
+        visible: Vec<VisibleDir>,
+        read_only: Vec<(PathBuf, MsFlags)>,
+    }
+
+    /// A container directory put back after containers/ is covered
+    struct VisibleDir {
+        mount: PathBuf,
+        source: PathBuf,
+        // Reserves the fd number behind `source`
+        fd: File,
+    }
+
+    impl SandboxPlan {
+        pub fn new(
+            workspace: &Path,
+            container: &str,
+            inherited: &[String],
+            options: &SandboxOptions,
+        ) -> io::Result<Self> {
+            let containers_dir = workspace.join("containers");
+            let container_dir = containers_dir.join(container);
+            let local_dir = container_dir.join("local");
+            let tmp_dir = container_dir.join("tmp");
+            fs::create_dir_all(&local_dir)?;
+            if options.read_only_host {
+                fs::create_dir_all(&tmp_dir)?;
+            }
+
+            let mut visible = Vec::new();
+            for name in std::iter::once(container).chain(inherited.iter().map(String::as_str)) {
+                let mount = containers_dir.join(name);
+                if !mount.is_dir() {
+                    continue;
+                }
+                let fd = File::open(&mount)?;
+                let source = PathBuf::from(format!("/proc/self/fd/{}", fd.as_raw_fd()));
+                visible.push(VisibleDir { mount, source, fd });
+            }
+
+            let read_only = if options.read_only_host {
+                let mountinfo = fs::read_to_string("/proc/self/mountinfo")?;
+                parse_mountinfo(&mountinfo)
+                    .into_iter()
+                    .filter(|(mount_point, _)| {
+                        !["/proc", "/sys", "/dev", "/usr/local"].iter().any(|p| mount_point.starts_with(p))
+                            && !mount_point.starts_with(&containers_dir)
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            let uid = getuid();
+            let gid = getgid();
+            Ok(Self {
+                options: options.clone(),
+                uid_map: format!("{} {} 1\n", uid, uid),
+                gid_map: format!("{} {} 1\n", gid, gid),
+                local_dir,
+                tmp_dir,
+                containers_dir,
+                visible,
+                read_only,
+            })
+        }
+
+        pub fn tmp_dir(&self) -> &Path {
+            &self.tmp_dir
+        }
+
+        /// Runs in the forked child right before exec
+        pub fn enter(&self) -> io::Result<()> {
+            let mut flags = CloneFlags::CLONE_NEWUSER | CloneFlags::CLONE_NEWNS;
+            if !self.options.network {
+                flags |= CloneFlags::CLONE_NEWNET;
+            }
+            unshare(flags)?;
+
+            // Map ourselves to our own ids; capabilities are dropped again at exec
+            fs::write("/proc/self/setgroups", "deny")?;
+            fs::write("/proc/self/uid_map", &self.uid_map)?;
+            fs::write("/proc/self/gid_map", &self.gid_map)?;
+
+            // Nothing below may propagate back to the host
+            mount(None::<&str>, "/", None::<&str>, MsFlags::MS_REC | MsFlags::MS_PRIVATE, None::<&str>)?;
+
+            bind(&self.local_dir, Path::new("/usr/local"))?;
+
+            // Cover containers/ with an empty tmpfs, then put the visible ones back.
+            // Directories are reopened in the new namespace (binds can't cross
+            // namespaces) and parked on the reserved fds the /proc paths point at.
+            for dir in &self.visible {
+                let reopened = File::open(&dir.mount)?.into_raw_fd();
+                dup2(reopened, dir.fd.as_raw_fd())?;
+                close(reopened)?;
+            }
+            mount(Some("tmpfs"), &self.containers_dir, Some("tmpfs"), MsFlags::empty(), None::<&str>)?;
+            for dir in &self.visible {
+                fs::create_dir(&dir.mount)?;
+                bind(&dir.source, &dir.mount)?;
+            }
+
+            for (mount_point, locked) in &self.read_only {
+                // Best effort: some mounts refuse a remount from inside a user namespace
+                let _ = mount(
+                    None::<&str>,
+                    mount_point,
+                    None::<&str>,
+                    MsFlags::MS_BIND | MsFlags::MS_REMOUNT | MsFlags::MS_RDONLY | *locked,
+                    None::<&str>,
+                );
+            }
+            Ok(())
+        }
+    }
+
+    fn bind(source: &Path, target: &Path) -> io::Result<()> {
+        mount(Some(source), target, None::<&str>, MsFlags::MS_BIND | MsFlags::MS_REC, None::<&str>)?;
+        Ok(())
+    }
+
+    /// Mount points from /proc/self/mountinfo, with the per-mount flags a
+    /// user namespace has to keep when remounting them
+    pub fn parse_mountinfo(content: &str) -> Vec<(PathBuf, MsFlags)> {
+        content
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split(' ');
+                let mount_point = fields.nth(4)?;
+                let options = fields.next()?;
+                let mut flags = MsFlags::empty();
+                for option in options.split(',') {
+                    flags |= match option {
+                        "nosuid" => MsFlags::MS_NOSUID,
+                        "nodev" => MsFlags::MS_NODEV,
+                        "noexec" => MsFlags::MS_NOEXEC,
+                        "noatime" => MsFlags::MS_NOATIME,
+                        "nodiratime" => MsFlags::MS_NODIRATIME,
+                        "relatime" => MsFlags::MS_RELATIME,
+                        _ => MsFlags::empty(),
+                    };
+                }
+                Some((PathBuf::from(unescape(mount_point)), flags))
+            })
+            .collect()
+    }
+
+    /// mountinfo escapes space, tab, newline and backslash as `\ooo`
+    fn unescape(field: &str) -> String {
+        let bytes = field.as_bytes();
+        let mut out = Vec::with_capacity(bytes.len());
+        let mut i = 0;
+        while i < bytes.len() {
+            if bytes[i] == b'\\' && i + 3 < bytes.len() && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b)) {
+                out.push((bytes[i + 1] - b'0') * 64 + (bytes[i + 2] - b'0') * 8 + (bytes[i + 3] - b'0'));
+                i += 4;
+            } else {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+        String::from_utf8_lossy(&out).into_owned()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn test_parse_mountinfo_flags_and_escapes() {
+            let info = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+35 22 0:30 / /mnt/my\\040disk rw,nosuid,nodev,noexec shared:9 - vfat /dev/sdb1 rw
+";
+            let mounts = parse_mountinfo(info);
+            assert_eq!(mounts.len(), 2);
+            assert_eq!(mounts[0], (PathBuf::from("/"), MsFlags::MS_RELATIME));
+            assert_eq!(mounts[1].0, PathBuf::from("/mnt/my disk"));
+            assert_eq!(mounts[1].1, MsFlags::MS_NOSUID | MsFlags::MS_NODEV | MsFlags::MS_NOEXEC);
+        }
+    }
+}
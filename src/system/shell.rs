@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use serde::{Deserialize, Serialize};
+
+use crate::container::ContainerConfig;
+use crate::error::{Result, SfcError};
+
+/// Marker file that binds a directory tree to a container
+pub const MARKER_FILE: &str = ".sfc-container";
+
+/// Environment variable holding the hook state between prompts
+pub const HOOK_STATE_VAR: &str = "SFC_HOOK_STATE";
+
+/// Shells supported by the prompt hook and container shells
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// State carried in `SFC_HOOK_STATE` so the hook can undo its own changes
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct HookState {
+    pub container: String,
+    pub marker_dir: PathBuf,
+    /// Previous values of every variable the hook overrode (`None` = was unset)
+    pub saved: HashMap<String, Option<String>>,
+}
+
+impl FromStr for ShellKind {
+    type Err = SfcError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "bash" => Ok(ShellKind::Bash),
+            "zsh" => Ok(ShellKind::Zsh),
+            "fish" => Ok(ShellKind::Fish),
+            other => Err(SfcError::Validation {
+                field: "shell".to_string(),
+                value: other.to_string(),
+                reason: "supported shells are bash, zsh and fish".to_string(),
+            }),
+        }
+    }
+}
+
+impl fmt::Display for ShellKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ShellKind::Bash => "bash",
+            ShellKind::Zsh => "zsh",
+            ShellKind::Fish => "fish",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl ShellKind {
+    /// Detect the shell kind from a shell executable path such as `/usr/bin/zsh`
+    pub fn from_path(shell: &str) -> Option<Self> {
+        let name = Path::new(shell).file_name()?.to_str()?;
+        name.parse().ok()
+    }
+
+    /// Quote a value so the shell reads it back verbatim
+    pub fn quote(&self, value: &str) -> String {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => format!("'{}'", value.replace('\'', "'\\''")),
+            ShellKind::Fish => format!("'{}'", value.replace('\\', "\\\\").replace('\'', "\\'")),
+        }
+    }
+
+    /// Statement exporting `key=value`
+    pub fn export(&self, key: &str, value: &str) -> String {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => format!("export {}={};", key, self.quote(value)),
+            // fish keeps PATH as a list, so split it rather than exporting one long element
+            ShellKind::Fish if key == "PATH" => {
+                format!("set -gx PATH (string split : -- {});", self.quote(value))
+            }
+            ShellKind::Fish => format!("set -gx {} {};", key, self.quote(value)),
+        }
+    }
+
+    /// Statement removing `key` from the environment
+    pub fn unset(&self, key: &str) -> String {
+        match self {
+            ShellKind::Bash | ShellKind::Zsh => format!("unset {};", key),
+            ShellKind::Fish => format!("set -e {};", key),
+        }
+    }
+
+    /// Environment variables that give an interactive shell a container prompt
+    pub fn prompt_env(&self, label: &str) -> Vec<(String, String)> {
+        match self {
+            ShellKind::Bash => vec![(
+                "PS1".to_string(),
+                format!("\\[\\033[32m\\]sfc[{}]\\[\\033[0m\\] \\w $ ", label),
+            )],
+            ShellKind::Zsh => vec![(
+                "PS1".to_string(),
+                format!("%F{{green}}sfc[{}]%f %~ %# ", label),
+            )],
+            // fish ignores PS1; the prompt is wrapped through `prompt_args` instead
+            ShellKind::Fish => Vec::new(),
+        }
+    }
+
+    /// Extra arguments for spawning an interactive shell with a container prompt
+    pub fn prompt_args(&self, label: &str) -> Vec<String> {
+        match self {
+            ShellKind::Fish => vec![
+                "--init-command".to_string(),
+                format!(
+                    "functions -c fish_prompt _sfc_orig_fish_prompt; \
+                     function fish_prompt; set_color green; echo -n {}; set_color normal; _sfc_orig_fish_prompt; end",
+                    self.quote(&format!("sfc[{}] ", label))
+                ),
+            ],
+            ShellKind::Bash | ShellKind::Zsh => Vec::new(),
+        }
+    }
+
+    /// Init script to be evaluated from the user's shell rc file
+    pub fn init_script(&self, sfc_exe: &str) -> String {
+        let exe = self.quote(sfc_exe);
+        match self {
+            ShellKind::Bash => format!(
+                r#"_sfc_hook() {{
+  local previous_exit_status=$?
+  eval "$({exe} hook-env bash)"
+  if [ -n "${{SFC_CONTAINER:-}}" ] && [ -n "${{SFC_HOOK_STATE:-}}" ]; then
+    [ -z "${{_SFC_OLD_PS1+x}}" ] && _SFC_OLD_PS1="$PS1"
+    PS1="(sfc:$SFC_CONTAINER) $_SFC_OLD_PS1"
+  elif [ -n "${{_SFC_OLD_PS1+x}}" ]; then
+    PS1="$_SFC_OLD_PS1"
+    unset _SFC_OLD_PS1
+  fi
+  return $previous_exit_status
+}}
+if [[ ";${{PROMPT_COMMAND[*]:-}};" != *";_sfc_hook;"* ]]; then
+  if [[ "$(declare -p PROMPT_COMMAND 2>&1)" == "declare -a"* ]]; then
+    PROMPT_COMMAND=(_sfc_hook "${{PROMPT_COMMAND[@]}}")
+  else
+    PROMPT_COMMAND="_sfc_hook${{PROMPT_COMMAND:+;$PROMPT_COMMAND}}"
+  fi
+fi
+"#
+            ),
+            ShellKind::Zsh => format!(
+                r#"_sfc_hook() {{
+  eval "$({exe} hook-env zsh)"
+  if [[ -n "${{SFC_CONTAINER:-}}" && -n "${{SFC_HOOK_STATE:-}}" ]]; then
+    [[ -z "${{_SFC_OLD_PS1+x}}" ]] && _SFC_OLD_PS1="$PS1"
+    PS1="(sfc:$SFC_CONTAINER) $_SFC_OLD_PS1"
+  elif [[ -n "${{_SFC_OLD_PS1+x}}" ]]; then
+    PS1="$_SFC_OLD_PS1"
+    unset _SFC_OLD_PS1
+  fi
+}}
+typeset -ag precmd_functions chpwd_functions
+if (( ! ${{precmd_functions[(I)_sfc_hook]}} )); then
+  precmd_functions=(_sfc_hook $precmd_functions)
+fi
+if (( ! ${{chpwd_functions[(I)_sfc_hook]}} )); then
+  chpwd_functions=(_sfc_hook $chpwd_functions)
+fi
+"#
+            ),
+            ShellKind::Fish => format!(
+                r#"function _sfc_hook --on-variable PWD --description 'sfc container auto-activation'
+  {exe} hook-env fish | source
+end
+if functions -q fish_prompt; and not functions -q _sfc_orig_fish_prompt
+  functions -c fish_prompt _sfc_orig_fish_prompt
+  function fish_prompt
+    if set -q SFC_CONTAINER; and set -q SFC_HOOK_STATE
+      set_color green; echo -n "(sfc:$SFC_CONTAINER) "; set_color normal
+    end
+    _sfc_orig_fish_prompt
+  end
+end
+_sfc_hook
+"#
+            ),
+        }
+    }
+}
+
+/// Find the nearest `.sfc-container` marker at or above `dir` and the container it names
+pub fn find_marker(dir: &Path) -> Option<(PathBuf, String)> {
+    for ancestor in dir.ancestors() {
+        let marker = ancestor.join(MARKER_FILE);
+        if marker.is_file() {
+            let content = fs::read_to_string(&marker).ok()?;
+            let name = content
+                .lines()
+                .map(str::trim)
+                .find(|l| !l.is_empty() && !l.starts_with('#'))?;
+            return Some((ancestor.to_path_buf(), name.to_string()));
+        }
+    }
+    None
+}
+
+/// Shell code that moves the current shell from its previous hook state to whatever
+/// container `cwd` is bound to. Empty output means nothing changed.
+pub fn hook_env(
+    shell: ShellKind,
+    workspace: &Path,
+    cwd: &Path,
+    current_env: &HashMap<String, String>,
+) -> Result<String> {
+    let previous: Option<HookState> = current_env
+        .get(HOOK_STATE_VAR)
+        .and_then(|raw| serde_json::from_str(raw).ok());
+
+    let target = find_marker(cwd)
+        .filter(|(_, name)| workspace.join("containers").join(name).is_dir());
+
+    if let (Some(prev), Some((dir, name))) = (&previous, &target) {
+        if &prev.container == name && &prev.marker_dir == dir {
+            return Ok(String::new());
+        }
+    }
+
+    let mut out = Vec::new();
+
+    // Baseline environment as it was before the previous activation
+    let mut baseline = current_env.clone();
+    if let Some(prev) = &previous {
+        for (key, old) in &prev.saved {
+            match old {
+                Some(value) => {
+                    out.push(shell.export(key, value));
+                    baseline.insert(key.clone(), value.clone());
+                }
+                None => {
+                    out.push(shell.unset(key));
+                    baseline.remove(key);
+                }
+            }
+        }
+    }
+
+    match target {
+        Some((dir, name)) => {
+            let container = ContainerConfig::load_effective(workspace, &name)?;
+            let base_path = baseline.get("PATH").cloned().unwrap_or_default();
+            let env = container.activation_env(workspace, &base_path);
+
+            let mut keys: Vec<_> = env.keys().cloned().collect();
+            keys.sort();
+
+            let mut saved = HashMap::new();
+            for key in keys {
+                saved.insert(key.clone(), baseline.get(&key).cloned());
+                out.push(shell.export(&key, &env[&key]));
+            }
+
+            let state = HookState { container: name, marker_dir: dir, saved };
+            let encoded = serde_json::to_string(&state).map_err(|e| SfcError::Generic {
+                message: format!("Failed to serialize hook state: {}", e),
+                source: Some(Box::new(e)),
+            })?;
+            out.push(shell.export(HOOK_STATE_VAR, &encoded));
+        }
+        None => {
+            if previous.is_some() {
+                out.push(shell.unset(HOOK_STATE_VAR));
+            }
+        }
+    }
+
+    Ok(out.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn workspace_with(name: &str) -> tempfile::TempDir {
+        let tmp = tempfile::tempdir().unwrap();
+        fs::create_dir_all(tmp.path().join("containers").join(name)).unwrap();
+        let mut config = ContainerConfig::new(name.to_string());
+        config.environment.insert("APP_ENV".to_string(), "dev".to_string());
+        config.save(tmp.path()).unwrap();
+        tmp
+    }
+
+    #[test]
+    fn test_quote_round_trips_single_quotes() {
+        assert_eq!(ShellKind::Bash.quote("it's"), "'it'\\''s'");
+        assert_eq!(ShellKind::Fish.quote("it's"), "'it\\'s'");
+    }
+
+    #[test]
+    fn test_from_path() {
+        assert_eq!(ShellKind::from_path("/usr/bin/zsh"), Some(ShellKind::Zsh));
+        assert_eq!(ShellKind::from_path("/usr/local/bin/fish"), Some(ShellKind::Fish));
+        assert_eq!(ShellKind::from_path("/bin/tcsh"), None);
+    }
+
+    #[test]
+    fn test_hook_env_activates_and_deactivates() {
+        let ws = workspace_with("demo");
+        let project = tempfile::tempdir().unwrap();
+        fs::write(project.path().join(MARKER_FILE), "demo\n").unwrap();
+        let nested = project.path().join("src");
+        fs::create_dir_all(&nested).unwrap();
+
+        let mut env = HashMap::new();
+        env.insert("PATH".to_string(), "/usr/bin".to_string());
+
+        let activate = hook_env(ShellKind::Bash, ws.path(), &nested, &env).unwrap();
+        assert!(activate.contains("export SFC_CONTAINER='demo';"));
+        assert!(activate.contains("export APP_ENV='dev';"));
+
+        // Same directory again: nothing to do
+        let state_line = activate.lines().find(|l| l.contains(HOOK_STATE_VAR)).unwrap();
+        let raw = state_line
+            .trim_start_matches("export SFC_HOOK_STATE='")
+            .trim_end_matches("';")
+            .replace("'\\''", "'");
+        env.insert(HOOK_STATE_VAR.to_string(), raw);
+        env.insert("SFC_CONTAINER".to_string(), "demo".to_string());
+        assert!(hook_env(ShellKind::Bash, ws.path(), &nested, &env).unwrap().is_empty());
+
+        // Leaving the project restores the previous environment
+        let outside = tempfile::tempdir().unwrap();
+        let deactivate = hook_env(ShellKind::Bash, ws.path(), outside.path(), &env).unwrap();
+        assert!(deactivate.contains("unset SFC_CONTAINER;"));
+        assert!(deactivate.contains("unset APP_ENV;"));
+        assert!(deactivate.contains("export PATH='/usr/bin';"));
+        assert!(deactivate.contains("unset SFC_HOOK_STATE;"));
+    }
+}
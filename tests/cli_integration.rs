@@ -59,3 +59,14 @@ fn init_and_create_and_status_flow() {
 }
 
 
+
+#[test]
+fn hook_env_never_creates_the_workspace() {
+    let home = tempdir().unwrap();
+    for name in ["sfc", "sfc-new"] {
+        let mut cmd = Command::cargo_bin(name).unwrap();
+        cmd.env("HOME", home.path()).current_dir(home.path());
+        cmd.arg("hook-env").arg("bash").assert().success().stdout(predicate::str::is_empty());
+    }
+    assert!(!home.path().join(".sfc").exists());
+}
@@ -0,0 +1,53 @@
+{
+  description = "SFC container: web";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-24.05";
+    cli = {
+      url = "https://example.com/dl/cli";
+      flake = false;
+    };
+    data_tar_gz = {
+      url = "https://example.com/src/data.tar.gz";
+      flake = false;
+    };
+    my_tool.url = "github:owner/my-tool/v1.2.0";
+  };
+
+  outputs = { self, nixpkgs, ... }@inputs:
+    let
+      systems = [ "x86_64-linux" "aarch64-linux" "x86_64-darwin" "aarch64-darwin" ];
+      forAllSystems = f: nixpkgs.lib.genAttrs systems (system: f system nixpkgs.legacyPackages.${system});
+    in
+    {
+      devShells = forAllSystems (system: pkgs: {
+        default = pkgs.mkShell {
+          # npm 10: nodejs ships its bundled npm
+          # rust 1.78: nixpkgs has one rustc per revision; pin nixpkgs to choose it
+          # node: provided by the nodejs toolchain
+          # ripgrep@14: no versioned nixpkgs attribute, using ripgrep
+          packages = [
+            pkgs.nodejs_20
+            pkgs.rustc
+            pkgs.cargo
+            (pkgs.runCommand "cli" { } "install -Dm755 ${inputs.cli} $out/bin/'cli'")
+            (pkgs.runCommand "data.tar.gz" { } "ln -s ${inputs.data_tar_gz} $out")
+            inputs.my_tool.packages.${system}.default
+            pkgs.python312
+            pkgs.ripgrep
+          ];
+
+          env = {
+            GREETING = "say \"hi\" \${USER}";
+            NODE_ENV = "development";
+          };
+
+          shellHook = ''
+            echo '🚀 Entering web container'
+            echo '📦 Packages: cli, data.tar.gz, my-tool, node, python, ripgrep'
+            export SFC_CONTAINER='web'
+          '';
+        };
+      });
+    };
+}
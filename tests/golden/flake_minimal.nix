@@ -0,0 +1,26 @@
+{
+  description = "SFC container: empty";
+
+  inputs = {
+    nixpkgs.url = "github:NixOS/nixpkgs/nixos-unstable";
+  };
+
+  outputs = { self, nixpkgs, ... }@inputs:
+    let
+      systems = [ "x86_64-linux" "aarch64-linux" "x86_64-darwin" "aarch64-darwin" ];
+      forAllSystems = f: nixpkgs.lib.genAttrs systems (system: f system nixpkgs.legacyPackages.${system});
+    in
+    {
+      devShells = forAllSystems (system: pkgs: {
+        default = pkgs.mkShell {
+          packages = [ ];
+
+          shellHook = ''
+            echo '🚀 Entering empty container'
+            echo '📦 Packages: '
+            export SFC_CONTAINER='empty'
+          '';
+        };
+      });
+    };
+}